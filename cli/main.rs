@@ -1,14 +1,36 @@
 use jstime_core as jstime;
 use std::env;
+use std::io::{IsTerminal, Read, Seek, SeekFrom, Write};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
 use std::process;
-use structopt::StructOpt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use structopt::{clap::AppSettings, StructOpt};
 
+static SNAPSHOT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/snapshot_data.blob"));
+
+// Executables made by `jstime compile` end in a bundle of the script's
+// module graph, followed by its length and this marker.
+const BUNDLE_MARKER: &[u8; 8] = b"jstimebn";
+
+// Filenames like test.js would otherwise be rejected as misspelled
+// subcommands. Everything after the filename is left to the script, even
+// arguments that look like jstime's own flags or subcommands.
 #[derive(StructOpt)]
-#[structopt(name = "jstime", rename_all = "kebab-case")]
+#[structopt(
+    name = "jstime",
+    rename_all = "kebab-case",
+    settings = &[
+        AppSettings::InferSubcommands,
+        AppSettings::TrailingVarArg,
+        AppSettings::ArgsNegateSubcommands,
+    ]
+)]
 struct Opt {
-    /// File to read from, or "-" to read from stdin. Interactive mode if a tty
-    #[structopt()]
-    filename: Option<String>,
+    /// File to read from, or "-" to read from stdin, followed by arguments
+    /// for the script. Interactive mode if a tty
+    #[structopt(value_name = "FILENAME [ARGS]...")]
+    script: Vec<String>,
 
     /// Prints version information
     #[structopt(short, long)]
@@ -17,9 +39,263 @@ struct Opt {
     /// Options for V8
     #[structopt(long)]
     v8_options: Option<String>,
+
+    /// Don't remap stack traces through source maps
+    #[structopt(long)]
+    no_source_maps: bool,
+
+    /// Always compile modules from source, without reading or writing
+    /// the V8 code cache
+    #[structopt(long)]
+    no_code_cache: bool,
+
+    /// Directory to make available to a WASI module, can be repeated.
+    /// Access to it still needs --allow-read and --allow-write
+    #[structopt(long, number_of_values = 1)]
+    preopen: Vec<String>,
+
+    /// Listen for Chrome DevTools clients, on 127.0.0.1:9229 by default
+    #[structopt(long, require_equals = true, value_name = "[host:]port")]
+    inspect: Option<Option<String>>,
+
+    /// Like --inspect, but wait for a client and break before the script
+    /// starts
+    #[structopt(long, require_equals = true, value_name = "[host:]port")]
+    inspect_brk: Option<Option<String>>,
+
+    /// Write a CPU profile of the script to a .cpuprofile file on exit
+    #[structopt(long)]
+    cpu_prof: bool,
+
+    /// Directory to write CPU profiles to, the current directory by default
+    #[structopt(long, requires = "cpu-prof", value_name = "dir")]
+    cpu_prof_dir: Option<PathBuf>,
+
+    /// Sampling interval of the CPU profiler, in microseconds
+    #[structopt(long, default_value = "1000", value_name = "us")]
+    cpu_prof_interval: u64,
+
+    /// Write a heap snapshot to a .heapsnapshot file on exit
+    #[structopt(long)]
+    heap_snapshot_on_exit: bool,
+
+    /// Write a heap snapshot to a .heapsnapshot file whenever the signal is
+    /// received, e.g. SIGUSR2
+    #[structopt(long, value_name = "signal")]
+    heap_snapshot_signal: Option<String>,
+
+    /// Write code coverage of the script's modules to a directory on exit,
+    /// see `jstime coverage`
+    #[structopt(long, value_name = "dir")]
+    coverage: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    permissions: PermissionOpt,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+enum Command {
+    /// Print a summary of the coverage written by --coverage and
+    /// convert it to LCOV
+    Coverage {
+        /// Directory the coverage was written to
+        dir: PathBuf,
+
+        /// Where to write the LCOV tracefile, lcov.info in the coverage
+        /// directory by default
+        #[structopt(long, value_name = "file")]
+        lcov: Option<PathBuf>,
+    },
+
+    /// Compile a script and the modules it imports into a standalone
+    /// executable
+    Compile {
+        /// Entry module of the script
+        entry: String,
+
+        /// Where to write the executable, the entry's name without its
+        /// extension by default
+        #[structopt(short, long, value_name = "file")]
+        output: Option<PathBuf>,
+
+        // What the executable may access, as when running the script.
+        #[structopt(flatten)]
+        permissions: PermissionOpt,
+    },
+
+    /// Pack a script and the modules it imports into a .jspack archive,
+    /// run with `jstime app.jspack`
+    Pack {
+        /// Entry module of the script
+        entry: String,
+
+        /// Where to write the archive, the entry's name with a .jspack
+        /// extension by default
+        #[structopt(short, long, value_name = "file")]
+        output: Option<PathBuf>,
+
+        /// Include V8 code cache data, used when the archive is run by the
+        /// same version of jstime
+        #[structopt(long)]
+        code_cache: bool,
+    },
+
+    /// Run the tests in *.test.js and *.test.mjs files, registered through
+    /// the jstime:test module
+    Test(TestOpt),
+
+    /// Run the benchmarks in *.bench.js and *.bench.mjs files, registered
+    /// through the jstime:bench module
+    Bench(BenchOpt),
+}
+
+// Without a value, --allow-* flags allow everything of their kind.
+#[derive(StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct PermissionOpt {
+    /// Allow network access, to the given hosts only if any
+    #[structopt(long, require_equals = true, use_delimiter = true, value_name = "host")]
+    allow_net: Option<Vec<String>>,
+
+    /// Allow reading files, in the given paths only if any
+    #[structopt(long, require_equals = true, use_delimiter = true, value_name = "path")]
+    allow_read: Option<Vec<String>>,
+
+    /// Allow writing files, in the given paths only if any
+    #[structopt(long, require_equals = true, use_delimiter = true, value_name = "path")]
+    allow_write: Option<Vec<String>>,
+
+    /// Allow access to environment variables, the given ones only if any
+    #[structopt(long, require_equals = true, use_delimiter = true, value_name = "name")]
+    allow_env: Option<Vec<String>>,
+
+    /// Allow running programs, the given ones only if any
+    #[structopt(
+        long,
+        require_equals = true,
+        use_delimiter = true,
+        value_name = "program"
+    )]
+    allow_run: Option<Vec<String>>,
+
+    /// Allow everything
+    #[structopt(short = "A", long)]
+    allow_all: bool,
+
+    /// Deny what wasn't allowed right away, instead of asking when running
+    /// in a terminal
+    #[structopt(long)]
+    no_prompt: bool,
+}
+
+impl PermissionOpt {
+    fn permissions(&self) -> jstime::Permissions {
+        if self.allow_all {
+            return jstime::Permissions::allow_all();
+        }
+
+        let permission = |values: &Option<Vec<String>>| match values {
+            None => jstime::Permission::Denied,
+            Some(values) if values.is_empty() => jstime::Permission::Granted,
+            Some(values) => jstime::Permission::GrantedFor(values.clone()),
+        };
+        jstime::Permissions {
+            net: permission(&self.allow_net),
+            read: permission(&self.allow_read),
+            write: permission(&self.allow_write),
+            env: permission(&self.allow_env),
+            run: permission(&self.allow_run),
+            prompt: !self.no_prompt
+                && std::io::stdin().is_terminal()
+                && std::io::stderr().is_terminal(),
+        }
+    }
+
+    /// The flags these options were parsed from, to embed in compiled
+    /// executables.
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let flags = [
+            ("--allow-net", &self.allow_net),
+            ("--allow-read", &self.allow_read),
+            ("--allow-write", &self.allow_write),
+            ("--allow-env", &self.allow_env),
+            ("--allow-run", &self.allow_run),
+        ];
+        for (flag, values) in flags.iter() {
+            match values {
+                Some(values) if values.is_empty() => args.push(flag.to_string()),
+                Some(values) => args.push(format!("{}={}", flag, values.join(","))),
+                None => {}
+            }
+        }
+        if self.allow_all {
+            args.push("--allow-all".to_owned());
+        }
+        if self.no_prompt {
+            args.push("--no-prompt".to_owned());
+        }
+        args
+    }
+}
+
+#[derive(StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct TestOpt {
+    /// Test files, or directories to search for them, the current
+    /// directory by default
+    paths: Vec<PathBuf>,
+
+    /// How to report results
+    #[structopt(long, default_value = "spec", possible_values = &["spec", "tap", "junit"])]
+    reporter: jstime::TestReporter,
+
+    /// Timeout of each test and hook that doesn't set its own, in
+    /// milliseconds, or 0 for none
+    #[structopt(long, default_value = "5000", value_name = "ms")]
+    timeout: u64,
+}
+
+#[derive(StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+struct BenchOpt {
+    /// Benchmark files, or directories to search for them, the current
+    /// directory by default
+    paths: Vec<PathBuf>,
+
+    /// Results of an earlier run, saved with --save, to compare against
+    #[structopt(long, value_name = "file")]
+    baseline: Option<PathBuf>,
+
+    /// Save the results as JSON, for use as a baseline
+    #[structopt(long, value_name = "file")]
+    save: Option<PathBuf>,
+
+    /// How many percent slower than the baseline a benchmark may get
+    /// before it counts as a regression
+    #[structopt(long, default_value = "10", value_name = "percent")]
+    threshold: f64,
+
+    /// How long to run each benchmark before measuring it, in milliseconds
+    #[structopt(long, default_value = "100", value_name = "ms")]
+    warmup: u64,
+
+    /// How long to measure each benchmark for, in milliseconds
+    #[structopt(long, default_value = "500", value_name = "ms")]
+    time: u64,
 }
 
 fn main() {
+    // Arguments of compiled executables are left to the script.
+    if let Some((bundle, permissions)) = embedded_bundle() {
+        let args = env::args().skip(1).collect();
+        process::exit(run_bundle(bundle, permissions, args));
+    }
+
     let opt = Opt::from_args();
 
     if opt.version {
@@ -27,31 +303,499 @@ fn main() {
         process::exit(0);
     }
 
+    let (test, bench) = match opt.command {
+        Some(Command::Coverage { dir, lcov }) => process::exit(coverage_report(&dir, lcov)),
+        Some(Command::Compile {
+            entry,
+            output,
+            permissions,
+        }) => process::exit(compile(&entry, output, permissions)),
+        Some(Command::Pack {
+            entry,
+            output,
+            code_cache,
+        }) => process::exit(pack(&entry, output, code_cache)),
+        Some(Command::Test(test)) => (Some(test), None),
+        Some(Command::Bench(bench)) => (None, Some(bench)),
+        None => (None, None),
+    };
+
     jstime::init(
         opt.v8_options
             .map(|o| o.split(' ').map(|s| s.to_owned()).collect()),
     );
 
-    let options = jstime::Options::new(Some(include_bytes!(concat!(
-        env!("OUT_DIR"),
-        "/snapshot_data.blob"
-    ))));
+    let mut options = jstime::Options::new(Some(SNAPSHOT));
+    options.source_maps = !opt.no_source_maps;
+    options.permissions = opt.permissions.permissions();
+    let mut script = opt.script.into_iter();
+    let mut filename = script.next();
+    let args = script.collect::<Vec<_>>();
+    options.args = args.clone();
+    // Code from the cache has no coverage counters.
+    if !opt.no_code_cache && opt.coverage.is_none() {
+        options.code_cache = dirs::cache_dir().map(|mut p| {
+            p.push("jstime");
+            p.push("code_cache");
+            p
+        });
+    }
 
-    let mut jstime = jstime::JSTime::new(options);
+    options.inspect_brk = opt.inspect_brk.is_some();
+    if let Some(inspect) = opt.inspect_brk.or(opt.inspect) {
+        options.inspect = match inspect_address(inspect.as_deref()) {
+            Some(addr) => Some(addr),
+            None => {
+                eprintln!("Invalid inspector address: {}", inspect.unwrap_or_default());
+                process::exit(1);
+            }
+        };
+    }
 
-    if let Some(filename) = opt.filename {
-        std::process::exit(match jstime.import(&filename) {
-            Ok(_) => 0,
+    // Archives made by `jstime pack` run their entry module, with every
+    // module loaded from the archive.
+    if let Some(pack) = filename.as_ref().filter(|f| f.ends_with(".jspack")) {
+        let bundle = match std::fs::read(pack) {
+            Ok(bytes) => jstime::Bundle::from_bytes(&bytes),
+            Err(e) => Err(e.to_string()),
+        };
+        match bundle {
+            Ok(bundle) => {
+                filename = Some(bundle.entry().to_owned());
+                options.bundle = Some(bundle);
+            }
             Err(e) => {
-                eprintln!("{}", e);
-                1
+                eprintln!("{}: {}", pack, e);
+                process::exit(1);
             }
-        });
+        }
+    }
+
+    let mut jstime = jstime::JSTime::new(options);
+
+    if let Some(signal) = opt.heap_snapshot_signal {
+        #[cfg(unix)]
+        let result = heap_snapshot_on_signal(&mut jstime, &signal);
+        #[cfg(not(unix))]
+        let result: Result<(), String> = Err("signals are not supported".to_owned());
+        if let Err(e) = result {
+            eprintln!("Failed to listen for {}: {}", signal, e);
+            process::exit(1);
+        }
+    }
+
+    if filename.is_some() || test.is_some() || bench.is_some() {
+        if opt.cpu_prof {
+            let interval = Duration::from_micros(opt.cpu_prof_interval);
+            if let Err(e) = jstime.start_cpu_profiling(interval) {
+                eprintln!("Failed to start CPU profiling: {}", e);
+            }
+        }
+
+        if opt.coverage.is_some() {
+            if let Err(e) = jstime.start_coverage() {
+                eprintln!("Failed to start coverage: {}", e);
+            }
+        }
+
+        let code = match (test, bench, filename) {
+            (Some(test), _, _) => run_tests(&mut jstime, test),
+            (_, Some(bench), _) => run_benchmarks(&mut jstime, bench),
+            (None, None, Some(filename)) if filename.ends_with(".wasm") => {
+                let args = std::iter::once(filename.clone())
+                    .chain(args)
+                    .collect::<Vec<_>>();
+                match jstime.run_wasi(&filename, &args, &opt.preopen) {
+                    Ok(code) => code,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        1
+                    }
+                }
+            }
+            (None, None, Some(filename)) => match jstime.import(&filename) {
+                Ok(_) => jstime.exit_code(),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    1
+                }
+            },
+            (None, None, None) => unreachable!(),
+        };
+
+        if opt.cpu_prof {
+            if let Err(e) = write_cpu_profile(&mut jstime, opt.cpu_prof_dir) {
+                eprintln!("Failed to write CPU profile: {}", e);
+            }
+        }
+        if let Some(dir) = opt.coverage {
+            if let Err(e) = write_coverage(&mut jstime, dir) {
+                eprintln!("Failed to write coverage: {}", e);
+            }
+        }
+        if opt.heap_snapshot_on_exit {
+            if let Err(e) = jstime.write_heap_snapshot(heap_snapshot_path()) {
+                eprintln!("Failed to write heap snapshot: {}", e);
+            }
+        }
+        // Let DevTools clients see the session end.
+        drop(jstime);
+        std::process::exit(code);
     } else {
         repl(jstime);
     }
 }
 
+fn write_cpu_profile(jstime: &mut jstime::JSTime, dir: Option<PathBuf>) -> Result<(), String> {
+    let profile = jstime.stop_cpu_profiling()?;
+    let dir = dir.unwrap_or_else(|| PathBuf::from("."));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let path = dir.join(format!(
+        "CPU.{}.{}.cpuprofile",
+        now.as_millis(),
+        process::id()
+    ));
+    std::fs::write(&path, profile).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn write_coverage(jstime: &mut jstime::JSTime, dir: PathBuf) -> Result<(), String> {
+    let coverage = jstime.stop_coverage()?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let path = dir.join(format!(
+        "coverage-{}-{}.json",
+        process::id(),
+        now.as_millis()
+    ));
+    std::fs::write(&path, coverage).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn coverage_report(dir: &Path, lcov: Option<PathBuf>) -> i32 {
+    let report = match jstime::coverage::Report::from_dir(dir) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let lcov = lcov.unwrap_or_else(|| dir.join("lcov.info"));
+    if let Err(e) = std::fs::write(&lcov, report.to_lcov()) {
+        eprintln!("{}: {}", lcov.display(), e);
+        return 1;
+    }
+    print!("{}", report.summary());
+    0
+}
+
+fn compile(entry: &str, output: Option<PathBuf>, permissions: PermissionOpt) -> i32 {
+    let output = output.unwrap_or_else(|| {
+        let stem = Path::new(entry).file_stem().unwrap_or_default();
+        PathBuf::from(format!(
+            "{}{}",
+            stem.to_string_lossy(),
+            env::consts::EXE_SUFFIX
+        ))
+    });
+    if output == Path::new(entry) {
+        eprintln!("Refusing to overwrite {}, pass -o", entry);
+        return 1;
+    }
+
+    let bundle = match bundle(entry, &jstime::BundleOptions::default()) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    let permissions = permissions.to_args().join("\n");
+    if let Err(e) = write_executable(&output, &bundle.to_bytes(), permissions.as_bytes()) {
+        eprintln!("{}: {}", output.display(), e);
+        return 1;
+    }
+    0
+}
+
+fn pack(entry: &str, output: Option<PathBuf>, code_cache: bool) -> i32 {
+    let output = output.unwrap_or_else(|| Path::new(entry).with_extension("jspack"));
+    let options = jstime::BundleOptions { code_cache };
+    let bundle = match bundle(entry, &options) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    if let Err(e) = std::fs::write(&output, bundle.to_bytes()) {
+        eprintln!("{}: {}", output.display(), e);
+        return 1;
+    }
+    0
+}
+
+fn bundle(entry: &str, options: &jstime::BundleOptions) -> Result<jstime::Bundle, String> {
+    jstime::init(None);
+    let mut jstime = jstime::JSTime::new(jstime::Options::new(Some(SNAPSHOT)));
+    jstime.bundle(entry, options)
+}
+
+/// Write a copy of this executable, which includes the runtime and the
+/// builtins snapshot, with `bundle` and the `--allow-*` flags for running it
+/// appended.
+fn write_executable(output: &Path, bundle: &[u8], permissions: &[u8]) -> std::io::Result<()> {
+    std::fs::copy(env::current_exe()?, output)?;
+    let mut file = std::fs::OpenOptions::new().append(true).open(output)?;
+    file.write_all(bundle)?;
+    file.write_all(permissions)?;
+    file.write_all(&(bundle.len() as u64).to_le_bytes())?;
+    file.write_all(&(permissions.len() as u64).to_le_bytes())?;
+    file.write_all(BUNDLE_MARKER)
+}
+
+/// The bundle appended to this executable by `jstime compile`, and what it
+/// may access, if any.
+fn embedded_bundle() -> Option<(jstime::Bundle, jstime::Permissions)> {
+    const TRAILER_LEN: u64 = 24;
+
+    let mut file = std::fs::File::open(env::current_exe().ok()?).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    if file_len < TRAILER_LEN {
+        return None;
+    }
+    let mut bundle_len = [0; 8];
+    let mut permissions_len = [0; 8];
+    let mut marker = [0; 8];
+    file.seek(SeekFrom::Start(file_len - TRAILER_LEN)).ok()?;
+    file.read_exact(&mut bundle_len).ok()?;
+    file.read_exact(&mut permissions_len).ok()?;
+    file.read_exact(&mut marker).ok()?;
+    if &marker != BUNDLE_MARKER {
+        return None;
+    }
+
+    // The lengths are only trusted once they're known to fit in the file,
+    // before anything is allocated for them.
+    let bundle_len = u64::from_le_bytes(bundle_len);
+    let permissions_len = u64::from_le_bytes(permissions_len);
+    let appended_len = bundle_len
+        .checked_add(permissions_len)?
+        .checked_add(TRAILER_LEN)?;
+    if appended_len > file_len {
+        return None;
+    }
+    file.seek(SeekFrom::Start(file_len - appended_len)).ok()?;
+    let mut bundle = vec![0; bundle_len as usize];
+    file.read_exact(&mut bundle).ok()?;
+    let mut permissions = vec![0; permissions_len as usize];
+    file.read_exact(&mut permissions).ok()?;
+
+    let bundle = match jstime::Bundle::from_bytes(&bundle) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let permissions = String::from_utf8_lossy(&permissions);
+    let args = permissions.lines().filter(|arg| !arg.is_empty());
+    let permissions = PermissionOpt::from_iter(std::iter::once("jstime").chain(args));
+    Some((bundle, permissions.permissions()))
+}
+
+fn run_bundle(bundle: jstime::Bundle, permissions: jstime::Permissions, args: Vec<String>) -> i32 {
+    jstime::init(None);
+    let entry = bundle.entry().to_owned();
+    let mut options = jstime::Options::new(Some(SNAPSHOT));
+    options.bundle = Some(bundle);
+    options.permissions = permissions;
+    options.args = args;
+    let mut jstime = jstime::JSTime::new(options);
+    match jstime.import(&entry) {
+        Ok(_) => jstime.exit_code(),
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn run_tests(jstime: &mut jstime::JSTime, opt: TestOpt) -> i32 {
+    let files = match find_files(opt.paths, &[".test.js", ".test.mjs"]) {
+        Ok(files) if files.is_empty() => {
+            eprintln!("No test files found");
+            return 1;
+        }
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let options = jstime::TestOptions {
+        reporter: opt.reporter,
+        timeout: Duration::from_millis(opt.timeout),
+    };
+    match jstime.run_tests(&files, &options) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn run_benchmarks(jstime: &mut jstime::JSTime, opt: BenchOpt) -> i32 {
+    let files = match find_files(opt.paths, &[".bench.js", ".bench.mjs"]) {
+        Ok(files) if files.is_empty() => {
+            eprintln!("No benchmark files found");
+            return 1;
+        }
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let baseline = match &opt.baseline {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(baseline) => Some(baseline),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                return 1;
+            }
+        },
+        None => None,
+    };
+    let options = jstime::BenchOptions {
+        warmup: Duration::from_millis(opt.warmup),
+        time: Duration::from_millis(opt.time),
+        baseline,
+        threshold: opt.threshold,
+    };
+    let results = match jstime.run_benchmarks(&files, &options) {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    if let Some(path) = opt.save {
+        if let Err(e) = std::fs::write(&path, &results.json) {
+            eprintln!("{}: {}", path.display(), e);
+            return 1;
+        }
+    }
+    if results.regressions.is_empty() && results.failures.is_empty() {
+        0
+    } else {
+        1
+    }
+}
+
+/// Collect the files in `paths` whose names end in one of `suffixes`,
+/// searching the current directory if there are no paths.
+fn find_files(paths: Vec<PathBuf>, suffixes: &[&str]) -> Result<Vec<String>, String> {
+    let paths = if paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        paths
+    };
+    let mut files = Vec::new();
+    for path in &paths {
+        find_files_in(path, suffixes, &mut files)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    Ok(files)
+}
+
+/// Collect matching files under `path`, skipping hidden directories and
+/// node_modules, or `path` itself if it's a file.
+fn find_files_in(path: &Path, suffixes: &[&str], files: &mut Vec<String>) -> std::io::Result<()> {
+    if !std::fs::metadata(path)?.is_dir() {
+        files.push(path.to_string_lossy().into_owned());
+        return Ok(());
+    }
+
+    let mut entries = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy();
+        if entry.is_dir() {
+            if !name.starts_with('.') && name != "node_modules" {
+                find_files_in(&entry, suffixes, files)?;
+            }
+        } else if suffixes.iter().any(|suffix| name.ends_with(suffix)) {
+            // Report ./a.test.js as a.test.js.
+            let entry = entry.strip_prefix(".").unwrap_or(&entry);
+            files.push(entry.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+fn heap_snapshot_path() -> PathBuf {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    PathBuf::from(format!(
+        "Heap.{}.{}.heapsnapshot",
+        now.as_millis(),
+        process::id()
+    ))
+}
+
+/// Request a heap snapshot each time `signal`, a name like SIGUSR2 or a
+/// number, is received.
+#[cfg(unix)]
+fn heap_snapshot_on_signal(jstime: &mut jstime::JSTime, signal: &str) -> Result<(), String> {
+    use signal_hook::{consts::FORBIDDEN, iterator::Signals, low_level::signal_name};
+
+    let name = signal.to_ascii_uppercase();
+    let name = if name.starts_with("SIG") {
+        name
+    } else {
+        format!("SIG{}", name)
+    };
+    let number = match signal.parse() {
+        Ok(number) => number,
+        Err(_) => (1..32)
+            .find(|&n| signal_name(n) == Some(&name))
+            .ok_or("unknown signal")?,
+    };
+
+    if FORBIDDEN.contains(&number) {
+        return Err("signal can't be handled".to_owned());
+    }
+
+    let mut signals = Signals::new([number]).map_err(|e| e.to_string())?;
+    let requester = jstime.heap_snapshot_requester();
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            requester.request(heap_snapshot_path());
+        }
+    });
+    Ok(())
+}
+
+/// Parse `host:port`, `port` or `host`, defaulting to 127.0.0.1:9229.
+fn inspect_address(value: Option<&str>) -> Option<SocketAddr> {
+    let (host, port) = match value {
+        None => ("127.0.0.1", "9229"),
+        Some(value) => match value.rsplit_once(':') {
+            Some((host, port)) => (host.trim_start_matches('[').trim_end_matches(']'), port),
+            None if value.parse::<u16>().is_ok() => ("127.0.0.1", value),
+            None => (value, "9229"),
+        },
+    };
+    let port = port.parse::<u16>().ok()?;
+    (host, port).to_socket_addrs().ok()?.next()
+}
+
 fn repl(mut jstime: jstime::JSTime) {
     use dirs::home_dir;
     use rustyline::{error::ReadlineError, Editor};
@@ -0,0 +1,9 @@
+import bench from 'jstime:bench';
+
+bench('sum', () => {
+  let sum = 0;
+  for (let i = 0; i < 100; i += 1) sum += i;
+  return sum;
+});
+
+bench.skip('skipped', () => {});
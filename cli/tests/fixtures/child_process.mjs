@@ -0,0 +1,26 @@
+import { output, outputSync, spawn } from 'jstime:child_process';
+import { env } from 'jstime:process';
+
+const decoder = new TextDecoder();
+
+const sync = outputSync('sh', { args: ['-c', 'echo sync; exit 3'] });
+console.log(decoder.decode(sync.stdout).trim(), sync.code, sync.success);
+
+const result = await output('sh', { args: ['-c', 'echo out; echo err >&2'] });
+console.log(decoder.decode(result.stdout).trim(), decoder.decode(result.stderr).trim());
+
+const cat = spawn('cat', { stdin: 'piped', stdout: 'piped' });
+await cat.stdin.write('piped');
+cat.stdin.close();
+const { stdout, code } = await cat.output();
+console.log(decoder.decode(stdout), code);
+
+const sleep = spawn('sleep', { args: ['10'] });
+sleep.kill();
+const { signal } = await sleep.status;
+console.log(signal);
+
+// Variables set through jstime:process are passed on.
+env.set('JSTIME_CHILD_VAR', 'inherited');
+const inherited = outputSync('sh', { args: ['-c', 'echo $JSTIME_CHILD_VAR'] });
+console.log(decoder.decode(inherited.stdout).trim());
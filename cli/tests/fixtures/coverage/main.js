@@ -0,0 +1,9 @@
+function covered() {
+  return 'covered';
+}
+
+function uncovered() {
+  return 'uncovered';
+}
+
+console.log(covered());
@@ -0,0 +1,5 @@
+import { args, exit } from 'jstime:process';
+
+console.log(args.join(' '));
+exit(2);
+console.log('not reached');
@@ -0,0 +1,5 @@
+import { readTextFile, readTextFileSync } from 'jstime:fs';
+
+const path = './tests/fixtures/fs/hello.txt';
+console.log(readTextFileSync(path).trim());
+console.log((await readTextFile(path)).trim());
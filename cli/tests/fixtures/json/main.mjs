@@ -0,0 +1,4 @@
+import data from './data.json';
+import {hello} from '../deep/hello.mjs';
+
+console.log(`${hello} ${data.name} ${data.versions.length}`);
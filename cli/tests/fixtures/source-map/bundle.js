@@ -0,0 +1,6 @@
+"use strict";
+function fail() {
+  throw new Error("oh no, mapped");
+}
+fail();
+//# sourceMappingURL=bundle.js.map
@@ -0,0 +1,9 @@
+const { stdin, stdout, stderr } = jstime;
+
+const lines = [];
+for await (const line of stdin.lines()) {
+  lines.push(line);
+}
+await stdout.write(lines.join(','));
+await stdout.write(new Uint8Array([0x21, 0x0a]));
+stderr.writeSync(`${stdin.isTerminal()} ${stdout.isTerminal()}`);
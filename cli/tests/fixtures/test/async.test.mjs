@@ -0,0 +1,11 @@
+import { test } from 'jstime:test';
+
+test('resolves', async () => {
+  await new Promise((resolve) => setTimeout(resolve, 5));
+});
+
+test('rejects', async () => {
+  throw new Error('expected 1 to equal 2');
+});
+
+test('hangs', () => new Promise(() => {}));
@@ -0,0 +1,15 @@
+import { test, describe, beforeEach } from 'jstime:test';
+
+let total;
+beforeEach(() => {
+  total = 0;
+});
+
+describe('add', () => {
+  test('adds numbers', () => {
+    total += 1 + 2;
+    if (total !== 3) throw new Error(`expected 3, got ${total}`);
+  });
+
+  test.skip('adds strings', () => {});
+});
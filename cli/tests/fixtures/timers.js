@@ -0,0 +1,6 @@
+setTimeout(() => console.log(3), 20);
+const id = setTimeout(() => console.log('cleared'), 10);
+setTimeout(() => console.log(2), 10);
+clearTimeout(id);
+setTimeout(() => console.log(1));
+console.log(0);
@@ -0,0 +1,5 @@
+await Promise.resolve();
+
+export function double(n) {
+  return n * 2;
+}
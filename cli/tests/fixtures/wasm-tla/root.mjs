@@ -0,0 +1,3 @@
+import { quadruple } from './quadruple.wasm';
+
+console.log(quadruple(3));
@@ -0,0 +1,5 @@
+import { add } from './math.wasm';
+import { quadruple } from './quadruple.wasm';
+
+console.log(add(1, 2));
+console.log(quadruple(3));
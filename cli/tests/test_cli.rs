@@ -94,11 +94,77 @@ first second %s
 fn fetch() {
     Command::cargo_bin("jstime")
         .unwrap()
+        .arg("--allow-net=google.com")
         .arg("./tests/fixtures/fetch.js")
         .assert()
         .code(0);
 }
 
+#[test]
+fn fetch_permission_denied() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("--allow-net=example.com")
+        .arg("./tests/fixtures/fetch.js")
+        .assert()
+        .stderr(predicate::str::contains(
+            "PermissionDenied: Requires net access to \"google.com:80\"",
+        ))
+        .failure()
+        .code(1);
+}
+
+#[test]
+fn fs() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("--allow-read=./tests/fixtures/fs")
+        .arg("./tests/fixtures/fs/read.mjs")
+        .assert()
+        .stdout("hello from a file\nhello from a file\n")
+        .success()
+        .code(0);
+}
+
+#[test]
+fn fs_permission_denied() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("./tests/fixtures/fs/read.mjs")
+        .assert()
+        .stderr(predicate::str::contains(
+            "PermissionDenied: Requires read access to",
+        ))
+        .failure()
+        .code(1);
+}
+
+#[test]
+fn exit() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("./tests/fixtures/exit.mjs")
+        .arg("a")
+        .arg("--version")
+        .assert()
+        .stdout("a --version\n")
+        .failure()
+        .code(2);
+}
+
+#[test]
+fn stdio() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("./tests/fixtures/stdio.mjs")
+        .write_stdin("one\ntwo\r\nthree")
+        .assert()
+        .stdout("one,two,three!\n")
+        .stderr("false false")
+        .success()
+        .code(0);
+}
+
 #[test]
 fn top_level_await() {
     Command::cargo_bin("jstime")
@@ -139,3 +205,417 @@ end root
         .success()
         .code(0);
 }
+
+#[test]
+fn source_map() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("./tests/fixtures/source-map/bundle.js")
+        .assert()
+        .stderr(predicate::str::contains("at fail ("))
+        .stderr(predicate::str::contains("src/original.ts:4:9)"))
+        .failure()
+        .code(1);
+}
+
+#[test]
+fn no_source_maps() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("--no-source-maps")
+        .arg("./tests/fixtures/source-map/bundle.js")
+        .assert()
+        .stderr(predicate::str::contains("bundle.js:3:9)"))
+        .failure()
+        .code(1);
+}
+
+#[test]
+fn no_code_cache() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("--no-code-cache")
+        .arg("./tests/fixtures/module.mjs")
+        .assert()
+        .stdout("This should only be logged once.\nhello world exactly 1 time\n")
+        .success()
+        .code(0);
+}
+
+#[test]
+fn wasm_modules() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("./tests/fixtures/wasm/root.mjs")
+        .assert()
+        .stdout("3\n12\n")
+        .success()
+        .code(0);
+}
+
+#[test]
+fn wasm_import_with_top_level_await() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("./tests/fixtures/wasm-tla/root.mjs")
+        .assert()
+        .stdout("12\n")
+        .success()
+        .code(0);
+}
+
+#[test]
+fn wasi_command() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("./tests/fixtures/wasi/hello.wasm")
+        .assert()
+        .stdout("hello from wasi\n")
+        .failure()
+        .code(7);
+}
+
+#[test]
+fn inspect() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("--inspect=127.0.0.1:0")
+        .arg("./tests/fixtures/queue-microtask.js")
+        .assert()
+        .stdout("0\n1\n2\n3\n4\n5\n")
+        .stderr(predicate::str::contains(
+            "Debugger listening on ws://127.0.0.1:",
+        ))
+        .success()
+        .code(0);
+}
+
+#[test]
+fn cpu_prof() {
+    let dir = std::env::temp_dir().join(format!("jstime-cpu-prof-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("--cpu-prof")
+        .arg("--cpu-prof-dir")
+        .arg(&dir)
+        .arg("./tests/fixtures/queue-microtask.js")
+        .assert()
+        .stdout("0\n1\n2\n3\n4\n5\n")
+        .success()
+        .code(0);
+
+    let profiles = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    assert_eq!(profiles.len(), 1);
+    assert_eq!(profiles[0].extension().unwrap(), "cpuprofile");
+    let profile = std::fs::read_to_string(&profiles[0]).unwrap();
+    assert!(profile.contains("\"nodes\""));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn heap_snapshot_on_exit() {
+    let dir = std::env::temp_dir().join(format!("jstime-heap-snapshot-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir(&dir).unwrap();
+    let fixture = std::fs::canonicalize("./tests/fixtures/queue-microtask.js").unwrap();
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .current_dir(&dir)
+        .arg("--heap-snapshot-on-exit")
+        .arg(fixture)
+        .assert()
+        .stdout("0\n1\n2\n3\n4\n5\n")
+        .success()
+        .code(0);
+
+    let snapshots = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].extension().unwrap(), "heapsnapshot");
+    let snapshot = std::fs::read_to_string(&snapshots[0]).unwrap();
+    assert!(snapshot.starts_with("{\"snapshot\":"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn coverage() {
+    let dir = std::env::temp_dir().join(format!("jstime-coverage-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("--coverage")
+        .arg(&dir)
+        .arg("./tests/fixtures/coverage/main.js")
+        .assert()
+        .stdout("covered\n")
+        .success()
+        .code(0);
+
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("coverage")
+        .arg(&dir)
+        .assert()
+        .stdout(predicate::str::contains(
+            "tests/fixtures/coverage/main.js | 57.14% (4/7) | 50.00% (1/2)",
+        ))
+        .stdout(predicate::str::contains(
+            "All files                       | 57.14% (4/7) | 50.00% (1/2)",
+        ))
+        .success()
+        .code(0);
+
+    let lcov = std::fs::read_to_string(dir.join("lcov.info")).unwrap();
+    assert!(lcov.contains("FNDA:1,covered\nFNDA:0,uncovered\n"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn timers() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("./tests/fixtures/timers.js")
+        .assert()
+        .stdout("0\n1\n2\n3\n")
+        .success()
+        .code(0);
+}
+
+#[test]
+fn test_passing() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("test")
+        .arg("tests/fixtures/test/math.test.js")
+        .assert()
+        .stdout(predicate::str::starts_with(
+            "tests/fixtures/test/math.test.js\n  add\n    ✓ adds numbers\n    - adds strings (skipped)\n\n2 tests: 1 passed, 1 skipped (",
+        ))
+        .success()
+        .code(0);
+}
+
+#[test]
+fn test_failing() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("test")
+        .arg("--timeout=50")
+        .arg("tests/fixtures/test")
+        .assert()
+        .stdout(predicate::str::contains(
+            "tests/fixtures/test/async.test.mjs\n  ✓ resolves\n  ✗ rejects (1)\n  ✗ hangs (2)\n",
+        ))
+        .stdout(predicate::str::contains(
+            "1) tests/fixtures/test/async.test.mjs > rejects\n   Error: expected 1 to equal 2\n",
+        ))
+        .stdout(predicate::str::contains(
+            "2) tests/fixtures/test/async.test.mjs > hangs\n   Error: Timed out after 50ms\n",
+        ))
+        .stdout(predicate::str::contains(
+            "5 tests: 2 passed, 2 failed, 1 skipped (",
+        ))
+        .failure()
+        .code(1);
+}
+
+#[test]
+fn test_tap() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("test")
+        .arg("--reporter=tap")
+        .arg("tests/fixtures/test/math.test.js")
+        .assert()
+        .stdout(
+            "TAP version 13\n\
+             ok 1 - tests/fixtures/test/math.test.js > add > adds numbers\n\
+             ok 2 - tests/fixtures/test/math.test.js > add > adds strings # SKIP\n\
+             1..2\n# tests 2\n# pass 1\n# fail 0\n# skip 1\n",
+        )
+        .success()
+        .code(0);
+}
+
+#[test]
+fn test_junit() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("test")
+        .arg("--reporter=junit")
+        .arg("tests/fixtures/test/math.test.js")
+        .assert()
+        .stdout(predicate::str::contains(
+            "<testsuites name=\"jstime test\" tests=\"2\" failures=\"0\" skipped=\"1\"",
+        ))
+        .stdout(predicate::str::contains(
+            "<testcase classname=\"tests/fixtures/test/math.test.js\" name=\"add &#62; adds strings\" time=\"0.000\">\n      <skipped/>\n    </testcase>",
+        ))
+        .success()
+        .code(0);
+}
+
+#[test]
+fn test_no_files() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("test")
+        .arg("tests/fixtures/wasm")
+        .assert()
+        .stderr("No test files found\n")
+        .failure()
+        .code(1);
+}
+
+#[test]
+fn bench() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .args(&["bench", "--warmup=1", "--time=10"])
+        .arg("tests/fixtures/bench/math.bench.js")
+        .assert()
+        .stdout(predicate::str::starts_with(
+            "tests/fixtures/bench/math.bench.js\n  benchmark            mean        median           p99       ops/sec       samples\n  sum      ",
+        ))
+        .stdout(predicate::str::contains("  skipped           skipped\n"))
+        .success()
+        .code(0);
+}
+
+#[test]
+fn bench_regression() {
+    let dir = std::env::temp_dir().join(format!("jstime-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let save = dir.join("results.json");
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .args(&["bench", "--warmup=1", "--time=10"])
+        .arg("--baseline=tests/fixtures/bench/baseline.json")
+        .arg("--save")
+        .arg(&save)
+        .arg("tests/fixtures/bench/math.bench.js")
+        .assert()
+        .stdout(predicate::str::contains("% regressed\n"))
+        .stdout(predicate::str::contains(
+            "1 of 1 benchmarks regressed by more than 10%\n",
+        ))
+        .failure()
+        .code(1);
+
+    let results = std::fs::read_to_string(&save).unwrap();
+    assert!(results.contains("\"tests/fixtures/bench/math.bench.js > sum\": {"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compile() {
+    let dir = std::env::temp_dir().join(format!("jstime-compile-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("module");
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .args(&["compile", "./tests/fixtures/module.mjs", "-o"])
+        .arg(&output)
+        .assert()
+        .success()
+        .code(0);
+
+    // Arguments are left to the script, not parsed as options.
+    Command::new(&output)
+        .arg("--version")
+        .current_dir(&dir)
+        .assert()
+        .stdout("This should only be logged once.\nhello world exactly 1 time\n")
+        .success()
+        .code(0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn json_modules() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("./tests/fixtures/json/main.mjs")
+        .assert()
+        .stdout("hello jstime 3\n")
+        .success()
+        .code(0);
+}
+
+#[test]
+fn pack() {
+    let dir = std::env::temp_dir().join(format!("jstime-pack-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = dir.join("app.jspack");
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .args(&[
+            "pack",
+            "./tests/fixtures/json/main.mjs",
+            "--code-cache",
+            "-o",
+        ])
+        .arg(&output)
+        .assert()
+        .success()
+        .code(0);
+
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg(&output)
+        .current_dir(&dir)
+        .assert()
+        .stdout("hello jstime 3\n")
+        .success()
+        .code(0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn pack_invalid() {
+    let dir = std::env::temp_dir().join(format!("jstime-pack-invalid-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let pack = dir.join("invalid.jspack");
+    std::fs::write(&pack, "console.log('not a pack');\n").unwrap();
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg(&pack)
+        .assert()
+        .stderr(predicate::str::contains("Not a jstime bundle"))
+        .failure()
+        .code(1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn child_process() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("--allow-run")
+        .arg("--allow-env")
+        .arg("./tests/fixtures/child_process.mjs")
+        .assert()
+        .stdout("sync 3 false\nout err\npiped 0\nSIGTERM\ninherited\n")
+        .success()
+        .code(0);
+}
+
+#[test]
+fn child_process_permission_denied() {
+    Command::cargo_bin("jstime")
+        .unwrap()
+        .arg("./tests/fixtures/child_process.mjs")
+        .assert()
+        .stderr(predicate::str::contains(
+            "PermissionDenied: Requires run access to",
+        ))
+        .failure()
+        .code(1);
+}
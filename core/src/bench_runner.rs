@@ -0,0 +1,97 @@
+// Glue between `JSTime::run_benchmarks` and the runner in builtins/bench.js.
+
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// Options for `JSTime::run_benchmarks`.
+pub struct BenchOptions {
+    /// How long to run each benchmark before measuring it, unless it sets
+    /// its own. Defaults to 100 milliseconds.
+    pub warmup: Duration,
+    /// How long to measure each benchmark for, unless it sets its own.
+    /// Defaults to 500 milliseconds.
+    pub time: Duration,
+    /// Results of an earlier run, as in `BenchResults::json`, to compare
+    /// against.
+    pub baseline: Option<String>,
+    /// How many percent slower than in the baseline a benchmark's median
+    /// time may get before it counts as a regression. Defaults to 10.
+    pub threshold: f64,
+}
+
+impl Default for BenchOptions {
+    fn default() -> BenchOptions {
+        BenchOptions {
+            warmup: Duration::from_millis(100),
+            time: Duration::from_millis(500),
+            baseline: None,
+            threshold: 10.0,
+        }
+    }
+}
+
+/// The outcome of `JSTime::run_benchmarks`.
+pub struct BenchResults {
+    /// Statistics of each benchmark, keyed by file and name, to save as a
+    /// baseline for later runs.
+    pub json: String,
+    /// Benchmarks that got slower than the baseline allows.
+    pub regressions: Vec<String>,
+    /// Benchmarks that threw, or files that failed to import.
+    pub failures: Vec<String>,
+}
+
+/// Start running the benchmarks registered through `jstime:bench`,
+/// returning a promise of their results.
+pub(crate) fn run<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    options: &BenchOptions,
+) -> Result<v8::Local<'a, v8::Value>, String> {
+    let object = v8::Object::new(scope);
+    let key = v8::String::new(scope, "warmup").unwrap();
+    let value = v8::Number::new(scope, options.warmup.as_secs_f64() * 1e3);
+    object.set(scope, key.into(), value.into());
+    let key = v8::String::new(scope, "time").unwrap();
+    let value = v8::Number::new(scope, options.time.as_secs_f64() * 1e3);
+    object.set(scope, key.into(), value.into());
+    if let Some(baseline) = &options.baseline {
+        let key = v8::String::new(scope, "baseline").unwrap();
+        let value = v8::String::new(scope, baseline).unwrap();
+        object.set(scope, key.into(), value.into());
+    }
+    let key = v8::String::new(scope, "threshold").unwrap();
+    let value = v8::Number::new(scope, options.threshold);
+    object.set(scope, key.into(), value.into());
+    crate::builtins::call_internal(scope, "bench", "run", &[object.into()])
+}
+
+/// Convert what the runner resolved to.
+pub(crate) fn results(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> BenchResults {
+    let object = v8::Local::<v8::Object>::try_from(value).unwrap();
+
+    let key = v8::String::new(scope, "json").unwrap();
+    let json = object.get(scope, key.into()).unwrap();
+    let json = json.to_rust_string_lossy(scope);
+
+    let mut names = |key: &str| {
+        let key = v8::String::new(scope, key).unwrap();
+        let array = object.get(scope, key.into()).unwrap();
+        let array = v8::Local::<v8::Array>::try_from(array).unwrap();
+        (0..array.length())
+            .map(|i| {
+                array
+                    .get_index(scope, i)
+                    .unwrap()
+                    .to_rust_string_lossy(scope)
+            })
+            .collect::<Vec<_>>()
+    };
+    let regressions = names("regressions");
+    let failures = names("failures");
+
+    BenchResults {
+        json,
+        regressions,
+        failures,
+    }
+}
@@ -0,0 +1,167 @@
+// AbortController and AbortSignal
+// https://dom.spec.whatwg.org/#aborting-ongoing-activities
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({ setUnrefTimeout, internals }) => {
+  const { events } = internals;
+
+  // Only AbortController and the static methods make signals.
+  const illegal = Symbol('illegal');
+
+  let signalAbort;
+  let createSignal;
+
+  class AbortSignal extends EventTarget {
+    #aborted = false;
+    #reason = undefined;
+    // The signals `AbortSignal.any` made from this one.
+    #dependents = [];
+    // What a signal `AbortSignal.any` made follows, or null for others.
+    #sources = null;
+    #onabort = null;
+    #onabortListening = false;
+
+    constructor(token = undefined) {
+      if (token !== illegal) {
+        throw new TypeError('Illegal constructor');
+      }
+      super();
+    }
+
+    static {
+      createSignal = () => new AbortSignal(illegal);
+
+      // Aborts the signal with `reason`, dispatching `abort`, unless it
+      // already was.
+      signalAbort = (signal, reason) => {
+        if (signal.#aborted) {
+          return;
+        }
+        signal.#aborted = true;
+        signal.#reason = reason === undefined
+          ? new DOMException('This operation was aborted', 'AbortError')
+          : reason;
+        // Dependents are aborted right away, but dispatch `abort` after
+        // the signal did.
+        const dependents = signal.#dependents.filter((dependent) => !dependent.#aborted);
+        signal.#dependents = [];
+        for (const dependent of dependents) {
+          dependent.#aborted = true;
+          dependent.#reason = signal.#reason;
+        }
+        events.dispatch(signal, new Event('abort'));
+        for (const dependent of dependents) {
+          events.dispatch(dependent, new Event('abort'));
+        }
+      };
+    }
+
+    static abort(reason = undefined) {
+      const signal = createSignal();
+      signalAbort(signal, reason);
+      return signal;
+    }
+
+    // Aborts with a "TimeoutError" after `milliseconds`. The timer doesn't
+    // keep jstime running.
+    static timeout(milliseconds) {
+      const delay = Number(milliseconds);
+      if (!Number.isFinite(delay) || delay < 0) {
+        throw new TypeError(`Invalid timeout ${milliseconds}`);
+      }
+      const signal = createSignal();
+      setUnrefTimeout(() => {
+        signalAbort(signal, new DOMException('The operation timed out', 'TimeoutError'));
+      }, delay);
+      return signal;
+    }
+
+    // Aborts as soon as any of `signals` does, for the same reason.
+    static any(signals) {
+      const sources = [...signals];
+      for (const source of sources) {
+        if (!(source instanceof AbortSignal)) {
+          throw new TypeError('signals must be AbortSignals');
+        }
+      }
+      const signal = createSignal();
+      const aborted = sources.find((source) => source.aborted);
+      if (aborted !== undefined) {
+        signalAbort(signal, aborted.reason);
+        return signal;
+      }
+      // Signals that follow others are never aborted by themselves, so the
+      // new one follows what they follow instead.
+      signal.#sources = [];
+      for (const source of sources) {
+        for (const root of source.#sources ?? [source]) {
+          if (!signal.#sources.includes(root)) {
+            signal.#sources.push(root);
+            root.#dependents.push(signal);
+          }
+        }
+      }
+      return signal;
+    }
+
+    get aborted() {
+      return this.#aborted;
+    }
+
+    get reason() {
+      return this.#reason;
+    }
+
+    throwIfAborted() {
+      if (this.#aborted) {
+        throw this.#reason;
+      }
+    }
+
+    get onabort() {
+      return this.#onabort;
+    }
+
+    // Like an event handler attribute, the handler is called in the order
+    // it was first set relative to the listeners.
+    set onabort(value) {
+      const handler = typeof value === 'function' ? value : null;
+      if (handler !== null && !this.#onabortListening) {
+        this.#onabortListening = true;
+        this.addEventListener('abort', (event) => {
+          if (this.#onabort !== null) {
+            this.#onabort.call(this, event);
+          }
+        });
+      }
+      this.#onabort = handler;
+    }
+  }
+
+  Object.defineProperty(AbortSignal.prototype, Symbol.toStringTag, {
+    value: 'AbortSignal',
+    configurable: true,
+  });
+
+  class AbortController {
+    #signal = createSignal();
+
+    get signal() {
+      return this.#signal;
+    }
+
+    abort(reason = undefined) {
+      signalAbort(this.#signal, reason);
+    }
+  }
+
+  Object.defineProperty(AbortController.prototype, Symbol.toStringTag, {
+    value: 'AbortController',
+    configurable: true,
+  });
+
+  globalThis.AbortController = AbortController;
+  globalThis.AbortSignal = AbortSignal;
+});
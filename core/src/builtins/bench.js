@@ -0,0 +1,268 @@
+// The jstime:bench module, and the runner behind `jstime bench`.
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({
+  builtinModules, internals, printer, perfNow,
+}) => {
+  // Benchmarks registered outside of `jstime bench` end up here, and
+  // never run.
+  let current = { name: undefined, benchmarks: [] };
+  const files = [];
+  let hasOnly = false;
+  let running = false;
+
+  function parseArgs(name, options, fn) {
+    if (typeof options === 'function') {
+      fn = options;
+      options = {};
+    }
+    if (typeof name !== 'string') {
+      throw new TypeError('The name must be a string');
+    }
+    if (typeof fn !== 'function') {
+      throw new TypeError('The benchmark must be a function');
+    }
+    if (running) {
+      throw new Error('Benchmarks must be registered before benchmarks run');
+    }
+    return { name, options: { ...options }, fn };
+  }
+
+  function add({ name, options, fn }) {
+    hasOnly = hasOnly || Boolean(options.only);
+    current.benchmarks.push({
+      name,
+      fn,
+      only: Boolean(options.only),
+      skip: Boolean(options.skip),
+      warmup: options.warmup,
+      time: options.time,
+    });
+  }
+
+  function bench(name, options, fn) {
+    add(parseArgs(name, options, fn));
+  }
+  bench.only = (name, options, fn) => {
+    const args = parseArgs(name, options, fn);
+    args.options.only = true;
+    add(args);
+  };
+  bench.skip = (name, options, fn) => {
+    const args = parseArgs(name, options, fn);
+    args.options.skip = true;
+    add(args);
+  };
+
+  builtinModules['jstime:bench'] = {
+    default: bench,
+    bench,
+  };
+
+  // Measuring
+
+  // Shorter batches would mostly measure the clock.
+  const MIN_BATCH_TIME = 1;
+  const MIN_SAMPLES = 10;
+
+  async function runBatch(fn, iterations, isAsync) {
+    const start = perfNow();
+    if (isAsync) {
+      for (let i = 0; i < iterations; i += 1) {
+        await fn();
+      }
+    } else {
+      for (let i = 0; i < iterations; i += 1) {
+        fn();
+      }
+    }
+    return perfNow() - start;
+  }
+
+  // Returns the time per iteration of each batch, in milliseconds.
+  async function measure(b, options) {
+    const warmup = b.warmup === undefined ? options.warmup : b.warmup;
+    const time = b.time === undefined ? options.time : b.time;
+
+    // The first call tells whether the benchmark is async.
+    const first = b.fn();
+    const isAsync = first !== null && typeof first === 'object' && typeof first.then === 'function';
+    await first;
+
+    // Warm up, growing batches until they take long enough to time.
+    let iterations = 1;
+    const warmupStart = perfNow();
+    for (;;) {
+      const elapsed = await runBatch(b.fn, iterations, isAsync);
+      if (elapsed >= MIN_BATCH_TIME && perfNow() - warmupStart >= warmup) {
+        break;
+      }
+      if (elapsed < MIN_BATCH_TIME) {
+        iterations *= 2;
+      }
+    }
+
+    const samples = [];
+    const start = perfNow();
+    while (samples.length < MIN_SAMPLES || perfNow() - start < time) {
+      samples.push((await runBatch(b.fn, iterations, isAsync)) / iterations);
+    }
+    return samples;
+  }
+
+  function statistics(samples) {
+    const sorted = [...samples].sort((a, b) => a - b);
+    const { length } = sorted;
+    const mean = sorted.reduce((sum, sample) => sum + sample, 0) / length;
+    const median = length % 2 === 1
+      ? sorted[(length - 1) / 2]
+      : (sorted[length / 2 - 1] + sorted[length / 2]) / 2;
+    return {
+      mean,
+      median,
+      p99: sorted[Math.ceil(length * 0.99) - 1],
+      min: sorted[0],
+      max: sorted[length - 1],
+      opsPerSec: 1000 / mean,
+      samples: length,
+    };
+  }
+
+  // Reporting
+
+  function formatTime(ms) {
+    const ns = ms * 1e6;
+    if (ns < 1e3) {
+      return `${ns.toFixed(2)}ns`;
+    }
+    if (ns < 1e6) {
+      return `${(ns / 1e3).toFixed(2)}µs`;
+    }
+    if (ns < 1e9) {
+      return `${(ns / 1e6).toFixed(2)}ms`;
+    }
+    return `${(ns / 1e9).toFixed(2)}s`;
+  }
+
+  function formatCount(n) {
+    return String(Math.round(n)).replace(/\B(?=(\d{3})+(?!\d))/g, ',');
+  }
+
+  function formatError(error) {
+    if (error instanceof Error && typeof error.stack === 'string') {
+      return error.stack
+        .split('\n')
+        .filter((line) => !/^\s+at (.* \()?\.\/bench\.js:\d+:\d+\)?$/.test(line))
+        .join('\n');
+    }
+    return String(error);
+  }
+
+  const COLUMNS = ['mean', 'median', 'p99', 'ops/sec', 'samples'];
+  const WIDTH = 14;
+
+  function row(name, width, cells) {
+    return `  ${name.padEnd(width)}${cells.map((cell) => `  ${cell.padStart(WIDTH - 2)}`).join('')}`;
+  }
+
+  internals.bench = {
+    // Benchmarks registered from here on belong to `file`.
+    setFile(file) {
+      current = { name: file, benchmarks: [] };
+      files.push(current);
+    },
+
+    // `file` couldn't be imported, report that as a failing benchmark.
+    loadError(file, error) {
+      current.benchmarks.push({
+        name: 'failed to load',
+        fn() {
+          throw error;
+        },
+        loadError: true,
+      });
+    },
+
+    // Run the registered benchmarks, resolving to their statistics, and
+    // which regressed or failed.
+    async run(options) {
+      running = true;
+      const baseline = options.baseline === undefined
+        ? {}
+        : JSON.parse(options.baseline).benchmarks || {};
+      const results = {};
+      const regressions = [];
+      const failures = [];
+      let compared = 0;
+
+      for (const file of files) {
+        printer(file.name);
+        const width = Math.max('benchmark'.length, ...file.benchmarks.map((b) => b.name.length)) + 2;
+        const header = options.baseline === undefined ? COLUMNS : [...COLUMNS, 'vs baseline'];
+        printer(row('benchmark', width, header));
+
+        for (const b of file.benchmarks) {
+          const key = `${file.name} > ${b.name}`;
+          if (!b.loadError && (b.skip || (hasOnly && !b.only))) {
+            printer(row(b.name, width, ['skipped']));
+            continue;
+          }
+
+          let stats;
+          try {
+            stats = statistics(await measure(b, options));
+          } catch (e) {
+            failures.push({ key, error: e });
+            printer(row(b.name, width, [`failed (${failures.length})`]));
+            continue;
+          }
+          results[key] = stats;
+
+          const cells = [
+            formatTime(stats.mean),
+            formatTime(stats.median),
+            formatTime(stats.p99),
+            formatCount(stats.opsPerSec),
+            formatCount(stats.samples),
+          ];
+          const base = baseline[key];
+          if (base && typeof base.median === 'number') {
+            compared += 1;
+            const change = ((stats.median - base.median) / base.median) * 100;
+            const sign = change >= 0 ? '+' : '';
+            let cell = `${sign}${change.toFixed(2)}%`;
+            if (change > options.threshold) {
+              regressions.push(key);
+              cell += ' regressed';
+            }
+            cells.push(cell);
+          } else if (options.baseline !== undefined) {
+            cells.push('new');
+          }
+          printer(row(b.name, width, cells));
+        }
+        printer('');
+      }
+
+      if (failures.length > 0) {
+        printer('Failures:');
+        failures.forEach(({ key, error }, i) => {
+          printer(`\n${i + 1}) ${key}`);
+          printer(formatError(error).split('\n').map((line) => `   ${line}`).join('\n'));
+        });
+        printer('');
+      }
+      if (options.baseline !== undefined) {
+        printer(`${regressions.length} of ${compared} benchmarks regressed by more than ${options.threshold}%`);
+      }
+
+      return {
+        json: JSON.stringify({ benchmarks: results }, null, 2),
+        regressions,
+        failures: failures.map(({ key }) => key),
+      };
+    },
+  };
+});
@@ -0,0 +1,151 @@
+// Blob and File
+// https://w3c.github.io/FileAPI/
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({ internals }) => {
+  const encoder = new TextEncoder();
+
+  function concat(chunks) {
+    const result = new Uint8Array(chunks.reduce((length, chunk) => length + chunk.length, 0));
+    let offset = 0;
+    for (const chunk of chunks) {
+      result.set(chunk, offset);
+      offset += chunk.length;
+    }
+    return result;
+  }
+
+  function convertLineEndings(s) {
+    return s.replace(/\r\n|\r/g, '\n');
+  }
+
+  // Types are lowercased, or empty if they aren't printable ASCII.
+  function normalizeType(type) {
+    const s = String(type);
+    return /^[\x20-\x7e]*$/.test(s) ? s.toLowerCase() : '';
+  }
+
+  let bytesOf;
+
+  class Blob {
+    #bytes;
+    #type;
+
+    constructor(blobParts = [], options = undefined) {
+      if (typeof blobParts !== 'object' || blobParts === null
+        || typeof blobParts[Symbol.iterator] !== 'function') {
+        throw new TypeError('blobParts must be a sequence');
+      }
+      const { type = '', endings = 'transparent' } = options ?? {};
+      if (endings !== 'transparent' && endings !== 'native') {
+        throw new TypeError(`Invalid endings "${endings}"`);
+      }
+      const chunks = [];
+      for (const part of blobParts) {
+        if (part instanceof Blob) {
+          chunks.push(part.#bytes);
+        } else if (part instanceof ArrayBuffer) {
+          chunks.push(new Uint8Array(part.slice(0)));
+        } else if (ArrayBuffer.isView(part)) {
+          chunks.push(new Uint8Array(
+            part.buffer.slice(part.byteOffset, part.byteOffset + part.byteLength),
+          ));
+        } else {
+          const s = String(part);
+          chunks.push(encoder.encode(endings === 'native' ? convertLineEndings(s) : s));
+        }
+      }
+      this.#bytes = concat(chunks);
+      this.#type = normalizeType(type);
+    }
+
+    static {
+      bytesOf = (blob) => blob.#bytes;
+    }
+
+    get size() {
+      return this.#bytes.length;
+    }
+
+    get type() {
+      return this.#type;
+    }
+
+    slice(start = 0, end = this.size, contentType = '') {
+      const { size } = this;
+      const relative = (n) => {
+        const i = Math.trunc(Number(n)) || 0;
+        return i < 0 ? Math.max(size + i, 0) : Math.min(i, size);
+      };
+      const blob = new Blob([], { type: contentType });
+      blob.#bytes = this.#bytes.slice(relative(start), Math.max(relative(end), relative(start)));
+      return blob;
+    }
+
+    stream() {
+      const bytes = this.#bytes;
+      return new ReadableStream({
+        type: 'bytes',
+        start(controller) {
+          if (bytes.length > 0) {
+            controller.enqueue(bytes.slice());
+          }
+          controller.close();
+        },
+      });
+    }
+
+    async text() {
+      return new TextDecoder().decode(this.#bytes);
+    }
+
+    async arrayBuffer() {
+      return this.#bytes.slice().buffer;
+    }
+
+    async bytes() {
+      return this.#bytes.slice();
+    }
+  }
+
+  Object.defineProperty(Blob.prototype, Symbol.toStringTag, {
+    value: 'Blob',
+    configurable: true,
+  });
+
+  class File extends Blob {
+    #name;
+    #lastModified;
+
+    constructor(fileBits, fileName, options = undefined) {
+      if (arguments.length < 2) {
+        throw new TypeError('File requires fileBits and fileName');
+      }
+      super(fileBits, options);
+      const { lastModified = Date.now() } = options ?? {};
+      this.#name = String(fileName);
+      this.#lastModified = Number(lastModified);
+    }
+
+    get name() {
+      return this.#name;
+    }
+
+    get lastModified() {
+      return this.#lastModified;
+    }
+  }
+
+  Object.defineProperty(File.prototype, Symbol.toStringTag, {
+    value: 'File',
+    configurable: true,
+  });
+
+  // The bytes of a blob without copying them, for fetch's bodies.
+  internals.blob = { bytes: bytesOf };
+
+  globalThis.Blob = Blob;
+  globalThis.File = File;
+});
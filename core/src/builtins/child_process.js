@@ -0,0 +1,167 @@
+// The jstime:child_process module.
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({
+  builtinModules,
+  childSpawn, childOutputSync, childKill, fsReadAsync, fsWriteAsync, fsClose,
+}) => {
+  const CHUNK_SIZE = 64 * 1024;
+
+  function concat(chunks) {
+    const result = new Uint8Array(chunks.reduce((length, chunk) => length + chunk.length, 0));
+    let offset = 0;
+    for (const chunk of chunks) {
+      result.set(chunk, offset);
+      offset += chunk.length;
+    }
+    return result;
+  }
+
+  // The reading end of a child's stdout or stderr.
+  class ChildReader {
+    #rid;
+
+    constructor(rid) {
+      this.#rid = rid;
+    }
+
+    // Reads into `buffer`, resolving to the number of bytes read, or null
+    // once the child closed its end.
+    async read(buffer) {
+      if (!ArrayBuffer.isView(buffer)) {
+        throw new TypeError('The buffer must be an ArrayBufferView');
+      }
+      const bytes = new Uint8Array(await fsReadAsync(this.#rid, buffer.byteLength));
+      new Uint8Array(buffer.buffer, buffer.byteOffset, buffer.byteLength).set(bytes);
+      return bytes.length === 0 && buffer.byteLength > 0 ? null : bytes.length;
+    }
+
+    async* [Symbol.asyncIterator]() {
+      for (;;) {
+        const chunk = new Uint8Array(await fsReadAsync(this.#rid, CHUNK_SIZE));
+        if (chunk.length === 0) {
+          return;
+        }
+        yield chunk;
+      }
+    }
+
+    // Reads until the child closes its end, and closes this one.
+    async readAll() {
+      const chunks = [];
+      for await (const chunk of this) {
+        chunks.push(chunk);
+      }
+      this.close();
+      return concat(chunks);
+    }
+
+    close() {
+      fsClose(this.#rid);
+    }
+  }
+
+  // The writing end of a child's stdin.
+  class ChildWriter {
+    #rid;
+
+    constructor(rid) {
+      this.#rid = rid;
+    }
+
+    // Writes all of `data`, a string or an ArrayBufferView, resolving to
+    // the number of bytes written.
+    async write(data) {
+      return fsWriteAsync(this.#rid, data);
+    }
+
+    // Closing stdin tells the child there's no more input.
+    close() {
+      fsClose(this.#rid);
+    }
+  }
+
+  function spawnOptions(options, defaults) {
+    const {
+      args = [], cwd, env, clearEnv = false, stdin, stdout, stderr,
+    } = options;
+    return {
+      args: Array.from(args, String),
+      cwd: cwd === undefined ? undefined : String(cwd),
+      env,
+      clearEnv: Boolean(clearEnv),
+      stdin: stdin || defaults.stdin,
+      stdout: stdout || defaults.stdout,
+      stderr: stderr || defaults.stderr,
+    };
+  }
+
+  const SPAWN_DEFAULTS = { stdin: 'inherit', stdout: 'inherit', stderr: 'inherit' };
+  const OUTPUT_DEFAULTS = { stdin: 'null', stdout: 'piped', stderr: 'piped' };
+
+  class ChildProcess {
+    #pid;
+
+    constructor({
+      pid, stdin, stdout, stderr, status,
+    }) {
+      this.#pid = pid;
+      this.stdin = stdin === null ? null : new ChildWriter(stdin);
+      this.stdout = stdout === null ? null : new ChildReader(stdout);
+      this.stderr = stderr === null ? null : new ChildReader(stderr);
+      // Resolves to `{ success, code, signal }` once the child exits.
+      this.status = status;
+    }
+
+    get pid() {
+      return this.#pid;
+    }
+
+    kill(signal = 'SIGTERM') {
+      childKill(this.#pid, String(signal));
+    }
+
+    // Resolves to the exit status, with what the child wrote to piped
+    // stdout and stderr, `null` for the others.
+    async output() {
+      const [status, stdout, stderr] = await Promise.all([
+        this.status,
+        this.stdout === null ? null : this.stdout.readAll(),
+        this.stderr === null ? null : this.stderr.readAll(),
+      ]);
+      return { ...status, stdout, stderr };
+    }
+  }
+
+  function spawn(command, options = {}) {
+    return new ChildProcess(childSpawn(String(command), spawnOptions(options, SPAWN_DEFAULTS)));
+  }
+
+  // Stdout and stderr are piped, and stdin is closed, unless set otherwise.
+  async function output(command, options = {}) {
+    const child = new ChildProcess(
+      childSpawn(String(command), spawnOptions(options, OUTPUT_DEFAULTS)),
+    );
+    return child.output();
+  }
+
+  function outputSync(command, options = {}) {
+    const result = childOutputSync(String(command), spawnOptions(options, OUTPUT_DEFAULTS));
+    return {
+      ...result,
+      stdout: new Uint8Array(result.stdout),
+      stderr: new Uint8Array(result.stderr),
+    };
+  }
+
+  const childProcess = {
+    ChildProcess,
+    spawn,
+    output,
+    outputSync,
+  };
+
+  builtinModules['jstime:child_process'] = { default: childProcess, ...childProcess };
+});
@@ -0,0 +1,348 @@
+// Bindings behind jstime:child_process.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::fs::{io_error, io_exception, Files};
+use super::ops;
+use crate::permissions::{check, Kind};
+
+// How often to check whether a child exited. The child is shared with
+// `kill`, so it can't be blocked on.
+const WAIT_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Running children, keyed by pid.
+pub(crate) struct Children(HashMap<u32, Arc<Mutex<Child>>>);
+
+impl Children {
+    pub(crate) fn new() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+/// How a child's stdin, stdout or stderr is set up.
+fn stdio(scope: &mut v8::HandleScope, options: v8::Local<v8::Object>, name: &str) -> Option<Stdio> {
+    let key = v8::String::new(scope, name).unwrap();
+    let value = options.get(scope, key.into())?;
+    if value.is_undefined() {
+        return Some(Stdio::inherit());
+    }
+    match value.to_rust_string_lossy(scope).as_str() {
+        "inherit" => Some(Stdio::inherit()),
+        "piped" => Some(Stdio::piped()),
+        "null" => Some(Stdio::null()),
+        mode => {
+            let message = format!("Invalid {} mode \"{}\"", name, mode);
+            super::exception(scope, &message);
+            None
+        }
+    }
+}
+
+fn get<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    obj: v8::Local<v8::Object>,
+    name: &str,
+) -> Option<v8::Local<'a, v8::Value>> {
+    let key = v8::String::new(scope, name).unwrap();
+    obj.get(scope, key.into())
+        .filter(|value| !value.is_undefined())
+}
+
+/// The command described by the arguments of `childSpawn` and
+/// `childOutputSync`, or `None` after throwing if they're invalid or
+/// running it isn't allowed.
+///
+/// Options are `args`, `cwd`, `env`, `clearEnv`, and `stdin`, `stdout` and
+/// `stderr`, which are `"inherit"`, `"piped"` or `"null"`.
+fn command(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Command> {
+    let program = args.get(0).to_rust_string_lossy(scope);
+    let options = match v8::Local::<v8::Object>::try_from(args.get(1)) {
+        Ok(options) => options,
+        Err(_) => {
+            super::exception(scope, "options must be an object");
+            return None;
+        }
+    };
+    if !check(scope, Kind::Run, &program) {
+        return None;
+    }
+
+    let mut command = Command::new(&program);
+    super::process::apply_env(&mut command);
+    if let Some(value) = get(scope, options, "args") {
+        let array = match v8::Local::<v8::Array>::try_from(value) {
+            Ok(array) => array,
+            Err(_) => {
+                super::exception(scope, "args must be an array");
+                return None;
+            }
+        };
+        for i in 0..array.length() {
+            let arg = array.get_index(scope, i)?;
+            command.arg(arg.to_rust_string_lossy(scope));
+        }
+    }
+    if let Some(cwd) = get(scope, options, "cwd") {
+        command.current_dir(cwd.to_rust_string_lossy(scope));
+    }
+    if let Some(clear_env) = get(scope, options, "clearEnv") {
+        if clear_env.boolean_value(scope) {
+            command.env_clear();
+        }
+    }
+    if let Some(value) = get(scope, options, "env") {
+        let env = match v8::Local::<v8::Object>::try_from(value) {
+            Ok(env) => env,
+            Err(_) => {
+                super::exception(scope, "env must be an object");
+                return None;
+            }
+        };
+        let names = env.get_own_property_names(scope)?;
+        for i in 0..names.length() {
+            let name = names.get_index(scope, i)?;
+            let value = env.get(scope, name)?;
+            command.env(
+                name.to_rust_string_lossy(scope),
+                value.to_rust_string_lossy(scope),
+            );
+        }
+    }
+    command.stdin(stdio(scope, options, "stdin")?);
+    command.stdout(stdio(scope, options, "stdout")?);
+    command.stderr(stdio(scope, options, "stderr")?);
+    Some(command)
+}
+
+/// Start a child, returning `{ pid, stdin, stdout, stderr, status }`. Piped
+/// stdio are resource ids for the fs bindings, others `null`, and `status`
+/// is a promise of the exit status.
+pub(crate) fn child_spawn(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let mut command = match command(scope, &args) {
+        Some(command) => command,
+        None => return,
+    };
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => return io_exception(scope, e),
+    };
+    let pid = child.id();
+    let pipes = [
+        child.stdin.take().map(pipe_file),
+        child.stdout.take().map(pipe_file),
+        child.stderr.take().map(pipe_file),
+    ];
+    let child = Arc::new(Mutex::new(child));
+    scope
+        .get_slot_mut::<Children>()
+        .unwrap()
+        .0
+        .insert(pid, child.clone());
+
+    let resolver = v8::PromiseResolver::new(scope).unwrap();
+    let status = resolver.get_promise(scope);
+    let resolver = v8::Global::new(scope, resolver);
+    let ops = scope.get_slot_mut::<ops::PendingOps>().unwrap();
+    let id = ops.register(resolver);
+    let sender = ops.sender();
+    std::thread::spawn(move || {
+        let result = wait(&child);
+        let completion = ops::completion(move |scope| {
+            scope.get_slot_mut::<Children>().unwrap().0.remove(&pid);
+            match result {
+                Ok(status) => Ok(status_object(scope, status).into()),
+                Err(e) => Err(io_error(scope, e)),
+            }
+        });
+        // The receiver is gone if the isolate was dropped meanwhile.
+        let _ = sender.send((id, completion));
+    });
+
+    let obj = v8::Object::new(scope);
+    let names = ["stdin", "stdout", "stderr"];
+    for (name, pipe) in names.iter().zip(pipes) {
+        let value: v8::Local<v8::Value> = match pipe {
+            Some(file) => {
+                let rid = scope.get_slot_mut::<Files>().unwrap().insert(file);
+                v8::Integer::new_from_unsigned(scope, rid).into()
+            }
+            None => v8::null(scope).into(),
+        };
+        let key = v8::String::new(scope, name).unwrap();
+        obj.set(scope, key.into(), value);
+    }
+    let key = v8::String::new(scope, "pid").unwrap();
+    let value = v8::Integer::new_from_unsigned(scope, pid);
+    obj.set(scope, key.into(), value.into());
+    let key = v8::String::new(scope, "status").unwrap();
+    obj.set(scope, key.into(), status.into());
+    rv.set(obj.into());
+}
+
+fn wait(child: &Mutex<Child>) -> std::io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.lock().unwrap().try_wait()? {
+            return Ok(status);
+        }
+        std::thread::sleep(WAIT_INTERVAL);
+    }
+}
+
+#[cfg(unix)]
+fn pipe_file<T: Into<std::os::unix::io::OwnedFd>>(pipe: T) -> File {
+    File::from(pipe.into())
+}
+
+#[cfg(windows)]
+fn pipe_file<T: Into<std::os::windows::io::OwnedHandle>>(pipe: T) -> File {
+    File::from(pipe.into())
+}
+
+/// Run a child to completion, returning its status with the `stdout` and
+/// `stderr` it wrote as `ArrayBuffer`s.
+pub(crate) fn child_output_sync(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let mut command = match command(scope, &args) {
+        Some(command) => command,
+        None => return,
+    };
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(e) => return io_exception(scope, e),
+    };
+
+    let obj = status_object(scope, output.status);
+    for (name, bytes) in [("stdout", output.stdout), ("stderr", output.stderr)] {
+        let store = v8::ArrayBuffer::new_backing_store_from_boxed_slice(bytes.into_boxed_slice());
+        let buffer = v8::ArrayBuffer::with_backing_store(scope, &store.make_shared());
+        let key = v8::String::new(scope, name).unwrap();
+        obj.set(scope, key.into(), buffer.into());
+    }
+    rv.set(obj.into());
+}
+
+/// `{ success, code, signal }`, where `code` is `null` if the child was
+/// killed by a signal, and `signal` is the signal's name or `null`.
+fn status_object<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    status: ExitStatus,
+) -> v8::Local<'a, v8::Object> {
+    let obj = v8::Object::new(scope);
+    let success = v8::Boolean::new(scope, status.success());
+    let code: v8::Local<v8::Value> = match status.code() {
+        Some(code) => v8::Integer::new(scope, code).into(),
+        None => v8::null(scope).into(),
+    };
+    let signal: v8::Local<v8::Value> = match signal_name(status) {
+        Some(name) => v8::String::new(scope, name).unwrap().into(),
+        None => v8::null(scope).into(),
+    };
+    for (name, value) in [
+        ("success", success.into()),
+        ("code", code),
+        ("signal", signal),
+    ] {
+        let key = v8::String::new(scope, name).unwrap();
+        obj.set(scope, key.into(), value);
+    }
+    obj
+}
+
+#[cfg(unix)]
+const SIGNALS: &[(&str, libc::c_int)] = &[
+    ("SIGHUP", libc::SIGHUP),
+    ("SIGINT", libc::SIGINT),
+    ("SIGQUIT", libc::SIGQUIT),
+    ("SIGABRT", libc::SIGABRT),
+    ("SIGKILL", libc::SIGKILL),
+    ("SIGUSR1", libc::SIGUSR1),
+    ("SIGUSR2", libc::SIGUSR2),
+    ("SIGPIPE", libc::SIGPIPE),
+    ("SIGALRM", libc::SIGALRM),
+    ("SIGTERM", libc::SIGTERM),
+    ("SIGCONT", libc::SIGCONT),
+    ("SIGSTOP", libc::SIGSTOP),
+    ("SIGTSTP", libc::SIGTSTP),
+    ("SIGWINCH", libc::SIGWINCH),
+];
+
+#[cfg(unix)]
+fn signal_name(status: ExitStatus) -> Option<&'static str> {
+    use std::os::unix::process::ExitStatusExt;
+    let signal = status.signal()?;
+    SIGNALS
+        .iter()
+        .find(|(_, number)| *number == signal)
+        .map(|(name, _)| *name)
+}
+
+#[cfg(not(unix))]
+fn signal_name(_status: ExitStatus) -> Option<&'static str> {
+    None
+}
+
+#[cfg(unix)]
+fn send_signal(child: &mut Child, signal: &str) -> std::io::Result<()> {
+    let number = match SIGNALS.iter().find(|(name, _)| *name == signal) {
+        Some((_, number)) => *number,
+        None => {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Unknown signal",
+            ))
+        }
+    };
+    // The child isn't reaped until `try_wait` says it exited, so its pid
+    // can't have been reused.
+    if unsafe { libc::kill(child.id() as libc::pid_t, number) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_signal(child: &mut Child, signal: &str) -> std::io::Result<()> {
+    match signal {
+        "SIGKILL" | "SIGTERM" => child.kill(),
+        _ => Err(std::io::Error::new(
+            ErrorKind::Unsupported,
+            "Only SIGKILL and SIGTERM are supported",
+        )),
+    }
+}
+
+/// Send a signal, like `"SIGTERM"`, to a running child.
+pub(crate) fn child_kill(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let pid = args.get(0).uint32_value(scope).unwrap_or(0);
+    let signal = args.get(1).to_rust_string_lossy(scope);
+    let child = match scope.get_slot::<Children>().unwrap().0.get(&pid) {
+        Some(child) => child.clone(),
+        None => return super::exception(scope, "The process has already exited"),
+    };
+    let mut child = child.lock().unwrap();
+    let result = match child.try_wait() {
+        Ok(Some(_)) => return super::exception(scope, "The process has already exited"),
+        Ok(None) => send_signal(&mut child, &signal),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        io_exception(scope, e);
+    }
+}
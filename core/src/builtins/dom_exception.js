@@ -0,0 +1,80 @@
+// DOMException
+// https://webidl.spec.whatwg.org/#idl-DOMException
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(() => {
+  // The legacy code constants, with the names that have them.
+  const CODES = [
+    ['IndexSizeError', 'INDEX_SIZE_ERR', 1],
+    [null, 'DOMSTRING_SIZE_ERR', 2],
+    ['HierarchyRequestError', 'HIERARCHY_REQUEST_ERR', 3],
+    ['WrongDocumentError', 'WRONG_DOCUMENT_ERR', 4],
+    ['InvalidCharacterError', 'INVALID_CHARACTER_ERR', 5],
+    [null, 'NO_DATA_ALLOWED_ERR', 6],
+    ['NoModificationAllowedError', 'NO_MODIFICATION_ALLOWED_ERR', 7],
+    ['NotFoundError', 'NOT_FOUND_ERR', 8],
+    ['NotSupportedError', 'NOT_SUPPORTED_ERR', 9],
+    ['InUseAttributeError', 'INUSE_ATTRIBUTE_ERR', 10],
+    ['InvalidStateError', 'INVALID_STATE_ERR', 11],
+    ['SyntaxError', 'SYNTAX_ERR', 12],
+    ['InvalidModificationError', 'INVALID_MODIFICATION_ERR', 13],
+    ['NamespaceError', 'NAMESPACE_ERR', 14],
+    ['InvalidAccessError', 'INVALID_ACCESS_ERR', 15],
+    [null, 'VALIDATION_ERR', 16],
+    ['TypeMismatchError', 'TYPE_MISMATCH_ERR', 17],
+    ['SecurityError', 'SECURITY_ERR', 18],
+    ['NetworkError', 'NETWORK_ERR', 19],
+    ['AbortError', 'ABORT_ERR', 20],
+    ['URLMismatchError', 'URL_MISMATCH_ERR', 21],
+    ['QuotaExceededError', 'QUOTA_EXCEEDED_ERR', 22],
+    ['TimeoutError', 'TIMEOUT_ERR', 23],
+    ['InvalidNodeTypeError', 'INVALID_NODE_TYPE_ERR', 24],
+    ['DataCloneError', 'DATA_CLONE_ERR', 25],
+  ];
+
+  class DOMException extends Error {
+    #name;
+
+    constructor(message = '', options = 'Error') {
+      super(String(message));
+      // The second argument is either the name, or options with the name
+      // and a cause.
+      if (typeof options === 'object' && options !== null) {
+        this.#name = options.name === undefined ? 'Error' : String(options.name);
+        if ('cause' in options) {
+          Object.defineProperty(this, 'cause', {
+            value: options.cause,
+            writable: true,
+            configurable: true,
+          });
+        }
+      } else {
+        this.#name = String(options);
+      }
+    }
+
+    get name() {
+      return this.#name;
+    }
+
+    get code() {
+      const entry = CODES.find(([name]) => name === this.#name);
+      return entry === undefined ? 0 : entry[2];
+    }
+  }
+
+  Object.defineProperty(DOMException.prototype, Symbol.toStringTag, {
+    value: 'DOMException',
+    configurable: true,
+  });
+
+  for (const [, constant, code] of CODES) {
+    const descriptor = { value: code, enumerable: true };
+    Object.defineProperty(DOMException, constant, descriptor);
+    Object.defineProperty(DOMException.prototype, constant, descriptor);
+  }
+
+  globalThis.DOMException = DOMException;
+});
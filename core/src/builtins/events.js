@@ -0,0 +1,510 @@
+// EventTarget, Event and CustomEvent
+// https://dom.spec.whatwg.org/#events
+//
+// There's no tree of nodes, so events only ever reach their target.
+// globalThis is an EventTarget too, for the events the runtime dispatches:
+// `load` once the main module was evaluated, `unload` when there's nothing
+// left to do, `error` for uncaught exceptions and `unhandledrejection` for
+// promises rejected without a handler. Cancelling the last two stops jstime
+// from reporting them.
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({ printer, internals }) => {
+  const NONE = 0;
+  const CAPTURING_PHASE = 1;
+  const AT_TARGET = 2;
+  const BUBBLING_PHASE = 3;
+
+  function dictionary(value, name) {
+    if (value === undefined || value === null) {
+      return {};
+    }
+    if (typeof value !== 'object' && typeof value !== 'function') {
+      throw new TypeError(`${name} must be an object`);
+    }
+    return value;
+  }
+
+  // Events dispatched by jstime rather than by scripts.
+  const trusted = new WeakSet();
+
+  function isTrusted() {
+    return trusted.has(this);
+  }
+
+  let setDispatching;
+  let isDispatching;
+  let isStoppedImmediately;
+
+  class Event {
+    #type;
+    #bubbles;
+    #cancelable;
+    #composed;
+    #target = null;
+    #currentTarget = null;
+    #eventPhase = NONE;
+    #canceled = false;
+    #stopPropagation = false;
+    #stopImmediatePropagation = false;
+    #dispatching = false;
+    #inPassiveListener = false;
+    #timeStamp = performance.now();
+
+    constructor(type, eventInitDict = undefined) {
+      if (arguments.length === 0) {
+        throw new TypeError('Event requires a type');
+      }
+      const { bubbles = false, cancelable = false, composed = false } = dictionary(
+        eventInitDict,
+        'eventInitDict',
+      );
+      this.#type = String(type);
+      this.#bubbles = Boolean(bubbles);
+      this.#cancelable = Boolean(cancelable);
+      this.#composed = Boolean(composed);
+      // Unforgeable, so it's an own property rather than on the prototype.
+      Object.defineProperty(this, 'isTrusted', { get: isTrusted, enumerable: true });
+    }
+
+    static {
+      // Moves an event through the phases of its dispatch to `target`,
+      // with the listener being called at the moment being passive or not.
+      setDispatching = (event, target, passive = false) => {
+        event.#dispatching = target !== null;
+        event.#target = target;
+        event.#currentTarget = target;
+        event.#eventPhase = target === null ? NONE : AT_TARGET;
+        event.#inPassiveListener = passive;
+        if (target === null) {
+          event.#stopPropagation = false;
+          event.#stopImmediatePropagation = false;
+        }
+      };
+      isDispatching = (event) => event.#dispatching;
+      isStoppedImmediately = (event) => event.#stopImmediatePropagation;
+    }
+
+    get type() {
+      return this.#type;
+    }
+
+    get target() {
+      return this.#target;
+    }
+
+    get srcElement() {
+      return this.#target;
+    }
+
+    get currentTarget() {
+      return this.#currentTarget;
+    }
+
+    composedPath() {
+      return this.#currentTarget === null ? [] : [this.#currentTarget];
+    }
+
+    get eventPhase() {
+      return this.#eventPhase;
+    }
+
+    stopPropagation() {
+      this.#stopPropagation = true;
+    }
+
+    get cancelBubble() {
+      return this.#stopPropagation;
+    }
+
+    set cancelBubble(value) {
+      if (value) {
+        this.#stopPropagation = true;
+      }
+    }
+
+    stopImmediatePropagation() {
+      this.#stopPropagation = true;
+      this.#stopImmediatePropagation = true;
+    }
+
+    get bubbles() {
+      return this.#bubbles;
+    }
+
+    get cancelable() {
+      return this.#cancelable;
+    }
+
+    get returnValue() {
+      return !this.#canceled;
+    }
+
+    set returnValue(value) {
+      if (!value) {
+        this.preventDefault();
+      }
+    }
+
+    preventDefault() {
+      if (this.#cancelable && !this.#inPassiveListener) {
+        this.#canceled = true;
+      }
+    }
+
+    get defaultPrevented() {
+      return this.#canceled;
+    }
+
+    get composed() {
+      return this.#composed;
+    }
+
+    get timeStamp() {
+      return this.#timeStamp;
+    }
+
+    initEvent(type, bubbles = false, cancelable = false) {
+      if (this.#dispatching) {
+        return;
+      }
+      this.#stopPropagation = false;
+      this.#stopImmediatePropagation = false;
+      this.#canceled = false;
+      this.#target = null;
+      this.#type = String(type);
+      this.#bubbles = Boolean(bubbles);
+      this.#cancelable = Boolean(cancelable);
+    }
+  }
+
+  for (const [name, value] of Object.entries({
+    NONE, CAPTURING_PHASE, AT_TARGET, BUBBLING_PHASE,
+  })) {
+    Object.defineProperty(Event, name, { value, enumerable: true });
+    Object.defineProperty(Event.prototype, name, { value, enumerable: true });
+  }
+
+  Object.defineProperty(Event.prototype, Symbol.toStringTag, {
+    value: 'Event',
+    configurable: true,
+  });
+
+  class CustomEvent extends Event {
+    #detail;
+
+    constructor(type, eventInitDict = undefined) {
+      super(type, eventInitDict);
+      const { detail = null } = dictionary(eventInitDict, 'eventInitDict');
+      this.#detail = detail;
+    }
+
+    get detail() {
+      return this.#detail;
+    }
+
+    initCustomEvent(type, bubbles = false, cancelable = false, detail = null) {
+      if (isDispatching(this)) {
+        return;
+      }
+      this.initEvent(type, bubbles, cancelable);
+      this.#detail = detail;
+    }
+  }
+
+  Object.defineProperty(CustomEvent.prototype, Symbol.toStringTag, {
+    value: 'CustomEvent',
+    configurable: true,
+  });
+
+  // An uncaught exception, dispatched as `error` on globalThis.
+  class ErrorEvent extends Event {
+    #message;
+    #filename;
+    #lineno;
+    #colno;
+    #error;
+
+    constructor(type, eventInitDict = undefined) {
+      super(type, eventInitDict);
+      const {
+        message = '', filename = '', lineno = 0, colno = 0, error,
+      } = dictionary(eventInitDict, 'eventInitDict');
+      this.#message = String(message);
+      this.#filename = String(filename);
+      this.#lineno = lineno >>> 0;
+      this.#colno = colno >>> 0;
+      this.#error = error;
+    }
+
+    get message() {
+      return this.#message;
+    }
+
+    get filename() {
+      return this.#filename;
+    }
+
+    get lineno() {
+      return this.#lineno;
+    }
+
+    get colno() {
+      return this.#colno;
+    }
+
+    get error() {
+      return this.#error;
+    }
+  }
+
+  Object.defineProperty(ErrorEvent.prototype, Symbol.toStringTag, {
+    value: 'ErrorEvent',
+    configurable: true,
+  });
+
+  // A promise rejected without a handler, dispatched as
+  // `unhandledrejection` on globalThis.
+  class PromiseRejectionEvent extends Event {
+    #promise;
+    #reason;
+
+    constructor(type, eventInitDict) {
+      super(type, eventInitDict);
+      const { promise, reason } = dictionary(eventInitDict, 'eventInitDict');
+      if (promise === undefined) {
+        throw new TypeError('PromiseRejectionEvent requires a promise');
+      }
+      this.#promise = promise;
+      this.#reason = reason;
+    }
+
+    get promise() {
+      return this.#promise;
+    }
+
+    get reason() {
+      return this.#reason;
+    }
+  }
+
+  Object.defineProperty(PromiseRejectionEvent.prototype, Symbol.toStringTag, {
+    value: 'PromiseRejectionEvent',
+    configurable: true,
+  });
+
+  // The listeners of each EventTarget by type. A WeakMap rather than a
+  // private field, as globalThis wasn't made by the constructor.
+  const targets = new WeakMap();
+
+  // Methods called without a receiver, like a plain `addEventListener()`,
+  // act on globalThis.
+  function listenersOf(target) {
+    const listeners = targets.get(target ?? globalThis);
+    if (listeners === undefined) {
+      throw new TypeError('Illegal invocation');
+    }
+    return listeners;
+  }
+
+  function flatten(options) {
+    return typeof options === 'object' && options !== null
+      ? Boolean(options.capture)
+      : Boolean(options);
+  }
+
+  class EventTarget {
+    constructor() {
+      targets.set(this, new Map());
+    }
+
+    addEventListener(type, callback, options = undefined) {
+      const listeners = listenersOf(this);
+      const target = this ?? globalThis;
+      const capture = flatten(options);
+      const {
+        once = false, passive = false, signal,
+      } = typeof options === 'object' && options !== null ? options : {};
+      if (signal !== undefined && !(signal instanceof AbortSignal)) {
+        throw new TypeError('signal must be an AbortSignal');
+      }
+      if (callback === null || callback === undefined) {
+        return;
+      }
+      if (signal !== undefined && signal.aborted) {
+        return;
+      }
+      const key = String(type);
+      let list = listeners.get(key);
+      if (list === undefined) {
+        list = [];
+        listeners.set(key, list);
+      }
+      if (list.some((listener) => listener.callback === callback && listener.capture === capture)) {
+        return;
+      }
+      const listener = {
+        callback, capture, once: Boolean(once), passive: Boolean(passive), removed: false,
+      };
+      list.push(listener);
+      if (signal !== undefined) {
+        signal.addEventListener('abort', () => {
+          target.removeEventListener(key, callback, { capture });
+        });
+      }
+    }
+
+    removeEventListener(type, callback, options = undefined) {
+      const listeners = listenersOf(this);
+      const capture = flatten(options);
+      const key = String(type);
+      const list = listeners.get(key);
+      if (list === undefined) {
+        return;
+      }
+      const index = list.findIndex(
+        (listener) => listener.callback === callback && listener.capture === capture,
+      );
+      if (index !== -1) {
+        list[index].removed = true;
+        list.splice(index, 1);
+      }
+    }
+
+    // Calls the listeners for the event's type, returning false if one of
+    // them cancelled it.
+    dispatchEvent(event) {
+      const listeners = listenersOf(this);
+      const target = this ?? globalThis;
+      if (!(event instanceof Event)) {
+        throw new TypeError('dispatchEvent requires an Event');
+      }
+      if (isDispatching(event)) {
+        throw new DOMException('The event is already being dispatched', 'InvalidStateError');
+      }
+      // Listeners added meanwhile aren't called, removed ones aren't either.
+      const list = [...(listeners.get(event.type) ?? [])];
+      setDispatching(event, target);
+      try {
+        for (const listener of list) {
+          if (listener.removed) {
+            continue;
+          }
+          if (listener.once) {
+            target.removeEventListener(event.type, listener.callback, { capture: listener.capture });
+          }
+          setDispatching(event, target, listener.passive);
+          try {
+            const { callback } = listener;
+            if (typeof callback === 'function') {
+              callback.call(target, event);
+            } else {
+              callback.handleEvent(event);
+            }
+          } catch (e) {
+            // eslint-disable-next-line no-use-before-define
+            reportError(e);
+          }
+          if (isStoppedImmediately(event)) {
+            break;
+          }
+        }
+      } finally {
+        setDispatching(event, null);
+      }
+      return !event.defaultPrevented;
+    }
+  }
+
+  Object.defineProperty(EventTarget.prototype, Symbol.toStringTag, {
+    value: 'EventTarget',
+    configurable: true,
+  });
+
+  // Dispatches an event that jstime rather than a script created.
+  function dispatch(target, event) {
+    trusted.add(event);
+    return target.dispatchEvent(event);
+  }
+
+  function describe(error) {
+    if (error instanceof Error && typeof error.stack === 'string') {
+      return error.stack;
+    }
+    try {
+      return String(error);
+    } catch {
+      return Object.prototype.toString.call(error);
+    }
+  }
+
+  // Dispatches `error` for an uncaught exception, returning whether it
+  // still needs reporting.
+  function dispatchError(error) {
+    const event = new ErrorEvent('error', {
+      cancelable: true,
+      message: error instanceof Error ? error.message : describe(error),
+      error,
+    });
+    return dispatch(globalThis, event);
+  }
+
+  // Reports an exception like an uncaught one, unless a listener for
+  // `error` cancels it.
+  function reportError(error) {
+    if (dispatchError(error)) {
+      printer(`Uncaught: ${describe(error)}`, true);
+    }
+  }
+
+  internals.events = {
+    dispatch,
+    // Once the main module was evaluated, which `evaluation` settles if it
+    // uses top-level await.
+    load(evaluation) {
+      const fire = () => dispatch(globalThis, new Event('load'));
+      if (evaluation instanceof Promise) {
+        evaluation.then(fire, () => {});
+      } else {
+        fire();
+      }
+    },
+    unload() {
+      dispatch(globalThis, new Event('unload'));
+    },
+    dispatchError,
+    reportError,
+    // Returns whether the rejection was reported, as no listener
+    // cancelled it.
+    unhandledRejection(promise, reason) {
+      const event = new PromiseRejectionEvent('unhandledrejection', {
+        cancelable: true,
+        promise,
+        reason,
+      });
+      if (!dispatch(globalThis, event)) {
+        return false;
+      }
+      printer(`Uncaught (in promise): ${describe(reason)}`, true);
+      return true;
+    },
+  };
+
+  // globalThis is an EventTarget, as `window` is in browsers.
+  targets.set(globalThis, new Map());
+  Object.setPrototypeOf(globalThis, EventTarget.prototype);
+
+  globalThis.Event = Event;
+  globalThis.CustomEvent = CustomEvent;
+  globalThis.ErrorEvent = ErrorEvent;
+  globalThis.PromiseRejectionEvent = PromiseRejectionEvent;
+  globalThis.EventTarget = EventTarget;
+  globalThis.reportError = function reportErrorGlobal(...args) {
+    if (args.length === 0) {
+      throw new TypeError('reportError requires an argument');
+    }
+    reportError(args[0]);
+  };
+});
@@ -0,0 +1,114 @@
+// The lifecycle events jstime dispatches on globalThis, through the hooks
+// events.js registers as `internals.events`.
+
+use super::call_internal;
+
+/// Promises rejected without a handler, reported as `unhandledrejection`
+/// unless one is added before the microtasks at hand ran.
+pub(crate) struct Rejections {
+    pending: Vec<(v8::Global<v8::Promise>, v8::Global<v8::Value>)>,
+}
+
+impl Rejections {
+    pub(crate) fn new() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+}
+
+pub(crate) extern "C" fn promise_reject_callback(message: v8::PromiseRejectMessage) {
+    let scope = &mut unsafe { v8::CallbackScope::new(&message) };
+    let promise = message.get_promise();
+    match message.get_event() {
+        v8::PromiseRejectEvent::PromiseRejectWithNoHandler => {
+            let reason = message
+                .get_value()
+                .unwrap_or_else(|| v8::undefined(scope).into());
+            let entry = (
+                v8::Global::new(scope, promise),
+                v8::Global::new(scope, reason),
+            );
+            if let Some(rejections) = scope.get_slot_mut::<Rejections>() {
+                rejections.pending.push(entry);
+            }
+        }
+        v8::PromiseRejectEvent::PromiseHandlerAddedAfterReject => handled(scope, promise),
+        _ => {}
+    }
+}
+
+/// Stop tracking a rejected promise whose rejection is dealt with some
+/// other way.
+pub(crate) fn handled(scope: &mut v8::HandleScope, promise: v8::Local<v8::Promise>) {
+    let pending = match scope.get_slot_mut::<Rejections>() {
+        Some(rejections) => std::mem::take(&mut rejections.pending),
+        None => return,
+    };
+    let pending = pending
+        .into_iter()
+        .filter(|(rejected, _)| {
+            let rejected = v8::Local::new(scope, rejected);
+            !rejected.strict_equals(promise.into())
+        })
+        .collect::<Vec<_>>();
+    let rejections = scope.get_slot_mut::<Rejections>().unwrap();
+    rejections.pending.extend(pending);
+}
+
+/// Dispatch `unhandledrejection` for the promises that are still rejected
+/// without a handler once microtasks ran. Reporting one makes jstime exit
+/// with 1, unless the script chose an exit code.
+pub(crate) fn report_rejections(scope: &mut v8::HandleScope) {
+    let pending = std::mem::take(&mut scope.get_slot_mut::<Rejections>().unwrap().pending);
+    for (promise, reason) in pending {
+        let promise = v8::Local::new(scope, promise);
+        let reason = v8::Local::new(scope, reason);
+        match call_internal(
+            scope,
+            "events",
+            "unhandledRejection",
+            &[promise.into(), reason],
+        ) {
+            Ok(reported) if reported.is_true() => {
+                let state = crate::IsolateState::get(scope);
+                let mut state = state.borrow_mut();
+                if state.exit_code == 0 {
+                    state.exit_code = 1;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+/// Report an uncaught exception, unless a listener for `error` cancels it.
+pub(crate) fn report_error(scope: &mut v8::HandleScope, error: v8::Local<v8::Value>) {
+    if let Err(e) = call_internal(scope, "events", "reportError", &[error]) {
+        eprintln!("{}", e);
+    }
+}
+
+/// Dispatch `error` for an exception that is reported some other way,
+/// returning whether it still should be, as no listener cancelled it.
+pub(crate) fn dispatch_error(scope: &mut v8::HandleScope, error: v8::Local<v8::Value>) -> bool {
+    match call_internal(scope, "events", "dispatchError", &[error]) {
+        Ok(value) => value.is_true(),
+        Err(_) => true,
+    }
+}
+
+/// Dispatch `load` once `evaluation`, the result of evaluating the main
+/// module, settles.
+pub(crate) fn load(scope: &mut v8::HandleScope, evaluation: v8::Local<v8::Value>) {
+    if let Err(e) = call_internal(scope, "events", "load", &[evaluation]) {
+        eprintln!("{}", e);
+    }
+}
+
+pub(crate) fn unload(scope: &mut v8::HandleScope) {
+    if let Err(e) = call_internal(scope, "events", "unload", &[]) {
+        eprintln!("{}", e);
+    }
+}
@@ -1,6 +1,841 @@
-"use strict";
+// Fetch
+// https://fetch.spec.whatwg.org/
+
+'use strict';
 
 // eslint-disable-next-line no-unused-expressions
-({ fetch }) => {
+(({
+  fetch: fetchBinding, fetchCancel, fetchBody, fetchBodyWrite, fetchBodyRead, fetchBodyClose,
+  internals,
+}) => {
+  const { streams, blob } = internals;
+  const encoder = new TextEncoder();
+
+  const HTTP_TOKEN = /^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/;
+  const HTTP_WHITESPACE = /^[\t\n\r ]+|[\t\n\r ]+$/g;
+  const METHODS = ['DELETE', 'GET', 'HEAD', 'OPTIONS', 'POST', 'PUT'];
+  const FORBIDDEN_METHODS = ['CONNECT', 'TRACE', 'TRACK'];
+  const REDIRECT_MODES = ['follow', 'error', 'manual'];
+  const NULL_BODY_STATUSES = [101, 103, 204, 205, 304];
+  const REDIRECT_STATUSES = [301, 302, 303, 307, 308];
+  // Dropped when a redirect turns a request into a GET.
+  const REQUEST_BODY_HEADERS = [
+    'content-encoding', 'content-language', 'content-location', 'content-type',
+  ];
+  const MAX_REDIRECTS = 20;
+  const CHUNK_SIZE = 64 * 1024;
+
+  function concat(chunks) {
+    const result = new Uint8Array(chunks.reduce((length, chunk) => length + chunk.length, 0));
+    let offset = 0;
+    for (const chunk of chunks) {
+      result.set(chunk, offset);
+      offset += chunk.length;
+    }
+    return result;
+  }
+
+  function dictionary(value, context) {
+    if (value === undefined || value === null) {
+      return {};
+    }
+    if (typeof value !== 'object' && typeof value !== 'function') {
+      throw new TypeError(`${context} must be an object`);
+    }
+    return value;
+  }
+
+  // Headers
+
+  function headerName(name) {
+    const s = String(name);
+    if (!HTTP_TOKEN.test(s)) {
+      throw new TypeError(`Invalid header name "${s}"`);
+    }
+    return s.toLowerCase();
+  }
+
+  function headerValue(name, value) {
+    const s = String(value).replace(HTTP_WHITESPACE, '');
+    if (/[\0\r\n]/.test(s)) {
+      throw new TypeError(`Invalid value for header "${name}"`);
+    }
+    return s;
+  }
+
+  let headerList;
+  let makeImmutable;
+
+  class Headers {
+    // `[name, value]` pairs with lowercase names, in the order they were
+    // added.
+    #list = [];
+    #immutable = false;
+
+    constructor(init = undefined) {
+      if (init === undefined) {
+        return;
+      }
+      if (typeof init !== 'object' || init === null) {
+        throw new TypeError('Headers init must be an object');
+      }
+      if (typeof init[Symbol.iterator] === 'function') {
+        for (const header of init) {
+          const pair = Array.from(header);
+          if (pair.length !== 2) {
+            throw new TypeError('Headers init pairs must have a name and a value');
+          }
+          this.append(pair[0], pair[1]);
+        }
+      } else {
+        for (const name of Object.keys(init)) {
+          this.append(name, init[name]);
+        }
+      }
+    }
+
+    static {
+      headerList = (headers) => headers.#list;
+      makeImmutable = (headers) => {
+        headers.#immutable = true;
+      };
+    }
+
+    #checkMutable() {
+      if (this.#immutable) {
+        throw new TypeError('Headers are immutable');
+      }
+    }
+
+    append(name, value) {
+      const key = headerName(name);
+      const normalized = headerValue(key, value);
+      this.#checkMutable();
+      this.#list.push([key, normalized]);
+    }
+
+    delete(name) {
+      const key = headerName(name);
+      this.#checkMutable();
+      this.#list = this.#list.filter(([entryName]) => entryName !== key);
+    }
+
+    // The values of `name`, combined with ", ", or null.
+    get(name) {
+      const key = headerName(name);
+      const values = this.#list.filter(([entryName]) => entryName === key).map(([, v]) => v);
+      return values.length === 0 ? null : values.join(', ');
+    }
+
+    // Set-Cookie values can't be combined, so they're got separately.
+    getSetCookie() {
+      return this.#list.filter(([name]) => name === 'set-cookie').map(([, value]) => value);
+    }
+
+    has(name) {
+      const key = headerName(name);
+      return this.#list.some(([entryName]) => entryName === key);
+    }
+
+    set(name, value) {
+      const key = headerName(name);
+      const normalized = headerValue(key, value);
+      this.#checkMutable();
+      const index = this.#list.findIndex(([entryName]) => entryName === key);
+      if (index === -1) {
+        this.#list.push([key, normalized]);
+        return;
+      }
+      this.#list = this.#list.filter(([entryName], i) => i <= index || entryName !== key);
+      this.#list[index] = [key, normalized];
+    }
+
+    forEach(callback, thisArg = undefined) {
+      for (const [name, value] of this) {
+        callback.call(thisArg, value, name, this);
+      }
+    }
+
+    // Sorted by name, with the values of each combined, except for
+    // Set-Cookie.
+    * entries() {
+      const names = [...new Set(this.#list.map(([name]) => name))].sort();
+      for (const name of names) {
+        if (name === 'set-cookie') {
+          for (const value of this.getSetCookie()) {
+            yield [name, value];
+          }
+        } else {
+          yield [name, this.get(name)];
+        }
+      }
+    }
+
+    * keys() {
+      for (const [name] of this.entries()) {
+        yield name;
+      }
+    }
+
+    * values() {
+      for (const [, value] of this.entries()) {
+        yield value;
+      }
+    }
+
+    [Symbol.iterator]() {
+      return this.entries();
+    }
+  }
+
+  Object.defineProperty(Headers.prototype, Symbol.toStringTag, {
+    value: 'Headers',
+    configurable: true,
+  });
+
+  // Bodies
+
+  function escapeMultipart(s) {
+    return s.replace(/\n/g, '%0A').replace(/\r/g, '%0D').replace(/"/g, '%22');
+  }
+
+  function normalizeNewlines(s) {
+    return s.replace(/\r(?!\n)|(?<!\r)\n/g, '\r\n');
+  }
+
+  function multipart(formData) {
+    const boundary = `----jstime-${Math.random().toString(16).slice(2)}${Date.now().toString(16)}`;
+    const chunks = [];
+    for (const [name, value] of formData) {
+      const head = `--${boundary}\r\nContent-Disposition: form-data; name="${escapeMultipart(normalizeNewlines(name))}"`;
+      if (typeof value === 'string') {
+        chunks.push(encoder.encode(`${head}\r\n\r\n${normalizeNewlines(value)}\r\n`));
+      } else {
+        const type = value.type || 'application/octet-stream';
+        chunks.push(
+          encoder.encode(`${head}; filename="${escapeMultipart(value.name)}"\r\nContent-Type: ${type}\r\n\r\n`),
+          blob.bytes(value),
+          encoder.encode('\r\n'),
+        );
+      }
+    }
+    chunks.push(encoder.encode(`--${boundary}--\r\n`));
+    return { source: concat(chunks), type: `multipart/form-data; boundary=${boundary}` };
+  }
+
+  // A body is a byte stream, with the bytes it streams if they're known
+  // up front.
+  function bytesBody(source) {
+    const stream = new ReadableStream({
+      type: 'bytes',
+      pull(controller) {
+        if (source.length > 0) {
+          controller.enqueue(source.slice());
+        }
+        controller.close();
+        if (controller.byobRequest !== null) {
+          controller.byobRequest.respond(0);
+        }
+      },
+    });
+    return { stream, source };
+  }
+
+  // A response body read from the network as it's consumed, which errors
+  // if the fetch is aborted before it's done. `closed` resolves once it's
+  // done, and aborting doesn't matter anymore.
+  function networkBody(rid) {
+    let streamController;
+    let resolveClosed;
+    const closed = new Promise((resolve) => {
+      resolveClosed = resolve;
+    });
+    const close = () => {
+      fetchBodyClose(rid);
+      resolveClosed();
+    };
+    const stream = new ReadableStream({
+      type: 'bytes',
+      autoAllocateChunkSize: CHUNK_SIZE,
+      start(controller) {
+        streamController = controller;
+      },
+      async pull(controller) {
+        const request = controller.byobRequest;
+        const size = request === null ? CHUNK_SIZE : request.view.byteLength;
+        let bytes;
+        try {
+          bytes = new Uint8Array(await fetchBodyRead(rid, size));
+        } catch (e) {
+          close();
+          throw e;
+        }
+        if (request !== null && request.view === null) {
+          // The stream was cancelled while reading.
+          return;
+        }
+        if (bytes.length === 0) {
+          close();
+          controller.close();
+          if (request !== null) {
+            request.respond(0);
+          }
+        } else if (request === null) {
+          controller.enqueue(bytes);
+        } else {
+          request.view.set(bytes);
+          request.respond(bytes.length);
+        }
+      },
+      cancel() {
+        close();
+      },
+    });
+    const abort = (reason) => {
+      streamController.error(reason);
+      close();
+    };
+    return { stream, source: undefined, abort, closed };
+  }
+
+  // The body for `object`, and the Content-Type it implies, if any.
+  function extractBody(object) {
+    if (object instanceof ReadableStream) {
+      if (streams.isDisturbed(object) || object.locked) {
+        throw new TypeError('The body stream was already read or is locked');
+      }
+      return { body: { stream: object, source: undefined }, type: null };
+    }
+    let source;
+    let type = null;
+    if (object instanceof ArrayBuffer) {
+      source = new Uint8Array(object.slice(0));
+    } else if (ArrayBuffer.isView(object)) {
+      source = new Uint8Array(
+        object.buffer.slice(object.byteOffset, object.byteOffset + object.byteLength),
+      );
+    } else if (object instanceof Blob) {
+      source = blob.bytes(object);
+      if (object.type !== '') {
+        type = object.type;
+      }
+    } else if (object instanceof FormData) {
+      ({ source, type } = multipart(object));
+    } else if (object instanceof URLSearchParams) {
+      source = encoder.encode(object.toString());
+      type = 'application/x-www-form-urlencoded;charset=UTF-8';
+    } else {
+      source = encoder.encode(String(object));
+      type = 'text/plain;charset=UTF-8';
+    }
+    return { body: bytesBody(source), type };
+  }
+
+  function isUnusable(body) {
+    return body !== null && (streams.isDisturbed(body.stream) || body.stream.locked);
+  }
+
+  // Both bodies stream the same bytes, read separately.
+  function cloneBody(body) {
+    const [stream1, stream2] = body.stream.tee();
+    body.stream = stream1;
+    return { stream: stream2, source: body.source };
+  }
+
+  let bodyOf;
+  let setBody;
+
+  // The body of a Request or Response, and ways to read it.
+  class Body {
+    #body = null;
+
+    static {
+      bodyOf = (object) => object.#body;
+      setBody = (object, body) => {
+        object.#body = body;
+      };
+    }
+
+    get body() {
+      return this.#body === null ? null : this.#body.stream;
+    }
+
+    get bodyUsed() {
+      return this.#body !== null && streams.isDisturbed(this.#body.stream);
+    }
+
+    // Reads the whole body.
+    async #consume() {
+      const body = this.#body;
+      if (body === null) {
+        return new Uint8Array(0);
+      }
+      if (isUnusable(body)) {
+        throw new TypeError('Body is unusable: body has already been read');
+      }
+      const reader = body.stream.getReader();
+      const chunks = [];
+      for (;;) {
+        const { value, done } = await reader.read();
+        if (done) {
+          return concat(chunks);
+        }
+        chunks.push(value);
+      }
+    }
+
+    async arrayBuffer() {
+      const bytes = await this.#consume();
+      return bytes.buffer.byteLength === bytes.byteLength ? bytes.buffer : bytes.slice().buffer;
+    }
+
+    async blob() {
+      const bytes = await this.#consume();
+      return new Blob([bytes], { type: this.headers.get('content-type') ?? '' });
+    }
+
+    async bytes() {
+      return this.#consume();
+    }
+
+    async json() {
+      return JSON.parse(await this.text());
+    }
+
+    async text() {
+      return new TextDecoder().decode(await this.#consume());
+    }
+  }
+
+  // Request
+
+  function normalizeMethod(method) {
+    const s = String(method);
+    if (!HTTP_TOKEN.test(s)) {
+      throw new TypeError(`Invalid method "${s}"`);
+    }
+    const upper = s.toUpperCase();
+    if (FORBIDDEN_METHODS.includes(upper)) {
+      throw new TypeError(`Method "${s}" is forbidden`);
+    }
+    return METHODS.includes(upper) ? upper : s;
+  }
+
+  class Request extends Body {
+    #method = 'GET';
+    #url;
+    #headers;
+    #redirect = 'follow';
+    #signal;
+
+    constructor(input, init = undefined) {
+      super();
+      const options = dictionary(init, 'Request init');
+      let inputBody = null;
+      let signal = null;
+      if (input instanceof Request) {
+        this.#method = input.#method;
+        this.#url = input.#url;
+        this.#redirect = input.#redirect;
+        signal = input.#signal;
+        inputBody = bodyOf(input);
+      } else {
+        // Like the Rust side would, but so that `url` is the normalized
+        // href and invalid URLs fail when the request is made.
+        const url = URL.parse(String(input));
+        if (url === null) {
+          throw new TypeError(`Invalid URL: ${input}`);
+        }
+        this.#url = url.href;
+      }
+      if (options.signal !== undefined) {
+        if (options.signal !== null && !(options.signal instanceof AbortSignal)) {
+          throw new TypeError('signal must be an AbortSignal');
+        }
+        signal = options.signal;
+      }
+      // A request's signal follows the one it was given, so that it can't
+      // be aborted through the request.
+      this.#signal = signal === null ? new AbortController().signal : AbortSignal.any([signal]);
+      if (options.method !== undefined) {
+        this.#method = normalizeMethod(options.method);
+      }
+      if (options.redirect !== undefined) {
+        const redirect = String(options.redirect);
+        if (!REDIRECT_MODES.includes(redirect)) {
+          throw new TypeError(`Invalid redirect mode "${redirect}"`);
+        }
+        this.#redirect = redirect;
+      }
+      let headersInit = options.headers;
+      if (headersInit === undefined && input instanceof Request) {
+        headersInit = input.#headers;
+      }
+      this.#headers = new Headers(headersInit);
+
+      const hasBody = options.body !== undefined && options.body !== null;
+      if ((hasBody || inputBody !== null) && (this.#method === 'GET' || this.#method === 'HEAD')) {
+        throw new TypeError('Request with GET/HEAD method cannot have body');
+      }
+      let body = null;
+      if (hasBody) {
+        if (options.body instanceof ReadableStream && options.duplex !== 'half') {
+          throw new TypeError('duplex must be "half" when the body is a ReadableStream');
+        }
+        const extracted = extractBody(options.body);
+        body = extracted.body;
+        if (extracted.type !== null && !this.#headers.has('content-type')) {
+          this.#headers.append('content-type', extracted.type);
+        }
+      } else if (inputBody !== null) {
+        if (isUnusable(inputBody)) {
+          throw new TypeError('Cannot construct a Request with a Request whose body was already used');
+        }
+        // The body moves to the new request, and the input can't be read
+        // anymore.
+        body = {
+          stream: inputBody.stream.pipeThrough(new TransformStream()),
+          source: inputBody.source,
+        };
+      }
+      setBody(this, body);
+    }
+
+    get method() {
+      return this.#method;
+    }
+
+    get url() {
+      return this.#url;
+    }
+
+    get headers() {
+      return this.#headers;
+    }
+
+    get redirect() {
+      return this.#redirect;
+    }
+
+    get signal() {
+      return this.#signal;
+    }
+
+    clone() {
+      if (this.bodyUsed) {
+        throw new TypeError('Cannot clone a Request whose body was already used');
+      }
+      const request = new Request(this.#url, {
+        method: this.#method,
+        headers: this.#headers,
+        redirect: this.#redirect,
+        signal: this.#signal,
+      });
+      const body = bodyOf(this);
+      setBody(request, body === null ? null : cloneBody(body));
+      return request;
+    }
+  }
+
+  Object.defineProperty(Request.prototype, Symbol.toStringTag, {
+    value: 'Request',
+    configurable: true,
+  });
+
+  // Response
+
+  let networkResponse;
+
+  class Response extends Body {
+    #type = 'default';
+    #url = '';
+    #redirected = false;
+    #status;
+    #statusText;
+    #headers;
+
+    constructor(body = null, init = undefined) {
+      super();
+      const { status = 200, statusText = '', headers } = dictionary(init, 'Response init');
+      const code = Number(status);
+      if (!Number.isInteger(code) || code < 200 || code > 599) {
+        throw new RangeError(`Invalid status ${status}, it must be in the range 200 to 599`);
+      }
+      const text = String(statusText);
+      if (!/^[\t\x20-\x7e\x80-\xff]*$/.test(text)) {
+        throw new TypeError(`Invalid statusText "${text}"`);
+      }
+      this.#status = code;
+      this.#statusText = text;
+      this.#headers = new Headers(headers);
+      if (body !== null && body !== undefined) {
+        if (NULL_BODY_STATUSES.includes(code)) {
+          throw new TypeError(`Response with status ${code} cannot have body`);
+        }
+        const extracted = extractBody(body);
+        if (extracted.type !== null && !this.#headers.has('content-type')) {
+          this.#headers.append('content-type', extracted.type);
+        }
+        setBody(this, extracted.body);
+      }
+    }
+
+    static {
+      // The Response to a request sent by `fetch`.
+      networkResponse = (raw, method, redirected) => {
+        const response = new Response(null, { headers: raw.headers });
+        response.#type = 'basic';
+        response.#url = raw.url.replace(/#.*$/, '');
+        response.#redirected = redirected;
+        response.#status = raw.status;
+        response.#statusText = raw.statusText;
+        makeImmutable(response.#headers);
+        if (!NULL_BODY_STATUSES.includes(raw.status) && method !== 'HEAD') {
+          setBody(response, networkBody(raw.body));
+        } else {
+          fetchBodyClose(raw.body);
+        }
+        return response;
+      };
+    }
+
+    // A network error, as the Response of a failed fetch.
+    static error() {
+      const response = new Response();
+      response.#type = 'error';
+      response.#status = 0;
+      makeImmutable(response.#headers);
+      return response;
+    }
+
+    static redirect(url, status = 302) {
+      const code = Number(status);
+      if (!REDIRECT_STATUSES.includes(code)) {
+        throw new RangeError(`Invalid redirect status ${status}`);
+      }
+      const parsed = URL.parse(String(url));
+      if (parsed === null) {
+        throw new TypeError(`Invalid URL: ${url}`);
+      }
+      const response = new Response(null, { status: code, headers: { location: parsed.href } });
+      makeImmutable(response.#headers);
+      return response;
+    }
+
+    static json(data, init = undefined) {
+      const text = JSON.stringify(data);
+      if (text === undefined) {
+        throw new TypeError('The data is not JSON serializable');
+      }
+      const response = new Response(text, init);
+      response.#headers.set('content-type', 'application/json');
+      return response;
+    }
+
+    get type() {
+      return this.#type;
+    }
+
+    get url() {
+      return this.#url;
+    }
+
+    get redirected() {
+      return this.#redirected;
+    }
+
+    get status() {
+      return this.#status;
+    }
+
+    get ok() {
+      return this.#status >= 200 && this.#status <= 299;
+    }
+
+    get statusText() {
+      return this.#statusText;
+    }
+
+    get headers() {
+      return this.#headers;
+    }
+
+    clone() {
+      if (this.bodyUsed) {
+        throw new TypeError('Cannot clone a Response whose body was already used');
+      }
+      const response = new Response(null, { headers: this.#headers });
+      response.#type = this.#type;
+      response.#url = this.#url;
+      response.#redirected = this.#redirected;
+      response.#status = this.#status;
+      response.#statusText = this.#statusText;
+      const body = bodyOf(this);
+      setBody(response, body === null ? null : cloneBody(body));
+      return response;
+    }
+  }
+
+  Object.defineProperty(Response.prototype, Symbol.toStringTag, {
+    value: 'Response',
+    configurable: true,
+  });
+
+  // fetch
+
+  function origin(url) {
+    const parsed = URL.parse(url);
+    return parsed === null ? url : parsed.origin;
+  }
+
+  // The bytes to send of a request's body, or its stream if they aren't
+  // known up front. The body can't be read after.
+  function takeBody(request) {
+    const body = bodyOf(request);
+    if (body === null) {
+      return undefined;
+    }
+    if (isUnusable(body)) {
+      throw new TypeError('Body is unusable: body has already been read');
+    }
+    if (body.source === undefined) {
+      return body.stream;
+    }
+    body.stream.cancel();
+    return body.source;
+  }
+
+  // Sends the chunks of a streamed request body as the request takes them.
+  async function pump(stream, rid) {
+    const reader = stream.getReader();
+    try {
+      for (;;) {
+        const { value, done } = await reader.read();
+        if (done) {
+          break;
+        }
+        if (!(value instanceof Uint8Array)) {
+          throw new TypeError('Request body chunks must be Uint8Arrays');
+        }
+        await fetchBodyWrite(rid, value);
+      }
+      fetchBodyClose(rid, false);
+    } catch (e) {
+      fetchBodyClose(rid, true);
+      reader.cancel(e).catch(() => {});
+    }
+  }
+
+  // Sends a request, streaming its body if it's a stream. Returns the
+  // request's id, the resource id of a streamed body and the promise of
+  // the response.
+  function send(url, method, headers, body) {
+    if (!(body instanceof ReadableStream)) {
+      return fetchBinding(url, method, headers, body);
+    }
+    const rid = fetchBody();
+    let sent;
+    try {
+      sent = fetchBinding(url, method, headers, rid);
+    } catch (e) {
+      fetchBodyClose(rid, true);
+      throw e;
+    }
+    pump(body, rid);
+    return { id: sent.id, rid, promise: sent.promise };
+  }
+
+  function fetch(input, init = undefined) {
+    let request;
+    let body;
+    let method;
+    let url;
+    let headers;
+    let current;
+    // Invalid URLs and denied net access reject the promise, as any other
+    // failure to fetch does.
+    try {
+      request = new Request(input, init);
+      request.signal.throwIfAborted();
+      body = takeBody(request);
+      ({ method, url } = request);
+      headers = headerList(request.headers).map(([name, value]) => [name, value]);
+      current = send(url, method, headers, body);
+    } catch (e) {
+      return Promise.reject(e);
+    }
+    const { signal } = request;
+    let redirects = 0;
+    let response = null;
+
+    // Redirects are followed here, rather than by the network thread, so
+    // that net access to where they lead is checked too.
+    function follow(raw) {
+      if (signal.aborted) {
+        fetchBodyClose(raw.body);
+        throw signal.reason;
+      }
+      if (raw.location === null || request.redirect === 'manual') {
+        response = networkResponse(raw, method, redirects > 0);
+        return response;
+      }
+      fetchBodyClose(raw.body);
+      if (request.redirect === 'error') {
+        throw new TypeError(`Redirected to ${raw.location} with redirect mode "error"`);
+      }
+      redirects += 1;
+      if (redirects > MAX_REDIRECTS) {
+        throw new TypeError(`Too many redirects fetching ${request.url}`);
+      }
+      const { status } = raw;
+      if ((status === 303 && method !== 'GET' && method !== 'HEAD')
+        || ((status === 301 || status === 302) && method === 'POST')) {
+        method = 'GET';
+        body = undefined;
+        headers = headers.filter(([name]) => !REQUEST_BODY_HEADERS.includes(name));
+      }
+      if (body instanceof ReadableStream) {
+        throw new TypeError('Cannot follow a redirect with a streamed request body');
+      }
+      if (origin(raw.location) !== origin(raw.url)) {
+        headers = headers.filter(([name]) => name !== 'authorization');
+      }
+      url = raw.location;
+      current = send(url, method, headers, body);
+      return current.promise.then(follow);
+    }
+
+    // Aborting cancels the request in flight, or errors the body of the
+    // response if it already arrived.
+    let rejectAborted;
+    const aborted = new Promise((resolve, reject) => {
+      rejectAborted = reject;
+    });
+    function abort() {
+      signal.removeEventListener('abort', abort);
+      if (response !== null) {
+        bodyOf(response)?.abort(signal.reason);
+        return;
+      }
+      fetchCancel(current.id);
+      if (current.rid !== undefined) {
+        fetchBodyClose(current.rid, true);
+      }
+      rejectAborted(signal.reason);
+    }
+    signal.addEventListener('abort', abort);
+
+    // The signal stops holding on to the request once it failed, or once
+    // the body of its response was read or closed.
+    const fetched = current.promise.then(follow);
+    fetched
+      .then((result) => bodyOf(result)?.closed, () => {})
+      .finally(() => signal.removeEventListener('abort', abort));
+    return Promise.race([fetched, aborted]);
+  }
+
+  globalThis.Headers = Headers;
+  globalThis.Request = Request;
+  globalThis.Response = Response;
   globalThis.fetch = fetch;
-};
+});
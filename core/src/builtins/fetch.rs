@@ -0,0 +1,550 @@
+// The network side of `fetch`. Requests run concurrently on a few
+// background threads that share pooled connections, which settle the
+// promises `fetch` returned once the response headers arrived. Bodies are
+// streamed in both directions, a chunk at a time as JS asks for or provides
+// one, which is what applies backpressure. Aborting a request shuts its
+// connection down, which frees the thread waiting on it.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{ErrorKind, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+
+use super::http;
+use super::ops;
+use crate::permissions::{check, net_resource, Kind};
+
+/// Requests run on up to this many threads at once, and the others wait
+/// for one of them.
+const MAX_WORKERS: usize = 8;
+
+/// The network backend, started by the first `fetch` so that isolates which
+/// never use it don't start threads.
+pub(crate) struct Context {
+    client: Option<Client>,
+    // Cancels a request, by its op id, until it completes.
+    cancels: HashMap<u32, http::Cancel>,
+}
+
+impl Context {
+    pub(crate) fn new() -> Self {
+        Self {
+            client: None,
+            cancels: HashMap::new(),
+        }
+    }
+}
+
+struct Client {
+    agent: http::Agent,
+    completions: Sender<(u32, ops::Completion)>,
+    pool: ops::Pool,
+}
+
+impl Client {
+    fn new(completions: Sender<(u32, ops::Completion)>) -> Self {
+        // Each worker can keep a connection to the same host open.
+        Self {
+            agent: http::Agent::new(MAX_WORKERS),
+            completions,
+            pool: ops::Pool::new(Some(MAX_WORKERS)),
+        }
+    }
+
+    fn send(&mut self, req: FetchRequest) {
+        let agent = self.agent.clone();
+        let completions = self.completions.clone();
+        self.pool.run(move || {
+            // A request cancelled while it waited isn't sent at all.
+            if req.cancel.is_cancelled() {
+                return;
+            }
+            let id = req.id;
+            let response = req.send(&agent);
+            let completion = ops::completion(move |scope| {
+                let ctx = scope.get_slot_mut::<Context>().unwrap();
+                ctx.cancels.remove(&id);
+                match response {
+                    Ok(response) => Ok(response.to_object(scope).into()),
+                    Err(message) => {
+                        let message = v8::String::new(scope, &message).unwrap();
+                        Err(v8::Exception::type_error(scope, message))
+                    }
+                }
+            });
+            // The receiver is gone if the isolate was dropped meanwhile.
+            let _ = completions.send((id, completion));
+        });
+    }
+}
+
+/// A request body streamed from JS. Each chunk is handed over when the
+/// request reads it, so writes wait for the network.
+struct StreamedBody {
+    receiver: Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+    // Set when the stream errored rather than closed, so that the request
+    // fails instead of sending a truncated body.
+    errored: Arc<AtomicBool>,
+}
+
+impl Read for StreamedBody {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.receiver.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Err(_) if self.errored.load(Ordering::SeqCst) => {
+                    let message = "The request body stream errored";
+                    return Err(std::io::Error::new(ErrorKind::Other, message));
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+type ResponseReader = Box<dyn Read + Send>;
+
+enum Body {
+    /// The receiving end of a response body, with the op ids of reads in
+    /// progress and what cancels its request.
+    Response {
+        reader: Arc<Mutex<ResponseReader>>,
+        reads: Vec<u32>,
+        cancel: http::Cancel,
+    },
+    /// The sending end of a request body, with the receiving end until the
+    /// request is sent.
+    Request {
+        sender: SyncSender<Vec<u8>>,
+        errored: Arc<AtomicBool>,
+        reader: Option<StreamedBody>,
+    },
+}
+
+/// Bodies being streamed, keyed by resource id.
+pub(crate) struct Bodies {
+    next_rid: u32,
+    bodies: HashMap<u32, Body>,
+}
+
+impl Bodies {
+    pub(crate) fn new() -> Self {
+        Self {
+            next_rid: 1,
+            bodies: HashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, body: Body) -> u32 {
+        let rid = self.next_rid;
+        self.next_rid += 1;
+        self.bodies.insert(rid, body);
+        rid
+    }
+}
+
+fn type_error(scope: &mut v8::HandleScope, message: &str) {
+    let message = v8::String::new(scope, message).unwrap();
+    let error = v8::Exception::type_error(scope, message);
+    scope.throw_exception(error);
+}
+
+/// `[name, value]` pairs from an array of them.
+fn headers(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> Option<Vec<(String, String)>> {
+    let array = match v8::Local::<v8::Array>::try_from(value) {
+        Ok(array) => array,
+        Err(_) => {
+            type_error(scope, "headers must be an array");
+            return None;
+        }
+    };
+    let mut headers = Vec::new();
+    for i in 0..array.length() {
+        let pair = array.get_index(scope, i)?;
+        let pair = match v8::Local::<v8::Array>::try_from(pair) {
+            Ok(pair) if pair.length() == 2 => pair,
+            _ => {
+                type_error(scope, "headers must be [name, value] pairs");
+                return None;
+            }
+        };
+        let name = pair.get_index(scope, 0)?.to_rust_string_lossy(scope);
+        let value = pair.get_index(scope, 1)?.to_rust_string_lossy(scope);
+        headers.push((name, value));
+    }
+    Some(headers)
+}
+
+/// Send a request, with the arguments `url`, `method`, `headers` as
+/// `[name, value]` pairs and `body` as an `ArrayBufferView`, the resource
+/// id of a `fetchBody`, or undefined. Returns the request's `id`, to cancel
+/// it with, and a `promise` that resolves to the response once its headers
+/// arrived, with the resource id of its body, and rejects with a
+/// `TypeError` if the request couldn't be made.
+pub(crate) fn fetch(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let resource = args.get(0).to_rust_string_lossy(scope);
+    let url = match url::Url::parse(&resource) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+        Ok(url) => {
+            let message = format!("URL scheme \"{}\" is not supported", url.scheme());
+            return type_error(scope, &message);
+        }
+        Err(e) => return type_error(scope, &format!("Invalid URL \"{}\": {}", resource, e)),
+    };
+    // Before the body is taken, so a denied request leaves a streamed body
+    // to the caller.
+    let host = net_resource(&url);
+    if !check(scope, Kind::Net, host.as_deref().unwrap_or(&resource)) {
+        return;
+    }
+    let method = args.get(1).to_rust_string_lossy(scope);
+    let headers = match headers(scope, args.get(2)) {
+        Some(headers) => headers,
+        None => return,
+    };
+    let body = args.get(3);
+    let body = if body.is_undefined() {
+        http::RequestBody::None
+    } else if body.is_number() {
+        let rid = body.uint32_value(scope).unwrap_or(0);
+        let bodies = scope.get_slot_mut::<Bodies>().unwrap();
+        let reader = match bodies.bodies.get_mut(&rid) {
+            Some(Body::Request { reader, .. }) => reader.take(),
+            _ => None,
+        };
+        match reader {
+            Some(reader) => http::RequestBody::Stream(Box::new(reader)),
+            None => return type_error(scope, "body is not a request body or was already sent"),
+        }
+    } else {
+        match v8::Local::<v8::ArrayBufferView>::try_from(body) {
+            Ok(view) => {
+                let mut buf = vec![0; view.byte_length()];
+                view.copy_contents(&mut buf);
+                http::RequestBody::Bytes(buf)
+            }
+            Err(_) => return type_error(scope, "body must be an ArrayBufferView"),
+        }
+    };
+
+    let resolver = v8::PromiseResolver::new(scope).unwrap();
+    let promise = resolver.get_promise(scope);
+    let resolver = v8::Global::new(scope, resolver);
+
+    let ops = scope.get_slot_mut::<ops::PendingOps>().unwrap();
+    let id = ops.register(resolver);
+    let completions = ops.sender();
+    let ctx = scope.get_slot_mut::<Context>().unwrap();
+    let cancel = http::Cancel::default();
+    ctx.cancels.insert(id, cancel.clone());
+    ctx.client
+        .get_or_insert_with(|| Client::new(completions))
+        .send(FetchRequest {
+            id,
+            request: http::Request {
+                method,
+                url,
+                headers,
+                body,
+            },
+            cancel,
+        });
+
+    let obj = v8::Object::new(scope);
+    let key = v8::String::new(scope, "id").unwrap();
+    let value = v8::Integer::new_from_unsigned(scope, id);
+    obj.set(scope, key.into(), value.into());
+    let key = v8::String::new(scope, "promise").unwrap();
+    obj.set(scope, key.into(), promise.into());
+    rv.set(obj.into());
+}
+
+/// Cancel a request by the id `fetch` returned. A request that wasn't sent
+/// yet never is, and the connection of one that was is shut down. Its
+/// promise never settles.
+pub(crate) fn fetch_cancel(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let id = args.get(0).uint32_value(scope).unwrap_or(0);
+    let ctx = scope.get_slot_mut::<Context>().unwrap();
+    if let Some(cancel) = ctx.cancels.remove(&id) {
+        cancel.cancel();
+        scope.get_slot_mut::<ops::PendingOps>().unwrap().cancel(id);
+    }
+}
+
+struct FetchRequest {
+    id: u32,
+    request: http::Request,
+    cancel: http::Cancel,
+}
+
+impl FetchRequest {
+    fn send(self, agent: &http::Agent) -> Result<FetchResponse, String> {
+        let url = self.request.url.clone();
+        // fetch resolves for any HTTP status, not just 2xx, and follows
+        // redirects itself, so that access to where they lead is checked
+        // too.
+        match agent.send(self.request, &self.cancel) {
+            Ok(response) => Ok(FetchResponse::new(response, &url, self.cancel)),
+            Err(e) => Err(format!("error sending request for url ({}): {}", url, e)),
+        }
+    }
+}
+
+/// A response whose headers arrived, with its body still to be read.
+struct FetchResponse {
+    status: u16,
+    status_text: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    /// Where a redirect leads, resolved against the request's URL.
+    location: Option<String>,
+    body: ResponseReader,
+    cancel: http::Cancel,
+}
+
+impl FetchResponse {
+    fn new(response: http::Response, url: &url::Url, cancel: http::Cancel) -> FetchResponse {
+        let location = match response.status {
+            301 | 302 | 303 | 307 | 308 => response
+                .headers
+                .iter()
+                .find(|(name, _)| name == "location")
+                .and_then(|(_, location)| url.join(location).ok())
+                .map(String::from),
+            _ => None,
+        };
+
+        FetchResponse {
+            status: response.status,
+            status_text: response.status_text,
+            url: url.to_string(),
+            headers: response.headers,
+            location,
+            body: response.body,
+            cancel,
+        }
+    }
+
+    fn to_object<'a>(self, scope: &mut v8::HandleScope<'a>) -> v8::Local<'a, v8::Object> {
+        let obj = v8::Object::new(scope);
+
+        macro_rules! set {
+            ($name:expr, $value:expr) => {
+                let key = v8::String::new(scope, $name).unwrap();
+                let value = $value;
+                obj.set(scope, key.into(), value.into());
+            };
+        }
+
+        set!("status", v8::Integer::new(scope, self.status as i32));
+        set!(
+            "statusText",
+            v8::String::new(scope, &self.status_text).unwrap()
+        );
+        set!("url", v8::String::new(scope, &self.url).unwrap());
+
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let pair = [
+                    v8::String::new(scope, name).unwrap().into(),
+                    v8::String::new(scope, value).unwrap().into(),
+                ];
+                v8::Array::new_with_elements(scope, &pair).into()
+            })
+            .collect::<Vec<v8::Local<v8::Value>>>();
+        set!("headers", v8::Array::new_with_elements(scope, &headers));
+
+        match &self.location {
+            Some(location) => {
+                set!("location", v8::String::new(scope, location).unwrap());
+            }
+            None => {
+                set!("location", v8::null(scope));
+            }
+        }
+
+        let body = Body::Response {
+            reader: Arc::new(Mutex::new(self.body)),
+            reads: Vec::new(),
+            cancel: self.cancel,
+        };
+        let rid = scope.get_slot_mut::<Bodies>().unwrap().insert(body);
+        set!("body", v8::Integer::new_from_unsigned(scope, rid));
+
+        obj
+    }
+}
+
+/// Create a request body to stream with `fetchBodyWrite`, returning its
+/// resource id to pass to `fetch`.
+pub(crate) fn fetch_body(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    // Nothing is buffered, so each write waits until the request read it.
+    let (sender, receiver) = sync_channel(0);
+    let errored = Arc::new(AtomicBool::new(false));
+    let reader = StreamedBody {
+        receiver,
+        chunk: Vec::new(),
+        pos: 0,
+        errored: errored.clone(),
+    };
+    let body = Body::Request {
+        sender,
+        errored,
+        reader: Some(reader),
+    };
+    let rid = scope.get_slot_mut::<Bodies>().unwrap().insert(body);
+    rv.set(v8::Integer::new_from_unsigned(scope, rid).into());
+}
+
+fn bad_resource(scope: &mut v8::HandleScope) {
+    type_error(scope, "Bad resource ID");
+}
+
+/// Send a chunk of a request body, an `ArrayBufferView`, resolving once the
+/// request took it.
+pub(crate) fn fetch_body_write(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(0);
+    let chunk = match v8::Local::<v8::ArrayBufferView>::try_from(args.get(1)) {
+        Ok(view) => {
+            let mut buf = vec![0; view.byte_length()];
+            view.copy_contents(&mut buf);
+            buf
+        }
+        Err(_) => return type_error(scope, "chunk must be an ArrayBufferView"),
+    };
+    let sender = match scope.get_slot::<Bodies>().unwrap().bodies.get(&rid) {
+        Some(Body::Request { sender, .. }) => Some(sender.clone()),
+        _ => None,
+    };
+    let sender = match sender {
+        Some(sender) => sender,
+        None => return bad_resource(scope),
+    };
+    let promise = ops::spawn(scope, move || {
+        let result = sender.send(chunk);
+        ops::completion(move |scope| match result {
+            Ok(()) => Ok(v8::undefined(scope).into()),
+            Err(_) => {
+                let message = v8::String::new(scope, "The request was closed").unwrap();
+                Err(v8::Exception::type_error(scope, message))
+            }
+        })
+    });
+    rv.set(promise.into());
+}
+
+/// Read up to the given number of bytes of a response body, resolving to
+/// an `ArrayBuffer` that's empty at its end.
+pub(crate) fn fetch_body_read(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(0);
+    let len = args.get(1).uint32_value(scope).unwrap_or(0) as usize;
+    let reader = match scope.get_slot::<Bodies>().unwrap().bodies.get(&rid) {
+        Some(Body::Response { reader, .. }) => Some(reader.clone()),
+        _ => None,
+    };
+    let reader = match reader {
+        Some(reader) => reader,
+        None => return bad_resource(scope),
+    };
+    let (id, promise) = ops::spawn_cancellable(scope, move || {
+        let mut buf = vec![0; len];
+        let result = reader.lock().unwrap().read(&mut buf).map(|n| {
+            buf.truncate(n);
+            buf
+        });
+        ops::completion(move |scope| {
+            if let Some(Body::Response { reads, .. }) =
+                scope.get_slot_mut::<Bodies>().unwrap().bodies.get_mut(&rid)
+            {
+                reads.retain(|read| *read != id);
+            }
+            match result {
+                Ok(buf) => {
+                    let store =
+                        v8::ArrayBuffer::new_backing_store_from_boxed_slice(buf.into_boxed_slice());
+                    Ok(v8::ArrayBuffer::with_backing_store(scope, &store.make_shared()).into())
+                }
+                Err(e) => {
+                    let message = format!("error reading a response body: {}", e);
+                    let message = v8::String::new(scope, &message).unwrap();
+                    Err(v8::Exception::type_error(scope, message))
+                }
+            }
+        })
+    });
+    if let Some(Body::Response { reads, .. }) =
+        scope.get_slot_mut::<Bodies>().unwrap().bodies.get_mut(&rid)
+    {
+        reads.push(id);
+    }
+    rv.set(promise.into());
+}
+
+/// Close a body. A request body ends, failing the request if the second
+/// argument is true because its stream errored. A response body is
+/// dropped, and its connection shut down unless it was read to the end,
+/// and reads of it in progress are cancelled, so their promises never
+/// settle.
+pub(crate) fn fetch_body_close(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(0);
+    let errored = args.get(1).boolean_value(scope);
+    let body = scope.get_slot_mut::<Bodies>().unwrap().bodies.remove(&rid);
+    match body {
+        Some(Body::Request {
+            errored: flag,
+            sender,
+            ..
+        }) => {
+            flag.store(errored, Ordering::SeqCst);
+            drop(sender);
+        }
+        Some(Body::Response { reads, cancel, .. }) => {
+            cancel.cancel();
+            let ops = scope.get_slot_mut::<ops::PendingOps>().unwrap();
+            for id in reads {
+                ops.cancel(id);
+            }
+        }
+        None => {}
+    }
+}
@@ -0,0 +1,109 @@
+// FormData
+// https://xhr.spec.whatwg.org/#interface-formdata
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(() => {
+  function entryValue(value, filename) {
+    if (!(value instanceof Blob)) {
+      return String(value);
+    }
+    if (value instanceof File && filename === undefined) {
+      return value;
+    }
+    let name = 'blob';
+    if (filename !== undefined) {
+      name = String(filename);
+    } else if (value instanceof File) {
+      name = value.name;
+    }
+    return new File([value], name, { type: value.type });
+  }
+
+  class FormData {
+    #entries = [];
+
+    constructor(form = undefined) {
+      if (form !== undefined) {
+        throw new TypeError('FormData can only be constructed without a form');
+      }
+    }
+
+    append(name, value, filename = undefined) {
+      this.#entries.push([String(name), entryValue(value, filename)]);
+    }
+
+    delete(name) {
+      const key = String(name);
+      this.#entries = this.#entries.filter(([entryName]) => entryName !== key);
+    }
+
+    get(name) {
+      const key = String(name);
+      const entry = this.#entries.find(([entryName]) => entryName === key);
+      return entry === undefined ? null : entry[1];
+    }
+
+    getAll(name) {
+      const key = String(name);
+      return this.#entries.filter(([entryName]) => entryName === key).map(([, value]) => value);
+    }
+
+    has(name) {
+      const key = String(name);
+      return this.#entries.some(([entryName]) => entryName === key);
+    }
+
+    // Replaces the first entry named `name` and removes the others, or
+    // appends one if there's none.
+    set(name, value, filename = undefined) {
+      const key = String(name);
+      const entry = [key, entryValue(value, filename)];
+      const index = this.#entries.findIndex(([entryName]) => entryName === key);
+      if (index === -1) {
+        this.#entries.push(entry);
+        return;
+      }
+      this.#entries = this.#entries.filter(([entryName], i) => i <= index || entryName !== key);
+      this.#entries[index] = entry;
+    }
+
+    forEach(callback, thisArg = undefined) {
+      for (const [name, value] of this) {
+        callback.call(thisArg, value, name, this);
+      }
+    }
+
+    * entries() {
+      // Entries added while iterating are visited too.
+      for (let i = 0; i < this.#entries.length; i += 1) {
+        const [name, value] = this.#entries[i];
+        yield [name, value];
+      }
+    }
+
+    * keys() {
+      for (const [name] of this.entries()) {
+        yield name;
+      }
+    }
+
+    * values() {
+      for (const [, value] of this.entries()) {
+        yield value;
+      }
+    }
+
+    [Symbol.iterator]() {
+      return this.entries();
+    }
+  }
+
+  Object.defineProperty(FormData.prototype, Symbol.toStringTag, {
+    value: 'FormData',
+    configurable: true,
+  });
+
+  globalThis.FormData = FormData;
+});
@@ -0,0 +1,206 @@
+// The jstime:fs module.
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({
+  builtinModules,
+  fsOpen, fsOpenAsync, fsClose, fsRead, fsReadAsync, fsWrite, fsWriteAsync,
+  fsSeek, fsSeekAsync, fsFstat, fsFstatAsync, fsStat, fsStatAsync,
+  fsReadDir, fsReadDirAsync, fsMkdir, fsMkdirAsync, fsRemove, fsRemoveAsync,
+  fsRename, fsRenameAsync, fsCopyFile, fsCopyFileAsync, fsSymlink,
+  fsSymlinkAsync, fsRealPath, fsRealPathAsync, fsReadFile, fsReadFileAsync,
+  fsWriteFile, fsWriteFileAsync,
+}) => {
+  const SeekMode = Object.freeze({ Start: 0, Current: 1, End: 2 });
+
+  function toFileInfo(stat) {
+    const date = (ms) => (ms === null ? null : new Date(ms));
+    return {
+      ...stat,
+      mtime: date(stat.mtime),
+      atime: date(stat.atime),
+      birthtime: date(stat.birthtime),
+    };
+  }
+
+  function openOptions(options = { read: true }) {
+    return {
+      read: Boolean(options.read),
+      write: Boolean(options.write),
+      append: Boolean(options.append),
+      truncate: Boolean(options.truncate),
+      create: Boolean(options.create),
+      createNew: Boolean(options.createNew),
+    };
+  }
+
+  function checkBuffer(buffer) {
+    if (!ArrayBuffer.isView(buffer)) {
+      throw new TypeError('The buffer must be an ArrayBufferView');
+    }
+  }
+
+  class FsFile {
+    #rid;
+
+    constructor(rid) {
+      this.#rid = rid;
+    }
+
+    get rid() {
+      return this.#rid;
+    }
+
+    // Reads into `buffer`, resolving to the number of bytes read, or null
+    // at the end of the file.
+    async read(buffer) {
+      checkBuffer(buffer);
+      const bytes = new Uint8Array(await fsReadAsync(this.#rid, buffer.byteLength));
+      new Uint8Array(buffer.buffer, buffer.byteOffset, buffer.byteLength).set(bytes);
+      return bytes.length === 0 && buffer.byteLength > 0 ? null : bytes.length;
+    }
+
+    readSync(buffer) {
+      checkBuffer(buffer);
+      const n = fsRead(this.#rid, buffer);
+      return n === 0 && buffer.byteLength > 0 ? null : n;
+    }
+
+    // Writes all of `data`, a string or an ArrayBufferView, resolving to
+    // the number of bytes written.
+    async write(data) {
+      return fsWriteAsync(this.#rid, data);
+    }
+
+    writeSync(data) {
+      return fsWrite(this.#rid, data);
+    }
+
+    async seek(offset, whence = SeekMode.Start) {
+      return fsSeekAsync(this.#rid, offset, whence);
+    }
+
+    seekSync(offset, whence = SeekMode.Start) {
+      return fsSeek(this.#rid, offset, whence);
+    }
+
+    async stat() {
+      return toFileInfo(await fsFstatAsync(this.#rid));
+    }
+
+    statSync() {
+      return toFileInfo(fsFstat(this.#rid));
+    }
+
+    close() {
+      fsClose(this.#rid);
+    }
+  }
+
+  const recursive = (options) => Boolean(options && options.recursive);
+
+  // The promise-based functions are async so that invalid arguments and
+  // denied permissions reject rather than throw.
+  const fs = {
+    SeekMode,
+    FsFile,
+
+    async open(path, options) {
+      return new FsFile(await fsOpenAsync(String(path), openOptions(options)));
+    },
+    openSync(path, options) {
+      return new FsFile(fsOpen(String(path), openOptions(options)));
+    },
+
+    async readFile(path) {
+      return new Uint8Array(await fsReadFileAsync(String(path), false));
+    },
+    readFileSync(path) {
+      return new Uint8Array(fsReadFile(String(path), false));
+    },
+    async readTextFile(path) {
+      return fsReadFileAsync(String(path), true);
+    },
+    readTextFileSync(path) {
+      return fsReadFile(String(path), true);
+    },
+
+    async writeFile(path, data, options) {
+      return fsWriteFileAsync(String(path), data, options);
+    },
+    writeFileSync(path, data, options) {
+      return fsWriteFile(String(path), data, options);
+    },
+    async writeTextFile(path, text, options) {
+      return fsWriteFileAsync(String(path), String(text), options);
+    },
+    writeTextFileSync(path, text, options) {
+      return fsWriteFile(String(path), String(text), options);
+    },
+
+    async stat(path) {
+      return toFileInfo(await fsStatAsync(String(path), true));
+    },
+    statSync(path) {
+      return toFileInfo(fsStat(String(path), true));
+    },
+    async lstat(path) {
+      return toFileInfo(await fsStatAsync(String(path), false));
+    },
+    lstatSync(path) {
+      return toFileInfo(fsStat(String(path), false));
+    },
+
+    async readDir(path) {
+      return fsReadDirAsync(String(path));
+    },
+    readDirSync(path) {
+      return fsReadDir(String(path));
+    },
+
+    async mkdir(path, options) {
+      return fsMkdirAsync(String(path), recursive(options));
+    },
+    mkdirSync(path, options) {
+      return fsMkdir(String(path), recursive(options));
+    },
+
+    async remove(path, options) {
+      return fsRemoveAsync(String(path), recursive(options));
+    },
+    removeSync(path, options) {
+      return fsRemove(String(path), recursive(options));
+    },
+
+    async rename(from, to) {
+      return fsRenameAsync(String(from), String(to));
+    },
+    renameSync(from, to) {
+      return fsRename(String(from), String(to));
+    },
+
+    async copyFile(from, to) {
+      return fsCopyFileAsync(String(from), String(to));
+    },
+    copyFileSync(from, to) {
+      return fsCopyFile(String(from), String(to));
+    },
+
+    async symlink(target, path) {
+      return fsSymlinkAsync(String(target), String(path));
+    },
+    symlinkSync(target, path) {
+      return fsSymlink(String(target), String(path));
+    },
+
+    async realPath(path) {
+      return fsRealPathAsync(String(path));
+    },
+    realPathSync(path) {
+      return fsRealPath(String(path));
+    },
+  };
+
+  builtinModules['jstime:fs'] = { default: fs, ...fs };
+});
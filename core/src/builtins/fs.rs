@@ -0,0 +1,722 @@
+// Filesystem bindings behind jstime:fs and WASI. Most come in pairs, one
+// that blocks and one suffixed `Async` that returns a promise and does the
+// work on a background thread.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::{File, FileType, Metadata, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::ops;
+use crate::permissions::{check, check_all, Kind};
+
+/// Open files, keyed by resource id. Ids 0, 1 and 2 are stdin, stdout and
+/// stderr.
+pub(crate) struct Files {
+    next_rid: u32,
+    files: HashMap<u32, File>,
+}
+
+impl Files {
+    pub(crate) fn new() -> Self {
+        Self {
+            next_rid: 3,
+            files: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, file: File) -> u32 {
+        let rid = self.next_rid;
+        self.next_rid += 1;
+        self.files.insert(rid, file);
+        rid
+    }
+}
+
+/// An `Error` carrying a POSIX style `code` such as `ENOENT`.
+pub(crate) fn io_error<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    err: std::io::Error,
+) -> v8::Local<'a, v8::Value> {
+    let message = v8::String::new(scope, &err.to_string()).unwrap();
+    let exception = v8::Exception::error(scope, message);
+    let code_key = v8::String::new(scope, "code").unwrap();
+    let code = v8::String::new(scope, error_code(&err)).unwrap();
+    v8::Local::<v8::Object>::try_from(exception)
+        .unwrap()
+        .set(scope, code_key.into(), code.into());
+    exception
+}
+
+/// Throw the error of `io_error`.
+pub(crate) fn io_exception(scope: &mut v8::HandleScope, err: std::io::Error) {
+    let exception = io_error(scope, err);
+    scope.throw_exception(exception);
+}
+
+fn error_code(err: &std::io::Error) -> &'static str {
+    match err.kind() {
+        ErrorKind::NotFound => "ENOENT",
+        ErrorKind::PermissionDenied => "EACCES",
+        ErrorKind::AlreadyExists => "EEXIST",
+        ErrorKind::InvalidInput => "EINVAL",
+        ErrorKind::Unsupported => "ENOTSUP",
+        ErrorKind::BrokenPipe => "EPIPE",
+        _ => os_error_code(err).unwrap_or("EIO"),
+    }
+}
+
+// The kinds for these errors are newer than the Rust version we support, so
+// they're told apart by the OS error.
+#[cfg(unix)]
+fn os_error_code(err: &std::io::Error) -> Option<&'static str> {
+    match err.raw_os_error()? {
+        libc::ENOTDIR => Some("ENOTDIR"),
+        libc::EISDIR => Some("EISDIR"),
+        libc::ENOTEMPTY => Some("ENOTEMPTY"),
+        _ => None,
+    }
+}
+
+#[cfg(windows)]
+fn os_error_code(err: &std::io::Error) -> Option<&'static str> {
+    const ERROR_DIR_NOT_EMPTY: i32 = 145;
+    const ERROR_DIRECTORY: i32 = 267;
+    match err.raw_os_error()? {
+        ERROR_DIRECTORY => Some("ENOTDIR"),
+        ERROR_DIR_NOT_EMPTY => Some("ENOTEMPTY"),
+        _ => None,
+    }
+}
+
+fn bad_resource() -> std::io::Error {
+    std::io::Error::new(ErrorKind::InvalidInput, "Bad resource ID")
+}
+
+fn get_bool(scope: &mut v8::HandleScope, obj: v8::Local<v8::Object>, name: &str) -> bool {
+    let key = v8::String::new(scope, name).unwrap();
+    obj.get(scope, key.into())
+        .map(|v| v.boolean_value(scope))
+        .unwrap_or(false)
+}
+
+/// Something to read from or write to. Files are cloned, so an op can use
+/// them on another thread, sharing the position of the original.
+enum Handle {
+    Stdin,
+    Stdout,
+    Stderr,
+    File(File),
+}
+
+impl Handle {
+    fn read(self, len: usize) -> std::io::Result<Vec<u8>> {
+        let mut buf = vec![0; len];
+        let n = match self {
+            Handle::Stdin => std::io::stdin().read(&mut buf)?,
+            Handle::File(mut file) => file.read(&mut buf)?,
+            Handle::Stdout | Handle::Stderr => return Err(bad_resource()),
+        };
+        buf.truncate(n);
+        Ok(buf)
+    }
+
+    fn write(self, data: &[u8]) -> std::io::Result<()> {
+        match self {
+            Handle::Stdout => {
+                let mut stdout = std::io::stdout();
+                stdout.write_all(data).and_then(|_| stdout.flush())
+            }
+            Handle::Stderr => std::io::stderr().write_all(data),
+            Handle::File(mut file) => file.write_all(data),
+            Handle::Stdin => Err(bad_resource()),
+        }
+    }
+}
+
+/// The handle for resource id `rid`, or `None` after throwing if there's
+/// no such resource.
+fn handle(scope: &mut v8::HandleScope, rid: u32) -> Option<Handle> {
+    let result = match rid {
+        0 => Ok(Handle::Stdin),
+        1 => Ok(Handle::Stdout),
+        2 => Ok(Handle::Stderr),
+        _ => match scope.get_slot::<Files>().unwrap().files.get(&rid) {
+            Some(file) => file.try_clone().map(Handle::File),
+            None => Err(bad_resource()),
+        },
+    };
+    match result {
+        Ok(handle) => Some(handle),
+        Err(e) => {
+            io_exception(scope, e);
+            None
+        }
+    }
+}
+
+fn file(scope: &mut v8::HandleScope, rid: u32) -> Option<File> {
+    match handle(scope, rid)? {
+        Handle::File(file) => Some(file),
+        _ => {
+            io_exception(scope, bad_resource());
+            None
+        }
+    }
+}
+
+/// The bytes of a string, as UTF-8, or of an `ArrayBufferView`.
+fn bytes(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Option<Vec<u8>> {
+    if value.is_string() {
+        return Some(value.to_rust_string_lossy(scope).into_bytes());
+    }
+    match v8::Local::<v8::ArrayBufferView>::try_from(value) {
+        Ok(view) => {
+            let mut buf = vec![0; view.byte_length()];
+            view.copy_contents(&mut buf);
+            Some(buf)
+        }
+        Err(_) => {
+            super::exception(scope, "data must be a string or an ArrayBufferView");
+            None
+        }
+    }
+}
+
+/// Filesystem work, with its arguments checked and permissions granted, so
+/// it can run on any thread.
+enum Op {
+    Open(PathBuf, OpenOptions),
+    Read(Handle, usize),
+    Write(Handle, Vec<u8>),
+    Seek(File, SeekFrom),
+    Fstat(File),
+    Stat(PathBuf, bool),
+    ReadDir(PathBuf),
+    Mkdir(PathBuf, bool),
+    Remove(PathBuf, bool),
+    Rename(PathBuf, PathBuf),
+    CopyFile(PathBuf, PathBuf),
+    Symlink(PathBuf, PathBuf),
+    RealPath(PathBuf),
+    ReadFile(PathBuf, bool),
+    WriteFile(PathBuf, Vec<u8>, OpenOptions),
+}
+
+/// What an `Op` produced, to be turned into a JS value on the main thread.
+enum Output {
+    Undefined,
+    File(File),
+    Bytes(Vec<u8>),
+    Text(String),
+    Count(usize),
+    Position(u64),
+    Stat(Metadata),
+    Entries(Vec<(String, FileType)>),
+    Path(PathBuf),
+}
+
+impl Op {
+    fn run(self) -> std::io::Result<Output> {
+        Ok(match self {
+            Op::Open(path, options) => Output::File(options.open(path)?),
+            Op::Read(handle, len) => Output::Bytes(handle.read(len)?),
+            Op::Write(handle, data) => {
+                handle.write(&data)?;
+                Output::Count(data.len())
+            }
+            Op::Seek(mut file, pos) => Output::Position(file.seek(pos)?),
+            Op::Fstat(file) => Output::Stat(file.metadata()?),
+            Op::Stat(path, true) => Output::Stat(std::fs::metadata(path)?),
+            Op::Stat(path, false) => Output::Stat(std::fs::symlink_metadata(path)?),
+            Op::ReadDir(path) => {
+                let mut entries = Vec::new();
+                for entry in std::fs::read_dir(path)? {
+                    let entry = entry?;
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    entries.push((name, entry.file_type()?));
+                }
+                Output::Entries(entries)
+            }
+            Op::Mkdir(path, true) => {
+                std::fs::create_dir_all(path)?;
+                Output::Undefined
+            }
+            Op::Mkdir(path, false) => {
+                std::fs::create_dir(path)?;
+                Output::Undefined
+            }
+            Op::Remove(path, recursive) => {
+                let metadata = std::fs::symlink_metadata(&path)?;
+                if !metadata.is_dir() {
+                    std::fs::remove_file(path)?;
+                } else if recursive {
+                    std::fs::remove_dir_all(path)?;
+                } else {
+                    std::fs::remove_dir(path)?;
+                }
+                Output::Undefined
+            }
+            Op::Rename(from, to) => {
+                std::fs::rename(from, to)?;
+                Output::Undefined
+            }
+            Op::CopyFile(from, to) => {
+                std::fs::copy(from, to)?;
+                Output::Undefined
+            }
+            Op::Symlink(target, path) => {
+                create_symlink(&target, &path)?;
+                Output::Undefined
+            }
+            Op::RealPath(path) => Output::Path(std::fs::canonicalize(path)?),
+            Op::ReadFile(path, false) => Output::Bytes(std::fs::read(path)?),
+            Op::ReadFile(path, true) => {
+                let bytes = std::fs::read(path)?;
+                Output::Text(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            Op::WriteFile(path, data, options) => {
+                options.open(path)?.write_all(&data)?;
+                Output::Undefined
+            }
+        })
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, path: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, path: &Path) -> std::io::Result<()> {
+    // Windows needs to know what kind of link to make, relative targets are
+    // relative to the link.
+    let resolved = match path.parent() {
+        Some(dir) => dir.join(target),
+        None => target.to_owned(),
+    };
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, path)
+    } else {
+        std::os::windows::fs::symlink_file(target, path)
+    }
+}
+
+impl Output {
+    fn into_value<'a>(self, scope: &mut v8::HandleScope<'a>) -> v8::Local<'a, v8::Value> {
+        match self {
+            Output::Undefined => v8::undefined(scope).into(),
+            Output::File(file) => {
+                let rid = scope.get_slot_mut::<Files>().unwrap().insert(file);
+                v8::Integer::new_from_unsigned(scope, rid).into()
+            }
+            Output::Bytes(bytes) => {
+                let store =
+                    v8::ArrayBuffer::new_backing_store_from_boxed_slice(bytes.into_boxed_slice());
+                v8::ArrayBuffer::with_backing_store(scope, &store.make_shared()).into()
+            }
+            Output::Text(text) => v8::String::new(scope, &text).unwrap().into(),
+            Output::Count(n) => v8::Number::new(scope, n as f64).into(),
+            Output::Position(pos) => v8::Number::new(scope, pos as f64).into(),
+            Output::Stat(metadata) => stat_object(scope, metadata).into(),
+            Output::Entries(entries) => {
+                let entries = entries
+                    .into_iter()
+                    .map(|(name, file_type)| entry_object(scope, &name, file_type).into())
+                    .collect::<Vec<_>>();
+                v8::Array::new_with_elements(scope, &entries).into()
+            }
+            Output::Path(path) => v8::String::new(scope, &path.to_string_lossy())
+                .unwrap()
+                .into(),
+        }
+    }
+}
+
+fn millis(time: std::io::Result<SystemTime>) -> Option<f64> {
+    let time = time.ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(time.as_secs_f64() * 1e3)
+}
+
+fn stat_object<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    metadata: Metadata,
+) -> v8::Local<'a, v8::Object> {
+    let obj = v8::Object::new(scope);
+
+    macro_rules! set {
+        ($name:expr, $value:expr) => {
+            let key = v8::String::new(scope, $name).unwrap();
+            let value = $value;
+            obj.set(scope, key.into(), value.into());
+        };
+    }
+    macro_rules! set_time {
+        ($name:expr, $value:expr) => {
+            match millis($value) {
+                Some(ms) => {
+                    set!($name, v8::Number::new(scope, ms));
+                }
+                None => {
+                    set!($name, v8::null(scope));
+                }
+            }
+        };
+    }
+
+    let file_type = metadata.file_type();
+    set!("isFile", v8::Boolean::new(scope, file_type.is_file()));
+    set!("isDirectory", v8::Boolean::new(scope, file_type.is_dir()));
+    set!("isSymlink", v8::Boolean::new(scope, file_type.is_symlink()));
+    set!("size", v8::Number::new(scope, metadata.len() as f64));
+    set_time!("mtime", metadata.modified());
+    set_time!("atime", metadata.accessed());
+    set_time!("birthtime", metadata.created());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        set!("dev", v8::Number::new(scope, metadata.dev() as f64));
+        set!("ino", v8::Number::new(scope, metadata.ino() as f64));
+        set!("nlink", v8::Number::new(scope, metadata.nlink() as f64));
+        set!("mode", v8::Number::new(scope, metadata.mode() as f64));
+    }
+
+    obj
+}
+
+/// A `{ name, isFile, isDirectory, isSymlink }` directory entry.
+fn entry_object<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    name: &str,
+    file_type: FileType,
+) -> v8::Local<'a, v8::Object> {
+    let obj = v8::Object::new(scope);
+    let fields: [(&str, v8::Local<v8::Value>); 4] = [
+        ("name", v8::String::new(scope, name).unwrap().into()),
+        (
+            "isFile",
+            v8::Boolean::new(scope, file_type.is_file()).into(),
+        ),
+        (
+            "isDirectory",
+            v8::Boolean::new(scope, file_type.is_dir()).into(),
+        ),
+        (
+            "isSymlink",
+            v8::Boolean::new(scope, file_type.is_symlink()).into(),
+        ),
+    ];
+    for (name, value) in fields.iter() {
+        let key = v8::String::new(scope, name).unwrap();
+        obj.set(scope, key.into(), *value);
+    }
+    obj
+}
+
+/// Check access to `path`, for use with `?`.
+fn allow(scope: &mut v8::HandleScope, kind: Kind, path: &Path) -> Option<()> {
+    if check(scope, kind, &path.to_string_lossy()) {
+        Some(())
+    } else {
+        None
+    }
+}
+
+fn path_arg(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments, i: i32) -> PathBuf {
+    PathBuf::from(args.get(i).to_rust_string_lossy(scope))
+}
+
+// Each of these reads the arguments of a binding into an `Op`, or returns
+// `None` after throwing if they're invalid or access isn't granted.
+
+fn open(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let path = args.get(0).to_rust_string_lossy(scope);
+    let options = match v8::Local::<v8::Object>::try_from(args.get(1)) {
+        Ok(options) => options,
+        Err(_) => {
+            super::exception(scope, "options must be an object");
+            return None;
+        }
+    };
+
+    let read = get_bool(scope, options, "read");
+    let write = get_bool(scope, options, "write");
+    let append = get_bool(scope, options, "append");
+    let truncate = get_bool(scope, options, "truncate");
+    let create = get_bool(scope, options, "create");
+    let create_new = get_bool(scope, options, "createNew");
+    if read && !check(scope, Kind::Read, &path) {
+        return None;
+    }
+    if (write || append || truncate || create || create_new) && !check(scope, Kind::Write, &path) {
+        return None;
+    }
+
+    let mut open_options = OpenOptions::new();
+    open_options
+        .read(read)
+        .write(write)
+        .append(append)
+        .truncate(truncate)
+        .create(create)
+        .create_new(create_new);
+    Some(Op::Open(path.into(), open_options))
+}
+
+fn read_async(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(0);
+    let len = args.get(1).uint32_value(scope).unwrap_or(0);
+    Some(Op::Read(handle(scope, rid)?, len as usize))
+}
+
+fn write(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(0);
+    let data = bytes(scope, args.get(1))?;
+    Some(Op::Write(handle(scope, rid)?, data))
+}
+
+/// Seek relative to the start (0), current position (1) or end (2).
+fn seek(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(0);
+    let offset = args.get(1).integer_value(scope).unwrap_or(0);
+    let pos = match args.get(2).uint32_value(scope).unwrap_or(0) {
+        0 => SeekFrom::Start(offset as u64),
+        1 => SeekFrom::Current(offset),
+        2 => SeekFrom::End(offset),
+        _ => {
+            super::exception(scope, "Invalid seek mode");
+            return None;
+        }
+    };
+    Some(Op::Seek(file(scope, rid)?, pos))
+}
+
+fn fstat(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(0);
+    Some(Op::Fstat(file(scope, rid)?))
+}
+
+/// Stat a path, following symlinks unless the second argument is `false`.
+fn stat(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let path = path_arg(scope, args, 0);
+    let follow = args.length() < 2 || args.get(1).boolean_value(scope);
+    allow(scope, Kind::Read, &path)?;
+    Some(Op::Stat(path, follow))
+}
+
+fn read_dir(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let path = path_arg(scope, args, 0);
+    allow(scope, Kind::Read, &path)?;
+    Some(Op::ReadDir(path))
+}
+
+fn mkdir(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let path = path_arg(scope, args, 0);
+    let recursive = args.get(1).boolean_value(scope);
+    allow(scope, Kind::Write, &path)?;
+    Some(Op::Mkdir(path, recursive))
+}
+
+/// Remove a file or directory, including its contents when the second
+/// argument is `true`.
+fn remove(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let path = path_arg(scope, args, 0);
+    let recursive = args.get(1).boolean_value(scope);
+    allow(scope, Kind::Write, &path)?;
+    Some(Op::Remove(path, recursive))
+}
+
+fn rename(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let from = path_arg(scope, args, 0);
+    let to = path_arg(scope, args, 1);
+    allow(scope, Kind::Write, &from)?;
+    allow(scope, Kind::Write, &to)?;
+    Some(Op::Rename(from, to))
+}
+
+fn copy_file(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let from = path_arg(scope, args, 0);
+    let to = path_arg(scope, args, 1);
+    allow(scope, Kind::Read, &from)?;
+    allow(scope, Kind::Write, &to)?;
+    Some(Op::CopyFile(from, to))
+}
+
+/// Create a symlink at the second argument pointing at the first. A
+/// symlink can point anywhere, so this requires full read and write
+/// access.
+fn symlink(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let target = path_arg(scope, args, 0);
+    let path = path_arg(scope, args, 1);
+    if !check_all(scope, Kind::Read) || !check_all(scope, Kind::Write) {
+        return None;
+    }
+    Some(Op::Symlink(target, path))
+}
+
+fn real_path(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let path = path_arg(scope, args, 0);
+    allow(scope, Kind::Read, &path)?;
+    Some(Op::RealPath(path))
+}
+
+/// Read a whole file into an `ArrayBuffer`, or a string if the second
+/// argument is `true`.
+fn read_file(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let path = path_arg(scope, args, 0);
+    let text = args.get(1).boolean_value(scope);
+    allow(scope, Kind::Read, &path)?;
+    Some(Op::ReadFile(path, text))
+}
+
+/// Write a string or `ArrayBufferView` to a file, replacing it unless
+/// `append` is set. `create` and `createNew` work as for `fsOpen`, and the
+/// file is created by default.
+fn write_file(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Option<Op> {
+    let path = path_arg(scope, args, 0);
+    let data = bytes(scope, args.get(1))?;
+    let (append, create, create_new) = match v8::Local::<v8::Object>::try_from(args.get(2)) {
+        Ok(options) => {
+            let key = v8::String::new(scope, "create").unwrap();
+            let create = match options.get(scope, key.into()) {
+                Some(value) if !value.is_undefined() => value.boolean_value(scope),
+                _ => true,
+            };
+            (
+                get_bool(scope, options, "append"),
+                create,
+                get_bool(scope, options, "createNew"),
+            )
+        }
+        Err(_) => (false, true, false),
+    };
+    allow(scope, Kind::Write, &path)?;
+
+    let mut options = OpenOptions::new();
+    options
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .create(create)
+        .create_new(create_new);
+    Some(Op::WriteFile(path, data, options))
+}
+
+macro_rules! bindings {
+    ($($parse:ident => $sync:ident, $promise:ident;)*) => {
+        $(
+            pub(crate) fn $sync(
+                scope: &mut v8::HandleScope,
+                args: v8::FunctionCallbackArguments,
+                mut rv: v8::ReturnValue,
+            ) {
+                if let Some(op) = $parse(scope, &args) {
+                    match op.run() {
+                        Ok(output) => rv.set(output.into_value(scope)),
+                        Err(e) => io_exception(scope, e),
+                    }
+                }
+            }
+
+            pub(crate) fn $promise(
+                scope: &mut v8::HandleScope,
+                args: v8::FunctionCallbackArguments,
+                mut rv: v8::ReturnValue,
+            ) {
+                if let Some(op) = $parse(scope, &args) {
+                    rv.set(spawn(scope, op).into());
+                }
+            }
+        )*
+    };
+}
+
+bindings! {
+    open => fs_open, fs_open_async;
+    write => fs_write, fs_write_async;
+    seek => fs_seek, fs_seek_async;
+    fstat => fs_fstat, fs_fstat_async;
+    stat => fs_stat, fs_stat_async;
+    read_dir => fs_read_dir, fs_read_dir_async;
+    mkdir => fs_mkdir, fs_mkdir_async;
+    remove => fs_remove, fs_remove_async;
+    rename => fs_rename, fs_rename_async;
+    copy_file => fs_copy_file, fs_copy_file_async;
+    symlink => fs_symlink, fs_symlink_async;
+    real_path => fs_real_path, fs_real_path_async;
+    read_file => fs_read_file, fs_read_file_async;
+    write_file => fs_write_file, fs_write_file_async;
+}
+
+fn spawn<'a>(scope: &mut v8::HandleScope<'a>, op: Op) -> v8::Local<'a, v8::Promise> {
+    ops::spawn(scope, move || {
+        let result = op.run();
+        ops::completion(move |scope| match result {
+            Ok(output) => Ok(output.into_value(scope)),
+            Err(e) => Err(io_error(scope, e)),
+        })
+    })
+}
+
+/// Read into the given `Uint8Array`, returning the number of bytes read.
+pub(crate) fn fs_read(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(0);
+    let view = match v8::Local::<v8::ArrayBufferView>::try_from(args.get(1)) {
+        Ok(view) => view,
+        Err(_) => return super::exception(scope, "buffer must be an ArrayBufferView"),
+    };
+    let handle = match handle(scope, rid) {
+        Some(handle) => handle,
+        None => return,
+    };
+
+    match handle.read(view.byte_length()) {
+        Ok(buf) => {
+            let store = view.buffer(scope).unwrap().get_backing_store();
+            let offset = view.byte_offset();
+            for (i, byte) in buf.iter().enumerate() {
+                store[offset + i].set(*byte);
+            }
+            rv.set(v8::Integer::new_from_unsigned(scope, buf.len() as u32).into());
+        }
+        Err(e) => io_exception(scope, e),
+    }
+}
+
+/// Read up to the given number of bytes, resolving to an `ArrayBuffer`
+/// that's empty at the end of the file.
+pub(crate) fn fs_read_async(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    if let Some(op) = read_async(scope, &args) {
+        rv.set(spawn(scope, op).into());
+    }
+}
+
+pub(crate) fn fs_close(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(0);
+    if rid > 2
+        && scope
+            .get_slot_mut::<Files>()
+            .unwrap()
+            .files
+            .remove(&rid)
+            .is_none()
+    {
+        io_exception(scope, bad_resource());
+    }
+}
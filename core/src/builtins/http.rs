@@ -0,0 +1,559 @@
+// A small HTTP/1.1 client for `fetch`. It owns its sockets, so that a
+// request can be cancelled by shutting its connection down, even while a
+// thread is blocked reading from it. Connections whose response was read
+// to the end are kept for reuse by later requests to the same origin.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long to wait for a connection before failing the request. Once
+/// connected, a request waits for the server for as long as it takes, like
+/// long polling or server-sent events do, until it's cancelled.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The longest status or header line a response may have.
+const MAX_LINE: u64 = 64 * 1024;
+
+fn error(kind: ErrorKind, message: &str) -> std::io::Error {
+    std::io::Error::new(kind, message)
+}
+
+/// Cancels a request by shutting down the connection it's using, if it has
+/// one yet, which ends any read or write blocked on it.
+#[derive(Clone, Default)]
+pub(crate) struct Cancel(Arc<Mutex<CancelState>>);
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: bool,
+    socket: Option<TcpStream>,
+}
+
+impl Cancel {
+    pub(crate) fn cancel(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.cancelled = true;
+        if let Some(socket) = state.socket.take() {
+            let _ = socket.shutdown(Shutdown::Both);
+        }
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.lock().unwrap().cancelled
+    }
+
+    /// Use `socket` for the request until `release`, failing if it was
+    /// cancelled already.
+    fn watch(&self, socket: &TcpStream) -> std::io::Result<()> {
+        let mut state = self.0.lock().unwrap();
+        if state.cancelled {
+            return Err(error(ErrorKind::Interrupted, "The request was cancelled"));
+        }
+        state.socket = Some(socket.try_clone()?);
+        Ok(())
+    }
+
+    fn release(&self) {
+        self.0.lock().unwrap().socket = None;
+    }
+}
+
+pub(crate) enum RequestBody {
+    None,
+    Bytes(Vec<u8>),
+    /// Sent chunked, as its length isn't known up front.
+    Stream(Box<dyn Read + Send>),
+}
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) url: url::Url,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: RequestBody,
+}
+
+/// A response whose head arrived, with its body still to be read. Header
+/// names are lowercase.
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) status_text: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Box<dyn Read + Send>,
+}
+
+type Origin = (String, String, u16);
+
+enum Stream {
+    Plain(TcpStream),
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.read(buf),
+            // Many servers close the connection without a close_notify, so
+            // that's treated as the end too, and bodies with a length tell
+            // when they were cut short.
+            Stream::Tls(stream) => match stream.read(buf) {
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(0),
+                result => result,
+            },
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.write(buf),
+            Stream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.flush(),
+            Stream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+struct Connection {
+    origin: Origin,
+    reader: BufReader<Stream>,
+    /// The socket under `reader`, to shut down.
+    socket: TcpStream,
+}
+
+/// Makes requests, sharing idle connections between them.
+#[derive(Clone)]
+pub(crate) struct Agent {
+    idle: Arc<Mutex<HashMap<Origin, Vec<Connection>>>>,
+    max_idle_per_origin: usize,
+    tls: Arc<rustls::ClientConfig>,
+}
+
+impl Agent {
+    pub(crate) fn new(max_idle_per_origin: usize) -> Self {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                ta.subject,
+                ta.spki,
+                ta.name_constraints,
+            )
+        }));
+        let tls = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Self {
+            idle: Arc::new(Mutex::new(HashMap::new())),
+            max_idle_per_origin,
+            tls: Arc::new(tls),
+        }
+    }
+
+    /// Send `request`, returning once the head of its response arrived.
+    /// Redirects aren't followed.
+    pub(crate) fn send(&self, mut request: Request, cancel: &Cancel) -> std::io::Result<Response> {
+        let origin = match (request.url.host_str(), request.url.port_or_known_default()) {
+            (Some(host), Some(port)) => (request.url.scheme().to_owned(), host.to_owned(), port),
+            _ => return Err(error(ErrorKind::InvalidInput, "The URL has no host")),
+        };
+
+        // The server may have closed an idle connection meanwhile, which
+        // only shows once it's used, so a request that can be sent again
+        // is, on a new connection.
+        if !matches!(request.body, RequestBody::Stream(_)) {
+            let idle = self
+                .idle
+                .lock()
+                .unwrap()
+                .get_mut(&origin)
+                .and_then(Vec::pop);
+            if let Some(connection) = idle {
+                match self.exchange(connection, &mut request, cancel) {
+                    Err(e) if is_stale(&e) && !cancel.is_cancelled() => {}
+                    result => return result,
+                }
+            }
+        }
+        let connection = self.connect(origin)?;
+        self.exchange(connection, &mut request, cancel)
+    }
+
+    fn connect(&self, origin: Origin) -> std::io::Result<Connection> {
+        // IPv6 hosts are in brackets in URLs.
+        let host = origin.1.trim_start_matches('[').trim_end_matches(']');
+        let mut socket = Err(error(ErrorKind::NotFound, "The host has no address"));
+        for addr in (host, origin.2).to_socket_addrs()? {
+            socket = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT);
+            if socket.is_ok() {
+                break;
+            }
+        }
+        let socket = socket?;
+        socket.set_nodelay(true)?;
+
+        let stream = if origin.0 == "https" {
+            let name = rustls::ServerName::try_from(host)
+                .map_err(|e| std::io::Error::new(ErrorKind::InvalidInput, e))?;
+            let tls = rustls::ClientConnection::new(self.tls.clone(), name)
+                .map_err(|e| std::io::Error::new(ErrorKind::Other, e))?;
+            Stream::Tls(Box::new(rustls::StreamOwned::new(tls, socket.try_clone()?)))
+        } else {
+            Stream::Plain(socket.try_clone()?)
+        };
+        Ok(Connection {
+            origin,
+            reader: BufReader::new(stream),
+            socket,
+        })
+    }
+
+    fn exchange(
+        &self,
+        mut connection: Connection,
+        request: &mut Request,
+        cancel: &Cancel,
+    ) -> std::io::Result<Response> {
+        cancel.watch(&connection.socket)?;
+        let result = write_request(connection.reader.get_mut(), request)
+            .and_then(|()| read_head(&mut connection.reader));
+        let head = match result {
+            Ok(head) => head,
+            Err(e) => {
+                cancel.release();
+                return Err(e);
+            }
+        };
+
+        let header = |name: &str| {
+            head.headers
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.to_ascii_lowercase())
+        };
+        let framing = if request.method.eq_ignore_ascii_case("HEAD")
+            || head.status == 204
+            || head.status == 304
+        {
+            Framing::Length(0)
+        } else if header("transfer-encoding").map_or(false, |value| value.ends_with("chunked")) {
+            Framing::Chunked(Chunk::Size)
+        } else if let Some(length) = header("content-length") {
+            match length.trim().parse() {
+                Ok(length) => Framing::Length(length),
+                Err(_) => {
+                    cancel.release();
+                    return Err(error(ErrorKind::InvalidData, "Invalid Content-Length"));
+                }
+            }
+        } else {
+            Framing::Close
+        };
+        let reusable = head.keep_alive
+            && !header("connection").map_or(false, |value| value.contains("close"))
+            && !matches!(framing, Framing::Close);
+
+        let mut body = ResponseBody {
+            agent: self.clone(),
+            cancel: cancel.clone(),
+            connection: Some(connection),
+            framing,
+            reusable,
+        };
+        // A body that's empty is over already.
+        if let Framing::Length(0) = body.framing {
+            body.finish();
+        }
+        Ok(Response {
+            status: head.status,
+            status_text: head.status_text,
+            headers: head.headers,
+            body: Box::new(body),
+        })
+    }
+
+    fn release(&self, connection: Connection) {
+        let mut idle = self.idle.lock().unwrap();
+        let connections = idle.entry(connection.origin.clone()).or_default();
+        if connections.len() < self.max_idle_per_origin {
+            connections.push(connection);
+        }
+    }
+}
+
+/// Whether `e` means that an idle connection was closed before it was used.
+fn is_stale(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::UnexpectedEof
+            | ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+    )
+}
+
+fn write_request(stream: &mut Stream, request: &mut Request) -> std::io::Result<()> {
+    let url = &request.url;
+    let has = |name: &str| {
+        request
+            .headers
+            .iter()
+            .any(|(n, _)| n.eq_ignore_ascii_case(name))
+    };
+
+    let mut head = format!(
+        "{} {} HTTP/1.1\r\n",
+        request.method,
+        &url[url::Position::BeforePath..url::Position::AfterQuery]
+    );
+    if !has("host") {
+        let host = url.host_str().unwrap_or_default();
+        match url.port() {
+            Some(port) => head.push_str(&format!("Host: {}:{}\r\n", host, port)),
+            None => head.push_str(&format!("Host: {}\r\n", host)),
+        }
+    }
+    for (name, value) in &request.headers {
+        if name.contains(&['\r', '\n', ':'][..]) || value.contains(&['\r', '\n'][..]) {
+            return Err(error(ErrorKind::InvalidInput, "Invalid header"));
+        }
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !has("accept") {
+        head.push_str("Accept: */*\r\n");
+    }
+    if !has("user-agent") {
+        head.push_str(concat!(
+            "User-Agent: jstime/",
+            env!("CARGO_PKG_VERSION"),
+            "\r\n"
+        ));
+    }
+    match &request.body {
+        RequestBody::Bytes(body) if !has("content-length") => {
+            head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        RequestBody::Stream(_) => head.push_str("Transfer-Encoding: chunked\r\n"),
+        _ => {}
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+
+    match &mut request.body {
+        RequestBody::None => {}
+        RequestBody::Bytes(body) => stream.write_all(body)?,
+        RequestBody::Stream(body) => {
+            let mut buf = vec![0; 16 * 1024];
+            loop {
+                let n = body.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                stream.write_all(format!("{:x}\r\n", n).as_bytes())?;
+                stream.write_all(&buf[..n])?;
+                stream.write_all(b"\r\n")?;
+                stream.flush()?;
+            }
+            stream.write_all(b"0\r\n\r\n")?;
+        }
+    }
+    stream.flush()
+}
+
+/// A line of the response head, without its line ending.
+fn read_line(reader: &mut BufReader<Stream>) -> std::io::Result<String> {
+    let mut line = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE)
+        .read_until(b'\n', &mut line)?;
+    if line.is_empty() {
+        return Err(error(
+            ErrorKind::UnexpectedEof,
+            "The connection closed before the response",
+        ));
+    }
+    if !line.ends_with(b"\n") {
+        return Err(error(ErrorKind::InvalidData, "Invalid response"));
+    }
+    while line.ends_with(b"\n") || line.ends_with(b"\r") {
+        line.pop();
+    }
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+struct Head {
+    status: u16,
+    status_text: String,
+    headers: Vec<(String, String)>,
+    keep_alive: bool,
+}
+
+fn read_head(reader: &mut BufReader<Stream>) -> std::io::Result<Head> {
+    loop {
+        let line = read_line(reader)?;
+        let mut parts = line.splitn(3, ' ');
+        let version = parts.next().unwrap_or_default();
+        let status = parts.next().and_then(|status| status.parse().ok());
+        let status = match status {
+            Some(status) if version.starts_with("HTTP/1.") => status,
+            _ => return Err(error(ErrorKind::InvalidData, "Invalid response")),
+        };
+        let status_text = parts.next().unwrap_or_default().to_owned();
+
+        let mut headers = Vec::new();
+        loop {
+            let line = read_line(reader)?;
+            if line.is_empty() {
+                break;
+            }
+            match line.split_once(':') {
+                Some((name, value)) => {
+                    headers.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()))
+                }
+                None => return Err(error(ErrorKind::InvalidData, "Invalid response header")),
+            }
+        }
+
+        // Informational responses come before the one to the request.
+        if (100..200).contains(&status) {
+            continue;
+        }
+        return Ok(Head {
+            status,
+            status_text,
+            headers,
+            keep_alive: version == "HTTP/1.1",
+        });
+    }
+}
+
+enum Framing {
+    /// The number of bytes of the body left.
+    Length(u64),
+    Chunked(Chunk),
+    /// The body ends when the connection does.
+    Close,
+}
+
+enum Chunk {
+    Size,
+    /// The number of bytes of the chunk left.
+    Data(u64),
+    End,
+}
+
+struct ResponseBody {
+    agent: Agent,
+    cancel: Cancel,
+    /// Until the end of the body was read.
+    connection: Option<Connection>,
+    framing: Framing,
+    reusable: bool,
+}
+
+impl ResponseBody {
+    /// The body was read to the end, so its connection can take another
+    /// request.
+    fn finish(&mut self) {
+        self.cancel.release();
+        if let Some(connection) = self.connection.take() {
+            if self.reusable {
+                self.agent.release(connection);
+            }
+        }
+    }
+
+    fn read_chunked(
+        chunk: &mut Chunk,
+        reader: &mut BufReader<Stream>,
+        buf: &mut [u8],
+    ) -> std::io::Result<usize> {
+        loop {
+            match chunk {
+                Chunk::Size => {
+                    let line = read_line(reader)?;
+                    let size = line.split(';').next().unwrap_or_default().trim();
+                    let size = u64::from_str_radix(size, 16)
+                        .map_err(|_| error(ErrorKind::InvalidData, "Invalid chunk size"))?;
+                    if size == 0 {
+                        // Trailers are ignored.
+                        while !read_line(reader)?.is_empty() {}
+                        *chunk = Chunk::End;
+                    } else {
+                        *chunk = Chunk::Data(size);
+                    }
+                }
+                Chunk::Data(left) => {
+                    let max = buf.len().min(usize::try_from(*left).unwrap_or(usize::MAX));
+                    let n = reader.read(&mut buf[..max])?;
+                    if n == 0 {
+                        return Err(truncated());
+                    }
+                    *left -= n as u64;
+                    if *left == 0 {
+                        read_line(reader)?;
+                        *chunk = Chunk::Size;
+                    }
+                    return Ok(n);
+                }
+                Chunk::End => return Ok(0),
+            }
+        }
+    }
+}
+
+fn truncated() -> std::io::Error {
+    error(
+        ErrorKind::UnexpectedEof,
+        "The connection closed before the end of the body",
+    )
+}
+
+impl Read for ResponseBody {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let connection = match &mut self.connection {
+            Some(connection) if !buf.is_empty() => connection,
+            _ => return Ok(0),
+        };
+        let n = match &mut self.framing {
+            Framing::Length(left) => {
+                let max = buf.len().min(usize::try_from(*left).unwrap_or(usize::MAX));
+                let n = connection.reader.read(&mut buf[..max])?;
+                if n == 0 {
+                    return Err(truncated());
+                }
+                *left -= n as u64;
+                if *left == 0 {
+                    self.finish();
+                }
+                return Ok(n);
+            }
+            Framing::Chunked(chunk) => Self::read_chunked(chunk, &mut connection.reader, buf)?,
+            Framing::Close => connection.reader.read(buf)?,
+        };
+        if n == 0 {
+            self.finish();
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for ResponseBody {
+    // A body that wasn't read to the end can't be, so its connection is
+    // closed.
+    fn drop(&mut self) {
+        if self.connection.is_some() {
+            self.cancel.cancel();
+        }
+    }
+}
@@ -1,10 +1,18 @@
 use rand::prelude::*;
 use std::convert::TryFrom;
 use std::iter::IntoIterator;
-use std::sync::mpsc::channel;
-use std::sync::mpsc::{Receiver, Sender};
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
+mod child_process;
+pub(crate) mod events;
+mod fetch;
+mod fs;
+mod http;
+mod ops;
+mod process;
+mod tty;
+mod url;
+
 lazy_static! {
     pub(crate) static ref EXTERNAL_REFERENCES: v8::ExternalReferences =
         v8::ExternalReferences::new(&[
@@ -18,14 +26,194 @@ lazy_static! {
                 function: v8::MapFnTo::map_fn_to(queue_microtask),
             },
             v8::ExternalReference {
-                function: v8::MapFnTo::map_fn_to(fetch),
+                function: v8::MapFnTo::map_fn_to(array_buffer_transfer),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fetch::fetch),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fetch::fetch_cancel),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fetch::fetch_body),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fetch::fetch_body_write),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fetch::fetch_body_read),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fetch::fetch_body_close),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(url::url_parse),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(url::url_set),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(url::url_search_params_parse),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(url::url_search_params_stringify),
             },
             v8::ExternalReference {
                 function: v8::MapFnTo::map_fn_to(set_timeout),
             },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(clear_timeout),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(set_unref_timeout),
+            },
             v8::ExternalReference {
                 function: v8::MapFnTo::map_fn_to(random_float),
             },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(apply_source_map),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_open),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_close),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_read),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_write),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_seek),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_stat),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_fstat),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_read_dir),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_mkdir),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_remove),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_rename),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_real_path),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_copy_file),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_symlink),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_read_file),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_write_file),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_open_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_read_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_write_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_seek_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_stat_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_fstat_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_read_dir_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_mkdir_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_remove_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_rename_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_real_path_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_copy_file_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_symlink_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_read_file_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(fs::fs_write_file_async),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(process::process_args),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(process::process_env_get),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(process::process_env_set),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(process::process_env_delete),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(process::process_cwd),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(process::process_chdir),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(process::process_info),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(process::process_exit_code),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(process::process_set_exit_code),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(process::process_exit),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(child_process::child_spawn),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(child_process::child_output_sync),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(child_process::child_kill),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(tty::tty_is_terminal),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(tty::tty_set_raw),
+            },
+            v8::ExternalReference {
+                function: v8::MapFnTo::map_fn_to(tty::tty_size),
+            },
         ]);
 }
 
@@ -35,8 +223,20 @@ impl Builtins {
     pub(crate) fn create(scope: &mut v8::HandleScope) {
         let bindings = v8::Object::new(scope);
 
+        // Builtins register the exports of `jstime:` modules, and hooks that
+        // jstime calls into, in these. They're kept on the global object
+        // under private keys so they end up in the snapshot.
+        for registry in &[BUILTIN_MODULES, INTERNALS] {
+            let value = v8::Object::new(scope);
+            let name = v8::String::new(scope, registry).unwrap();
+            bindings.set(scope, name.into(), value.into());
+            let global = scope.get_current_context().global(scope);
+            let key = v8::Private::for_api(scope, Some(name));
+            global.set_private(scope, key, value.into());
+        }
+
         macro_rules! binding {
-            ($name:expr, $fn:ident) => {
+            ($name:expr, $fn:path) => {
                 let name = v8::String::new(scope, $name).unwrap();
                 let value = v8::Function::new(scope, $fn).unwrap();
                 bindings.set(scope, name.into(), value.into());
@@ -45,10 +245,70 @@ impl Builtins {
 
         binding!("printer", printer);
         binding!("perfNow", performance_now);
-        binding!("fetch", fetch);
+        binding!("arrayBufferTransfer", array_buffer_transfer);
+        binding!("fetch", fetch::fetch);
+        binding!("fetchCancel", fetch::fetch_cancel);
+        binding!("fetchBody", fetch::fetch_body);
+        binding!("fetchBodyWrite", fetch::fetch_body_write);
+        binding!("fetchBodyRead", fetch::fetch_body_read);
+        binding!("fetchBodyClose", fetch::fetch_body_close);
+        binding!("urlParse", url::url_parse);
+        binding!("urlSet", url::url_set);
+        binding!("urlSearchParamsParse", url::url_search_params_parse);
+        binding!("urlSearchParamsStringify", url::url_search_params_stringify);
         binding!("queueMicrotask", queue_microtask);
         binding!("randomFloat", random_float);
         binding!("setTimeout", set_timeout);
+        binding!("clearTimeout", clear_timeout);
+        binding!("setUnrefTimeout", set_unref_timeout);
+        binding!("applySourceMap", apply_source_map);
+        binding!("fsOpen", fs::fs_open);
+        binding!("fsClose", fs::fs_close);
+        binding!("fsRead", fs::fs_read);
+        binding!("fsWrite", fs::fs_write);
+        binding!("fsSeek", fs::fs_seek);
+        binding!("fsStat", fs::fs_stat);
+        binding!("fsFstat", fs::fs_fstat);
+        binding!("fsReadDir", fs::fs_read_dir);
+        binding!("fsMkdir", fs::fs_mkdir);
+        binding!("fsRemove", fs::fs_remove);
+        binding!("fsRename", fs::fs_rename);
+        binding!("fsRealPath", fs::fs_real_path);
+        binding!("fsCopyFile", fs::fs_copy_file);
+        binding!("fsSymlink", fs::fs_symlink);
+        binding!("fsReadFile", fs::fs_read_file);
+        binding!("fsWriteFile", fs::fs_write_file);
+        binding!("fsOpenAsync", fs::fs_open_async);
+        binding!("fsReadAsync", fs::fs_read_async);
+        binding!("fsWriteAsync", fs::fs_write_async);
+        binding!("fsSeekAsync", fs::fs_seek_async);
+        binding!("fsStatAsync", fs::fs_stat_async);
+        binding!("fsFstatAsync", fs::fs_fstat_async);
+        binding!("fsReadDirAsync", fs::fs_read_dir_async);
+        binding!("fsMkdirAsync", fs::fs_mkdir_async);
+        binding!("fsRemoveAsync", fs::fs_remove_async);
+        binding!("fsRenameAsync", fs::fs_rename_async);
+        binding!("fsRealPathAsync", fs::fs_real_path_async);
+        binding!("fsCopyFileAsync", fs::fs_copy_file_async);
+        binding!("fsSymlinkAsync", fs::fs_symlink_async);
+        binding!("fsReadFileAsync", fs::fs_read_file_async);
+        binding!("fsWriteFileAsync", fs::fs_write_file_async);
+        binding!("processArgs", process::process_args);
+        binding!("processEnvGet", process::process_env_get);
+        binding!("processEnvSet", process::process_env_set);
+        binding!("processEnvDelete", process::process_env_delete);
+        binding!("processCwd", process::process_cwd);
+        binding!("processChdir", process::process_chdir);
+        binding!("processInfo", process::process_info);
+        binding!("processExitCode", process::process_exit_code);
+        binding!("processSetExitCode", process::process_set_exit_code);
+        binding!("processExit", process::process_exit);
+        binding!("childSpawn", child_process::child_spawn);
+        binding!("childOutputSync", child_process::child_output_sync);
+        binding!("childKill", child_process::child_kill);
+        binding!("ttyIsTerminal", tty::tty_is_terminal);
+        binding!("ttySetRaw", tty::tty_set_raw);
+        binding!("ttySize", tty::tty_size);
 
         macro_rules! builtin {
             ($name:expr) => {
@@ -67,35 +327,90 @@ impl Builtins {
         builtin!("./console.js");
         builtin!("./crypto.js");
         builtin!("./timers.js");
-        builtin!("./fetch.js");
         builtin!("./performance.js");
         builtin!("./encoders.js");
         builtin!("./queue_microtask.js");
+        builtin!("./events.js");
+        builtin!("./dom_exception.js");
+        builtin!("./abort.js");
+        builtin!("./url.js");
+        builtin!("./url_pattern.js");
+        builtin!("./streams.js");
+        builtin!("./blob.js");
+        builtin!("./form_data.js");
+        builtin!("./fetch.js");
+        builtin!("./source_map.js");
+        builtin!("./wasm.js");
+        builtin!("./wasi.js");
+        builtin!("./fs.js");
+        builtin!("./process.js");
+        builtin!("./child_process.js");
+        builtin!("./stdio.js");
+        builtin!("./test.js");
+        builtin!("./bench.js");
     }
     pub(crate) fn init(scope: &mut v8::HandleScope) {
         scope.set_slot(TimerQueue::new());
         scope.set_slot(Instant::now() as TimeOrigin);
+        scope.set_slot(fs::Files::new());
+        scope.set_slot(child_process::Children::new());
+        scope.set_slot(tty::RawMode::new());
+
+        scope.set_slot(ops::PendingOps::new());
+        scope.set_slot(events::Rejections::new());
+        scope.set_promise_reject_callback(events::promise_reject_callback);
+        scope.set_slot(fetch::Context::new());
+        scope.set_slot(fetch::Bodies::new());
+    }
+}
 
-        let (send, recv) = channel();
-        let (send2, recv2) = channel();
-
-        std::thread::spawn(move || loop {
-            let req: RequestRequest = recv2.recv().unwrap();
+const BUILTIN_MODULES: &str = "builtinModules";
+const INTERNALS: &str = "internals";
+
+fn registry_entry<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    registry: &str,
+    name: &str,
+) -> Option<v8::Local<'a, v8::Object>> {
+    let global = scope.get_current_context().global(scope);
+    let registry = v8::String::new(scope, registry).unwrap();
+    let key = v8::Private::for_api(scope, Some(registry));
+    let registry = global.get_private(scope, key)?;
+    let registry = v8::Local::<v8::Object>::try_from(registry).ok()?;
+    let name = v8::String::new(scope, name).unwrap();
+    let entry = registry.get(scope, name.into())?;
+    v8::Local::<v8::Object>::try_from(entry).ok()
+}
 
-            send.send(RequestResponse {
-                id: req.id,
-                value: req.value.call(),
-            })
-            .unwrap();
-        });
+/// The exports of a builtin module such as `jstime:test`.
+pub(crate) fn module_exports<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    specifier: &str,
+) -> Option<v8::Local<'a, v8::Object>> {
+    registry_entry(scope, BUILTIN_MODULES, specifier)
+}
 
-        scope.set_slot(Context {
-            tq: TimerQueue::new(),
-            outstanding_promises: std::collections::HashMap::new(),
-            promise_counter: 0,
-            response_receiver: recv,
-            request_sender: send2,
-        });
+/// Call `method` of hooks that a builtin registered for jstime, e.g.
+/// `internals.test.run`.
+pub(crate) fn call_internal<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    name: &str,
+    method: &str,
+    args: &[v8::Local<v8::Value>],
+) -> Result<v8::Local<'a, v8::Value>, String> {
+    let scope = &mut v8::TryCatch::new(scope);
+    let hooks = registry_entry(scope, INTERNALS, name).unwrap();
+    let key = v8::String::new(scope, method).unwrap();
+    let func = hooks.get(scope, key.into()).unwrap();
+    let func = v8::Local::<v8::Function>::try_from(func).unwrap();
+    match func.call(scope, hooks.into(), args) {
+        Some(value) => Ok(value),
+        None => {
+            let exception = scope
+                .exception()
+                .unwrap_or_else(|| v8::undefined(scope).into());
+            Err(crate::error_to_string(scope, exception))
+        }
     }
 }
 
@@ -108,6 +423,27 @@ fn random_float(
     rv.set(v8::Number::new(scope, rng.gen::<f64>()).into());
 }
 
+fn apply_source_map(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let filepath = args.get(0).to_rust_string_lossy(scope);
+    let line = args.get(1).uint32_value(scope).unwrap_or(0);
+    let column = args.get(2).uint32_value(scope).unwrap_or(0);
+
+    let state = crate::IsolateState::get(scope);
+    let mapped = state.borrow().source_maps.apply(&filepath, line, column);
+    if let Some((source, line, column)) = mapped {
+        let location = [
+            v8::String::new(scope, &source).unwrap().into(),
+            v8::Integer::new_from_unsigned(scope, line).into(),
+            v8::Integer::new_from_unsigned(scope, column).into(),
+        ];
+        rv.set(v8::Array::new_with_elements(scope, &location).into());
+    }
+}
+
 type TimeOrigin = Instant;
 
 fn performance_now(
@@ -156,6 +492,29 @@ fn queue_microtask(
     scope.enqueue_microtask(func);
 }
 
+/// Move the contents of an `ArrayBuffer` to a new one, detaching it, as
+/// `ArrayBuffer.prototype.transfer` does where V8 has it. The new buffer
+/// shares the old one's backing store, so nothing is copied.
+fn array_buffer_transfer(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let buffer = match v8::Local::<v8::ArrayBuffer>::try_from(args.get(0)) {
+        Ok(buffer) if buffer.is_detachable() => buffer,
+        _ => {
+            let message = v8::String::new(scope, "buffer can't be transferred").unwrap();
+            let error = v8::Exception::type_error(scope, message);
+            scope.throw_exception(error);
+            return;
+        }
+    };
+    let store = buffer.get_backing_store();
+    buffer.detach();
+    let transferred = v8::ArrayBuffer::with_backing_store(scope, &store);
+    rv.set(transferred.into());
+}
+
 fn exception(scope: &mut v8::HandleScope, err: &str) {
     let e = v8::String::new(scope, err).unwrap();
     let error = v8::Exception::error(scope, e);
@@ -165,7 +524,26 @@ fn exception(scope: &mut v8::HandleScope, err: &str) {
 fn set_timeout(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
-    _rv: v8::ReturnValue,
+    rv: v8::ReturnValue,
+) {
+    add_timer(scope, args, rv, true);
+}
+
+/// Like `setTimeout`, but the timer doesn't keep the event loop running,
+/// for timers that only matter while something else is still pending.
+fn set_unref_timeout(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    add_timer(scope, args, rv, false);
+}
+
+fn add_timer(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+    keep_alive: bool,
 ) {
     if args.length() == 0 {
         return;
@@ -175,163 +553,155 @@ fn set_timeout(
         exception(scope, "Callback must be a function");
         return;
     }
-
     let func = v8::Local::<v8::Function>::try_from(val).unwrap();
-    if args.length() == 1 {
-        scope.enqueue_microtask(func);
-        return;
-    }
 
     let delay_arg = args.get(1);
-    if !delay_arg.is_number() {
+    let delay = if delay_arg.is_undefined() {
+        0.0
+    } else if delay_arg.is_number() {
+        delay_arg.number_value(scope).unwrap()
+    } else {
         exception(scope, "Delay must be a number");
         return;
-    }
+    };
     let global_func = v8::Global::new(scope, func);
-    let delay = v8::Local::<v8::Number>::try_from(delay_arg).unwrap();
 
+    // NaN and negative delays run as soon as possible.
+    let delay = if delay > 0.0 { delay as u128 } else { 0 };
     let queue = scope.get_slot_mut::<TimerQueue>().unwrap();
-    queue.timers.push(TimerEvent {
-        call_at: epoch_millis() + delay.value() as u128,
-        func: global_func,
-        interval: None,
-    })
+    let id = queue.add(
+        epoch_millis().saturating_add(delay),
+        global_func,
+        keep_alive,
+    );
+    rv.set(v8::Integer::new_from_unsigned(scope, id).into());
+}
+
+fn clear_timeout(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    if let Some(id) = args.get(0).uint32_value(scope) {
+        let queue = scope.get_slot_mut::<TimerQueue>().unwrap();
+        queue.timers.retain(|timer| timer.id != id);
+    }
 }
 
 struct TimerEvent {
+    id: u32,
     call_at: u128,
     func: v8::Global<v8::Function>,
-    interval: Option<u64>,
+    keep_alive: bool,
 }
 
 struct TimerQueue {
     timers: Vec<TimerEvent>,
+    next_id: u32,
 }
 
 impl TimerQueue {
     fn new() -> Self {
-        Self { timers: Vec::new() }
-    }
-    fn empty(&self) -> bool {
-        self.timers.len() == 0
+        Self {
+            timers: Vec::new(),
+            next_id: 1,
+        }
     }
-}
-
-fn epoch_millis() -> u128 {
-    let start = SystemTime::now();
-    let since_the_epoch = start.duration_since(UNIX_EPOCH).unwrap();
-    since_the_epoch.as_millis()
-}
 
-fn fetch(
-    scope: &mut v8::HandleScope,
-    args: v8::FunctionCallbackArguments,
-    mut rv: v8::ReturnValue,
-) {
-    if args.length() == 0 {
-        return exception(scope, "1 argument required, but only 0 present");
-    }
-    let resource = args.get(0);
-    if !resource.is_string() {
-        return exception(scope, "first argument to fetch must be a string");
-    }
-    let method = "GET";
-    let headers: std::collections::HashMap<String, String> = std::collections::HashMap::new();
-    if args.length() >= 2 {
-        let init = args.get(1);
-        if !init.is_object() {
-            return exception(scope, "fetch init argument must be an object");
-        }
-        let options = v8::Local::<v8::Object>::try_from(init).unwrap();
-        let headers_key = v8::String::new(scope, "headers").unwrap();
-        if let Some(headers_local) = options.get(scope, headers_key.into()) {
-            if !headers_local.is_object() {
-                return exception(scope, "headers must be an object");
-            }
-            let headers_val = v8::Local::<v8::Object>::try_from(headers_local).unwrap();
-            let _names = headers_val.get_property_names(scope).unwrap();
-            // TODO: complete
-        }
-        // TODO: complete
-        // let method_key = v8::String::new(scope, "method").unwrap();
-        // if let Some(method_local) = options.get(scope, headers_key.into()) {
-        //     if method_local.is_string() {
-        //         let method_val = v8::Local::<v8::String>::try_from(method_local).unwrap();
-        //         let m_string: String = method_val.to_rust_string_lossy(scope).to_owned();
-        //         method = &*m_string;
-        //     }
-        // }
+    fn add(&mut self, call_at: u128, func: v8::Global<v8::Function>, keep_alive: bool) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.timers.push(TimerEvent {
+            id,
+            call_at,
+            func,
+            keep_alive,
+        });
+        id
     }
 
-    let resolver = v8::PromiseResolver::new(scope).unwrap();
-    let global_promise = v8::Global::new(scope, resolver);
-    let promise = resolver.get_promise(scope);
-    rv.set(promise.into());
+    /// Remove the earliest timer that is due at `now` and was added before
+    /// timer `before`.
+    fn pop_due(&mut self, now: u128, before: u32) -> Option<TimerEvent> {
+        let index = self
+            .timers
+            .iter()
+            .enumerate()
+            .filter(|(_, timer)| timer.call_at <= now && timer.id < before)
+            .min_by_key(|(_, timer)| (timer.call_at, timer.id))
+            .map(|(index, _)| index)?;
+        Some(self.timers.remove(index))
+    }
 
-    let resource = &resource.to_rust_string_lossy(scope).to_owned();
-    let ctx = scope.get_slot_mut::<Context>().unwrap();
-    ctx.fetch(global_promise, ureq::request(method, resource));
-}
+    fn next_call_at(&self) -> Option<u128> {
+        self.timers.iter().map(|timer| timer.call_at).min()
+    }
 
-struct Context {
-    tq: TimerQueue,
-    outstanding_promises: std::collections::HashMap<u32, v8::Global<v8::PromiseResolver>>,
-    promise_counter: u32,
-    response_receiver: Receiver<RequestResponse>,
-    request_sender: Sender<RequestRequest>,
+    fn keeps_alive(&self) -> bool {
+        self.timers.iter().any(|timer| timer.keep_alive)
+    }
 }
 
-impl Context {
-    fn fetch(&mut self, pr: v8::Global<v8::PromiseResolver>, req: ureq::Request) {
-        self.promise_counter += 1;
-        self.request_sender
-            .send(RequestRequest {
-                id: self.promise_counter,
-                value: req,
-            })
-            .unwrap();
-        self.outstanding_promises.insert(self.promise_counter, pr);
+/// Call the timers that are due. Timers added meanwhile wait for the next
+/// tick, so a timer that keeps adding itself can't starve the event loop.
+fn run_timers(scope: &mut v8::HandleScope) {
+    let now = epoch_millis();
+    let before = scope.get_slot::<TimerQueue>().unwrap().next_id;
+    // Stop once a timer calls `exit`.
+    while !scope.is_execution_terminating() {
+        let timer = match scope
+            .get_slot_mut::<TimerQueue>()
+            .unwrap()
+            .pop_due(now, before)
+        {
+            Some(timer) => timer,
+            None => break,
+        };
+        let func = v8::Local::new(scope, timer.func);
+        let scope = &mut v8::TryCatch::new(scope);
+        let recv = v8::undefined(scope).into();
+        if func.call(scope, recv, &[]).is_none() && !scope.has_terminated() {
+            if let Some(exception) = scope.exception() {
+                events::report_error(scope, exception);
+            }
+        }
     }
 }
 
-struct RequestRequest {
-    id: u32,
-    value: ureq::Request,
+fn epoch_millis() -> u128 {
+    let start = SystemTime::now();
+    let since_the_epoch = start.duration_since(UNIX_EPOCH).unwrap();
+    since_the_epoch.as_millis()
 }
 
-struct RequestResponse {
-    id: u32,
-    value: Result<ureq::Response, ureq::Error>,
-}
+// The longest the event loop waits for pending ops and timers, so it still
+// gets around to other work, like inspector messages, now and then.
+const MAX_WAIT: std::time::Duration = std::time::Duration::from_millis(100);
 
 pub fn tick(scope: &mut v8::HandleScope) -> bool {
-    let ctx = scope.get_slot_mut::<Context>().unwrap();
-    let no_promises = ctx.outstanding_promises.len() == 0;
-    let no_timers = ctx.tq.empty();
-    if no_promises && no_timers {
-        return false;
-    }
-    if no_promises {
-        // TODO: sleep until next timer and then add microtask
-        return false;
+    run_timers(scope);
+    if scope.is_execution_terminating() {
+        return true;
     }
-    println!("possible promise");
-    let possible_promise = if no_timers {
-        Some(ctx.response_receiver.recv().unwrap())
-    } else {
-        match ctx
-            .response_receiver
-            .recv_timeout(std::time::Duration::from_millis(100))
-        {
-            Ok(v) => Some(v),
-            Err(_) => None,
-        }
-    };
-    if let Some(result) = possible_promise {
-        let resolver_global = ctx.outstanding_promises.remove(&result.id).unwrap();
-        let resolver = resolver_global.open(scope);
-        let status_code = v8::Number::new(scope, result.value.unwrap().status() as f64);
-        resolver.resolve(scope, status_code.into());
+    let wait = scope
+        .get_slot::<TimerQueue>()
+        .unwrap()
+        .next_call_at()
+        .map(|call_at| {
+            let millis = call_at.saturating_sub(epoch_millis()) as u64;
+            std::time::Duration::from_millis(millis).min(MAX_WAIT)
+        });
+
+    if scope.get_slot::<ops::PendingOps>().unwrap().is_empty() {
+        let keep_alive = scope.get_slot::<TimerQueue>().unwrap().keeps_alive();
+        return match wait {
+            Some(wait) if keep_alive => {
+                std::thread::sleep(wait);
+                true
+            }
+            _ => false,
+        };
     }
+    ops::poll(scope, Some(wait.unwrap_or(MAX_WAIT)));
     true
 }
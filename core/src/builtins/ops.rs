@@ -0,0 +1,192 @@
+// Promises settled by work done on other threads. The event loop waits for
+// their completions in `tick`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Settles the promise of an op on the main thread, turning what the op
+/// produced into JS values.
+pub(crate) type Completion = Box<
+    dyn for<'s> FnOnce(
+            &mut v8::HandleScope<'s>,
+        ) -> Result<v8::Local<'s, v8::Value>, v8::Local<'s, v8::Value>>
+        + Send,
+>;
+
+/// Box `f` as a `Completion`, which lets the compiler infer the signature
+/// of closures.
+pub(crate) fn completion<F>(f: F) -> Completion
+where
+    F: for<'s> FnOnce(
+            &mut v8::HandleScope<'s>,
+        ) -> Result<v8::Local<'s, v8::Value>, v8::Local<'s, v8::Value>>
+        + Send
+        + 'static,
+{
+    Box::new(f)
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Threads that run jobs. Ops like reading from a pipe can block for as long
+/// as the other end likes, so a thread is started whenever the others are
+/// busy, rather than queueing jobs behind them, unless there are as many as
+/// the pool was limited to.
+pub(crate) struct Pool {
+    sender: Sender<Job>,
+    receiver: Arc<Mutex<Receiver<Job>>>,
+    max_threads: Option<usize>,
+    threads: usize,
+    // Threads waiting for a job that no job was sent for yet.
+    idle: Arc<AtomicUsize>,
+}
+
+impl Pool {
+    /// A pool that starts threads on demand, up to `max_threads` if given.
+    pub(crate) fn new(max_threads: Option<usize>) -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+            max_threads,
+            threads: 0,
+            idle: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Run `job` on a thread of the pool, once one is free if the pool is
+    /// at its limit.
+    pub(crate) fn run<F>(&mut self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let reserved = self
+            .idle
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        let limited = self.max_threads.map_or(false, |max| self.threads >= max);
+        if reserved.is_err() && !limited {
+            self.threads += 1;
+            let receiver = self.receiver.clone();
+            let idle = self.idle.clone();
+            std::thread::spawn(move || loop {
+                let job = match receiver.lock().unwrap().recv() {
+                    Ok(job) => job,
+                    // The isolate is gone.
+                    Err(_) => break,
+                };
+                job();
+                idle.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        self.sender.send(Box::new(job)).unwrap();
+    }
+}
+
+pub(crate) struct PendingOps {
+    next_id: u32,
+    resolvers: HashMap<u32, v8::Global<v8::PromiseResolver>>,
+    sender: Sender<(u32, Completion)>,
+    receiver: Receiver<(u32, Completion)>,
+    // Started on first use.
+    pool: Option<Pool>,
+}
+
+impl PendingOps {
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            next_id: 0,
+            resolvers: HashMap::new(),
+            sender,
+            receiver,
+            pool: None,
+        }
+    }
+
+    /// Track `resolver` until a completion is sent for the returned id.
+    pub(crate) fn register(&mut self, resolver: v8::Global<v8::PromiseResolver>) -> u32 {
+        self.next_id += 1;
+        self.resolvers.insert(self.next_id, resolver);
+        self.next_id
+    }
+
+    /// A sender for completions of registered ops, for threads other than
+    /// the pool to use.
+    pub(crate) fn sender(&self) -> Sender<(u32, Completion)> {
+        self.sender.clone()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.resolvers.is_empty()
+    }
+
+    /// Stop tracking an op, whose promise then never settles. Its
+    /// completion is ignored if it still arrives.
+    pub(crate) fn cancel(&mut self, id: u32) {
+        self.resolvers.remove(&id);
+    }
+
+    fn run(&mut self, job: Job) {
+        self.pool.get_or_insert_with(|| Pool::new(None)).run(job);
+    }
+}
+
+/// Run `work` on a background thread, returning a promise that the
+/// completion it returns settles.
+pub(crate) fn spawn<'s, F>(scope: &mut v8::HandleScope<'s>, work: F) -> v8::Local<'s, v8::Promise>
+where
+    F: FnOnce() -> Completion + Send + 'static,
+{
+    spawn_cancellable(scope, work).1
+}
+
+/// Like `spawn`, also returning the id of the op to `PendingOps::cancel`
+/// it with.
+pub(crate) fn spawn_cancellable<'s, F>(
+    scope: &mut v8::HandleScope<'s>,
+    work: F,
+) -> (u32, v8::Local<'s, v8::Promise>)
+where
+    F: FnOnce() -> Completion + Send + 'static,
+{
+    let resolver = v8::PromiseResolver::new(scope).unwrap();
+    let promise = resolver.get_promise(scope);
+    let resolver = v8::Global::new(scope, resolver);
+
+    let ops = scope.get_slot_mut::<PendingOps>().unwrap();
+    let id = ops.register(resolver);
+    let sender = ops.sender();
+    ops.run(Box::new(move || {
+        // The receiver is gone if the isolate was dropped meanwhile.
+        let _ = sender.send((id, work()));
+    }));
+    (id, promise)
+}
+
+/// Settle the promises of ops that completed, waiting up to `wait`, or
+/// until one completes if `None`.
+pub(crate) fn poll(scope: &mut v8::HandleScope, wait: Option<Duration>) {
+    let ops = scope.get_slot_mut::<PendingOps>().unwrap();
+    let first = match wait {
+        None => ops.receiver.recv().ok(),
+        Some(wait) => ops.receiver.recv_timeout(wait).ok(),
+    };
+    let mut completions = first.into_iter().collect::<Vec<_>>();
+    completions.extend(ops.receiver.try_iter());
+
+    for (id, completion) in completions {
+        let ops = scope.get_slot_mut::<PendingOps>().unwrap();
+        let resolver = match ops.resolvers.remove(&id) {
+            Some(resolver) => resolver,
+            None => continue,
+        };
+        let resolver = v8::Local::new(scope, resolver);
+        match completion(scope) {
+            Ok(value) => resolver.resolve(scope, value),
+            Err(error) => resolver.reject(scope, error),
+        };
+    }
+}
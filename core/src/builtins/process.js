@@ -0,0 +1,90 @@
+// The jstime:process module.
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({
+  builtinModules,
+  processArgs, processEnvGet, processEnvSet, processEnvDelete, processCwd,
+  processChdir, processInfo, processExitCode, processSetExitCode, processExit,
+}) => {
+  const env = Object.freeze({
+    get(name) {
+      return processEnvGet(String(name));
+    },
+    set(name, value) {
+      processEnvSet(String(name), String(value));
+    },
+    delete(name) {
+      processEnvDelete(String(name));
+    },
+  });
+
+  // Looked up when imported, not when the snapshot is taken.
+  let info;
+  const getInfo = () => {
+    info = info || processInfo();
+    return info;
+  };
+
+  const process = {
+    // Arguments after the script name.
+    get args() {
+      return processArgs();
+    },
+    env,
+    // Exit with `code`, `exitCode` by default, once the current
+    // callback stops running, without waiting for timers or other work.
+    exit(code) {
+      processExit(code === undefined ? undefined : Number(code));
+    },
+    // The code to exit with once there's nothing left to do.
+    get exitCode() {
+      return processExitCode();
+    },
+    set exitCode(code) {
+      processSetExitCode(Number(code));
+    },
+    cwd() {
+      return processCwd();
+    },
+    chdir(dir) {
+      processChdir(String(dir));
+    },
+    get pid() {
+      return getInfo().pid;
+    },
+    get platform() {
+      return getInfo().platform;
+    },
+    get arch() {
+      return getInfo().arch;
+    },
+    get versions() {
+      return getInfo().versions;
+    },
+  };
+
+  builtinModules['jstime:process'] = {
+    default: process,
+    get args() {
+      return process.args;
+    },
+    env,
+    exit: process.exit,
+    cwd: process.cwd,
+    chdir: process.chdir,
+    get pid() {
+      return process.pid;
+    },
+    get platform() {
+      return process.platform;
+    },
+    get arch() {
+      return process.arch;
+    },
+    get versions() {
+      return process.versions;
+    },
+  };
+});
@@ -0,0 +1,217 @@
+// Bindings behind jstime:process.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::Command;
+use std::sync::Mutex;
+
+use crate::permissions::{check, Kind};
+
+lazy_static! {
+    // Variables set, or deleted as `None`, over the environment jstime
+    // started with. Changing the real environment isn't sound while other
+    // threads may read it, so child processes are given these instead.
+    static ref ENV: Mutex<HashMap<String, Option<String>>> = Mutex::new(HashMap::new());
+}
+
+/// Give `command` the variables set or deleted with `process.env`.
+pub(crate) fn apply_env(command: &mut Command) {
+    for (name, value) in ENV.lock().unwrap().iter() {
+        match value {
+            Some(value) => command.env(name, value),
+            None => command.env_remove(name),
+        };
+    }
+}
+
+pub(crate) fn process_args(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let state = crate::IsolateState::get(scope);
+    let args = state
+        .borrow()
+        .args
+        .iter()
+        .map(|arg| v8::String::new(scope, arg).unwrap().into())
+        .collect::<Vec<v8::Local<v8::Value>>>();
+    rv.set(v8::Array::new_with_elements(scope, &args).into());
+}
+
+/// Check that `name` can be the name of an environment variable.
+fn valid_env_name(scope: &mut v8::HandleScope, name: &str) -> bool {
+    if name.is_empty() || name.contains(&['=', '\0'][..]) {
+        let message = format!("Invalid environment variable name: \"{}\"", name);
+        let message = v8::String::new(scope, &message).unwrap();
+        let exception = v8::Exception::type_error(scope, message);
+        scope.throw_exception(exception);
+        return false;
+    }
+    true
+}
+
+pub(crate) fn process_env_get(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let name = args.get(0).to_rust_string_lossy(scope);
+    if !valid_env_name(scope, &name) || !check(scope, Kind::Env, &name) {
+        return;
+    }
+    let value = match ENV.lock().unwrap().get(&name) {
+        Some(value) => value.clone(),
+        None => std::env::var_os(&name).map(|value| value.to_string_lossy().into_owned()),
+    };
+    if let Some(value) = value {
+        let value = v8::String::new(scope, &value).unwrap();
+        rv.set(value.into());
+    }
+}
+
+pub(crate) fn process_env_set(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let name = args.get(0).to_rust_string_lossy(scope);
+    let value = args.get(1).to_rust_string_lossy(scope);
+    if !valid_env_name(scope, &name) || !check(scope, Kind::Env, &name) {
+        return;
+    }
+    if value.contains('\0') {
+        return super::exception(scope, "Environment variable values can't contain NUL");
+    }
+    ENV.lock().unwrap().insert(name, Some(value));
+}
+
+pub(crate) fn process_env_delete(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let name = args.get(0).to_rust_string_lossy(scope);
+    if !valid_env_name(scope, &name) || !check(scope, Kind::Env, &name) {
+        return;
+    }
+    ENV.lock().unwrap().insert(name, None);
+}
+
+pub(crate) fn process_cwd(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let cwd = match std::env::current_dir() {
+        Ok(cwd) => cwd.to_string_lossy().into_owned(),
+        Err(e) => return super::fs::io_exception(scope, e),
+    };
+    if check(scope, Kind::Read, &cwd) {
+        rv.set(v8::String::new(scope, &cwd).unwrap().into());
+    }
+}
+
+pub(crate) fn process_chdir(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let dir = args.get(0).to_rust_string_lossy(scope);
+    if !check(scope, Kind::Read, &dir) {
+        return;
+    }
+    if let Err(e) = std::env::set_current_dir(&dir) {
+        super::fs::io_exception(scope, e);
+    }
+}
+
+/// `pid`, `platform`, `arch` and `versions`, which don't change while jstime
+/// runs but may differ from when the snapshot was taken.
+pub(crate) fn process_info(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let obj = v8::Object::new(scope);
+
+    macro_rules! set {
+        ($obj:expr, $name:expr, $value:expr) => {
+            let key = v8::String::new(scope, $name).unwrap();
+            let value = $value;
+            $obj.set(scope, key.into(), value.into());
+        };
+    }
+
+    set!(
+        obj,
+        "pid",
+        v8::Integer::new_from_unsigned(scope, std::process::id())
+    );
+    set!(
+        obj,
+        "platform",
+        v8::String::new(scope, std::env::consts::OS).unwrap()
+    );
+    set!(
+        obj,
+        "arch",
+        v8::String::new(scope, std::env::consts::ARCH).unwrap()
+    );
+    let versions = v8::Object::new(scope);
+    set!(
+        versions,
+        "jstime",
+        v8::String::new(scope, env!("CARGO_PKG_VERSION")).unwrap()
+    );
+    set!(
+        versions,
+        "v8",
+        v8::String::new(scope, v8::V8::get_version()).unwrap()
+    );
+    set!(obj, "versions", versions);
+
+    rv.set(obj.into());
+}
+
+pub(crate) fn process_exit_code(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let code = crate::IsolateState::get(scope).borrow().exit_code;
+    rv.set(v8::Integer::new(scope, code).into());
+}
+
+pub(crate) fn process_set_exit_code(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let code = args.get(0).int32_value(scope).unwrap_or(0);
+    crate::IsolateState::get(scope).borrow_mut().exit_code = code;
+}
+
+/// Stop running JS, and the event loop, with the given exit code.
+pub(crate) fn process_exit(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let code = if args.get(0).is_undefined() {
+        None
+    } else {
+        Some(args.get(0).int32_value(scope).unwrap_or(0))
+    };
+    let state = crate::IsolateState::get(scope);
+    let mut state = state.borrow_mut();
+    if let Some(code) = code {
+        state.exit_code = code;
+    }
+    state.exiting = true;
+    drop(state);
+
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+    scope.terminate_execution();
+}
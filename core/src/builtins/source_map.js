@@ -0,0 +1,70 @@
+// Source mapped stack traces
+// https://v8.dev/docs/stack-trace-api
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({ applySourceMap }) => {
+  // Mirrors V8's own CallSite formatting, but with a remapped location.
+  function formatCallSite(callSite, fileLocation) {
+    const functionName = callSite.getFunctionName();
+    const isConstructor = callSite.isConstructor();
+    const isMethodCall = !(callSite.isToplevel() || isConstructor);
+    let prefix = callSite.isAsync() ? 'async ' : '';
+
+    if (callSite.isPromiseAll()) {
+      return `${prefix}Promise.all (index ${callSite.getPromiseIndex()})`;
+    }
+
+    if (isMethodCall) {
+      const typeName = callSite.getTypeName();
+      const methodName = callSite.getMethodName();
+      if (functionName) {
+        if (typeName && !functionName.startsWith(typeName)) {
+          prefix += `${typeName}.`;
+        }
+        prefix += functionName;
+        if (methodName && !functionName.endsWith(methodName)) {
+          prefix += ` [as ${methodName}]`;
+        }
+      } else {
+        prefix += `${typeName}.${methodName || '<anonymous>'}`;
+      }
+    } else if (isConstructor) {
+      prefix += `new ${functionName || '<anonymous>'}`;
+    } else if (functionName) {
+      prefix += functionName;
+    } else {
+      return `${prefix}${fileLocation}`;
+    }
+
+    return `${prefix} (${fileLocation})`;
+  }
+
+  function mapCallSite(callSite) {
+    const fileName = callSite.getFileName();
+    const lineNumber = callSite.getLineNumber();
+    const columnNumber = callSite.getColumnNumber();
+    if (!fileName || lineNumber == null || columnNumber == null) {
+      return String(callSite);
+    }
+
+    const location = applySourceMap(fileName, lineNumber, columnNumber);
+    if (location === undefined) {
+      return String(callSite);
+    }
+
+    const [source, line, column] = location;
+    return formatCallSite(callSite, `${source}:${line}:${column}`);
+  }
+
+  Error.prepareStackTrace = (error, callSites) => {
+    let header;
+    try {
+      header = String(error);
+    } catch {
+      header = '<error>';
+    }
+    return [header, ...callSites.map((c) => `    at ${mapCallSite(c)}`)].join('\n');
+  };
+});
@@ -0,0 +1,153 @@
+// jstime.stdin, jstime.stdout and jstime.stderr
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({
+  fsReadAsync, fsWrite, fsWriteAsync, ttyIsTerminal, ttySetRaw, ttySize,
+}) => {
+  const STDIN = 0;
+  const STDOUT = 1;
+  const STDERR = 2;
+  const CHUNK_SIZE = 16 * 1024;
+
+  function toBytes(chunk) {
+    if (typeof chunk === 'string' || ArrayBuffer.isView(chunk)) {
+      return chunk;
+    }
+    if (chunk instanceof ArrayBuffer) {
+      return new Uint8Array(chunk);
+    }
+    throw new TypeError('Chunks must be strings, ArrayBuffers or ArrayBufferViews');
+  }
+
+  // Stdin as a byte stream, read only as fast as it's consumed.
+  class Stdin extends ReadableStream {
+    constructor() {
+      super({
+        type: 'bytes',
+        autoAllocateChunkSize: CHUNK_SIZE,
+        async pull(controller) {
+          const request = controller.byobRequest;
+          const size = request === null ? CHUNK_SIZE : request.view.byteLength;
+          const bytes = new Uint8Array(await fsReadAsync(STDIN, size));
+          if (bytes.length === 0) {
+            controller.close();
+            if (request !== null) {
+              request.respond(0);
+            }
+          } else if (request === null) {
+            controller.enqueue(bytes);
+          } else {
+            request.view.set(bytes);
+            request.respond(bytes.length);
+          }
+        },
+      });
+    }
+
+    // Lines of text, without their `\n` or `\r\n`. Only as much input as
+    // the next line needs is read.
+    async* lines() {
+      const decoder = new TextDecoder();
+      let buffered = '';
+      for await (const chunk of this) {
+        buffered += decoder.decode(chunk, { stream: true });
+        let end = buffered.indexOf('\n');
+        while (end !== -1) {
+          yield buffered.slice(0, end).replace(/\r$/, '');
+          buffered = buffered.slice(end + 1);
+          end = buffered.indexOf('\n');
+        }
+      }
+      buffered += decoder.decode();
+      if (buffered !== '') {
+        yield buffered;
+      }
+    }
+
+    isTerminal() {
+      return ttyIsTerminal(STDIN);
+    }
+
+    // Turn raw mode on or off, so input arrives a key at a time without
+    // being echoed. Throws if stdin isn't a terminal.
+    setRaw(enabled) {
+      ttySetRaw(Boolean(enabled));
+    }
+  }
+
+  // Writes go out in order, each once the one before it finished, whether
+  // they come through a writer or `write`.
+  class WriteQueue {
+    #rid;
+    #pending = Promise.resolve();
+
+    constructor(rid) {
+      this.#rid = rid;
+    }
+
+    push(chunk) {
+      const bytes = toBytes(chunk);
+      const result = this.#pending.then(() => fsWriteAsync(this.#rid, bytes));
+      this.#pending = result.catch(() => {});
+      return result;
+    }
+  }
+
+  // Stdout or stderr as a stream of strings or bytes.
+  class Output extends WritableStream {
+    #rid;
+    #writes;
+
+    constructor(rid) {
+      const writes = new WriteQueue(rid);
+      super({
+        async write(chunk) {
+          await writes.push(chunk);
+        },
+      });
+      this.#rid = rid;
+      this.#writes = writes;
+    }
+
+    // Write a string, as UTF-8, or bytes, without adding a newline.
+    // Resolves to the number of bytes written.
+    write(chunk) {
+      return this.#writes.push(chunk);
+    }
+
+    writeSync(chunk) {
+      return fsWrite(this.#rid, toBytes(chunk));
+    }
+
+    isTerminal() {
+      return ttyIsTerminal(this.#rid);
+    }
+
+    // `{ columns, rows }` of the terminal. Throws if this isn't a terminal.
+    size() {
+      return ttySize(this.#rid);
+    }
+  }
+
+  // Made on first use, so that no stream is started while the builtins are
+  // being snapshotted.
+  function lazy(object, name, create) {
+    Object.defineProperty(object, name, {
+      get() {
+        const value = create();
+        Object.defineProperty(object, name, { value, enumerable: true });
+        return value;
+      },
+      enumerable: true,
+      configurable: true,
+    });
+  }
+
+  const jstime = {};
+  lazy(jstime, 'stdin', () => new Stdin());
+  lazy(jstime, 'stdout', () => new Output(STDOUT));
+  lazy(jstime, 'stderr', () => new Output(STDERR));
+  globalThis.jstime = jstime;
+});
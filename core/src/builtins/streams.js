@@ -0,0 +1,3215 @@
+// Streams
+// https://streams.spec.whatwg.org/
+//
+// Abstract operations follow the spec's names and steps, so they can be
+// checked against it. Internal slots are properties prefixed with `_`.
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({ internals, arrayBufferTransfer }) => {
+  // Lets builtins construct controllers and requests, which scripts can't.
+  const illegal = Symbol('illegal');
+
+  const CancelSteps = Symbol('CancelSteps');
+  const PullSteps = Symbol('PullSteps');
+  const ReleaseSteps = Symbol('ReleaseSteps');
+  const AbortSteps = Symbol('AbortSteps');
+  const ErrorSteps = Symbol('ErrorSteps');
+
+  function illegalConstructor(token) {
+    if (token !== illegal) {
+      throw new TypeError('Illegal constructor');
+    }
+  }
+
+  function typeIsObject(x) {
+    return (typeof x === 'object' && x !== null) || typeof x === 'function';
+  }
+
+  // Promises
+
+  // A promise with its resolving functions, and whether it settled.
+  function newPromise() {
+    const deferred = { state: 'pending' };
+    deferred.promise = new Promise((resolve, reject) => {
+      deferred.resolve = (value) => {
+        if (deferred.state === 'pending') {
+          deferred.state = 'fulfilled';
+          resolve(value);
+        }
+      };
+      deferred.reject = (reason) => {
+        if (deferred.state === 'pending') {
+          deferred.state = 'rejected';
+          reject(reason);
+        }
+      };
+    });
+    return deferred;
+  }
+
+  function resolvedDeferred(value) {
+    const deferred = newPromise();
+    deferred.resolve(value);
+    return deferred;
+  }
+
+  function rejectedDeferred(reason) {
+    const deferred = newPromise();
+    deferred.reject(reason);
+    setPromiseIsHandledToTrue(deferred.promise);
+    return deferred;
+  }
+
+  function promiseResolvedWith(value) {
+    return Promise.resolve(value);
+  }
+
+  function promiseRejectedWith(reason) {
+    return Promise.reject(reason);
+  }
+
+  function setPromiseIsHandledToTrue(promise) {
+    promise.then(undefined, () => {});
+  }
+
+  function uponPromise(promise, onFulfilled, onRejected) {
+    promise.then(onFulfilled, onRejected);
+  }
+
+  function uponFulfillment(promise, onFulfilled) {
+    promise.then(onFulfilled, () => {});
+  }
+
+  function uponRejection(promise, onRejected) {
+    promise.then(undefined, onRejected);
+  }
+
+  function transformPromiseWith(promise, onFulfilled, onRejected) {
+    return promise.then(onFulfilled, onRejected);
+  }
+
+  // Call `method` of `obj`, turning what it returns or throws into a
+  // promise.
+  function promiseCall(method, obj, ...args) {
+    try {
+      return promiseResolvedWith(method.call(obj, ...args));
+    } catch (e) {
+      return promiseRejectedWith(e);
+    }
+  }
+
+  // The method `name` of a dictionary member such as an underlying source.
+  function getMethod(obj, name, context) {
+    const method = obj[name];
+    if (method !== undefined && typeof method !== 'function') {
+      throw new TypeError(`${context}.${name} must be a function`);
+    }
+    return method;
+  }
+
+  // Buffers
+
+  // Buffers detached by transferArrayBuffer, as V8 doesn't tell without
+  // ArrayBuffer.prototype.detached.
+  const detachedBuffers = new WeakSet();
+
+  function transferArrayBuffer(buffer) {
+    const transferred = arrayBufferTransfer(buffer);
+    detachedBuffers.add(buffer);
+    return transferred;
+  }
+
+  function isDetachedBuffer(buffer) {
+    return buffer.detached === true || detachedBuffers.has(buffer);
+  }
+
+  function cloneAsUint8Array(view) {
+    const buffer = view.buffer.slice(view.byteOffset, view.byteOffset + view.byteLength);
+    return new Uint8Array(buffer);
+  }
+
+  function copyDataBlockBytes(dest, destOffset, src, srcOffset, count) {
+    new Uint8Array(dest, destOffset, count).set(new Uint8Array(src, srcOffset, count));
+  }
+
+  // Queue with sizes
+
+  function isNonNegativeNumber(v) {
+    return typeof v === 'number' && !Number.isNaN(v) && v >= 0;
+  }
+
+  function dequeueValue(container) {
+    const pair = container._queue.shift();
+    container._queueTotalSize -= pair.size;
+    if (container._queueTotalSize < 0) {
+      container._queueTotalSize = 0;
+    }
+    return pair.value;
+  }
+
+  function enqueueValueWithSize(container, value, size) {
+    if (!isNonNegativeNumber(size) || size === Infinity) {
+      throw new RangeError('The size of a chunk must be a finite, non-negative number');
+    }
+    container._queue.push({ value, size });
+    container._queueTotalSize += size;
+  }
+
+  function peekQueueValue(container) {
+    return container._queue[0].value;
+  }
+
+  function resetQueue(container) {
+    container._queue = [];
+    container._queueTotalSize = 0;
+  }
+
+  // Queuing strategies
+
+  function dictionary(value, context) {
+    if (value === undefined || value === null) {
+      return {};
+    }
+    if (!typeIsObject(value)) {
+      throw new TypeError(`${context} must be an object`);
+    }
+    return value;
+  }
+
+  function extractHighWaterMark(strategy, defaultHWM) {
+    if (strategy.highWaterMark === undefined) {
+      return defaultHWM;
+    }
+    const highWaterMark = Number(strategy.highWaterMark);
+    if (Number.isNaN(highWaterMark) || highWaterMark < 0) {
+      throw new RangeError('highWaterMark must be a non-negative number');
+    }
+    return highWaterMark;
+  }
+
+  function extractSizeAlgorithm(strategy) {
+    const { size } = strategy;
+    if (size === undefined) {
+      return () => 1;
+    }
+    if (typeof size !== 'function') {
+      throw new TypeError('size must be a function');
+    }
+    return (chunk) => size(chunk);
+  }
+
+  function queuingStrategyInit(init, name) {
+    if (!typeIsObject(init) || init.highWaterMark === undefined) {
+      throw new TypeError(`${name} requires a highWaterMark`);
+    }
+    return Number(init.highWaterMark);
+  }
+
+  // Shared by all instances, as the spec requires.
+  const byteLengthSize = function size(chunk) {
+    return chunk.byteLength;
+  };
+
+  const countSize = function size() {
+    return 1;
+  };
+
+  class ByteLengthQueuingStrategy {
+    constructor(init) {
+      this._highWaterMark = queuingStrategyInit(init, 'ByteLengthQueuingStrategy');
+    }
+
+    get highWaterMark() {
+      return this._highWaterMark;
+    }
+
+    get size() {
+      return byteLengthSize;
+    }
+  }
+
+  class CountQueuingStrategy {
+    constructor(init) {
+      this._highWaterMark = queuingStrategyInit(init, 'CountQueuingStrategy');
+    }
+
+    get highWaterMark() {
+      return this._highWaterMark;
+    }
+
+    get size() {
+      return countSize;
+    }
+  }
+
+  // ReadableStream
+
+  class ReadableStream {
+    constructor(underlyingSource = undefined, strategy = undefined) {
+      const source = dictionary(underlyingSource, 'underlyingSource');
+      strategy = dictionary(strategy, 'strategy');
+      initializeReadableStream(this);
+      const type = source.type === undefined ? undefined : String(source.type);
+      if (type === 'bytes') {
+        if (strategy.size !== undefined) {
+          throw new RangeError('The strategy of a byte stream cannot have a size');
+        }
+        const highWaterMark = extractHighWaterMark(strategy, 0);
+        setUpReadableByteStreamControllerFromUnderlyingSource(this, source, highWaterMark);
+      } else if (type === undefined) {
+        const sizeAlgorithm = extractSizeAlgorithm(strategy);
+        const highWaterMark = extractHighWaterMark(strategy, 1);
+        setUpReadableStreamDefaultControllerFromUnderlyingSource(
+          this, source, highWaterMark, sizeAlgorithm,
+        );
+      } else {
+        throw new RangeError(`Invalid type "${type}"`);
+      }
+    }
+
+    static from(asyncIterable) {
+      return readableStreamFromIterable(asyncIterable);
+    }
+
+    get locked() {
+      return isReadableStreamLocked(this);
+    }
+
+    cancel(reason = undefined) {
+      if (isReadableStreamLocked(this)) {
+        return promiseRejectedWith(new TypeError('Cannot cancel a locked stream'));
+      }
+      return readableStreamCancel(this, reason);
+    }
+
+    getReader(options = undefined) {
+      const { mode } = dictionary(options, 'options');
+      if (mode === undefined) {
+        return acquireReadableStreamDefaultReader(this);
+      }
+      if (String(mode) !== 'byob') {
+        throw new TypeError(`Invalid mode "${mode}"`);
+      }
+      return acquireReadableStreamBYOBReader(this);
+    }
+
+    pipeThrough(transform, options = undefined) {
+      const { readable, writable } = dictionary(transform, 'transform');
+      if (!(readable instanceof ReadableStream) || !(writable instanceof WritableStream)) {
+        throw new TypeError('transform must have a readable and a writable stream');
+      }
+      const {
+        preventClose, preventAbort, preventCancel, signal,
+      } = pipeOptions(options);
+      if (isReadableStreamLocked(this)) {
+        throw new TypeError('Cannot pipe a locked stream');
+      }
+      if (isWritableStreamLocked(writable)) {
+        throw new TypeError('Cannot pipe to a locked stream');
+      }
+      const promise = readableStreamPipeTo(
+        this, writable, preventClose, preventAbort, preventCancel, signal,
+      );
+      setPromiseIsHandledToTrue(promise);
+      return readable;
+    }
+
+    pipeTo(destination, options = undefined) {
+      let parsed;
+      try {
+        parsed = pipeOptions(options);
+      } catch (e) {
+        return promiseRejectedWith(e);
+      }
+      if (!(destination instanceof WritableStream)) {
+        return promiseRejectedWith(new TypeError('destination must be a WritableStream'));
+      }
+      if (isReadableStreamLocked(this)) {
+        return promiseRejectedWith(new TypeError('Cannot pipe a locked stream'));
+      }
+      if (isWritableStreamLocked(destination)) {
+        return promiseRejectedWith(new TypeError('Cannot pipe to a locked stream'));
+      }
+      const {
+        preventClose, preventAbort, preventCancel, signal,
+      } = parsed;
+      return readableStreamPipeTo(
+        this, destination, preventClose, preventAbort, preventCancel, signal,
+      );
+    }
+
+    tee() {
+      return readableStreamTee(this, false);
+    }
+
+    values(options = undefined) {
+      const { preventCancel = false } = dictionary(options, 'options');
+      const reader = acquireReadableStreamDefaultReader(this);
+      const iterator = Object.create(ReadableStreamAsyncIteratorPrototype);
+      iterator._reader = reader;
+      iterator._preventCancel = Boolean(preventCancel);
+      iterator._isFinished = false;
+      iterator._ongoingPromise = undefined;
+      return iterator;
+    }
+
+    [Symbol.asyncIterator](options = undefined) {
+      return this.values(options);
+    }
+  }
+
+  Object.defineProperty(ReadableStream.prototype, Symbol.toStringTag, {
+    value: 'ReadableStream',
+    configurable: true,
+  });
+
+  function pipeOptions(options) {
+    const {
+      preventClose = false, preventAbort = false, preventCancel = false, signal,
+    } = dictionary(options, 'options');
+    if (signal !== undefined && !isAbortSignal(signal)) {
+      throw new TypeError('signal must be an AbortSignal');
+    }
+    return {
+      preventClose: Boolean(preventClose),
+      preventAbort: Boolean(preventAbort),
+      preventCancel: Boolean(preventCancel),
+      signal,
+    };
+  }
+
+  function isAbortSignal(value) {
+    return typeIsObject(value) && 'aborted' in value && typeof value.addEventListener === 'function';
+  }
+
+  function abortReason(signal) {
+    if (signal.reason !== undefined) {
+      return signal.reason;
+    }
+    return new DOMException('This operation was aborted', 'AbortError');
+  }
+
+  const AsyncIteratorPrototype = Object.getPrototypeOf(
+    Object.getPrototypeOf(async function* generator() {}).prototype,
+  );
+
+  const ReadableStreamAsyncIteratorPrototype = Object.setPrototypeOf({
+    next() {
+      const nextSteps = () => {
+        if (this._isFinished) {
+          return promiseResolvedWith({ value: undefined, done: true });
+        }
+        return readableStreamAsyncIteratorNext(this);
+      };
+      const previous = this._ongoingPromise;
+      const next = previous ? previous.then(nextSteps, nextSteps) : nextSteps();
+      this._ongoingPromise = next.then(
+        (result) => {
+          this._ongoingPromise = undefined;
+          return result;
+        },
+        (e) => {
+          this._ongoingPromise = undefined;
+          throw e;
+        },
+      );
+      return this._ongoingPromise;
+    },
+
+    return(value) {
+      const returnSteps = () => {
+        if (this._isFinished) {
+          return promiseResolvedWith(undefined);
+        }
+        this._isFinished = true;
+        return readableStreamAsyncIteratorReturn(this, value);
+      };
+      const previous = this._ongoingPromise;
+      this._ongoingPromise = previous ? previous.then(returnSteps, returnSteps) : returnSteps();
+      return this._ongoingPromise.then(() => ({ value, done: true }));
+    },
+  }, AsyncIteratorPrototype);
+
+  function readableStreamAsyncIteratorNext(iterator) {
+    const reader = iterator._reader;
+    if (reader._stream === undefined) {
+      return promiseRejectedWith(new TypeError('The iterator was released'));
+    }
+    const { promise, resolve, reject } = newPromise();
+    readableStreamDefaultReaderRead(reader, {
+      chunkSteps: (chunk) => resolve({ value: chunk, done: false }),
+      closeSteps: () => {
+        readableStreamDefaultReaderRelease(reader);
+        iterator._isFinished = true;
+        resolve({ value: undefined, done: true });
+      },
+      errorSteps: (e) => {
+        readableStreamDefaultReaderRelease(reader);
+        iterator._isFinished = true;
+        reject(e);
+      },
+    });
+    return promise;
+  }
+
+  function readableStreamAsyncIteratorReturn(iterator, value) {
+    const reader = iterator._reader;
+    if (reader._stream === undefined) {
+      return promiseResolvedWith(undefined);
+    }
+    if (!iterator._preventCancel) {
+      const result = readableStreamReaderGenericCancel(reader, value);
+      readableStreamDefaultReaderRelease(reader);
+      return result;
+    }
+    readableStreamDefaultReaderRelease(reader);
+    return promiseResolvedWith(undefined);
+  }
+
+  function acquireReadableStreamBYOBReader(stream) {
+    return new ReadableStreamBYOBReader(stream);
+  }
+
+  function acquireReadableStreamDefaultReader(stream) {
+    return new ReadableStreamDefaultReader(stream);
+  }
+
+  function createReadableStream(
+    startAlgorithm, pullAlgorithm, cancelAlgorithm, highWaterMark = 1, sizeAlgorithm = () => 1,
+  ) {
+    const stream = Object.create(ReadableStream.prototype);
+    initializeReadableStream(stream);
+    const controller = Object.create(ReadableStreamDefaultController.prototype);
+    setUpReadableStreamDefaultController(
+      stream, controller, startAlgorithm, pullAlgorithm, cancelAlgorithm,
+      highWaterMark, sizeAlgorithm,
+    );
+    return stream;
+  }
+
+  function createReadableByteStream(startAlgorithm, pullAlgorithm, cancelAlgorithm) {
+    const stream = Object.create(ReadableStream.prototype);
+    initializeReadableStream(stream);
+    const controller = Object.create(ReadableByteStreamController.prototype);
+    setUpReadableByteStreamController(
+      stream, controller, startAlgorithm, pullAlgorithm, cancelAlgorithm, 0, undefined,
+    );
+    return stream;
+  }
+
+  function initializeReadableStream(stream) {
+    stream._state = 'readable';
+    stream._reader = undefined;
+    stream._storedError = undefined;
+    stream._disturbed = false;
+    stream._controller = undefined;
+  }
+
+  function isReadableStreamLocked(stream) {
+    if (!(stream instanceof ReadableStream)) {
+      throw new TypeError('Not a ReadableStream');
+    }
+    return stream._reader !== undefined;
+  }
+
+  function readableStreamFromIterable(asyncIterable) {
+    let iterator;
+    const asyncMethod = asyncIterable == null ? undefined : asyncIterable[Symbol.asyncIterator];
+    if (asyncMethod !== undefined && asyncMethod !== null) {
+      iterator = asyncMethod.call(asyncIterable);
+    } else {
+      const syncMethod = asyncIterable == null ? undefined : asyncIterable[Symbol.iterator];
+      if (typeof syncMethod !== 'function') {
+        throw new TypeError('The argument must be iterable');
+      }
+      iterator = asyncFromSyncIterator(syncMethod.call(asyncIterable));
+    }
+    if (!typeIsObject(iterator)) {
+      throw new TypeError('The iterator must be an object');
+    }
+    const nextMethod = iterator.next;
+
+    let stream;
+    const startAlgorithm = () => undefined;
+    const pullAlgorithm = () => transformPromiseWith(
+      promiseCall(nextMethod, iterator),
+      (result) => {
+        if (!typeIsObject(result)) {
+          throw new TypeError('The iterator result must be an object');
+        }
+        if (result.done) {
+          readableStreamDefaultControllerClose(stream._controller);
+        } else {
+          readableStreamDefaultControllerEnqueue(stream._controller, result.value);
+        }
+      },
+    );
+    const cancelAlgorithm = (reason) => {
+      let returnMethod;
+      try {
+        returnMethod = iterator.return;
+      } catch (e) {
+        return promiseRejectedWith(e);
+      }
+      if (returnMethod === undefined || returnMethod === null) {
+        return promiseResolvedWith(undefined);
+      }
+      return transformPromiseWith(promiseCall(returnMethod, iterator, reason), (result) => {
+        if (!typeIsObject(result)) {
+          throw new TypeError('The iterator result must be an object');
+        }
+      });
+    };
+    stream = createReadableStream(startAlgorithm, pullAlgorithm, cancelAlgorithm, 0);
+    return stream;
+  }
+
+  function asyncFromSyncIterator(syncIterator) {
+    const settle = (result) => promiseResolvedWith(result.value)
+      .then((value) => ({ value, done: Boolean(result.done) }));
+    return {
+      next() {
+        return promiseCall(syncIterator.next, syncIterator).then(settle);
+      },
+      return(value) {
+        if (typeof syncIterator.return !== 'function') {
+          return promiseResolvedWith({ value, done: true });
+        }
+        return promiseCall(syncIterator.return, syncIterator, value).then(settle);
+      },
+    };
+  }
+
+  function readableStreamPipeTo(source, dest, preventClose, preventAbort, preventCancel, signal) {
+    const reader = acquireReadableStreamDefaultReader(source);
+    const writer = acquireWritableStreamDefaultWriter(dest);
+    source._disturbed = true;
+    let shuttingDown = false;
+    // Resolves once the most recent write finished, whether it failed.
+    let currentWrite = promiseResolvedWith(undefined);
+    const { promise, resolve, reject } = newPromise();
+
+    let abortAlgorithm;
+    if (signal !== undefined) {
+      abortAlgorithm = () => {
+        const error = abortReason(signal);
+        const actions = [];
+        if (!preventAbort) {
+          actions.push(() => {
+            if (dest._state === 'writable') {
+              return writableStreamAbort(dest, error);
+            }
+            return promiseResolvedWith(undefined);
+          });
+        }
+        if (!preventCancel) {
+          actions.push(() => {
+            if (source._state === 'readable') {
+              return readableStreamCancel(source, error);
+            }
+            return promiseResolvedWith(undefined);
+          });
+        }
+        shutdownWithAnAction(() => Promise.all(actions.map((action) => action())), true, error);
+      };
+      if (signal.aborted) {
+        abortAlgorithm();
+        return promise;
+      }
+      signal.addEventListener('abort', abortAlgorithm);
+    }
+
+    function pipeStep() {
+      if (shuttingDown) {
+        return promiseResolvedWith(true);
+      }
+      return writer._readyPromise.promise.then(() => {
+        if (shuttingDown) {
+          return true;
+        }
+        const read = newPromise();
+        readableStreamDefaultReaderRead(reader, {
+          chunkSteps: (chunk) => {
+            currentWrite = transformPromiseWith(
+              writableStreamDefaultWriterWrite(writer, chunk), undefined, () => {},
+            );
+            read.resolve(false);
+          },
+          closeSteps: () => read.resolve(true),
+          errorSteps: read.reject,
+        });
+        return read.promise;
+      });
+    }
+
+    function pipeLoop() {
+      return new Promise((resolveLoop, rejectLoop) => {
+        function next(done) {
+          if (done) {
+            resolveLoop();
+          } else {
+            pipeStep().then(next, rejectLoop);
+          }
+        }
+        next(false);
+      });
+    }
+
+    function waitForWritesToFinish() {
+      const oldCurrentWrite = currentWrite;
+      return currentWrite.then(() => (
+        oldCurrentWrite !== currentWrite ? waitForWritesToFinish() : undefined
+      ));
+    }
+
+    function isOrBecomesErrored(stream, closed, action) {
+      if (stream._state === 'errored') {
+        action(stream._storedError);
+      } else {
+        uponRejection(closed, action);
+      }
+    }
+
+    function isOrBecomesClosed(stream, closed, action) {
+      if (stream._state === 'closed') {
+        action();
+      } else {
+        uponFulfillment(closed, action);
+      }
+    }
+
+    function finalize(isError, error) {
+      writableStreamDefaultWriterRelease(writer);
+      readableStreamDefaultReaderRelease(reader);
+      if (signal !== undefined) {
+        signal.removeEventListener('abort', abortAlgorithm);
+      }
+      if (isError) {
+        reject(error);
+      } else {
+        resolve(undefined);
+      }
+    }
+
+    function shutdownWithAnAction(action, originalIsError, originalError) {
+      if (shuttingDown) {
+        return;
+      }
+      shuttingDown = true;
+      const doTheRest = () => {
+        uponPromise(
+          action(),
+          () => finalize(originalIsError, originalError),
+          (newError) => finalize(true, newError),
+        );
+      };
+      if (dest._state === 'writable' && !writableStreamCloseQueuedOrInFlight(dest)) {
+        uponFulfillment(waitForWritesToFinish(), doTheRest);
+      } else {
+        doTheRest();
+      }
+    }
+
+    function shutdown(isError, error) {
+      if (shuttingDown) {
+        return;
+      }
+      shuttingDown = true;
+      if (dest._state === 'writable' && !writableStreamCloseQueuedOrInFlight(dest)) {
+        uponFulfillment(waitForWritesToFinish(), () => finalize(isError, error));
+      } else {
+        finalize(isError, error);
+      }
+    }
+
+    isOrBecomesErrored(source, reader._closedPromise.promise, (storedError) => {
+      if (!preventAbort) {
+        shutdownWithAnAction(() => writableStreamAbort(dest, storedError), true, storedError);
+      } else {
+        shutdown(true, storedError);
+      }
+    });
+
+    isOrBecomesErrored(dest, writer._closedPromise.promise, (storedError) => {
+      if (!preventCancel) {
+        shutdownWithAnAction(() => readableStreamCancel(source, storedError), true, storedError);
+      } else {
+        shutdown(true, storedError);
+      }
+    });
+
+    isOrBecomesClosed(source, reader._closedPromise.promise, () => {
+      if (!preventClose) {
+        shutdownWithAnAction(() => writableStreamDefaultWriterCloseWithErrorPropagation(writer));
+      } else {
+        shutdown();
+      }
+    });
+
+    if (writableStreamCloseQueuedOrInFlight(dest) || dest._state === 'closed') {
+      const destClosed = new TypeError('The destination stream closed before all data was piped to it');
+      if (!preventCancel) {
+        shutdownWithAnAction(() => readableStreamCancel(source, destClosed), true, destClosed);
+      } else {
+        shutdown(true, destClosed);
+      }
+    }
+
+    setPromiseIsHandledToTrue(pipeLoop());
+    return promise;
+  }
+
+  function readableStreamTee(stream, cloneForBranch2) {
+    if (stream._controller instanceof ReadableByteStreamController) {
+      return readableByteStreamTee(stream);
+    }
+    return readableStreamDefaultTee(stream, cloneForBranch2);
+  }
+
+  function readableStreamDefaultTee(stream) {
+    const reader = acquireReadableStreamDefaultReader(stream);
+    let reading = false;
+    let readAgain = false;
+    let canceled1 = false;
+    let canceled2 = false;
+    let reason1;
+    let reason2;
+    let branch1;
+    let branch2;
+    const cancelPromise = newPromise();
+
+    function pullAlgorithm() {
+      if (reading) {
+        readAgain = true;
+        return promiseResolvedWith(undefined);
+      }
+      reading = true;
+      readableStreamDefaultReaderRead(reader, {
+        chunkSteps: (chunk) => {
+          // Lets errors from the source reach the branches first.
+          queueMicrotask(() => {
+            readAgain = false;
+            if (!canceled1) {
+              readableStreamDefaultControllerEnqueue(branch1._controller, chunk);
+            }
+            if (!canceled2) {
+              readableStreamDefaultControllerEnqueue(branch2._controller, chunk);
+            }
+            reading = false;
+            if (readAgain) {
+              pullAlgorithm();
+            }
+          });
+        },
+        closeSteps: () => {
+          reading = false;
+          if (!canceled1) {
+            readableStreamDefaultControllerClose(branch1._controller);
+          }
+          if (!canceled2) {
+            readableStreamDefaultControllerClose(branch2._controller);
+          }
+          if (!canceled1 || !canceled2) {
+            cancelPromise.resolve(undefined);
+          }
+        },
+        errorSteps: () => {
+          reading = false;
+        },
+      });
+      return promiseResolvedWith(undefined);
+    }
+
+    function cancel1Algorithm(reason) {
+      canceled1 = true;
+      reason1 = reason;
+      if (canceled2) {
+        cancelPromise.resolve(readableStreamCancel(stream, [reason1, reason2]));
+      }
+      return cancelPromise.promise;
+    }
+
+    function cancel2Algorithm(reason) {
+      canceled2 = true;
+      reason2 = reason;
+      if (canceled1) {
+        cancelPromise.resolve(readableStreamCancel(stream, [reason1, reason2]));
+      }
+      return cancelPromise.promise;
+    }
+
+    const startAlgorithm = () => undefined;
+    branch1 = createReadableStream(startAlgorithm, pullAlgorithm, cancel1Algorithm);
+    branch2 = createReadableStream(startAlgorithm, pullAlgorithm, cancel2Algorithm);
+
+    uponRejection(reader._closedPromise.promise, (r) => {
+      readableStreamDefaultControllerError(branch1._controller, r);
+      readableStreamDefaultControllerError(branch2._controller, r);
+      if (!canceled1 || !canceled2) {
+        cancelPromise.resolve(undefined);
+      }
+    });
+
+    return [branch1, branch2];
+  }
+
+  function readableByteStreamTee(stream) {
+    let reader = acquireReadableStreamDefaultReader(stream);
+    let reading = false;
+    let readAgainForBranch1 = false;
+    let readAgainForBranch2 = false;
+    let canceled1 = false;
+    let canceled2 = false;
+    let reason1;
+    let reason2;
+    let branch1;
+    let branch2;
+    const cancelPromise = newPromise();
+
+    function forwardReaderError(thisReader) {
+      uponRejection(thisReader._closedPromise.promise, (r) => {
+        if (thisReader !== reader) {
+          return;
+        }
+        readableByteStreamControllerError(branch1._controller, r);
+        readableByteStreamControllerError(branch2._controller, r);
+        if (!canceled1 || !canceled2) {
+          cancelPromise.resolve(undefined);
+        }
+      });
+    }
+
+    function cloneOrCancel(chunk) {
+      try {
+        return cloneAsUint8Array(chunk);
+      } catch (e) {
+        readableByteStreamControllerError(branch1._controller, e);
+        readableByteStreamControllerError(branch2._controller, e);
+        cancelPromise.resolve(readableStreamCancel(stream, e));
+        return undefined;
+      }
+    }
+
+    function pullAgain() {
+      if (readAgainForBranch1) {
+        pull1Algorithm();
+      } else if (readAgainForBranch2) {
+        pull2Algorithm();
+      }
+    }
+
+    function pullWithDefaultReader() {
+      if (reader instanceof ReadableStreamBYOBReader) {
+        readableStreamReaderGenericRelease(reader);
+        reader = acquireReadableStreamDefaultReader(stream);
+        forwardReaderError(reader);
+      }
+      readableStreamDefaultReaderRead(reader, {
+        chunkSteps: (chunk) => {
+          queueMicrotask(() => {
+            readAgainForBranch1 = false;
+            readAgainForBranch2 = false;
+            let chunk2 = chunk;
+            if (!canceled1 && !canceled2) {
+              chunk2 = cloneOrCancel(chunk);
+              if (chunk2 === undefined) {
+                return;
+              }
+            }
+            if (!canceled1) {
+              readableByteStreamControllerEnqueue(branch1._controller, chunk);
+            }
+            if (!canceled2) {
+              readableByteStreamControllerEnqueue(branch2._controller, chunk2);
+            }
+            reading = false;
+            pullAgain();
+          });
+        },
+        closeSteps: () => {
+          reading = false;
+          if (!canceled1) {
+            readableByteStreamControllerClose(branch1._controller);
+          }
+          if (!canceled2) {
+            readableByteStreamControllerClose(branch2._controller);
+          }
+          if (branch1._controller._pendingPullIntos.length > 0) {
+            readableByteStreamControllerRespond(branch1._controller, 0);
+          }
+          if (branch2._controller._pendingPullIntos.length > 0) {
+            readableByteStreamControllerRespond(branch2._controller, 0);
+          }
+          if (!canceled1 || !canceled2) {
+            cancelPromise.resolve(undefined);
+          }
+        },
+        errorSteps: () => {
+          reading = false;
+        },
+      });
+    }
+
+    function pullWithBYOBReader(view, forBranch2) {
+      if (reader instanceof ReadableStreamDefaultReader) {
+        readableStreamReaderGenericRelease(reader);
+        reader = acquireReadableStreamBYOBReader(stream);
+        forwardReaderError(reader);
+      }
+      const byobBranch = forBranch2 ? branch2 : branch1;
+      const otherBranch = forBranch2 ? branch1 : branch2;
+      readableStreamBYOBReaderRead(reader, view, 1, {
+        chunkSteps: (chunk) => {
+          queueMicrotask(() => {
+            readAgainForBranch1 = false;
+            readAgainForBranch2 = false;
+            const byobCanceled = forBranch2 ? canceled2 : canceled1;
+            const otherCanceled = forBranch2 ? canceled1 : canceled2;
+            if (!otherCanceled) {
+              const clonedChunk = cloneOrCancel(chunk);
+              if (clonedChunk === undefined) {
+                return;
+              }
+              if (!byobCanceled) {
+                readableByteStreamControllerRespondWithNewView(byobBranch._controller, chunk);
+              }
+              readableByteStreamControllerEnqueue(otherBranch._controller, clonedChunk);
+            } else if (!byobCanceled) {
+              readableByteStreamControllerRespondWithNewView(byobBranch._controller, chunk);
+            }
+            reading = false;
+            pullAgain();
+          });
+        },
+        closeSteps: (chunk) => {
+          reading = false;
+          const byobCanceled = forBranch2 ? canceled2 : canceled1;
+          const otherCanceled = forBranch2 ? canceled1 : canceled2;
+          if (!byobCanceled) {
+            readableByteStreamControllerClose(byobBranch._controller);
+          }
+          if (!otherCanceled) {
+            readableByteStreamControllerClose(otherBranch._controller);
+          }
+          if (chunk !== undefined) {
+            if (!byobCanceled) {
+              readableByteStreamControllerRespondWithNewView(byobBranch._controller, chunk);
+            }
+            if (!otherCanceled && otherBranch._controller._pendingPullIntos.length > 0) {
+              readableByteStreamControllerRespond(otherBranch._controller, 0);
+            }
+          }
+          if (!byobCanceled || !otherCanceled) {
+            cancelPromise.resolve(undefined);
+          }
+        },
+        errorSteps: () => {
+          reading = false;
+        },
+      });
+    }
+
+    function pull1Algorithm() {
+      if (reading) {
+        readAgainForBranch1 = true;
+        return promiseResolvedWith(undefined);
+      }
+      reading = true;
+      const byobRequest = readableByteStreamControllerGetBYOBRequest(branch1._controller);
+      if (byobRequest === null) {
+        pullWithDefaultReader();
+      } else {
+        pullWithBYOBReader(byobRequest._view, false);
+      }
+      return promiseResolvedWith(undefined);
+    }
+
+    function pull2Algorithm() {
+      if (reading) {
+        readAgainForBranch2 = true;
+        return promiseResolvedWith(undefined);
+      }
+      reading = true;
+      const byobRequest = readableByteStreamControllerGetBYOBRequest(branch2._controller);
+      if (byobRequest === null) {
+        pullWithDefaultReader();
+      } else {
+        pullWithBYOBReader(byobRequest._view, true);
+      }
+      return promiseResolvedWith(undefined);
+    }
+
+    function cancel1Algorithm(reason) {
+      canceled1 = true;
+      reason1 = reason;
+      if (canceled2) {
+        cancelPromise.resolve(readableStreamCancel(stream, [reason1, reason2]));
+      }
+      return cancelPromise.promise;
+    }
+
+    function cancel2Algorithm(reason) {
+      canceled2 = true;
+      reason2 = reason;
+      if (canceled1) {
+        cancelPromise.resolve(readableStreamCancel(stream, [reason1, reason2]));
+      }
+      return cancelPromise.promise;
+    }
+
+    const startAlgorithm = () => undefined;
+    branch1 = createReadableByteStream(startAlgorithm, pull1Algorithm, cancel1Algorithm);
+    branch2 = createReadableByteStream(startAlgorithm, pull2Algorithm, cancel2Algorithm);
+    forwardReaderError(reader);
+    return [branch1, branch2];
+  }
+
+  // Interfacing with controllers
+
+  function readableStreamAddReadIntoRequest(stream, readIntoRequest) {
+    stream._reader._readIntoRequests.push(readIntoRequest);
+  }
+
+  function readableStreamAddReadRequest(stream, readRequest) {
+    stream._reader._readRequests.push(readRequest);
+  }
+
+  function readableStreamCancel(stream, reason) {
+    stream._disturbed = true;
+    if (stream._state === 'closed') {
+      return promiseResolvedWith(undefined);
+    }
+    if (stream._state === 'errored') {
+      return promiseRejectedWith(stream._storedError);
+    }
+    readableStreamClose(stream);
+    const reader = stream._reader;
+    if (reader instanceof ReadableStreamBYOBReader) {
+      const readIntoRequests = reader._readIntoRequests;
+      reader._readIntoRequests = [];
+      for (const readIntoRequest of readIntoRequests) {
+        readIntoRequest.closeSteps(undefined);
+      }
+    }
+    const sourceCancelPromise = stream._controller[CancelSteps](reason);
+    return transformPromiseWith(sourceCancelPromise, () => undefined);
+  }
+
+  function readableStreamClose(stream) {
+    stream._state = 'closed';
+    const reader = stream._reader;
+    if (reader === undefined) {
+      return;
+    }
+    reader._closedPromise.resolve(undefined);
+    if (reader instanceof ReadableStreamDefaultReader) {
+      const readRequests = reader._readRequests;
+      reader._readRequests = [];
+      for (const readRequest of readRequests) {
+        readRequest.closeSteps();
+      }
+    }
+  }
+
+  function readableStreamError(stream, e) {
+    stream._state = 'errored';
+    stream._storedError = e;
+    const reader = stream._reader;
+    if (reader === undefined) {
+      return;
+    }
+    reader._closedPromise.reject(e);
+    setPromiseIsHandledToTrue(reader._closedPromise.promise);
+    if (reader instanceof ReadableStreamDefaultReader) {
+      readableStreamDefaultReaderErrorReadRequests(reader, e);
+    } else {
+      readableStreamBYOBReaderErrorReadIntoRequests(reader, e);
+    }
+  }
+
+  function readableStreamFulfillReadIntoRequest(stream, chunk, done) {
+    const readIntoRequest = stream._reader._readIntoRequests.shift();
+    if (done) {
+      readIntoRequest.closeSteps(chunk);
+    } else {
+      readIntoRequest.chunkSteps(chunk);
+    }
+  }
+
+  function readableStreamFulfillReadRequest(stream, chunk, done) {
+    const readRequest = stream._reader._readRequests.shift();
+    if (done) {
+      readRequest.closeSteps();
+    } else {
+      readRequest.chunkSteps(chunk);
+    }
+  }
+
+  function readableStreamGetNumReadIntoRequests(stream) {
+    return stream._reader._readIntoRequests.length;
+  }
+
+  function readableStreamGetNumReadRequests(stream) {
+    return stream._reader._readRequests.length;
+  }
+
+  function readableStreamHasBYOBReader(stream) {
+    return stream._reader instanceof ReadableStreamBYOBReader;
+  }
+
+  function readableStreamHasDefaultReader(stream) {
+    return stream._reader instanceof ReadableStreamDefaultReader;
+  }
+
+  // Readers
+
+  function readableStreamReaderGenericCancel(reader, reason) {
+    return readableStreamCancel(reader._stream, reason);
+  }
+
+  function readableStreamReaderGenericInitialize(reader, stream) {
+    reader._stream = stream;
+    stream._reader = reader;
+    if (stream._state === 'readable') {
+      reader._closedPromise = newPromise();
+    } else if (stream._state === 'closed') {
+      reader._closedPromise = resolvedDeferred(undefined);
+    } else {
+      reader._closedPromise = rejectedDeferred(stream._storedError);
+    }
+  }
+
+  function readableStreamReaderGenericRelease(reader) {
+    const stream = reader._stream;
+    const error = new TypeError('The reader was released');
+    if (stream._state === 'readable') {
+      reader._closedPromise.reject(error);
+    } else {
+      reader._closedPromise = rejectedDeferred(error);
+    }
+    setPromiseIsHandledToTrue(reader._closedPromise.promise);
+    stream._controller[ReleaseSteps]();
+    stream._reader = undefined;
+    reader._stream = undefined;
+  }
+
+  class ReadableStreamDefaultReader {
+    constructor(stream) {
+      if (isReadableStreamLocked(stream)) {
+        throw new TypeError('The stream is locked to a reader');
+      }
+      readableStreamReaderGenericInitialize(this, stream);
+      this._readRequests = [];
+    }
+
+    get closed() {
+      return this._closedPromise.promise;
+    }
+
+    cancel(reason = undefined) {
+      if (this._stream === undefined) {
+        return promiseRejectedWith(new TypeError('The reader was released'));
+      }
+      return readableStreamReaderGenericCancel(this, reason);
+    }
+
+    read() {
+      if (this._stream === undefined) {
+        return promiseRejectedWith(new TypeError('The reader was released'));
+      }
+      const { promise, resolve, reject } = newPromise();
+      readableStreamDefaultReaderRead(this, {
+        chunkSteps: (chunk) => resolve({ value: chunk, done: false }),
+        closeSteps: () => resolve({ value: undefined, done: true }),
+        errorSteps: (e) => reject(e),
+      });
+      return promise;
+    }
+
+    releaseLock() {
+      if (this._stream !== undefined) {
+        readableStreamDefaultReaderRelease(this);
+      }
+    }
+  }
+
+  Object.defineProperty(ReadableStreamDefaultReader.prototype, Symbol.toStringTag, {
+    value: 'ReadableStreamDefaultReader',
+    configurable: true,
+  });
+
+  function readableStreamDefaultReaderErrorReadRequests(reader, e) {
+    const readRequests = reader._readRequests;
+    reader._readRequests = [];
+    for (const readRequest of readRequests) {
+      readRequest.errorSteps(e);
+    }
+  }
+
+  function readableStreamDefaultReaderRead(reader, readRequest) {
+    const stream = reader._stream;
+    stream._disturbed = true;
+    if (stream._state === 'closed') {
+      readRequest.closeSteps();
+    } else if (stream._state === 'errored') {
+      readRequest.errorSteps(stream._storedError);
+    } else {
+      stream._controller[PullSteps](readRequest);
+    }
+  }
+
+  function readableStreamDefaultReaderRelease(reader) {
+    readableStreamReaderGenericRelease(reader);
+    readableStreamDefaultReaderErrorReadRequests(reader, new TypeError('The reader was released'));
+  }
+
+  class ReadableStreamBYOBReader {
+    constructor(stream) {
+      if (isReadableStreamLocked(stream)) {
+        throw new TypeError('The stream is locked to a reader');
+      }
+      if (!(stream._controller instanceof ReadableByteStreamController)) {
+        throw new TypeError('BYOB readers need a byte stream');
+      }
+      readableStreamReaderGenericInitialize(this, stream);
+      this._readIntoRequests = [];
+    }
+
+    get closed() {
+      return this._closedPromise.promise;
+    }
+
+    cancel(reason = undefined) {
+      if (this._stream === undefined) {
+        return promiseRejectedWith(new TypeError('The reader was released'));
+      }
+      return readableStreamReaderGenericCancel(this, reason);
+    }
+
+    read(view, options = undefined) {
+      if (!ArrayBuffer.isView(view)) {
+        return promiseRejectedWith(new TypeError('view must be an ArrayBufferView'));
+      }
+      if (view.byteLength === 0) {
+        return promiseRejectedWith(new TypeError('view must not be empty'));
+      }
+      if (view.buffer.byteLength === 0 || isDetachedBuffer(view.buffer)) {
+        return promiseRejectedWith(new TypeError('The buffer of view must not be detached'));
+      }
+      let min;
+      try {
+        ({ min = 1 } = dictionary(options, 'options'));
+        min = Number(min);
+      } catch (e) {
+        return promiseRejectedWith(e);
+      }
+      if (!Number.isInteger(min) || min <= 0) {
+        return promiseRejectedWith(new TypeError('min must be a positive integer'));
+      }
+      const length = view instanceof DataView ? view.byteLength : view.length;
+      if (min > length) {
+        return promiseRejectedWith(new RangeError('min must not exceed the length of view'));
+      }
+      if (this._stream === undefined) {
+        return promiseRejectedWith(new TypeError('The reader was released'));
+      }
+      const { promise, resolve, reject } = newPromise();
+      readableStreamBYOBReaderRead(this, view, min, {
+        chunkSteps: (chunk) => resolve({ value: chunk, done: false }),
+        closeSteps: (chunk) => resolve({ value: chunk, done: true }),
+        errorSteps: (e) => reject(e),
+      });
+      return promise;
+    }
+
+    releaseLock() {
+      if (this._stream !== undefined) {
+        readableStreamBYOBReaderRelease(this);
+      }
+    }
+  }
+
+  Object.defineProperty(ReadableStreamBYOBReader.prototype, Symbol.toStringTag, {
+    value: 'ReadableStreamBYOBReader',
+    configurable: true,
+  });
+
+  function readableStreamBYOBReaderErrorReadIntoRequests(reader, e) {
+    const readIntoRequests = reader._readIntoRequests;
+    reader._readIntoRequests = [];
+    for (const readIntoRequest of readIntoRequests) {
+      readIntoRequest.errorSteps(e);
+    }
+  }
+
+  function readableStreamBYOBReaderRead(reader, view, min, readIntoRequest) {
+    const stream = reader._stream;
+    stream._disturbed = true;
+    if (stream._state === 'errored') {
+      readIntoRequest.errorSteps(stream._storedError);
+    } else {
+      readableByteStreamControllerPullInto(stream._controller, view, min, readIntoRequest);
+    }
+  }
+
+  function readableStreamBYOBReaderRelease(reader) {
+    readableStreamReaderGenericRelease(reader);
+    readableStreamBYOBReaderErrorReadIntoRequests(reader, new TypeError('The reader was released'));
+  }
+
+  // Default controllers
+
+  class ReadableStreamDefaultController {
+    constructor(token) {
+      illegalConstructor(token);
+    }
+
+    get desiredSize() {
+      return readableStreamDefaultControllerGetDesiredSize(this);
+    }
+
+    close() {
+      if (!readableStreamDefaultControllerCanCloseOrEnqueue(this)) {
+        throw new TypeError('The stream is not in a state that permits close');
+      }
+      readableStreamDefaultControllerClose(this);
+    }
+
+    enqueue(chunk = undefined) {
+      if (!readableStreamDefaultControllerCanCloseOrEnqueue(this)) {
+        throw new TypeError('The stream is not in a state that permits enqueue');
+      }
+      readableStreamDefaultControllerEnqueue(this, chunk);
+    }
+
+    error(e = undefined) {
+      readableStreamDefaultControllerError(this, e);
+    }
+
+    [CancelSteps](reason) {
+      resetQueue(this);
+      const result = this._cancelAlgorithm(reason);
+      readableStreamDefaultControllerClearAlgorithms(this);
+      return result;
+    }
+
+    [PullSteps](readRequest) {
+      const stream = this._stream;
+      if (this._queue.length > 0) {
+        const chunk = dequeueValue(this);
+        if (this._closeRequested && this._queue.length === 0) {
+          readableStreamDefaultControllerClearAlgorithms(this);
+          readableStreamClose(stream);
+        } else {
+          readableStreamDefaultControllerCallPullIfNeeded(this);
+        }
+        readRequest.chunkSteps(chunk);
+      } else {
+        readableStreamAddReadRequest(stream, readRequest);
+        readableStreamDefaultControllerCallPullIfNeeded(this);
+      }
+    }
+
+    [ReleaseSteps]() {}
+  }
+
+  Object.defineProperty(ReadableStreamDefaultController.prototype, Symbol.toStringTag, {
+    value: 'ReadableStreamDefaultController',
+    configurable: true,
+  });
+
+  function readableStreamDefaultControllerCallPullIfNeeded(controller) {
+    if (!readableStreamDefaultControllerShouldCallPull(controller)) {
+      return;
+    }
+    if (controller._pulling) {
+      controller._pullAgain = true;
+      return;
+    }
+    controller._pulling = true;
+    uponPromise(
+      controller._pullAlgorithm(),
+      () => {
+        controller._pulling = false;
+        if (controller._pullAgain) {
+          controller._pullAgain = false;
+          readableStreamDefaultControllerCallPullIfNeeded(controller);
+        }
+      },
+      (e) => readableStreamDefaultControllerError(controller, e),
+    );
+  }
+
+  function readableStreamDefaultControllerShouldCallPull(controller) {
+    const stream = controller._stream;
+    if (!readableStreamDefaultControllerCanCloseOrEnqueue(controller)) {
+      return false;
+    }
+    if (!controller._started) {
+      return false;
+    }
+    if (isReadableStreamLocked(stream) && readableStreamGetNumReadRequests(stream) > 0) {
+      return true;
+    }
+    return readableStreamDefaultControllerGetDesiredSize(controller) > 0;
+  }
+
+  function readableStreamDefaultControllerClearAlgorithms(controller) {
+    controller._pullAlgorithm = undefined;
+    controller._cancelAlgorithm = undefined;
+    controller._strategySizeAlgorithm = undefined;
+  }
+
+  function readableStreamDefaultControllerClose(controller) {
+    if (!readableStreamDefaultControllerCanCloseOrEnqueue(controller)) {
+      return;
+    }
+    controller._closeRequested = true;
+    if (controller._queue.length === 0) {
+      readableStreamDefaultControllerClearAlgorithms(controller);
+      readableStreamClose(controller._stream);
+    }
+  }
+
+  function readableStreamDefaultControllerEnqueue(controller, chunk) {
+    if (!readableStreamDefaultControllerCanCloseOrEnqueue(controller)) {
+      return;
+    }
+    const stream = controller._stream;
+    if (isReadableStreamLocked(stream) && readableStreamGetNumReadRequests(stream) > 0) {
+      readableStreamFulfillReadRequest(stream, chunk, false);
+    } else {
+      let chunkSize;
+      try {
+        chunkSize = controller._strategySizeAlgorithm(chunk);
+      } catch (e) {
+        readableStreamDefaultControllerError(controller, e);
+        throw e;
+      }
+      try {
+        enqueueValueWithSize(controller, chunk, chunkSize);
+      } catch (e) {
+        readableStreamDefaultControllerError(controller, e);
+        throw e;
+      }
+    }
+    readableStreamDefaultControllerCallPullIfNeeded(controller);
+  }
+
+  function readableStreamDefaultControllerError(controller, e) {
+    const stream = controller._stream;
+    if (stream._state !== 'readable') {
+      return;
+    }
+    resetQueue(controller);
+    readableStreamDefaultControllerClearAlgorithms(controller);
+    readableStreamError(stream, e);
+  }
+
+  function readableStreamDefaultControllerGetDesiredSize(controller) {
+    const state = controller._stream._state;
+    if (state === 'errored') {
+      return null;
+    }
+    if (state === 'closed') {
+      return 0;
+    }
+    return controller._strategyHWM - controller._queueTotalSize;
+  }
+
+  function readableStreamDefaultControllerHasBackpressure(controller) {
+    return !readableStreamDefaultControllerShouldCallPull(controller);
+  }
+
+  function readableStreamDefaultControllerCanCloseOrEnqueue(controller) {
+    return !controller._closeRequested && controller._stream._state === 'readable';
+  }
+
+  function setUpReadableStreamDefaultController(
+    stream, controller, startAlgorithm, pullAlgorithm, cancelAlgorithm, highWaterMark, sizeAlgorithm,
+  ) {
+    controller._stream = stream;
+    resetQueue(controller);
+    controller._started = false;
+    controller._closeRequested = false;
+    controller._pullAgain = false;
+    controller._pulling = false;
+    controller._strategySizeAlgorithm = sizeAlgorithm;
+    controller._strategyHWM = highWaterMark;
+    controller._pullAlgorithm = pullAlgorithm;
+    controller._cancelAlgorithm = cancelAlgorithm;
+    stream._controller = controller;
+    const startResult = startAlgorithm();
+    uponPromise(
+      promiseResolvedWith(startResult),
+      () => {
+        controller._started = true;
+        readableStreamDefaultControllerCallPullIfNeeded(controller);
+      },
+      (r) => readableStreamDefaultControllerError(controller, r),
+    );
+  }
+
+  function setUpReadableStreamDefaultControllerFromUnderlyingSource(
+    stream, source, highWaterMark, sizeAlgorithm,
+  ) {
+    const controller = Object.create(ReadableStreamDefaultController.prototype);
+    const start = getMethod(source, 'start', 'underlyingSource');
+    const pull = getMethod(source, 'pull', 'underlyingSource');
+    const cancel = getMethod(source, 'cancel', 'underlyingSource');
+    const startAlgorithm = () => (start ? start.call(source, controller) : undefined);
+    const pullAlgorithm = () => (
+      pull ? promiseCall(pull, source, controller) : promiseResolvedWith(undefined)
+    );
+    const cancelAlgorithm = (reason) => (
+      cancel ? promiseCall(cancel, source, reason) : promiseResolvedWith(undefined)
+    );
+    setUpReadableStreamDefaultController(
+      stream, controller, startAlgorithm, pullAlgorithm, cancelAlgorithm,
+      highWaterMark, sizeAlgorithm,
+    );
+  }
+
+  // Byte stream controllers
+
+  class ReadableByteStreamController {
+    constructor(token) {
+      illegalConstructor(token);
+    }
+
+    get byobRequest() {
+      return readableByteStreamControllerGetBYOBRequest(this);
+    }
+
+    get desiredSize() {
+      return readableByteStreamControllerGetDesiredSize(this);
+    }
+
+    close() {
+      if (this._closeRequested) {
+        throw new TypeError('The stream is already closing');
+      }
+      if (this._stream._state !== 'readable') {
+        throw new TypeError('The stream is not readable');
+      }
+      readableByteStreamControllerClose(this);
+    }
+
+    enqueue(chunk) {
+      if (!ArrayBuffer.isView(chunk)) {
+        throw new TypeError('chunk must be an ArrayBufferView');
+      }
+      if (chunk.byteLength === 0) {
+        throw new TypeError('chunk must not be empty');
+      }
+      if (chunk.buffer.byteLength === 0) {
+        throw new TypeError('The buffer of chunk must not be detached');
+      }
+      if (this._closeRequested) {
+        throw new TypeError('The stream is already closing');
+      }
+      if (this._stream._state !== 'readable') {
+        throw new TypeError('The stream is not readable');
+      }
+      readableByteStreamControllerEnqueue(this, chunk);
+    }
+
+    error(e = undefined) {
+      readableByteStreamControllerError(this, e);
+    }
+
+    [CancelSteps](reason) {
+      readableByteStreamControllerClearPendingPullIntos(this);
+      resetQueue(this);
+      const result = this._cancelAlgorithm(reason);
+      readableByteStreamControllerClearAlgorithms(this);
+      return result;
+    }
+
+    [PullSteps](readRequest) {
+      const stream = this._stream;
+      if (this._queueTotalSize > 0) {
+        readableByteStreamControllerFillReadRequestFromQueue(this, readRequest);
+        return;
+      }
+      const autoAllocateChunkSize = this._autoAllocateChunkSize;
+      if (autoAllocateChunkSize !== undefined) {
+        let buffer;
+        try {
+          buffer = new ArrayBuffer(autoAllocateChunkSize);
+        } catch (e) {
+          readRequest.errorSteps(e);
+          return;
+        }
+        this._pendingPullIntos.push({
+          buffer,
+          bufferByteLength: autoAllocateChunkSize,
+          byteOffset: 0,
+          byteLength: autoAllocateChunkSize,
+          bytesFilled: 0,
+          minimumFill: 1,
+          elementSize: 1,
+          viewConstructor: Uint8Array,
+          readerType: 'default',
+        });
+      }
+      readableStreamAddReadRequest(stream, readRequest);
+      readableByteStreamControllerCallPullIfNeeded(this);
+    }
+
+    [ReleaseSteps]() {
+      if (this._pendingPullIntos.length > 0) {
+        const firstPendingPullInto = this._pendingPullIntos[0];
+        firstPendingPullInto.readerType = 'none';
+        this._pendingPullIntos = [firstPendingPullInto];
+      }
+    }
+  }
+
+  Object.defineProperty(ReadableByteStreamController.prototype, Symbol.toStringTag, {
+    value: 'ReadableByteStreamController',
+    configurable: true,
+  });
+
+  class ReadableStreamBYOBRequest {
+    constructor(token) {
+      illegalConstructor(token);
+    }
+
+    get view() {
+      return this._view;
+    }
+
+    respond(bytesWritten) {
+      if (this._controller === undefined) {
+        throw new TypeError('The request was already responded to');
+      }
+      if (isDetachedBuffer(this._view.buffer)) {
+        throw new TypeError('The buffer of view was detached');
+      }
+      const n = Number(bytesWritten);
+      if (!Number.isInteger(n) || n < 0) {
+        throw new TypeError('bytesWritten must be a non-negative integer');
+      }
+      readableByteStreamControllerRespond(this._controller, n);
+    }
+
+    respondWithNewView(view) {
+      if (!ArrayBuffer.isView(view)) {
+        throw new TypeError('view must be an ArrayBufferView');
+      }
+      if (this._controller === undefined) {
+        throw new TypeError('The request was already responded to');
+      }
+      if (isDetachedBuffer(view.buffer)) {
+        throw new TypeError('The buffer of view was detached');
+      }
+      readableByteStreamControllerRespondWithNewView(this._controller, view);
+    }
+  }
+
+  Object.defineProperty(ReadableStreamBYOBRequest.prototype, Symbol.toStringTag, {
+    value: 'ReadableStreamBYOBRequest',
+    configurable: true,
+  });
+
+  function readableByteStreamControllerCallPullIfNeeded(controller) {
+    if (!readableByteStreamControllerShouldCallPull(controller)) {
+      return;
+    }
+    if (controller._pulling) {
+      controller._pullAgain = true;
+      return;
+    }
+    controller._pulling = true;
+    uponPromise(
+      controller._pullAlgorithm(),
+      () => {
+        controller._pulling = false;
+        if (controller._pullAgain) {
+          controller._pullAgain = false;
+          readableByteStreamControllerCallPullIfNeeded(controller);
+        }
+      },
+      (e) => readableByteStreamControllerError(controller, e),
+    );
+  }
+
+  function readableByteStreamControllerClearAlgorithms(controller) {
+    controller._pullAlgorithm = undefined;
+    controller._cancelAlgorithm = undefined;
+  }
+
+  function readableByteStreamControllerClearPendingPullIntos(controller) {
+    readableByteStreamControllerInvalidateBYOBRequest(controller);
+    controller._pendingPullIntos = [];
+  }
+
+  function readableByteStreamControllerClose(controller) {
+    const stream = controller._stream;
+    if (controller._closeRequested || stream._state !== 'readable') {
+      return;
+    }
+    if (controller._queueTotalSize > 0) {
+      controller._closeRequested = true;
+      return;
+    }
+    if (controller._pendingPullIntos.length > 0) {
+      const firstPendingPullInto = controller._pendingPullIntos[0];
+      if (firstPendingPullInto.bytesFilled % firstPendingPullInto.elementSize !== 0) {
+        const e = new TypeError('Insufficient bytes to fill elements in the given buffer');
+        readableByteStreamControllerError(controller, e);
+        throw e;
+      }
+    }
+    readableByteStreamControllerClearAlgorithms(controller);
+    readableStreamClose(stream);
+  }
+
+  function readableByteStreamControllerCommitPullIntoDescriptor(stream, pullIntoDescriptor) {
+    const done = stream._state === 'closed';
+    const filledView = readableByteStreamControllerConvertPullIntoDescriptor(pullIntoDescriptor);
+    if (pullIntoDescriptor.readerType === 'default') {
+      readableStreamFulfillReadRequest(stream, filledView, done);
+    } else {
+      readableStreamFulfillReadIntoRequest(stream, filledView, done);
+    }
+  }
+
+  function readableByteStreamControllerConvertPullIntoDescriptor(pullIntoDescriptor) {
+    const { bytesFilled, elementSize } = pullIntoDescriptor;
+    const buffer = transferArrayBuffer(pullIntoDescriptor.buffer);
+    const Constructor = pullIntoDescriptor.viewConstructor;
+    return new Constructor(buffer, pullIntoDescriptor.byteOffset, bytesFilled / elementSize);
+  }
+
+  function readableByteStreamControllerEnqueue(controller, chunk) {
+    const stream = controller._stream;
+    if (controller._closeRequested || stream._state !== 'readable') {
+      return;
+    }
+    const { buffer, byteOffset, byteLength } = chunk;
+    if (isDetachedBuffer(buffer)) {
+      throw new TypeError('The buffer of chunk was detached');
+    }
+    const transferredBuffer = transferArrayBuffer(buffer);
+    if (controller._pendingPullIntos.length > 0) {
+      const firstPendingPullInto = controller._pendingPullIntos[0];
+      if (isDetachedBuffer(firstPendingPullInto.buffer)) {
+        throw new TypeError('The BYOB request buffer was detached');
+      }
+      readableByteStreamControllerInvalidateBYOBRequest(controller);
+      firstPendingPullInto.buffer = transferArrayBuffer(firstPendingPullInto.buffer);
+      if (firstPendingPullInto.readerType === 'none') {
+        readableByteStreamControllerEnqueueDetachedPullIntoToQueue(
+          controller, firstPendingPullInto,
+        );
+      }
+    }
+    if (readableStreamHasDefaultReader(stream)) {
+      readableByteStreamControllerProcessReadRequestsUsingQueue(controller);
+      if (readableStreamGetNumReadRequests(stream) === 0) {
+        readableByteStreamControllerEnqueueChunkToQueue(
+          controller, transferredBuffer, byteOffset, byteLength,
+        );
+      } else {
+        if (controller._pendingPullIntos.length > 0) {
+          readableByteStreamControllerShiftPendingPullInto(controller);
+        }
+        const transferredView = new Uint8Array(transferredBuffer, byteOffset, byteLength);
+        readableStreamFulfillReadRequest(stream, transferredView, false);
+      }
+    } else if (readableStreamHasBYOBReader(stream)) {
+      readableByteStreamControllerEnqueueChunkToQueue(
+        controller, transferredBuffer, byteOffset, byteLength,
+      );
+      const filledPullIntos = readableByteStreamControllerProcessPullIntoDescriptorsUsingQueue(
+        controller,
+      );
+      for (const filledPullInto of filledPullIntos) {
+        readableByteStreamControllerCommitPullIntoDescriptor(stream, filledPullInto);
+      }
+    } else {
+      readableByteStreamControllerEnqueueChunkToQueue(
+        controller, transferredBuffer, byteOffset, byteLength,
+      );
+    }
+    readableByteStreamControllerCallPullIfNeeded(controller);
+  }
+
+  function readableByteStreamControllerEnqueueChunkToQueue(
+    controller, buffer, byteOffset, byteLength,
+  ) {
+    controller._queue.push({ buffer, byteOffset, byteLength });
+    controller._queueTotalSize += byteLength;
+  }
+
+  function readableByteStreamControllerEnqueueClonedChunkToQueue(
+    controller, buffer, byteOffset, byteLength,
+  ) {
+    let clone;
+    try {
+      clone = buffer.slice(byteOffset, byteOffset + byteLength);
+    } catch (e) {
+      readableByteStreamControllerError(controller, e);
+      throw e;
+    }
+    readableByteStreamControllerEnqueueChunkToQueue(controller, clone, 0, byteLength);
+  }
+
+  function readableByteStreamControllerEnqueueDetachedPullIntoToQueue(
+    controller, pullIntoDescriptor,
+  ) {
+    if (pullIntoDescriptor.bytesFilled > 0) {
+      readableByteStreamControllerEnqueueClonedChunkToQueue(
+        controller,
+        pullIntoDescriptor.buffer,
+        pullIntoDescriptor.byteOffset,
+        pullIntoDescriptor.bytesFilled,
+      );
+    }
+    readableByteStreamControllerShiftPendingPullInto(controller);
+  }
+
+  function readableByteStreamControllerFillHeadPullIntoDescriptor(
+    controller, size, pullIntoDescriptor,
+  ) {
+    pullIntoDescriptor.bytesFilled += size;
+  }
+
+  function readableByteStreamControllerFillPullIntoDescriptorFromQueue(
+    controller, pullIntoDescriptor,
+  ) {
+    const maxBytesToCopy = Math.min(
+      controller._queueTotalSize,
+      pullIntoDescriptor.byteLength - pullIntoDescriptor.bytesFilled,
+    );
+    const maxBytesFilled = pullIntoDescriptor.bytesFilled + maxBytesToCopy;
+    let totalBytesToCopyRemaining = maxBytesToCopy;
+    let ready = false;
+    const remainderBytes = maxBytesFilled % pullIntoDescriptor.elementSize;
+    const maxAlignedBytes = maxBytesFilled - remainderBytes;
+    if (maxAlignedBytes >= pullIntoDescriptor.minimumFill) {
+      totalBytesToCopyRemaining = maxAlignedBytes - pullIntoDescriptor.bytesFilled;
+      ready = true;
+    }
+    const queue = controller._queue;
+    while (totalBytesToCopyRemaining > 0) {
+      const headOfQueue = queue[0];
+      const bytesToCopy = Math.min(totalBytesToCopyRemaining, headOfQueue.byteLength);
+      const destStart = pullIntoDescriptor.byteOffset + pullIntoDescriptor.bytesFilled;
+      copyDataBlockBytes(
+        pullIntoDescriptor.buffer, destStart, headOfQueue.buffer, headOfQueue.byteOffset,
+        bytesToCopy,
+      );
+      if (headOfQueue.byteLength === bytesToCopy) {
+        queue.shift();
+      } else {
+        headOfQueue.byteOffset += bytesToCopy;
+        headOfQueue.byteLength -= bytesToCopy;
+      }
+      controller._queueTotalSize -= bytesToCopy;
+      readableByteStreamControllerFillHeadPullIntoDescriptor(
+        controller, bytesToCopy, pullIntoDescriptor,
+      );
+      totalBytesToCopyRemaining -= bytesToCopy;
+    }
+    return ready;
+  }
+
+  function readableByteStreamControllerFillReadRequestFromQueue(controller, readRequest) {
+    const entry = controller._queue.shift();
+    controller._queueTotalSize -= entry.byteLength;
+    readableByteStreamControllerHandleQueueDrain(controller);
+    const view = new Uint8Array(entry.buffer, entry.byteOffset, entry.byteLength);
+    readRequest.chunkSteps(view);
+  }
+
+  function readableByteStreamControllerGetBYOBRequest(controller) {
+    if (controller._byobRequest === null && controller._pendingPullIntos.length > 0) {
+      const firstDescriptor = controller._pendingPullIntos[0];
+      const view = new Uint8Array(
+        firstDescriptor.buffer,
+        firstDescriptor.byteOffset + firstDescriptor.bytesFilled,
+        firstDescriptor.byteLength - firstDescriptor.bytesFilled,
+      );
+      const byobRequest = Object.create(ReadableStreamBYOBRequest.prototype);
+      byobRequest._controller = controller;
+      byobRequest._view = view;
+      controller._byobRequest = byobRequest;
+    }
+    return controller._byobRequest;
+  }
+
+  function readableByteStreamControllerGetDesiredSize(controller) {
+    const state = controller._stream._state;
+    if (state === 'errored') {
+      return null;
+    }
+    if (state === 'closed') {
+      return 0;
+    }
+    return controller._strategyHWM - controller._queueTotalSize;
+  }
+
+  function readableByteStreamControllerHandleQueueDrain(controller) {
+    if (controller._queueTotalSize === 0 && controller._closeRequested) {
+      readableByteStreamControllerClearAlgorithms(controller);
+      readableStreamClose(controller._stream);
+    } else {
+      readableByteStreamControllerCallPullIfNeeded(controller);
+    }
+  }
+
+  function readableByteStreamControllerInvalidateBYOBRequest(controller) {
+    if (controller._byobRequest === null) {
+      return;
+    }
+    controller._byobRequest._controller = undefined;
+    controller._byobRequest._view = null;
+    controller._byobRequest = null;
+  }
+
+  function readableByteStreamControllerProcessPullIntoDescriptorsUsingQueue(controller) {
+    const filledPullIntos = [];
+    while (controller._pendingPullIntos.length > 0) {
+      if (controller._queueTotalSize === 0) {
+        break;
+      }
+      const pullIntoDescriptor = controller._pendingPullIntos[0];
+      if (readableByteStreamControllerFillPullIntoDescriptorFromQueue(
+        controller, pullIntoDescriptor,
+      )) {
+        readableByteStreamControllerShiftPendingPullInto(controller);
+        filledPullIntos.push(pullIntoDescriptor);
+      }
+    }
+    return filledPullIntos;
+  }
+
+  function readableByteStreamControllerProcessReadRequestsUsingQueue(controller) {
+    const reader = controller._stream._reader;
+    while (reader._readRequests.length > 0) {
+      if (controller._queueTotalSize === 0) {
+        return;
+      }
+      const readRequest = reader._readRequests.shift();
+      readableByteStreamControllerFillReadRequestFromQueue(controller, readRequest);
+    }
+  }
+
+  function readableByteStreamControllerPullInto(controller, view, min, readIntoRequest) {
+    const stream = controller._stream;
+    let elementSize = 1;
+    let ViewConstructor = DataView;
+    if (!(view instanceof DataView)) {
+      ViewConstructor = view.constructor;
+      elementSize = ViewConstructor.BYTES_PER_ELEMENT;
+    }
+    const minimumFill = min * elementSize;
+    const { byteOffset, byteLength } = view;
+    let buffer;
+    try {
+      buffer = transferArrayBuffer(view.buffer);
+    } catch (e) {
+      readIntoRequest.errorSteps(e);
+      return;
+    }
+    const pullIntoDescriptor = {
+      buffer,
+      bufferByteLength: buffer.byteLength,
+      byteOffset,
+      byteLength,
+      bytesFilled: 0,
+      minimumFill,
+      elementSize,
+      viewConstructor: ViewConstructor,
+      readerType: 'byob',
+    };
+    if (controller._pendingPullIntos.length > 0) {
+      controller._pendingPullIntos.push(pullIntoDescriptor);
+      readableStreamAddReadIntoRequest(stream, readIntoRequest);
+      return;
+    }
+    if (stream._state === 'closed') {
+      const emptyView = new ViewConstructor(
+        pullIntoDescriptor.buffer, pullIntoDescriptor.byteOffset, 0,
+      );
+      readIntoRequest.closeSteps(emptyView);
+      return;
+    }
+    if (controller._queueTotalSize > 0) {
+      if (readableByteStreamControllerFillPullIntoDescriptorFromQueue(
+        controller, pullIntoDescriptor,
+      )) {
+        const filledView = readableByteStreamControllerConvertPullIntoDescriptor(
+          pullIntoDescriptor,
+        );
+        readableByteStreamControllerHandleQueueDrain(controller);
+        readIntoRequest.chunkSteps(filledView);
+        return;
+      }
+      if (controller._closeRequested) {
+        const e = new TypeError('Insufficient bytes to fill elements in the given buffer');
+        readableByteStreamControllerError(controller, e);
+        readIntoRequest.errorSteps(e);
+        return;
+      }
+    }
+    controller._pendingPullIntos.push(pullIntoDescriptor);
+    readableStreamAddReadIntoRequest(stream, readIntoRequest);
+    readableByteStreamControllerCallPullIfNeeded(controller);
+  }
+
+  function readableByteStreamControllerRespond(controller, bytesWritten) {
+    const firstDescriptor = controller._pendingPullIntos[0];
+    if (controller._stream._state === 'closed') {
+      if (bytesWritten !== 0) {
+        throw new TypeError('bytesWritten must be 0 once the stream is closed');
+      }
+    } else {
+      if (bytesWritten === 0) {
+        throw new TypeError('bytesWritten must be greater than 0 while the stream is readable');
+      }
+      if (firstDescriptor.bytesFilled + bytesWritten > firstDescriptor.byteLength) {
+        throw new RangeError('bytesWritten out of range');
+      }
+    }
+    firstDescriptor.buffer = transferArrayBuffer(firstDescriptor.buffer);
+    readableByteStreamControllerRespondInternal(controller, bytesWritten);
+  }
+
+  function readableByteStreamControllerRespondInClosedState(controller, firstDescriptor) {
+    if (firstDescriptor.readerType === 'none') {
+      readableByteStreamControllerShiftPendingPullInto(controller);
+    }
+    const stream = controller._stream;
+    if (readableStreamHasBYOBReader(stream)) {
+      while (readableStreamGetNumReadIntoRequests(stream) > 0) {
+        const pullIntoDescriptor = readableByteStreamControllerShiftPendingPullInto(controller);
+        readableByteStreamControllerCommitPullIntoDescriptor(stream, pullIntoDescriptor);
+      }
+    }
+  }
+
+  function readableByteStreamControllerRespondInReadableState(
+    controller, bytesWritten, pullIntoDescriptor,
+  ) {
+    const stream = controller._stream;
+    readableByteStreamControllerFillHeadPullIntoDescriptor(
+      controller, bytesWritten, pullIntoDescriptor,
+    );
+    if (pullIntoDescriptor.readerType === 'none') {
+      readableByteStreamControllerEnqueueDetachedPullIntoToQueue(controller, pullIntoDescriptor);
+      const filledPullIntos = readableByteStreamControllerProcessPullIntoDescriptorsUsingQueue(
+        controller,
+      );
+      for (const filledPullInto of filledPullIntos) {
+        readableByteStreamControllerCommitPullIntoDescriptor(stream, filledPullInto);
+      }
+      return;
+    }
+    if (pullIntoDescriptor.bytesFilled < pullIntoDescriptor.minimumFill) {
+      return;
+    }
+    readableByteStreamControllerShiftPendingPullInto(controller);
+    const remainderSize = pullIntoDescriptor.bytesFilled % pullIntoDescriptor.elementSize;
+    if (remainderSize > 0) {
+      const end = pullIntoDescriptor.byteOffset + pullIntoDescriptor.bytesFilled;
+      readableByteStreamControllerEnqueueClonedChunkToQueue(
+        controller, pullIntoDescriptor.buffer, end - remainderSize, remainderSize,
+      );
+    }
+    pullIntoDescriptor.bytesFilled -= remainderSize;
+    const filledPullIntos = readableByteStreamControllerProcessPullIntoDescriptorsUsingQueue(
+      controller,
+    );
+    readableByteStreamControllerCommitPullIntoDescriptor(stream, pullIntoDescriptor);
+    for (const filledPullInto of filledPullIntos) {
+      readableByteStreamControllerCommitPullIntoDescriptor(stream, filledPullInto);
+    }
+  }
+
+  function readableByteStreamControllerRespondInternal(controller, bytesWritten) {
+    const firstDescriptor = controller._pendingPullIntos[0];
+    readableByteStreamControllerInvalidateBYOBRequest(controller);
+    if (controller._stream._state === 'closed') {
+      readableByteStreamControllerRespondInClosedState(controller, firstDescriptor);
+    } else {
+      readableByteStreamControllerRespondInReadableState(
+        controller, bytesWritten, firstDescriptor,
+      );
+    }
+    readableByteStreamControllerCallPullIfNeeded(controller);
+  }
+
+  function readableByteStreamControllerRespondWithNewView(controller, view) {
+    const firstDescriptor = controller._pendingPullIntos[0];
+    if (controller._stream._state === 'closed') {
+      if (view.byteLength !== 0) {
+        throw new TypeError('The view must be empty once the stream is closed');
+      }
+    } else if (view.byteLength === 0) {
+      throw new TypeError('The view must not be empty while the stream is readable');
+    }
+    if (firstDescriptor.byteOffset + firstDescriptor.bytesFilled !== view.byteOffset) {
+      throw new RangeError('The region specified by view does not match byobRequest');
+    }
+    if (firstDescriptor.bufferByteLength !== view.buffer.byteLength) {
+      throw new RangeError('The buffer of view has a different capacity than byobRequest');
+    }
+    if (firstDescriptor.bytesFilled + view.byteLength > firstDescriptor.byteLength) {
+      throw new RangeError('The region specified by view is larger than byobRequest');
+    }
+    const viewByteLength = view.byteLength;
+    firstDescriptor.buffer = transferArrayBuffer(view.buffer);
+    readableByteStreamControllerRespondInternal(controller, viewByteLength);
+  }
+
+  function readableByteStreamControllerShiftPendingPullInto(controller) {
+    return controller._pendingPullIntos.shift();
+  }
+
+  function readableByteStreamControllerShouldCallPull(controller) {
+    const stream = controller._stream;
+    if (stream._state !== 'readable' || controller._closeRequested || !controller._started) {
+      return false;
+    }
+    if (readableStreamHasDefaultReader(stream) && readableStreamGetNumReadRequests(stream) > 0) {
+      return true;
+    }
+    if (readableStreamHasBYOBReader(stream) && readableStreamGetNumReadIntoRequests(stream) > 0) {
+      return true;
+    }
+    return readableByteStreamControllerGetDesiredSize(controller) > 0;
+  }
+
+  function readableByteStreamControllerError(controller, e) {
+    const stream = controller._stream;
+    if (stream._state !== 'readable') {
+      return;
+    }
+    readableByteStreamControllerClearPendingPullIntos(controller);
+    resetQueue(controller);
+    readableByteStreamControllerClearAlgorithms(controller);
+    readableStreamError(stream, e);
+  }
+
+  function setUpReadableByteStreamController(
+    stream, controller, startAlgorithm, pullAlgorithm, cancelAlgorithm, highWaterMark,
+    autoAllocateChunkSize,
+  ) {
+    controller._stream = stream;
+    controller._pullAgain = false;
+    controller._pulling = false;
+    controller._byobRequest = null;
+    resetQueue(controller);
+    controller._closeRequested = false;
+    controller._started = false;
+    controller._strategyHWM = highWaterMark;
+    controller._pullAlgorithm = pullAlgorithm;
+    controller._cancelAlgorithm = cancelAlgorithm;
+    controller._autoAllocateChunkSize = autoAllocateChunkSize;
+    controller._pendingPullIntos = [];
+    stream._controller = controller;
+    const startResult = startAlgorithm();
+    uponPromise(
+      promiseResolvedWith(startResult),
+      () => {
+        controller._started = true;
+        readableByteStreamControllerCallPullIfNeeded(controller);
+      },
+      (r) => readableByteStreamControllerError(controller, r),
+    );
+  }
+
+  function setUpReadableByteStreamControllerFromUnderlyingSource(stream, source, highWaterMark) {
+    const controller = Object.create(ReadableByteStreamController.prototype);
+    const start = getMethod(source, 'start', 'underlyingSource');
+    const pull = getMethod(source, 'pull', 'underlyingSource');
+    const cancel = getMethod(source, 'cancel', 'underlyingSource');
+    let { autoAllocateChunkSize } = source;
+    if (autoAllocateChunkSize !== undefined) {
+      autoAllocateChunkSize = Number(autoAllocateChunkSize);
+      if (!Number.isInteger(autoAllocateChunkSize) || autoAllocateChunkSize <= 0) {
+        throw new TypeError('autoAllocateChunkSize must be a positive integer');
+      }
+    }
+    const startAlgorithm = () => (start ? start.call(source, controller) : undefined);
+    const pullAlgorithm = () => (
+      pull ? promiseCall(pull, source, controller) : promiseResolvedWith(undefined)
+    );
+    const cancelAlgorithm = (reason) => (
+      cancel ? promiseCall(cancel, source, reason) : promiseResolvedWith(undefined)
+    );
+    setUpReadableByteStreamController(
+      stream, controller, startAlgorithm, pullAlgorithm, cancelAlgorithm, highWaterMark,
+      autoAllocateChunkSize,
+    );
+  }
+
+  // WritableStream
+
+  class WritableStream {
+    constructor(underlyingSink = undefined, strategy = undefined) {
+      const sink = dictionary(underlyingSink, 'underlyingSink');
+      strategy = dictionary(strategy, 'strategy');
+      if (sink.type !== undefined) {
+        throw new RangeError('Invalid type');
+      }
+      initializeWritableStream(this);
+      const sizeAlgorithm = extractSizeAlgorithm(strategy);
+      const highWaterMark = extractHighWaterMark(strategy, 1);
+      setUpWritableStreamDefaultControllerFromUnderlyingSink(
+        this, sink, highWaterMark, sizeAlgorithm,
+      );
+    }
+
+    get locked() {
+      return isWritableStreamLocked(this);
+    }
+
+    abort(reason = undefined) {
+      if (isWritableStreamLocked(this)) {
+        return promiseRejectedWith(new TypeError('Cannot abort a locked stream'));
+      }
+      return writableStreamAbort(this, reason);
+    }
+
+    close() {
+      if (isWritableStreamLocked(this)) {
+        return promiseRejectedWith(new TypeError('Cannot close a locked stream'));
+      }
+      if (writableStreamCloseQueuedOrInFlight(this)) {
+        return promiseRejectedWith(new TypeError('The stream is already closing'));
+      }
+      return writableStreamClose(this);
+    }
+
+    getWriter() {
+      return acquireWritableStreamDefaultWriter(this);
+    }
+  }
+
+  Object.defineProperty(WritableStream.prototype, Symbol.toStringTag, {
+    value: 'WritableStream',
+    configurable: true,
+  });
+
+  function acquireWritableStreamDefaultWriter(stream) {
+    return new WritableStreamDefaultWriter(stream);
+  }
+
+  function createWritableStream(
+    startAlgorithm, writeAlgorithm, closeAlgorithm, abortAlgorithm, highWaterMark, sizeAlgorithm,
+  ) {
+    const stream = Object.create(WritableStream.prototype);
+    initializeWritableStream(stream);
+    const controller = Object.create(WritableStreamDefaultController.prototype);
+    setUpWritableStreamDefaultController(
+      stream, controller, startAlgorithm, writeAlgorithm, closeAlgorithm, abortAlgorithm,
+      highWaterMark, sizeAlgorithm,
+    );
+    return stream;
+  }
+
+  function initializeWritableStream(stream) {
+    stream._state = 'writable';
+    stream._storedError = undefined;
+    stream._writer = undefined;
+    stream._controller = undefined;
+    stream._inFlightWriteRequest = undefined;
+    stream._closeRequest = undefined;
+    stream._inFlightCloseRequest = undefined;
+    stream._pendingAbortRequest = undefined;
+    stream._writeRequests = [];
+    stream._backpressure = false;
+  }
+
+  function isWritableStreamLocked(stream) {
+    if (!(stream instanceof WritableStream)) {
+      throw new TypeError('Not a WritableStream');
+    }
+    return stream._writer !== undefined;
+  }
+
+  function writableStreamAbort(stream, reason) {
+    if (stream._state === 'closed' || stream._state === 'errored') {
+      return promiseResolvedWith(undefined);
+    }
+    stream._controller._abortController.abort(reason);
+    const state = stream._state;
+    if (state === 'closed' || state === 'errored') {
+      return promiseResolvedWith(undefined);
+    }
+    if (stream._pendingAbortRequest !== undefined) {
+      return stream._pendingAbortRequest.promise;
+    }
+    let wasAlreadyErroring = false;
+    if (state === 'erroring') {
+      wasAlreadyErroring = true;
+      reason = undefined;
+    }
+    const request = newPromise();
+    request.reason = reason;
+    request.wasAlreadyErroring = wasAlreadyErroring;
+    stream._pendingAbortRequest = request;
+    if (!wasAlreadyErroring) {
+      writableStreamStartErroring(stream, reason);
+    }
+    return request.promise;
+  }
+
+  function writableStreamClose(stream) {
+    const state = stream._state;
+    if (state === 'closed' || state === 'errored') {
+      return promiseRejectedWith(new TypeError('The stream is closed or errored'));
+    }
+    const closeRequest = newPromise();
+    stream._closeRequest = closeRequest;
+    const writer = stream._writer;
+    if (writer !== undefined && stream._backpressure && state === 'writable') {
+      writer._readyPromise.resolve(undefined);
+    }
+    writableStreamDefaultControllerClose(stream._controller);
+    return closeRequest.promise;
+  }
+
+  function writableStreamAddWriteRequest(stream) {
+    const writeRequest = newPromise();
+    stream._writeRequests.push(writeRequest);
+    return writeRequest.promise;
+  }
+
+  function writableStreamCloseQueuedOrInFlight(stream) {
+    return stream._closeRequest !== undefined || stream._inFlightCloseRequest !== undefined;
+  }
+
+  function writableStreamDealWithRejection(stream, error) {
+    if (stream._state === 'writable') {
+      writableStreamStartErroring(stream, error);
+      return;
+    }
+    writableStreamFinishErroring(stream);
+  }
+
+  function writableStreamFinishErroring(stream) {
+    stream._state = 'errored';
+    stream._controller[ErrorSteps]();
+    const storedError = stream._storedError;
+    for (const writeRequest of stream._writeRequests) {
+      writeRequest.reject(storedError);
+    }
+    stream._writeRequests = [];
+    if (stream._pendingAbortRequest === undefined) {
+      writableStreamRejectCloseAndClosedPromiseIfNeeded(stream);
+      return;
+    }
+    const abortRequest = stream._pendingAbortRequest;
+    stream._pendingAbortRequest = undefined;
+    if (abortRequest.wasAlreadyErroring) {
+      abortRequest.reject(storedError);
+      writableStreamRejectCloseAndClosedPromiseIfNeeded(stream);
+      return;
+    }
+    uponPromise(
+      stream._controller[AbortSteps](abortRequest.reason),
+      () => {
+        abortRequest.resolve(undefined);
+        writableStreamRejectCloseAndClosedPromiseIfNeeded(stream);
+      },
+      (reason) => {
+        abortRequest.reject(reason);
+        writableStreamRejectCloseAndClosedPromiseIfNeeded(stream);
+      },
+    );
+  }
+
+  function writableStreamFinishInFlightWrite(stream) {
+    stream._inFlightWriteRequest.resolve(undefined);
+    stream._inFlightWriteRequest = undefined;
+  }
+
+  function writableStreamFinishInFlightWriteWithError(stream, error) {
+    stream._inFlightWriteRequest.reject(error);
+    stream._inFlightWriteRequest = undefined;
+    writableStreamDealWithRejection(stream, error);
+  }
+
+  function writableStreamFinishInFlightClose(stream) {
+    stream._inFlightCloseRequest.resolve(undefined);
+    stream._inFlightCloseRequest = undefined;
+    if (stream._state === 'erroring') {
+      stream._storedError = undefined;
+      if (stream._pendingAbortRequest !== undefined) {
+        stream._pendingAbortRequest.resolve(undefined);
+        stream._pendingAbortRequest = undefined;
+      }
+    }
+    stream._state = 'closed';
+    const writer = stream._writer;
+    if (writer !== undefined) {
+      writer._closedPromise.resolve(undefined);
+    }
+  }
+
+  function writableStreamFinishInFlightCloseWithError(stream, error) {
+    stream._inFlightCloseRequest.reject(error);
+    stream._inFlightCloseRequest = undefined;
+    if (stream._pendingAbortRequest !== undefined) {
+      stream._pendingAbortRequest.reject(error);
+      stream._pendingAbortRequest = undefined;
+    }
+    writableStreamDealWithRejection(stream, error);
+  }
+
+  function writableStreamHasOperationMarkedInFlight(stream) {
+    return stream._inFlightWriteRequest !== undefined
+      || stream._inFlightCloseRequest !== undefined;
+  }
+
+  function writableStreamMarkCloseRequestInFlight(stream) {
+    stream._inFlightCloseRequest = stream._closeRequest;
+    stream._closeRequest = undefined;
+  }
+
+  function writableStreamMarkFirstWriteRequestInFlight(stream) {
+    stream._inFlightWriteRequest = stream._writeRequests.shift();
+  }
+
+  function writableStreamRejectCloseAndClosedPromiseIfNeeded(stream) {
+    if (stream._closeRequest !== undefined) {
+      stream._closeRequest.reject(stream._storedError);
+      stream._closeRequest = undefined;
+    }
+    const writer = stream._writer;
+    if (writer !== undefined) {
+      writer._closedPromise.reject(stream._storedError);
+      setPromiseIsHandledToTrue(writer._closedPromise.promise);
+    }
+  }
+
+  function writableStreamStartErroring(stream, reason) {
+    const controller = stream._controller;
+    stream._state = 'erroring';
+    stream._storedError = reason;
+    const writer = stream._writer;
+    if (writer !== undefined) {
+      writableStreamDefaultWriterEnsureReadyPromiseRejected(writer, reason);
+    }
+    if (!writableStreamHasOperationMarkedInFlight(stream) && controller._started) {
+      writableStreamFinishErroring(stream);
+    }
+  }
+
+  function writableStreamUpdateBackpressure(stream, backpressure) {
+    const writer = stream._writer;
+    if (writer !== undefined && backpressure !== stream._backpressure) {
+      if (backpressure) {
+        writer._readyPromise = newPromise();
+      } else {
+        writer._readyPromise.resolve(undefined);
+      }
+    }
+    stream._backpressure = backpressure;
+  }
+
+  class WritableStreamDefaultWriter {
+    constructor(stream) {
+      if (isWritableStreamLocked(stream)) {
+        throw new TypeError('The stream is locked to a writer');
+      }
+      this._stream = stream;
+      stream._writer = this;
+      const state = stream._state;
+      if (state === 'writable') {
+        if (!writableStreamCloseQueuedOrInFlight(stream) && stream._backpressure) {
+          this._readyPromise = newPromise();
+        } else {
+          this._readyPromise = resolvedDeferred(undefined);
+        }
+        this._closedPromise = newPromise();
+      } else if (state === 'erroring') {
+        this._readyPromise = rejectedDeferred(stream._storedError);
+        this._closedPromise = newPromise();
+      } else if (state === 'closed') {
+        this._readyPromise = resolvedDeferred(undefined);
+        this._closedPromise = resolvedDeferred(undefined);
+      } else {
+        this._readyPromise = rejectedDeferred(stream._storedError);
+        this._closedPromise = rejectedDeferred(stream._storedError);
+      }
+    }
+
+    get closed() {
+      return this._closedPromise.promise;
+    }
+
+    get desiredSize() {
+      if (this._stream === undefined) {
+        throw new TypeError('The writer was released');
+      }
+      return writableStreamDefaultWriterGetDesiredSize(this);
+    }
+
+    get ready() {
+      return this._readyPromise.promise;
+    }
+
+    abort(reason = undefined) {
+      if (this._stream === undefined) {
+        return promiseRejectedWith(new TypeError('The writer was released'));
+      }
+      return writableStreamAbort(this._stream, reason);
+    }
+
+    close() {
+      const stream = this._stream;
+      if (stream === undefined) {
+        return promiseRejectedWith(new TypeError('The writer was released'));
+      }
+      if (writableStreamCloseQueuedOrInFlight(stream)) {
+        return promiseRejectedWith(new TypeError('The stream is already closing'));
+      }
+      return writableStreamClose(stream);
+    }
+
+    releaseLock() {
+      if (this._stream !== undefined) {
+        writableStreamDefaultWriterRelease(this);
+      }
+    }
+
+    write(chunk = undefined) {
+      if (this._stream === undefined) {
+        return promiseRejectedWith(new TypeError('The writer was released'));
+      }
+      return writableStreamDefaultWriterWrite(this, chunk);
+    }
+  }
+
+  Object.defineProperty(WritableStreamDefaultWriter.prototype, Symbol.toStringTag, {
+    value: 'WritableStreamDefaultWriter',
+    configurable: true,
+  });
+
+  function writableStreamDefaultWriterCloseWithErrorPropagation(writer) {
+    const stream = writer._stream;
+    const state = stream._state;
+    if (writableStreamCloseQueuedOrInFlight(stream) || state === 'closed') {
+      return promiseResolvedWith(undefined);
+    }
+    if (state === 'errored') {
+      return promiseRejectedWith(stream._storedError);
+    }
+    return writableStreamClose(stream);
+  }
+
+  function writableStreamDefaultWriterEnsureClosedPromiseRejected(writer, error) {
+    if (writer._closedPromise.state === 'pending') {
+      writer._closedPromise.reject(error);
+    } else {
+      writer._closedPromise = rejectedDeferred(error);
+    }
+    setPromiseIsHandledToTrue(writer._closedPromise.promise);
+  }
+
+  function writableStreamDefaultWriterEnsureReadyPromiseRejected(writer, error) {
+    if (writer._readyPromise.state === 'pending') {
+      writer._readyPromise.reject(error);
+    } else {
+      writer._readyPromise = rejectedDeferred(error);
+    }
+    setPromiseIsHandledToTrue(writer._readyPromise.promise);
+  }
+
+  function writableStreamDefaultWriterGetDesiredSize(writer) {
+    const stream = writer._stream;
+    const state = stream._state;
+    if (state === 'errored' || state === 'erroring') {
+      return null;
+    }
+    if (state === 'closed') {
+      return 0;
+    }
+    return writableStreamDefaultControllerGetDesiredSize(stream._controller);
+  }
+
+  function writableStreamDefaultWriterRelease(writer) {
+    const stream = writer._stream;
+    const releasedError = new TypeError('The writer was released');
+    writableStreamDefaultWriterEnsureReadyPromiseRejected(writer, releasedError);
+    writableStreamDefaultWriterEnsureClosedPromiseRejected(writer, releasedError);
+    stream._writer = undefined;
+    writer._stream = undefined;
+  }
+
+  function writableStreamDefaultWriterWrite(writer, chunk) {
+    const stream = writer._stream;
+    const controller = stream._controller;
+    const chunkSize = writableStreamDefaultControllerGetChunkSize(controller, chunk);
+    if (stream !== writer._stream) {
+      return promiseRejectedWith(new TypeError('The writer was released'));
+    }
+    const state = stream._state;
+    if (state === 'errored') {
+      return promiseRejectedWith(stream._storedError);
+    }
+    if (writableStreamCloseQueuedOrInFlight(stream) || state === 'closed') {
+      return promiseRejectedWith(new TypeError('The stream is closing or closed'));
+    }
+    if (state === 'erroring') {
+      return promiseRejectedWith(stream._storedError);
+    }
+    const promise = writableStreamAddWriteRequest(stream);
+    writableStreamDefaultControllerWrite(controller, chunk, chunkSize);
+    return promise;
+  }
+
+  // Marks where close() was called in the queue of chunks.
+  const closeSentinel = Symbol('close');
+
+  class WritableStreamDefaultController {
+    constructor(token) {
+      illegalConstructor(token);
+    }
+
+    // Aborted when the stream is.
+    get signal() {
+      return this._abortController.signal;
+    }
+
+    error(e = undefined) {
+      if (this._stream._state !== 'writable') {
+        return;
+      }
+      writableStreamDefaultControllerError(this, e);
+    }
+
+    [AbortSteps](reason) {
+      const result = this._abortAlgorithm(reason);
+      writableStreamDefaultControllerClearAlgorithms(this);
+      return result;
+    }
+
+    [ErrorSteps]() {
+      resetQueue(this);
+    }
+  }
+
+  Object.defineProperty(WritableStreamDefaultController.prototype, Symbol.toStringTag, {
+    value: 'WritableStreamDefaultController',
+    configurable: true,
+  });
+
+  function setUpWritableStreamDefaultController(
+    stream, controller, startAlgorithm, writeAlgorithm, closeAlgorithm, abortAlgorithm,
+    highWaterMark, sizeAlgorithm,
+  ) {
+    controller._stream = stream;
+    stream._controller = controller;
+    resetQueue(controller);
+    controller._abortController = new AbortController();
+    controller._started = false;
+    controller._strategySizeAlgorithm = sizeAlgorithm;
+    controller._strategyHWM = highWaterMark;
+    controller._writeAlgorithm = writeAlgorithm;
+    controller._closeAlgorithm = closeAlgorithm;
+    controller._abortAlgorithm = abortAlgorithm;
+    const backpressure = writableStreamDefaultControllerGetBackpressure(controller);
+    writableStreamUpdateBackpressure(stream, backpressure);
+    const startResult = startAlgorithm();
+    uponPromise(
+      promiseResolvedWith(startResult),
+      () => {
+        controller._started = true;
+        writableStreamDefaultControllerAdvanceQueueIfNeeded(controller);
+      },
+      (r) => {
+        controller._started = true;
+        writableStreamDealWithRejection(stream, r);
+      },
+    );
+  }
+
+  function setUpWritableStreamDefaultControllerFromUnderlyingSink(
+    stream, sink, highWaterMark, sizeAlgorithm,
+  ) {
+    const controller = Object.create(WritableStreamDefaultController.prototype);
+    const start = getMethod(sink, 'start', 'underlyingSink');
+    const write = getMethod(sink, 'write', 'underlyingSink');
+    const close = getMethod(sink, 'close', 'underlyingSink');
+    const abort = getMethod(sink, 'abort', 'underlyingSink');
+    const startAlgorithm = () => (start ? start.call(sink, controller) : undefined);
+    const writeAlgorithm = (chunk) => (
+      write ? promiseCall(write, sink, chunk, controller) : promiseResolvedWith(undefined)
+    );
+    const closeAlgorithm = () => (
+      close ? promiseCall(close, sink) : promiseResolvedWith(undefined)
+    );
+    const abortAlgorithm = (reason) => (
+      abort ? promiseCall(abort, sink, reason) : promiseResolvedWith(undefined)
+    );
+    setUpWritableStreamDefaultController(
+      stream, controller, startAlgorithm, writeAlgorithm, closeAlgorithm, abortAlgorithm,
+      highWaterMark, sizeAlgorithm,
+    );
+  }
+
+  function writableStreamDefaultControllerAdvanceQueueIfNeeded(controller) {
+    const stream = controller._stream;
+    if (!controller._started || stream._inFlightWriteRequest !== undefined) {
+      return;
+    }
+    if (stream._state === 'erroring') {
+      writableStreamFinishErroring(stream);
+      return;
+    }
+    if (controller._queue.length === 0) {
+      return;
+    }
+    const value = peekQueueValue(controller);
+    if (value === closeSentinel) {
+      writableStreamDefaultControllerProcessClose(controller);
+    } else {
+      writableStreamDefaultControllerProcessWrite(controller, value);
+    }
+  }
+
+  function writableStreamDefaultControllerClearAlgorithms(controller) {
+    controller._writeAlgorithm = undefined;
+    controller._closeAlgorithm = undefined;
+    controller._abortAlgorithm = undefined;
+    controller._strategySizeAlgorithm = undefined;
+  }
+
+  function writableStreamDefaultControllerClose(controller) {
+    enqueueValueWithSize(controller, closeSentinel, 0);
+    writableStreamDefaultControllerAdvanceQueueIfNeeded(controller);
+  }
+
+  function writableStreamDefaultControllerError(controller, error) {
+    writableStreamDefaultControllerClearAlgorithms(controller);
+    writableStreamStartErroring(controller._stream, error);
+  }
+
+  function writableStreamDefaultControllerErrorIfNeeded(controller, error) {
+    if (controller._stream._state === 'writable') {
+      writableStreamDefaultControllerError(controller, error);
+    }
+  }
+
+  function writableStreamDefaultControllerGetBackpressure(controller) {
+    return writableStreamDefaultControllerGetDesiredSize(controller) <= 0;
+  }
+
+  function writableStreamDefaultControllerGetChunkSize(controller, chunk) {
+    if (controller._strategySizeAlgorithm === undefined) {
+      return 1;
+    }
+    try {
+      return controller._strategySizeAlgorithm(chunk);
+    } catch (e) {
+      writableStreamDefaultControllerErrorIfNeeded(controller, e);
+      return 1;
+    }
+  }
+
+  function writableStreamDefaultControllerGetDesiredSize(controller) {
+    return controller._strategyHWM - controller._queueTotalSize;
+  }
+
+  function writableStreamDefaultControllerProcessClose(controller) {
+    const stream = controller._stream;
+    writableStreamMarkCloseRequestInFlight(stream);
+    dequeueValue(controller);
+    const sinkClosePromise = controller._closeAlgorithm();
+    writableStreamDefaultControllerClearAlgorithms(controller);
+    uponPromise(
+      sinkClosePromise,
+      () => writableStreamFinishInFlightClose(stream),
+      (reason) => writableStreamFinishInFlightCloseWithError(stream, reason),
+    );
+  }
+
+  function writableStreamDefaultControllerProcessWrite(controller, chunk) {
+    const stream = controller._stream;
+    writableStreamMarkFirstWriteRequestInFlight(stream);
+    uponPromise(
+      controller._writeAlgorithm(chunk),
+      () => {
+        writableStreamFinishInFlightWrite(stream);
+        const state = stream._state;
+        dequeueValue(controller);
+        if (!writableStreamCloseQueuedOrInFlight(stream) && state === 'writable') {
+          const backpressure = writableStreamDefaultControllerGetBackpressure(controller);
+          writableStreamUpdateBackpressure(stream, backpressure);
+        }
+        writableStreamDefaultControllerAdvanceQueueIfNeeded(controller);
+      },
+      (reason) => {
+        if (stream._state === 'writable') {
+          writableStreamDefaultControllerClearAlgorithms(controller);
+        }
+        writableStreamFinishInFlightWriteWithError(stream, reason);
+      },
+    );
+  }
+
+  function writableStreamDefaultControllerWrite(controller, chunk, chunkSize) {
+    try {
+      enqueueValueWithSize(controller, chunk, chunkSize);
+    } catch (e) {
+      writableStreamDefaultControllerErrorIfNeeded(controller, e);
+      return;
+    }
+    const stream = controller._stream;
+    if (!writableStreamCloseQueuedOrInFlight(stream) && stream._state === 'writable') {
+      const backpressure = writableStreamDefaultControllerGetBackpressure(controller);
+      writableStreamUpdateBackpressure(stream, backpressure);
+    }
+    writableStreamDefaultControllerAdvanceQueueIfNeeded(controller);
+  }
+
+  // TransformStream
+
+  class TransformStream {
+    constructor(transformer = undefined, writableStrategy = undefined,
+      readableStrategy = undefined) {
+      const transformerDict = dictionary(transformer, 'transformer');
+      writableStrategy = dictionary(writableStrategy, 'writableStrategy');
+      readableStrategy = dictionary(readableStrategy, 'readableStrategy');
+      if (transformerDict.readableType !== undefined) {
+        throw new RangeError('Invalid readableType');
+      }
+      if (transformerDict.writableType !== undefined) {
+        throw new RangeError('Invalid writableType');
+      }
+      const readableHighWaterMark = extractHighWaterMark(readableStrategy, 0);
+      const readableSizeAlgorithm = extractSizeAlgorithm(readableStrategy);
+      const writableHighWaterMark = extractHighWaterMark(writableStrategy, 1);
+      const writableSizeAlgorithm = extractSizeAlgorithm(writableStrategy);
+      const startPromise = newPromise();
+      initializeTransformStream(
+        this, startPromise.promise, writableHighWaterMark, writableSizeAlgorithm,
+        readableHighWaterMark, readableSizeAlgorithm,
+      );
+      const start = getMethod(transformerDict, 'start', 'transformer');
+      setUpTransformStreamDefaultControllerFromTransformer(this, transformerDict);
+      if (start !== undefined) {
+        startPromise.resolve(start.call(transformerDict, this._controller));
+      } else {
+        startPromise.resolve(undefined);
+      }
+    }
+
+    get readable() {
+      return this._readable;
+    }
+
+    get writable() {
+      return this._writable;
+    }
+  }
+
+  Object.defineProperty(TransformStream.prototype, Symbol.toStringTag, {
+    value: 'TransformStream',
+    configurable: true,
+  });
+
+  function initializeTransformStream(
+    stream, startPromise, writableHighWaterMark, writableSizeAlgorithm, readableHighWaterMark,
+    readableSizeAlgorithm,
+  ) {
+    const startAlgorithm = () => startPromise;
+    stream._writable = createWritableStream(
+      startAlgorithm,
+      (chunk) => transformStreamDefaultSinkWriteAlgorithm(stream, chunk),
+      () => transformStreamDefaultSinkCloseAlgorithm(stream),
+      (reason) => transformStreamDefaultSinkAbortAlgorithm(stream, reason),
+      writableHighWaterMark,
+      writableSizeAlgorithm,
+    );
+    stream._readable = createReadableStream(
+      startAlgorithm,
+      () => transformStreamDefaultSourcePullAlgorithm(stream),
+      (reason) => transformStreamDefaultSourceCancelAlgorithm(stream, reason),
+      readableHighWaterMark,
+      readableSizeAlgorithm,
+    );
+    stream._backpressure = undefined;
+    stream._backpressureChangePromise = undefined;
+    transformStreamSetBackpressure(stream, true);
+    stream._controller = undefined;
+  }
+
+  function transformStreamError(stream, e) {
+    readableStreamDefaultControllerError(stream._readable._controller, e);
+    transformStreamErrorWritableAndUnblockWrite(stream, e);
+  }
+
+  function transformStreamErrorWritableAndUnblockWrite(stream, e) {
+    transformStreamDefaultControllerClearAlgorithms(stream._controller);
+    writableStreamDefaultControllerErrorIfNeeded(stream._writable._controller, e);
+    transformStreamUnblockWrite(stream);
+  }
+
+  function transformStreamSetBackpressure(stream, backpressure) {
+    if (stream._backpressureChangePromise !== undefined) {
+      stream._backpressureChangePromise.resolve(undefined);
+    }
+    stream._backpressureChangePromise = newPromise();
+    stream._backpressure = backpressure;
+  }
+
+  function transformStreamUnblockWrite(stream) {
+    if (stream._backpressure) {
+      transformStreamSetBackpressure(stream, false);
+    }
+  }
+
+  class TransformStreamDefaultController {
+    constructor(token) {
+      illegalConstructor(token);
+    }
+
+    get desiredSize() {
+      return readableStreamDefaultControllerGetDesiredSize(this._stream._readable._controller);
+    }
+
+    enqueue(chunk = undefined) {
+      transformStreamDefaultControllerEnqueue(this, chunk);
+    }
+
+    error(reason = undefined) {
+      transformStreamError(this._stream, reason);
+    }
+
+    terminate() {
+      transformStreamDefaultControllerTerminate(this);
+    }
+  }
+
+  Object.defineProperty(TransformStreamDefaultController.prototype, Symbol.toStringTag, {
+    value: 'TransformStreamDefaultController',
+    configurable: true,
+  });
+
+  function setUpTransformStreamDefaultController(
+    stream, controller, transformAlgorithm, flushAlgorithm, cancelAlgorithm,
+  ) {
+    controller._stream = stream;
+    stream._controller = controller;
+    controller._transformAlgorithm = transformAlgorithm;
+    controller._flushAlgorithm = flushAlgorithm;
+    controller._cancelAlgorithm = cancelAlgorithm;
+    controller._finishPromise = undefined;
+  }
+
+  function setUpTransformStreamDefaultControllerFromTransformer(stream, transformer) {
+    const controller = Object.create(TransformStreamDefaultController.prototype);
+    const transform = getMethod(transformer, 'transform', 'transformer');
+    const flush = getMethod(transformer, 'flush', 'transformer');
+    const cancel = getMethod(transformer, 'cancel', 'transformer');
+    let transformAlgorithm = (chunk) => {
+      try {
+        transformStreamDefaultControllerEnqueue(controller, chunk);
+        return promiseResolvedWith(undefined);
+      } catch (e) {
+        return promiseRejectedWith(e);
+      }
+    };
+    if (transform !== undefined) {
+      transformAlgorithm = (chunk) => promiseCall(transform, transformer, chunk, controller);
+    }
+    const flushAlgorithm = () => (
+      flush ? promiseCall(flush, transformer, controller) : promiseResolvedWith(undefined)
+    );
+    const cancelAlgorithm = (reason) => (
+      cancel ? promiseCall(cancel, transformer, reason) : promiseResolvedWith(undefined)
+    );
+    setUpTransformStreamDefaultController(
+      stream, controller, transformAlgorithm, flushAlgorithm, cancelAlgorithm,
+    );
+  }
+
+  function transformStreamDefaultControllerClearAlgorithms(controller) {
+    controller._transformAlgorithm = undefined;
+    controller._flushAlgorithm = undefined;
+    controller._cancelAlgorithm = undefined;
+  }
+
+  function transformStreamDefaultControllerEnqueue(controller, chunk) {
+    const stream = controller._stream;
+    const readableController = stream._readable._controller;
+    if (!readableStreamDefaultControllerCanCloseOrEnqueue(readableController)) {
+      throw new TypeError('The readable side is not in a state that permits enqueue');
+    }
+    try {
+      readableStreamDefaultControllerEnqueue(readableController, chunk);
+    } catch (e) {
+      transformStreamErrorWritableAndUnblockWrite(stream, e);
+      throw stream._readable._storedError;
+    }
+    const backpressure = readableStreamDefaultControllerHasBackpressure(readableController);
+    if (backpressure !== stream._backpressure) {
+      transformStreamSetBackpressure(stream, true);
+    }
+  }
+
+  function transformStreamDefaultControllerPerformTransform(controller, chunk) {
+    const transformPromise = controller._transformAlgorithm(chunk);
+    return transformPromiseWith(transformPromise, undefined, (r) => {
+      transformStreamError(controller._stream, r);
+      throw r;
+    });
+  }
+
+  function transformStreamDefaultControllerTerminate(controller) {
+    const stream = controller._stream;
+    readableStreamDefaultControllerClose(stream._readable._controller);
+    const error = new TypeError('The TransformStream was terminated');
+    transformStreamErrorWritableAndUnblockWrite(stream, error);
+  }
+
+  function transformStreamDefaultSinkWriteAlgorithm(stream, chunk) {
+    const controller = stream._controller;
+    if (stream._backpressure) {
+      return transformPromiseWith(stream._backpressureChangePromise.promise, () => {
+        const writable = stream._writable;
+        if (writable._state === 'erroring') {
+          throw writable._storedError;
+        }
+        return transformStreamDefaultControllerPerformTransform(controller, chunk);
+      });
+    }
+    return transformStreamDefaultControllerPerformTransform(controller, chunk);
+  }
+
+  function transformStreamDefaultSinkAbortAlgorithm(stream, reason) {
+    const controller = stream._controller;
+    if (controller._finishPromise !== undefined) {
+      return controller._finishPromise.promise;
+    }
+    const readable = stream._readable;
+    const finishPromise = newPromise();
+    controller._finishPromise = finishPromise;
+    const cancelPromise = controller._cancelAlgorithm(reason);
+    transformStreamDefaultControllerClearAlgorithms(controller);
+    uponPromise(
+      cancelPromise,
+      () => {
+        if (readable._state === 'errored') {
+          finishPromise.reject(readable._storedError);
+        } else {
+          readableStreamDefaultControllerError(readable._controller, reason);
+          finishPromise.resolve(undefined);
+        }
+      },
+      (r) => {
+        readableStreamDefaultControllerError(readable._controller, r);
+        finishPromise.reject(r);
+      },
+    );
+    return finishPromise.promise;
+  }
+
+  function transformStreamDefaultSinkCloseAlgorithm(stream) {
+    const controller = stream._controller;
+    if (controller._finishPromise !== undefined) {
+      return controller._finishPromise.promise;
+    }
+    const readable = stream._readable;
+    const finishPromise = newPromise();
+    controller._finishPromise = finishPromise;
+    const flushPromise = controller._flushAlgorithm();
+    transformStreamDefaultControllerClearAlgorithms(controller);
+    uponPromise(
+      flushPromise,
+      () => {
+        if (readable._state === 'errored') {
+          finishPromise.reject(readable._storedError);
+        } else {
+          readableStreamDefaultControllerClose(readable._controller);
+          finishPromise.resolve(undefined);
+        }
+      },
+      (r) => {
+        readableStreamDefaultControllerError(readable._controller, r);
+        finishPromise.reject(r);
+      },
+    );
+    return finishPromise.promise;
+  }
+
+  function transformStreamDefaultSourceCancelAlgorithm(stream, reason) {
+    const controller = stream._controller;
+    if (controller._finishPromise !== undefined) {
+      return controller._finishPromise.promise;
+    }
+    const writable = stream._writable;
+    const finishPromise = newPromise();
+    controller._finishPromise = finishPromise;
+    const cancelPromise = controller._cancelAlgorithm(reason);
+    transformStreamDefaultControllerClearAlgorithms(controller);
+    uponPromise(
+      cancelPromise,
+      () => {
+        if (writable._state === 'errored') {
+          finishPromise.reject(writable._storedError);
+        } else {
+          writableStreamDefaultControllerErrorIfNeeded(writable._controller, reason);
+          transformStreamUnblockWrite(stream);
+          finishPromise.resolve(undefined);
+        }
+      },
+      (r) => {
+        writableStreamDefaultControllerErrorIfNeeded(writable._controller, r);
+        transformStreamUnblockWrite(stream);
+        finishPromise.reject(r);
+      },
+    );
+    return finishPromise.promise;
+  }
+
+  function transformStreamDefaultSourcePullAlgorithm(stream) {
+    transformStreamSetBackpressure(stream, false);
+    return stream._backpressureChangePromise.promise;
+  }
+
+  const streams = {
+    ReadableStream,
+    ReadableStreamDefaultReader,
+    ReadableStreamBYOBReader,
+    ReadableStreamDefaultController,
+    ReadableByteStreamController,
+    ReadableStreamBYOBRequest,
+    WritableStream,
+    WritableStreamDefaultWriter,
+    WritableStreamDefaultController,
+    TransformStream,
+    TransformStreamDefaultController,
+    ByteLengthQueuingStrategy,
+    CountQueuingStrategy,
+  };
+
+  // For other builtins, like fetch's bodies.
+  internals.streams = {
+    isDisturbed(stream) {
+      return stream._disturbed;
+    },
+    isErrored(stream) {
+      return stream._state === 'errored';
+    },
+  };
+
+  for (const [name, value] of Object.entries(streams)) {
+    Object.defineProperty(globalThis, name, {
+      value,
+      writable: true,
+      configurable: true,
+    });
+  }
+});
@@ -0,0 +1,478 @@
+// The jstime:test module, and the runner behind `jstime test`.
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({
+  builtinModules, internals, printer, perfNow, setTimeout, clearTimeout,
+}) => {
+  class Suite {
+    constructor(name, parent, options = {}) {
+      this.name = name;
+      this.parent = parent;
+      this.only = Boolean(options.only);
+      this.skip = Boolean(options.skip);
+      this.timeout = options.timeout;
+      this.children = [];
+      this.before = [];
+      this.after = [];
+      this.beforeEach = [];
+      this.afterEach = [];
+    }
+  }
+
+  const root = new Suite(undefined, undefined);
+  let current = root;
+  let hasOnly = false;
+  let running = false;
+
+  function parseArgs(name, options, fn) {
+    if (typeof options === 'function') {
+      fn = options;
+      options = {};
+    }
+    if (typeof name !== 'string') {
+      throw new TypeError('The name must be a string');
+    }
+    if (typeof fn !== 'function') {
+      throw new TypeError('The callback must be a function');
+    }
+    if (running) {
+      throw new Error('Tests and suites must be registered before tests run');
+    }
+    return { name, options: { ...options }, fn };
+  }
+
+  function addTest({ name, options, fn }) {
+    hasOnly = hasOnly || Boolean(options.only);
+    current.children.push({
+      name,
+      fn,
+      parent: current,
+      only: Boolean(options.only),
+      skip: Boolean(options.skip),
+      timeout: options.timeout,
+    });
+  }
+
+  function addSuite({ name, options, fn }) {
+    hasOnly = hasOnly || Boolean(options.only);
+    const suite = new Suite(name, current, options);
+    current.children.push(suite);
+    current = suite;
+    try {
+      const result = fn();
+      if (result && typeof result.then === 'function') {
+        throw new TypeError(`describe('${name}') must register its tests synchronously`);
+      }
+    } finally {
+      current = suite.parent;
+    }
+  }
+
+  function withOption(add, option) {
+    return (name, options, fn) => {
+      const args = parseArgs(name, options, fn);
+      args.options[option] = true;
+      add(args);
+    };
+  }
+
+  function test(name, options, fn) {
+    addTest(parseArgs(name, options, fn));
+  }
+  test.only = withOption(addTest, 'only');
+  test.skip = withOption(addTest, 'skip');
+
+  function describe(name, options, fn) {
+    addSuite(parseArgs(name, options, fn));
+  }
+  describe.only = withOption(addSuite, 'only');
+  describe.skip = withOption(addSuite, 'skip');
+
+  function hook(kind) {
+    return (fn) => {
+      if (typeof fn !== 'function') {
+        throw new TypeError('The hook must be a function');
+      }
+      current[kind].push(fn);
+    };
+  }
+
+  builtinModules['jstime:test'] = {
+    default: test,
+    test,
+    it: test,
+    describe,
+    before: hook('before'),
+    after: hook('after'),
+    beforeEach: hook('beforeEach'),
+    afterEach: hook('afterEach'),
+  };
+
+  // Running
+
+  let defaultTimeout = 5000;
+
+  function ancestors(node) {
+    const nodes = [];
+    for (let n = node; n !== root; n = n.parent) {
+      nodes.unshift(n);
+    }
+    return nodes;
+  }
+
+  function isSkipped(node) {
+    if (node.loadError) {
+      return false;
+    }
+    const nodes = ancestors(node);
+    if (nodes.some((n) => n.skip)) {
+      return true;
+    }
+    return hasOnly && !nodes.some((n) => n.only);
+  }
+
+  function hasRunnableTests(suite) {
+    return suite.children.some((child) => (child instanceof Suite
+      ? hasRunnableTests(child)
+      : !isSkipped(child)));
+  }
+
+  function timeoutOf(node) {
+    for (let n = node; n !== root; n = n.parent) {
+      if (n.timeout !== undefined) {
+        return n.timeout;
+      }
+    }
+    return defaultTimeout;
+  }
+
+  function callWithTimeout(fn, ms) {
+    return new Promise((resolve, reject) => {
+      let timer;
+      if (ms > 0 && ms < Infinity) {
+        timer = setTimeout(() => reject(new Error(`Timed out after ${ms}ms`)), ms);
+      }
+      new Promise((resolveCall) => resolveCall(fn())).then((value) => {
+        clearTimeout(timer);
+        resolve(value);
+      }, (error) => {
+        clearTimeout(timer);
+        reject(error);
+      });
+    });
+  }
+
+  async function runHooks(hooks, ms) {
+    for (const fn of hooks) {
+      await callWithTimeout(fn, ms);
+    }
+  }
+
+  function resultOf(node, extra) {
+    const nodes = ancestors(node);
+    return {
+      file: nodes[0].name,
+      // Names of the enclosing describe blocks and the test itself.
+      path: nodes.slice(1).map((n) => n.name),
+      depth: nodes.length - 1,
+      ...extra,
+    };
+  }
+
+  async function runTest(t, reporter, beforeError) {
+    if (isSkipped(t)) {
+      reporter.test(resultOf(t, { status: 'skip', duration: 0 }));
+      return;
+    }
+
+    const nodes = ancestors(t);
+    const ms = timeoutOf(t);
+    const start = perfNow();
+    let error = beforeError;
+    let failed = error !== undefined;
+    if (!failed) {
+      try {
+        await runHooks(nodes.flatMap((n) => n.beforeEach || []), ms);
+        await callWithTimeout(t.fn, ms);
+      } catch (e) {
+        error = e;
+        failed = true;
+      }
+      try {
+        await runHooks(nodes.reverse().flatMap((n) => n.afterEach || []), ms);
+      } catch (e) {
+        if (!failed) {
+          error = e;
+          failed = true;
+        }
+      }
+    }
+    const duration = perfNow() - start;
+    reporter.test(resultOf(t, { status: failed ? 'fail' : 'pass', duration, error }));
+  }
+
+  async function runSuite(suite, reporter, beforeError) {
+    const runnable = hasRunnableTests(suite);
+    if (suite !== root) {
+      const nodes = ancestors(suite);
+      reporter.suite(nodes.map((n) => n.name), nodes.length - 1);
+    }
+
+    const ms = timeoutOf(suite);
+    let error = beforeError;
+    if (runnable && error === undefined) {
+      try {
+        await runHooks(suite.before, ms);
+      } catch (e) {
+        error = e;
+      }
+    }
+    for (const child of suite.children) {
+      if (child instanceof Suite) {
+        await runSuite(child, reporter, error);
+      } else {
+        await runTest(child, reporter, error);
+      }
+    }
+    if (runnable && beforeError === undefined) {
+      try {
+        await runHooks(suite.after, ms);
+      } catch (e) {
+        reporter.test(resultOf(suite, {
+          status: 'fail', duration: 0, error: e, hook: 'after', depth: ancestors(suite).length,
+        }));
+      }
+    }
+  }
+
+  // Reporting
+
+  function formatError(error) {
+    if (error instanceof Error && typeof error.stack === 'string') {
+      // Frames of the runner itself are just noise.
+      return error.stack
+        .split('\n')
+        .filter((line) => !/^\s+at (.* \()?\.\/test\.js:\d+:\d+\)?$/.test(line))
+        .join('\n');
+    }
+    return String(error);
+  }
+
+  // The name of a test within its file.
+  function testName(result) {
+    const path = result.hook ? [...result.path, `${result.hook} hook`] : result.path;
+    return path.join(' > ');
+  }
+
+  function fullName(result) {
+    return [result.file, testName(result)].filter(Boolean).join(' > ');
+  }
+
+  function formatDuration(ms) {
+    return ms < 1000 ? `${Math.round(ms)}ms` : `${(ms / 1000).toFixed(2)}s`;
+  }
+
+  function indent(text, prefix) {
+    return text.split('\n').map((line) => prefix + line).join('\n');
+  }
+
+  class SpecReporter {
+    constructor() {
+      this.failures = [];
+    }
+
+    suite(path, depth) {
+      printer(`${'  '.repeat(depth)}${path[path.length - 1]}`);
+    }
+
+    test(result) {
+      const pad = '  '.repeat(result.depth);
+      const name = result.hook ? `${result.hook} hook` : result.path[result.path.length - 1];
+      if (result.status === 'pass') {
+        printer(`${pad}✓ ${name}`);
+      } else if (result.status === 'skip') {
+        printer(`${pad}- ${name} (skipped)`);
+      } else {
+        this.failures.push(result);
+        printer(`${pad}✗ ${name} (${this.failures.length})`);
+      }
+    }
+
+    end(stats) {
+      if (this.failures.length > 0) {
+        printer('\nFailures:');
+        this.failures.forEach((result, i) => {
+          printer(`\n${i + 1}) ${fullName(result)}`);
+          printer(indent(formatError(result.error), '   '));
+        });
+      }
+      const counts = [`${stats.passed} passed`];
+      if (stats.failed > 0) {
+        counts.push(`${stats.failed} failed`);
+      }
+      if (stats.skipped > 0) {
+        counts.push(`${stats.skipped} skipped`);
+      }
+      const tests = stats.total === 1 ? 'test' : 'tests';
+      printer(`\n${stats.total} ${tests}: ${counts.join(', ')} (${formatDuration(stats.duration)})`);
+    }
+  }
+
+  // https://testanything.org/tap-version-13-specification.html
+  class TapReporter {
+    constructor() {
+      this.count = 0;
+      printer('TAP version 13');
+    }
+
+    suite() {}
+
+    test(result) {
+      this.count += 1;
+      const description = fullName(result).replace(/\\/g, '\\\\').replace(/#/g, '\\#');
+      if (result.status === 'pass') {
+        printer(`ok ${this.count} - ${description}`);
+      } else if (result.status === 'skip') {
+        printer(`ok ${this.count} - ${description} # SKIP`);
+      } else {
+        printer(`not ok ${this.count} - ${description}`);
+        printer('  ---');
+        printer(`  duration_ms: ${result.duration.toFixed(3)}`);
+        printer('  error: |-');
+        printer(indent(formatError(result.error), '    '));
+        printer('  ...');
+      }
+    }
+
+    end(stats) {
+      printer(`1..${this.count}`);
+      printer(`# tests ${stats.total}`);
+      printer(`# pass ${stats.passed}`);
+      printer(`# fail ${stats.failed}`);
+      printer(`# skip ${stats.skipped}`);
+    }
+  }
+
+  function escapeXml(text) {
+    return String(text).replace(/[<>&"']/g, (c) => `&#${c.charCodeAt(0)};`);
+  }
+
+  // The JUnit XML format as read by CI systems, one testsuite per file.
+  class JunitReporter {
+    constructor() {
+      this.files = new Map();
+    }
+
+    suite() {}
+
+    test(result) {
+      if (!this.files.has(result.file)) {
+        this.files.set(result.file, []);
+      }
+      this.files.get(result.file).push(result);
+    }
+
+    end(stats) {
+      const seconds = (ms) => (ms / 1000).toFixed(3);
+      const count = (results, status) => results.filter((r) => r.status === status).length;
+      const lines = [
+        '<?xml version="1.0" encoding="UTF-8"?>',
+        `<testsuites name="jstime test" tests="${stats.total}" failures="${stats.failed}" skipped="${stats.skipped}" time="${seconds(stats.duration)}">`,
+      ];
+      for (const [file, results] of this.files) {
+        const time = results.reduce((sum, r) => sum + r.duration, 0);
+        lines.push(`  <testsuite name="${escapeXml(file)}" tests="${results.length}" failures="${count(results, 'fail')}" skipped="${count(results, 'skip')}" time="${seconds(time)}">`);
+        for (const result of results) {
+          const testcase = `    <testcase classname="${escapeXml(file)}" name="${escapeXml(testName(result) || file)}" time="${seconds(result.duration)}"`;
+          if (result.status === 'pass') {
+            lines.push(`${testcase}/>`);
+          } else if (result.status === 'skip') {
+            lines.push(`${testcase}>`, '      <skipped/>', '    </testcase>');
+          } else {
+            const message = result.error instanceof Error
+              ? result.error.message
+              : String(result.error).split('\n')[0];
+            lines.push(
+              `${testcase}>`,
+              `      <failure message="${escapeXml(message)}">${escapeXml(formatError(result.error))}</failure>`,
+              '    </testcase>',
+            );
+          }
+        }
+        lines.push('  </testsuite>');
+      }
+      lines.push('</testsuites>');
+      printer(lines.join('\n'));
+    }
+  }
+
+  const reporters = {
+    spec: SpecReporter,
+    tap: TapReporter,
+    junit: JunitReporter,
+  };
+
+  // Counts results on their way to the reporter.
+  function counting(reporter, stats) {
+    return {
+      suite: (path, depth) => reporter.suite(path, depth),
+      test(result) {
+        // Failing after hooks add to the failures, not the tests.
+        if (!result.hook) {
+          stats.total += 1;
+        }
+        if (result.status === 'pass') {
+          stats.passed += 1;
+        } else if (result.status === 'skip') {
+          stats.skipped += 1;
+        } else {
+          stats.failed += 1;
+        }
+        reporter.test(result);
+      },
+    };
+  }
+
+  internals.test = {
+    // Tests registered from here on belong to `file`.
+    setFile(file) {
+      const suite = new Suite(file, root);
+      root.children.push(suite);
+      current = suite;
+    },
+
+    // `file` couldn't be imported, report that as a failing test.
+    loadError(file, error) {
+      const suite = root.children[root.children.length - 1];
+      suite.children.push({
+        name: 'failed to load',
+        fn() {
+          throw error;
+        },
+        parent: suite,
+        loadError: true,
+      });
+      current = root;
+    },
+
+    // Run the registered tests, resolving to whether they all passed.
+    async run(options) {
+      running = true;
+      current = root;
+      if (options.timeout !== undefined) {
+        defaultTimeout = options.timeout;
+      }
+      const reporter = new reporters[options.reporter]();
+      const stats = {
+        total: 0, passed: 0, failed: 0, skipped: 0, duration: 0,
+      };
+      const start = perfNow();
+      await runSuite(root, counting(reporter, stats));
+      stats.duration = perfNow() - start;
+      reporter.end(stats);
+      return stats.failed === 0;
+    },
+  };
+});
@@ -1,6 +1,7 @@
 "use strict";
 
 // eslint-disable-next-line no-unused-expressions
-({ setTimeout }) => {
+({ setTimeout, clearTimeout }) => {
   globalThis.setTimeout = setTimeout;
+  globalThis.clearTimeout = clearTimeout;
 };
@@ -0,0 +1,155 @@
+// Terminal bindings behind `jstime.stdin`, `jstime.stdout` and
+// `jstime.stderr`, which take resource ids 0, 1 and 2.
+
+use std::io::{ErrorKind, IsTerminal};
+
+use super::fs::io_exception;
+
+fn unsupported() -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::Unsupported,
+        "Not supported for this resource or platform",
+    )
+}
+
+fn is_terminal(rid: u32) -> bool {
+    match rid {
+        0 => std::io::stdin().is_terminal(),
+        1 => std::io::stdout().is_terminal(),
+        2 => std::io::stderr().is_terminal(),
+        _ => false,
+    }
+}
+
+pub(crate) fn tty_is_terminal(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(u32::MAX);
+    rv.set(v8::Boolean::new(scope, is_terminal(rid)).into());
+}
+
+/// The terminal settings stdin had before raw mode was turned on, restored
+/// when it's turned off or the isolate is dropped.
+pub(crate) struct RawMode {
+    #[cfg(unix)]
+    saved: Option<libc::termios>,
+}
+
+impl RawMode {
+    pub(crate) fn new() -> Self {
+        Self {
+            #[cfg(unix)]
+            saved: None,
+        }
+    }
+
+    #[cfg(unix)]
+    fn set(&mut self, enabled: bool) -> std::io::Result<()> {
+        if !enabled {
+            return self.restore();
+        }
+        if self.saved.is_some() {
+            return Ok(());
+        }
+        // Like cfmakeraw, but output processing stays on, so `\n` still
+        // starts a new line.
+        let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let saved = termios;
+        termios.c_iflag &= !(libc::BRKINT | libc::ICRNL | libc::INPCK | libc::ISTRIP | libc::IXON);
+        termios.c_cflag |= libc::CS8;
+        termios.c_lflag &= !(libc::ECHO | libc::ICANON | libc::IEXTEN | libc::ISIG);
+        termios.c_cc[libc::VMIN] = 1;
+        termios.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &termios) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        self.saved = Some(saved);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn restore(&mut self) -> std::io::Result<()> {
+        if let Some(saved) = self.saved.take() {
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &saved) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn set(&mut self, _enabled: bool) -> std::io::Result<()> {
+        Err(unsupported())
+    }
+
+    #[cfg(not(unix))]
+    fn restore(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
+
+/// Turn raw mode of stdin on or off. In raw mode input is available a key
+/// at a time, isn't echoed, and Ctrl-C doesn't send SIGINT.
+pub(crate) fn tty_set_raw(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let enabled = args.get(0).boolean_value(scope);
+    if !is_terminal(0) {
+        let message = "stdin is not a terminal";
+        let e = std::io::Error::new(ErrorKind::InvalidInput, message);
+        return io_exception(scope, e);
+    }
+    if let Err(e) = scope.get_slot_mut::<RawMode>().unwrap().set(enabled) {
+        io_exception(scope, e);
+    }
+}
+
+#[cfg(unix)]
+fn size(rid: u32) -> std::io::Result<(u16, u16)> {
+    if rid > 2 {
+        return Err(unsupported());
+    }
+    let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+    if unsafe { libc::ioctl(rid as libc::c_int, libc::TIOCGWINSZ, &mut size) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok((size.ws_col, size.ws_row))
+}
+
+#[cfg(not(unix))]
+fn size(_rid: u32) -> std::io::Result<(u16, u16)> {
+    Err(unsupported())
+}
+
+/// `{ columns, rows }` of the terminal behind a resource.
+pub(crate) fn tty_size(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let rid = args.get(0).uint32_value(scope).unwrap_or(u32::MAX);
+    let (columns, rows) = match size(rid) {
+        Ok(size) => size,
+        Err(e) => return io_exception(scope, e),
+    };
+    let obj = v8::Object::new(scope);
+    for (name, value) in [("columns", columns), ("rows", rows)] {
+        let key = v8::String::new(scope, name).unwrap();
+        let value = v8::Integer::new(scope, value.into());
+        obj.set(scope, key.into(), value.into());
+    }
+    rv.set(obj.into());
+}
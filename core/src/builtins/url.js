@@ -0,0 +1,254 @@
+// URL and URLSearchParams
+// https://url.spec.whatwg.org/
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({
+  urlParse, urlSet, urlSearchParamsParse, urlSearchParamsStringify,
+}) => {
+  // The order urlParse and urlSet return the components of a URL in.
+  const COMPONENTS = [
+    'href', 'origin', 'protocol', 'username', 'password', 'host', 'hostname', 'port',
+    'pathname', 'search', 'hash',
+  ];
+  const HREF = 0;
+  const SEARCH = 9;
+
+  // Only URL makes search params that update a URL.
+  let createLinkedParams;
+  let updateParams;
+
+  class URLSearchParams {
+    #list = [];
+    // The URL whose query these are, if any.
+    #url = null;
+
+    constructor(init = '') {
+      if (typeof init === 'object' && init !== null) {
+        if (typeof init[Symbol.iterator] === 'function') {
+          for (const pair of init) {
+            const entry = [...pair];
+            if (entry.length !== 2) {
+              throw new TypeError('Each pair must have a name and a value');
+            }
+            this.#list.push([String(entry[0]), String(entry[1])]);
+          }
+        } else {
+          for (const name of Reflect.ownKeys(init)) {
+            const descriptor = Reflect.getOwnPropertyDescriptor(init, name);
+            if (typeof name === 'string' && descriptor !== undefined && descriptor.enumerable) {
+              this.#list.push([name, String(init[name])]);
+            }
+          }
+        }
+      } else {
+        const query = String(init);
+        this.#list = urlSearchParamsParse(query.startsWith('?') ? query.slice(1) : query);
+      }
+    }
+
+    static {
+      createLinkedParams = (url, query) => {
+        const params = new URLSearchParams(query);
+        params.#url = url;
+        return params;
+      };
+
+      // Replaces the list after the query of the URL changed.
+      updateParams = (params, query) => {
+        params.#list = urlSearchParamsParse(query.startsWith('?') ? query.slice(1) : query);
+      };
+    }
+
+    #update() {
+      if (this.#url !== null) {
+        setSearch(this.#url, this.toString());
+      }
+    }
+
+    get size() {
+      return this.#list.length;
+    }
+
+    append(name, value) {
+      this.#list.push([String(name), String(value)]);
+      this.#update();
+    }
+
+    delete(name, value = undefined) {
+      const key = String(name);
+      const match = value === undefined ? null : String(value);
+      this.#list = this.#list.filter(([n, v]) => n !== key || (match !== null && v !== match));
+      this.#update();
+    }
+
+    get(name) {
+      const key = String(name);
+      const entry = this.#list.find(([n]) => n === key);
+      return entry === undefined ? null : entry[1];
+    }
+
+    getAll(name) {
+      const key = String(name);
+      return this.#list.filter(([n]) => n === key).map(([, v]) => v);
+    }
+
+    has(name, value = undefined) {
+      const key = String(name);
+      const match = value === undefined ? null : String(value);
+      return this.#list.some(([n, v]) => n === key && (match === null || v === match));
+    }
+
+    // Replaces the first pair named `name`, removing the others.
+    set(name, value) {
+      const key = String(name);
+      const entry = [key, String(value)];
+      const index = this.#list.findIndex(([n]) => n === key);
+      if (index === -1) {
+        this.#list.push(entry);
+      } else {
+        this.#list = this.#list.filter(([n], i) => i <= index || n !== key);
+        this.#list[index] = entry;
+      }
+      this.#update();
+    }
+
+    // Sorts by name in code units, keeping the order of pairs with the
+    // same name.
+    sort() {
+      this.#list.sort(([a], [b]) => {
+        if (a === b) {
+          return 0;
+        }
+        return a < b ? -1 : 1;
+      });
+      this.#update();
+    }
+
+    forEach(callback, thisArg = undefined) {
+      if (typeof callback !== 'function') {
+        throw new TypeError('callback must be a function');
+      }
+      for (let i = 0; i < this.#list.length; i += 1) {
+        const [name, value] = this.#list[i];
+        callback.call(thisArg, value, name, this);
+      }
+    }
+
+    * entries() {
+      for (let i = 0; i < this.#list.length; i += 1) {
+        const [name, value] = this.#list[i];
+        yield [name, value];
+      }
+    }
+
+    * keys() {
+      for (const [name] of this.entries()) {
+        yield name;
+      }
+    }
+
+    * values() {
+      for (const [, value] of this.entries()) {
+        yield value;
+      }
+    }
+
+    [Symbol.iterator]() {
+      return this.entries();
+    }
+
+    toString() {
+      return urlSearchParamsStringify(this.#list);
+    }
+  }
+
+  Object.defineProperty(URLSearchParams.prototype, Symbol.toStringTag, {
+    value: 'URLSearchParams',
+    configurable: true,
+  });
+
+  let setSearch;
+
+  class URL {
+    #components;
+    #searchParams;
+
+    constructor(url, base = undefined) {
+      const components = parse(url, base);
+      if (components === undefined) {
+        throw new TypeError(`Invalid URL: ${url}`);
+      }
+      this.#components = components;
+      this.#searchParams = createLinkedParams(this, components[SEARCH]);
+    }
+
+    static {
+      // Sets the query from the search params, without parsing them again.
+      setSearch = (url, query) => {
+        url.#components = urlSet(url.#components[HREF], 'search', query);
+      };
+
+      COMPONENTS.forEach((name, index) => {
+        const descriptor = {
+          get() {
+            return this.#components[index];
+          },
+          enumerable: true,
+          configurable: true,
+        };
+        if (name !== 'origin') {
+          descriptor.set = function set(value) {
+            this.#set(name, value);
+          };
+        }
+        Object.defineProperty(URL.prototype, name, descriptor);
+      });
+    }
+
+    static canParse(url, base = undefined) {
+      return parse(url, base) !== undefined;
+    }
+
+    // Like the constructor, but returns null for invalid URLs.
+    static parse(url, base = undefined) {
+      return URL.canParse(url, base) ? new URL(url, base) : null;
+    }
+
+    #set(name, value) {
+      const components = urlSet(this.#components[HREF], name, String(value));
+      if (components === undefined) {
+        throw new TypeError(`Invalid URL: ${value}`);
+      }
+      this.#components = components;
+      if (name === 'href' || name === 'search') {
+        updateParams(this.#searchParams, components[SEARCH]);
+      }
+    }
+
+    get searchParams() {
+      return this.#searchParams;
+    }
+
+    toString() {
+      return this.#components[HREF];
+    }
+
+    toJSON() {
+      return this.#components[HREF];
+    }
+  }
+
+  function parse(url, base) {
+    return urlParse(String(url), base === undefined ? undefined : String(base));
+  }
+
+  Object.defineProperty(URL.prototype, Symbol.toStringTag, {
+    value: 'URL',
+    configurable: true,
+  });
+
+  globalThis.URL = URL;
+  globalThis.URLSearchParams = URLSearchParams;
+});
@@ -0,0 +1,142 @@
+// The parsing behind URL and URLSearchParams, which url.js wraps.
+
+use std::convert::TryFrom;
+
+use url::{form_urlencoded, quirks, Url};
+
+/// The components of `url` in the order url.js expects them.
+fn components<'s>(scope: &mut v8::HandleScope<'s>, url: &Url) -> v8::Local<'s, v8::Array> {
+    let values = [
+        quirks::href(url).to_owned(),
+        quirks::origin(url),
+        quirks::protocol(url).to_owned(),
+        quirks::username(url).to_owned(),
+        quirks::password(url).to_owned(),
+        quirks::host(url).to_owned(),
+        quirks::hostname(url).to_owned(),
+        quirks::port(url).to_owned(),
+        quirks::pathname(url).to_owned(),
+        quirks::search(url).to_owned(),
+        quirks::hash(url).to_owned(),
+    ];
+    let values = values
+        .iter()
+        .map(|value| v8::String::new(scope, value).unwrap().into())
+        .collect::<Vec<_>>();
+    v8::Array::new_with_elements(scope, &values)
+}
+
+/// Parse the arguments `url` and `base`, if not undefined, returning the
+/// components of the URL, or undefined if either isn't valid.
+pub(crate) fn url_parse(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let input = args.get(0).to_rust_string_lossy(scope);
+    let base = args.get(1);
+    let base = if base.is_undefined() {
+        None
+    } else {
+        match Url::parse(&base.to_rust_string_lossy(scope)) {
+            Ok(base) => Some(base),
+            Err(_) => return,
+        }
+    };
+    if let Ok(url) = Url::options().base_url(base.as_ref()).parse(&input) {
+        rv.set(components(scope, &url).into());
+    }
+}
+
+/// Set a component of the URL `href` through the setter named by the
+/// second argument to the third, returning the components of the result,
+/// or undefined if setting `href` failed. Other setters ignore values they
+/// can't use, as URL's do.
+pub(crate) fn url_set(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let href = args.get(0).to_rust_string_lossy(scope);
+    let setter = args.get(1).to_rust_string_lossy(scope);
+    let value = args.get(2).to_rust_string_lossy(scope);
+    let mut url = match Url::parse(&href) {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+    let result = match setter.as_str() {
+        "href" => quirks::set_href(&mut url, &value).map_err(|_| ()),
+        "protocol" => quirks::set_protocol(&mut url, &value),
+        "username" => quirks::set_username(&mut url, &value),
+        "password" => quirks::set_password(&mut url, &value),
+        "host" => quirks::set_host(&mut url, &value),
+        "hostname" => quirks::set_hostname(&mut url, &value),
+        "port" => quirks::set_port(&mut url, &value),
+        "pathname" => {
+            quirks::set_pathname(&mut url, &value);
+            Ok(())
+        }
+        "search" => {
+            quirks::set_search(&mut url, &value);
+            Ok(())
+        }
+        "hash" => {
+            quirks::set_hash(&mut url, &value);
+            Ok(())
+        }
+        _ => Err(()),
+    };
+    if result.is_ok() || setter != "href" {
+        rv.set(components(scope, &url).into());
+    }
+}
+
+/// Parse `application/x-www-form-urlencoded` input into `[name, value]`
+/// pairs.
+pub(crate) fn url_search_params_parse(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let input = args.get(0).to_rust_string_lossy(scope);
+    let pairs = form_urlencoded::parse(input.as_bytes())
+        .map(|(name, value)| {
+            let name = v8::String::new(scope, &name).unwrap().into();
+            let value = v8::String::new(scope, &value).unwrap().into();
+            v8::Array::new_with_elements(scope, &[name, value]).into()
+        })
+        .collect::<Vec<_>>();
+    rv.set(v8::Array::new_with_elements(scope, &pairs).into());
+}
+
+/// Serialize an array of `[name, value]` pairs as
+/// `application/x-www-form-urlencoded`.
+pub(crate) fn url_search_params_stringify(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let pairs = match v8::Local::<v8::Array>::try_from(args.get(0)) {
+        Ok(pairs) => pairs,
+        Err(_) => return super::exception(scope, "pairs must be an array"),
+    };
+    let mut serializer = form_urlencoded::Serializer::new(String::new());
+    for i in 0..pairs.length() {
+        let pair = pairs.get_index(scope, i).unwrap();
+        let pair = match v8::Local::<v8::Array>::try_from(pair) {
+            Ok(pair) => pair,
+            Err(_) => return super::exception(scope, "pairs must be arrays"),
+        };
+        let name = pair
+            .get_index(scope, 0)
+            .unwrap()
+            .to_rust_string_lossy(scope);
+        let value = pair
+            .get_index(scope, 1)
+            .unwrap()
+            .to_rust_string_lossy(scope);
+        serializer.append_pair(&name, &value);
+    }
+    let serialized = v8::String::new(scope, &serializer.finish()).unwrap();
+    rv.set(serialized.into());
+}
@@ -0,0 +1,955 @@
+// URLPattern
+// https://urlpattern.spec.whatwg.org/
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(() => {
+  const COMPONENTS = [
+    'protocol', 'username', 'password', 'hostname', 'port', 'pathname', 'search', 'hash',
+  ];
+
+  const SPECIAL_SCHEMES = {
+    ftp: '21', file: '', http: '80', https: '443', ws: '80', wss: '443',
+  };
+
+  const FULL_WILDCARD = '.*';
+
+  const DEFAULT_OPTIONS = { delimiter: '', prefix: '', ignoreCase: false };
+  const HOSTNAME_OPTIONS = { delimiter: '.', prefix: '', ignoreCase: false };
+  const PATHNAME_OPTIONS = { delimiter: '/', prefix: '/', ignoreCase: false };
+
+  function escapeRegexp(string) {
+    return string.replace(/[.+*?^${}()[\]|/\\]/g, '\\$&');
+  }
+
+  function escapePatternString(string) {
+    return string.replace(/[+*?:{}()\\]/g, '\\$&');
+  }
+
+  function segmentWildcard(options) {
+    return `[^${escapeRegexp(options.delimiter)}]+?`;
+  }
+
+  function isNameCodePoint(char, first) {
+    return first
+      ? /[$_\p{ID_Start}]/u.test(char)
+      : /[$\u200C\u200D\p{ID_Continue}]/u.test(char);
+  }
+
+  function isAscii(char) {
+    return char.charCodeAt(0) < 0x80;
+  }
+
+  // Tokenizer
+
+  // Splits a pattern string into tokens, whose `index` is where they start
+  // in it. Errors throw, unless `lenient`, which makes them "invalid-char"
+  // tokens instead.
+  function tokenize(input, lenient) {
+    const tokens = [];
+    const add = (type, index, next, value = input.slice(index, next)) => {
+      tokens.push({ type, index, value });
+      return next;
+    };
+    const fail = (index, message) => {
+      if (!lenient) {
+        throw new TypeError(`${message} at ${index} in "${input}"`);
+      }
+      const next = index + String.fromCodePoint(input.codePointAt(index)).length;
+      return add('invalid-char', index, next);
+    };
+    const charAt = (index) => String.fromCodePoint(input.codePointAt(index));
+
+    let index = 0;
+    while (index < input.length) {
+      const char = charAt(index);
+      const next = index + char.length;
+      if (char === '*') {
+        index = add('asterisk', index, next);
+      } else if (char === '+' || char === '?') {
+        index = add('other-modifier', index, next);
+      } else if (char === '\\') {
+        if (next === input.length) {
+          index = fail(index, 'Trailing backslash');
+        } else {
+          const escaped = charAt(next);
+          index = add('escaped-char', index, next + escaped.length, escaped);
+        }
+      } else if (char === '{') {
+        index = add('open', index, next);
+      } else if (char === '}') {
+        index = add('close', index, next);
+      } else if (char === ':') {
+        let position = next;
+        while (position < input.length) {
+          const nameChar = charAt(position);
+          if (!isNameCodePoint(nameChar, position === next)) {
+            break;
+          }
+          position += nameChar.length;
+        }
+        if (position === next) {
+          index = fail(index, 'Missing name');
+        } else {
+          index = add('name', index, position, input.slice(next, position));
+        }
+      } else if (char === '(') {
+        index = tokenizeRegexp(input, index, add, fail);
+      } else {
+        index = add('char', index, next);
+      }
+    }
+    add('end', index, index, '');
+    return tokens;
+  }
+
+  function tokenizeRegexp(input, index, add, fail) {
+    let depth = 1;
+    let position = index + 1;
+    let value = '';
+    while (position < input.length) {
+      const char = input[position];
+      if (!isAscii(char)) {
+        return fail(index, 'Non-ASCII regexp');
+      }
+      if (position === index + 1 && char === '?') {
+        return fail(index, 'Regexp starting with "?"');
+      }
+      if (char === '\\') {
+        if (position === input.length - 1 || !isAscii(input[position + 1])) {
+          return fail(index, 'Invalid escape in regexp');
+        }
+        value += input.slice(position, position + 2);
+        position += 2;
+        continue; // eslint-disable-line no-continue
+      }
+      if (char === ')') {
+        depth -= 1;
+        if (depth === 0) {
+          position += 1;
+          break;
+        }
+      } else if (char === '(') {
+        depth += 1;
+        if (position === input.length - 1 || input[position + 1] !== '?') {
+          return fail(index, 'Capturing group in regexp');
+        }
+      }
+      value += char;
+      position += 1;
+    }
+    if (depth !== 0) {
+      return fail(index, 'Unbalanced regexp');
+    }
+    if (value === '') {
+      return fail(index, 'Missing regexp');
+    }
+    return add('regexp', index, position, value);
+  }
+
+  // Pattern parser
+
+  // Parses a pattern string into a list of parts, passing fixed text
+  // through `encode`.
+  function parsePattern(input, options, encode) {
+    const tokens = tokenize(input, false);
+    const parts = [];
+    const names = new Set();
+    const wildcard = segmentWildcard(options);
+    let pending = '';
+    let index = 0;
+    let nextNumericName = 0;
+
+    const tryConsume = (type) => {
+      const token = tokens[index];
+      if (token.type !== type) {
+        return null;
+      }
+      index += 1;
+      return token;
+    };
+    const consumeRequired = (type) => {
+      const token = tryConsume(type);
+      if (token === null) {
+        throw new TypeError(`Unexpected ${tokens[index].type} at ${tokens[index].index} in "${input}"`);
+      }
+      return token;
+    };
+    const consumeText = () => {
+      let text = '';
+      for (;;) {
+        const token = tryConsume('char') ?? tryConsume('escaped-char');
+        if (token === null) {
+          return text;
+        }
+        text += token.value;
+      }
+    };
+    const tryConsumeModifier = () => tryConsume('other-modifier') ?? tryConsume('asterisk');
+    const tryConsumeRegexpOrWildcard = (nameToken) => {
+      const token = tryConsume('regexp');
+      return token === null && nameToken === null ? tryConsume('asterisk') : token;
+    };
+    const flushPending = () => {
+      if (pending !== '') {
+        parts.push({
+          type: 'fixed-text', value: encode(pending), modifier: '', name: '', prefix: '', suffix: '',
+        });
+        pending = '';
+      }
+    };
+    const addPart = (prefix, nameToken, regexpToken, suffix, modifierToken) => {
+      const modifier = modifierToken === null ? '' : modifierToken.value;
+      if (nameToken === null && regexpToken === null && modifier === '') {
+        pending += prefix;
+        return;
+      }
+      flushPending();
+      if (nameToken === null && regexpToken === null) {
+        if (prefix !== '') {
+          parts.push({
+            type: 'fixed-text', value: encode(prefix), modifier, name: '', prefix: '', suffix: '',
+          });
+        }
+        return;
+      }
+      let value;
+      if (regexpToken === null) {
+        value = wildcard;
+      } else if (regexpToken.type === 'asterisk') {
+        value = FULL_WILDCARD;
+      } else {
+        value = regexpToken.value;
+      }
+      let type = 'regexp';
+      if (value === wildcard) {
+        type = 'segment-wildcard';
+        value = '';
+      } else if (value === FULL_WILDCARD) {
+        type = 'full-wildcard';
+        value = '';
+      }
+      let name;
+      if (nameToken !== null) {
+        name = nameToken.value;
+      } else {
+        name = String(nextNumericName);
+        nextNumericName += 1;
+      }
+      if (names.has(name)) {
+        throw new TypeError(`Duplicate name "${name}" in "${input}"`);
+      }
+      names.add(name);
+      parts.push({
+        type, value, modifier, name, prefix: encode(prefix), suffix: encode(suffix),
+      });
+    };
+
+    while (index < tokens.length) {
+      const charToken = tryConsume('char');
+      let nameToken = tryConsume('name');
+      let regexpToken = tryConsumeRegexpOrWildcard(nameToken);
+      if (nameToken !== null || regexpToken !== null) {
+        let prefix = charToken === null ? '' : charToken.value;
+        if (prefix !== '' && prefix !== options.prefix) {
+          pending += prefix;
+          prefix = '';
+        }
+        flushPending();
+        addPart(prefix, nameToken, regexpToken, '', tryConsumeModifier());
+        continue; // eslint-disable-line no-continue
+      }
+      const fixedToken = charToken ?? tryConsume('escaped-char');
+      if (fixedToken !== null) {
+        pending += fixedToken.value;
+        continue; // eslint-disable-line no-continue
+      }
+      if (tryConsume('open') !== null) {
+        const prefix = consumeText();
+        nameToken = tryConsume('name');
+        regexpToken = tryConsumeRegexpOrWildcard(nameToken);
+        const suffix = consumeText();
+        consumeRequired('close');
+        addPart(prefix, nameToken, regexpToken, suffix, tryConsumeModifier());
+        continue; // eslint-disable-line no-continue
+      }
+      flushPending();
+      consumeRequired('end');
+    }
+    return parts;
+  }
+
+  function regexpFor(parts, options) {
+    const wildcard = segmentWildcard(options);
+    let source = '^';
+    const names = [];
+    for (const part of parts) {
+      const { modifier } = part;
+      if (part.type === 'fixed-text') {
+        source += modifier === ''
+          ? escapeRegexp(part.value)
+          : `(?:${escapeRegexp(part.value)})${modifier}`;
+        continue; // eslint-disable-line no-continue
+      }
+      names.push(part.name);
+      let value = part.value;
+      if (part.type === 'segment-wildcard') {
+        value = wildcard;
+      } else if (part.type === 'full-wildcard') {
+        value = FULL_WILDCARD;
+      }
+      const prefix = escapeRegexp(part.prefix);
+      const suffix = escapeRegexp(part.suffix);
+      const repeated = modifier === '*' || modifier === '+';
+      if (prefix === '' && suffix === '') {
+        source += repeated ? `((?:${value})${modifier})` : `(${value})${modifier}`;
+      } else if (!repeated) {
+        source += `(?:${prefix}(${value})${suffix})${modifier}`;
+      } else {
+        source += `(?:${prefix}((?:${value})(?:${suffix}${prefix}(?:${value}))*)${suffix})`;
+        if (modifier === '*') {
+          source += '?';
+        }
+      }
+    }
+    source += '$';
+    return { source, names };
+  }
+
+  // The normalized pattern string of `parts`, which the getters return.
+  function patternStringFor(parts, options) {
+    const wildcard = segmentWildcard(options);
+    let result = '';
+    parts.forEach((part, i) => {
+      const previous = i > 0 ? parts[i - 1] : null;
+      const next = i < parts.length - 1 ? parts[i + 1] : null;
+      if (part.type === 'fixed-text') {
+        result += part.modifier === ''
+          ? escapePatternString(part.value)
+          : `{${escapePatternString(part.value)}}${part.modifier}`;
+        return;
+      }
+      const customName = !/^[0-9]/.test(part.name);
+      let needsGrouping = part.suffix !== ''
+        || (part.prefix !== '' && part.prefix !== options.prefix);
+      if (!needsGrouping && customName && part.type === 'segment-wildcard'
+        && part.modifier === '' && next !== null && next.prefix === '' && next.suffix === '') {
+        needsGrouping = next.type === 'fixed-text'
+          ? isNameCodePoint(String.fromCodePoint(next.value.codePointAt(0)), false)
+          : /^[0-9]/.test(next.name);
+      }
+      if (!needsGrouping && part.prefix === '' && previous !== null
+        && previous.type === 'fixed-text' && previous.value.endsWith(options.prefix)
+        && options.prefix !== '') {
+        needsGrouping = true;
+      }
+      if (needsGrouping) {
+        result += '{';
+      }
+      result += escapePatternString(part.prefix);
+      if (customName) {
+        result += `:${part.name}`;
+      }
+      if (part.type === 'regexp') {
+        result += `(${part.value})`;
+      } else if (part.type === 'segment-wildcard' && !customName) {
+        result += `(${wildcard})`;
+      } else if (part.type === 'full-wildcard') {
+        if (!customName && (previous === null || previous.type === 'fixed-text'
+          || previous.modifier !== '' || needsGrouping || part.prefix !== '')) {
+          result += '*';
+        } else {
+          result += `(${FULL_WILDCARD})`;
+        }
+      }
+      if (part.type === 'segment-wildcard' && customName && part.suffix !== ''
+        && isNameCodePoint(String.fromCodePoint(part.suffix.codePointAt(0)), false)) {
+        result += '\\';
+      }
+      result += escapePatternString(part.suffix);
+      if (needsGrouping) {
+        result += '}';
+      }
+      result += part.modifier;
+    });
+    return result;
+  }
+
+  function compileComponent(input, encode, options) {
+    const parts = parsePattern(input, options, encode);
+    const { source, names } = regexpFor(parts, options);
+    let regexp;
+    try {
+      regexp = new RegExp(source, options.ignoreCase ? 'ui' : 'u');
+    } catch (e) {
+      throw new TypeError(`Invalid pattern "${input}": ${e.message}`);
+    }
+    return {
+      pattern: patternStringFor(parts, options),
+      regexp,
+      names,
+      hasRegExpGroups: parts.some((part) => part.type === 'regexp'),
+    };
+  }
+
+  function matchesSpecialScheme(component) {
+    return Object.keys(SPECIAL_SCHEMES).some((scheme) => component.regexp.test(scheme));
+  }
+
+  // Canonicalization, through the URL parser
+
+  function invalid(component, value) {
+    return new TypeError(`Invalid ${component} "${value}"`);
+  }
+
+  function canonicalizeProtocol(value) {
+    if (value === '') {
+      return value;
+    }
+    const url = URL.parse(`${value}://dummy.test`);
+    if (url === null) {
+      throw invalid('protocol', value);
+    }
+    return url.protocol.slice(0, -1);
+  }
+
+  function canonicalizeUsername(value) {
+    if (value === '') {
+      return value;
+    }
+    const url = new URL('https://example.com');
+    url.username = value;
+    return url.username;
+  }
+
+  function canonicalizePassword(value) {
+    if (value === '') {
+      return value;
+    }
+    const url = new URL('https://example.com');
+    url.password = value;
+    return url.password;
+  }
+
+  function canonicalizeHostname(value) {
+    if (value === '') {
+      return value;
+    }
+    const url = /[\0\t\n\r #%/:<>?@[\]^\\|]/.test(value) ? null : URL.parse(`https://${value}/`);
+    if (url === null) {
+      throw invalid('hostname', value);
+    }
+    return url.hostname;
+  }
+
+  function canonicalizeIPv6Hostname(value) {
+    if (!/^[[\]0-9a-f:]*$/i.test(value)) {
+      throw invalid('hostname', value);
+    }
+    return value.toLowerCase();
+  }
+
+  function canonicalizePort(value, protocol = undefined) {
+    if (value === '') {
+      return value;
+    }
+    const url = /^[0-9]+$/.test(value)
+      ? URL.parse(`${protocol === undefined || protocol === '' ? 'fake' : protocol}://h:${value}`)
+      : null;
+    if (url === null) {
+      throw invalid('port', value);
+    }
+    return url.port;
+  }
+
+  function canonicalizePathname(value) {
+    if (value === '') {
+      return value;
+    }
+    // Relative segments are made absolute to be parsed, then made relative
+    // again.
+    const leadingSlash = value.startsWith('/');
+    const url = new URL('https://example.com');
+    url.pathname = leadingSlash ? value : `/-${value}`;
+    return leadingSlash ? url.pathname : url.pathname.slice(2);
+  }
+
+  function canonicalizeOpaquePathname(value) {
+    if (value === '') {
+      return value;
+    }
+    const url = URL.parse(`data:${value}`);
+    if (url === null) {
+      throw invalid('pathname', value);
+    }
+    return url.pathname;
+  }
+
+  function canonicalizeSearch(value) {
+    if (value === '') {
+      return value;
+    }
+    const url = new URL('https://example.com');
+    url.search = value;
+    return url.search.slice(1);
+  }
+
+  function canonicalizeHash(value) {
+    if (value === '') {
+      return value;
+    }
+    const url = new URL('https://example.com');
+    url.hash = value;
+    return url.hash.slice(1);
+  }
+
+  function isSpecial(protocol) {
+    return Object.prototype.hasOwnProperty.call(SPECIAL_SCHEMES, protocol);
+  }
+
+  function isAbsolutePathname(input, type) {
+    if (input.startsWith('/')) {
+      return true;
+    }
+    return type === 'pattern' && (input.startsWith('\\/') || input.startsWith('{/'));
+  }
+
+  // Turns a URLPatternInit into the components it has, filling in those
+  // it inherits from its base URL. When `type` is "url" they're
+  // canonicalized, and when it's "pattern" they're patterns.
+  function processInit(init, type, defaults) {
+    const result = { ...defaults };
+    const has = (...keys) => keys.some((key) => init[key] !== undefined);
+    const fromBase = (value) => (type === 'pattern' ? escapePatternString(value) : value);
+    const canonical = (canonicalize, value) => (type === 'pattern' ? value : canonicalize(value));
+
+    let base = null;
+    if (init.baseURL !== undefined) {
+      base = URL.parse(String(init.baseURL));
+      if (base === null) {
+        throw new TypeError(`Invalid base URL "${init.baseURL}"`);
+      }
+      if (!has('protocol')) {
+        result.protocol = fromBase(base.protocol.slice(0, -1));
+      }
+      if (type !== 'pattern' && !has('protocol', 'hostname', 'port', 'username')) {
+        result.username = fromBase(base.username);
+      }
+      if (type !== 'pattern' && !has('protocol', 'hostname', 'port', 'username', 'password')) {
+        result.password = fromBase(base.password);
+      }
+      if (!has('protocol', 'hostname')) {
+        result.hostname = fromBase(base.hostname);
+      }
+      if (!has('protocol', 'hostname', 'port')) {
+        result.port = fromBase(base.port);
+      }
+      if (!has('protocol', 'hostname', 'port', 'pathname')) {
+        result.pathname = fromBase(base.pathname);
+      }
+      if (!has('protocol', 'hostname', 'port', 'pathname', 'search')) {
+        result.search = fromBase(base.search.slice(1));
+      }
+      if (!has('protocol', 'hostname', 'port', 'pathname', 'search', 'hash')) {
+        result.hash = fromBase(base.hash.slice(1));
+      }
+    }
+
+    if (init.protocol !== undefined) {
+      const protocol = String(init.protocol).replace(/:$/, '');
+      result.protocol = canonical(canonicalizeProtocol, protocol);
+    }
+    if (init.username !== undefined) {
+      result.username = canonical(canonicalizeUsername, String(init.username));
+    }
+    if (init.password !== undefined) {
+      result.password = canonical(canonicalizePassword, String(init.password));
+    }
+    if (init.hostname !== undefined) {
+      result.hostname = canonical(canonicalizeHostname, String(init.hostname));
+    }
+    if (init.port !== undefined) {
+      const port = String(init.port);
+      result.port = type === 'pattern' ? port : canonicalizePort(port, result.protocol);
+    }
+    if (init.pathname !== undefined) {
+      let pathname = String(init.pathname);
+      // Relative pathnames are resolved against the base URL's, unless
+      // it's opaque.
+      if (base !== null && base.pathname.startsWith('/') && !isAbsolutePathname(pathname, type)) {
+        const basePathname = fromBase(base.pathname);
+        const slash = basePathname.lastIndexOf('/');
+        if (slash !== -1) {
+          pathname = basePathname.slice(0, slash + 1) + pathname;
+        }
+      }
+      if (type !== 'pattern') {
+        pathname = result.protocol === undefined || result.protocol === '' || isSpecial(result.protocol)
+          ? canonicalizePathname(pathname)
+          : canonicalizeOpaquePathname(pathname);
+      }
+      result.pathname = pathname;
+    }
+    if (init.search !== undefined) {
+      result.search = canonical(canonicalizeSearch, String(init.search).replace(/^\?/, ''));
+    }
+    if (init.hash !== undefined) {
+      result.hash = canonical(canonicalizeHash, String(init.hash).replace(/^#/, ''));
+    }
+    return result;
+  }
+
+  // Constructor string parser
+
+  // Splits a pattern string for a whole URL into its components.
+  function parseConstructorString(input) {
+    const tokens = tokenize(input, true);
+    const result = {};
+    let state = 'init';
+    let componentStart = 0;
+    let tokenIndex = 0;
+    let tokenIncrement = 1;
+    let groupDepth = 0;
+    let ipv6Depth = 0;
+    let protocolMatchesSpecialScheme = false;
+
+    const safeToken = (index) => tokens[Math.min(index, tokens.length - 1)];
+    const isChar = (index, value) => {
+      const token = safeToken(index);
+      return token.value === value
+        && (token.type === 'char' || token.type === 'escaped-char' || token.type === 'invalid-char');
+    };
+    const isSearchPrefix = () => {
+      if (isChar(tokenIndex, '?')) {
+        return true;
+      }
+      if (tokens[tokenIndex].value !== '?') {
+        return false;
+      }
+      // A `?` modifier isn't the start of the search, unless there's
+      // nothing it can modify.
+      if (tokenIndex === 0) {
+        return true;
+      }
+      const previous = safeToken(tokenIndex - 1);
+      return !['name', 'regexp', 'close', 'asterisk'].includes(previous.type);
+    };
+    const componentString = () => {
+      const start = safeToken(componentStart).index;
+      return input.slice(start, tokens[tokenIndex].index);
+    };
+    const rewind = (newState = state) => {
+      tokenIndex = componentStart;
+      tokenIncrement = 0;
+      state = newState;
+    };
+    const changeState = (newState, skip) => {
+      if (state !== 'init' && state !== 'authority' && state !== 'done') {
+        result[state] = componentString();
+      }
+      // Components between the ones the string has are empty.
+      if (state !== 'init' && newState !== 'done') {
+        const before = (states) => states.includes(state);
+        if (before(['protocol', 'authority', 'username', 'password'])
+          && ['port', 'pathname', 'search', 'hash'].includes(newState)
+          && result.hostname === undefined) {
+          result.hostname = '';
+        }
+        if (before(['protocol', 'authority', 'username', 'password', 'hostname', 'port'])
+          && ['search', 'hash'].includes(newState) && result.pathname === undefined) {
+          result.pathname = protocolMatchesSpecialScheme ? '/' : '';
+        }
+        if (before(['protocol', 'authority', 'username', 'password', 'hostname', 'port', 'pathname'])
+          && newState === 'hash' && result.search === undefined) {
+          result.search = '';
+        }
+      }
+      state = newState;
+      tokenIndex += skip;
+      componentStart = tokenIndex;
+      tokenIncrement = 0;
+    };
+
+    while (tokenIndex < tokens.length) {
+      tokenIncrement = 1;
+      const token = tokens[tokenIndex];
+      if (token.type === 'end') {
+        if (state === 'init') {
+          // Without a protocol, it's relative to the base URL.
+          rewind();
+          if (isChar(tokenIndex, '#')) {
+            changeState('hash', 1);
+          } else if (isSearchPrefix()) {
+            changeState('search', 1);
+          } else {
+            changeState('pathname', 0);
+          }
+          tokenIndex += tokenIncrement;
+          continue; // eslint-disable-line no-continue
+        }
+        if (state === 'authority') {
+          rewind('hostname');
+          tokenIndex += tokenIncrement;
+          continue; // eslint-disable-line no-continue
+        }
+        changeState('done', 0);
+        break;
+      }
+      if (token.type === 'open') {
+        groupDepth += 1;
+        tokenIndex += tokenIncrement;
+        continue; // eslint-disable-line no-continue
+      }
+      if (groupDepth > 0) {
+        if (token.type !== 'close') {
+          tokenIndex += tokenIncrement;
+          continue; // eslint-disable-line no-continue
+        }
+        groupDepth -= 1;
+      }
+      switch (state) {
+        case 'init':
+          if (isChar(tokenIndex, ':')) {
+            rewind('protocol');
+          }
+          break;
+        case 'protocol':
+          if (isChar(tokenIndex, ':')) {
+            const protocol = compileComponent(
+              componentString(),
+              canonicalizeProtocol,
+              DEFAULT_OPTIONS,
+            );
+            protocolMatchesSpecialScheme = matchesSpecialScheme(protocol);
+            if (isChar(tokenIndex + 1, '/') && isChar(tokenIndex + 2, '/')) {
+              changeState('authority', 3);
+            } else if (protocolMatchesSpecialScheme) {
+              changeState('authority', 1);
+            } else {
+              changeState('pathname', 1);
+            }
+          }
+          break;
+        case 'authority':
+          if (isChar(tokenIndex, '@')) {
+            rewind('username');
+          } else if (isChar(tokenIndex, '/') || isSearchPrefix() || isChar(tokenIndex, '#')) {
+            rewind('hostname');
+          }
+          break;
+        case 'username':
+          if (isChar(tokenIndex, ':')) {
+            changeState('password', 1);
+          } else if (isChar(tokenIndex, '@')) {
+            changeState('hostname', 1);
+          }
+          break;
+        case 'password':
+          if (isChar(tokenIndex, '@')) {
+            changeState('hostname', 1);
+          }
+          break;
+        case 'hostname':
+          if (isChar(tokenIndex, '[')) {
+            ipv6Depth += 1;
+          } else if (isChar(tokenIndex, ']')) {
+            ipv6Depth -= 1;
+          } else if (isChar(tokenIndex, ':') && ipv6Depth === 0) {
+            changeState('port', 1);
+          } else if (isChar(tokenIndex, '/')) {
+            changeState('pathname', 0);
+          } else if (isSearchPrefix()) {
+            changeState('search', 1);
+          } else if (isChar(tokenIndex, '#')) {
+            changeState('hash', 1);
+          }
+          break;
+        case 'port':
+          if (isChar(tokenIndex, '/')) {
+            changeState('pathname', 0);
+          } else if (isSearchPrefix()) {
+            changeState('search', 1);
+          } else if (isChar(tokenIndex, '#')) {
+            changeState('hash', 1);
+          }
+          break;
+        case 'pathname':
+          if (isSearchPrefix()) {
+            changeState('search', 1);
+          } else if (isChar(tokenIndex, '#')) {
+            changeState('hash', 1);
+          }
+          break;
+        case 'search':
+          if (isChar(tokenIndex, '#')) {
+            changeState('hash', 1);
+          }
+          break;
+        default:
+          break;
+      }
+      tokenIndex += tokenIncrement;
+    }
+    if (result.hostname !== undefined && result.port === undefined) {
+      result.port = '';
+    }
+    return result;
+  }
+
+  function isIPv6Pattern(hostname) {
+    return /^(\[|\{\[|\\\[)/.test(hostname);
+  }
+
+  class URLPattern {
+    #components;
+
+    constructor(input = {}, baseURL = undefined, options = undefined) {
+      // The base URL is optional, and options may come second.
+      let base = baseURL;
+      let settings = options;
+      if (typeof baseURL === 'object' && baseURL !== null && options === undefined) {
+        base = undefined;
+        settings = baseURL;
+      }
+      const ignoreCase = Boolean(settings?.ignoreCase);
+
+      let init;
+      if (typeof input === 'string') {
+        init = parseConstructorString(input);
+        if (base === undefined && init.protocol === undefined) {
+          throw new TypeError(`Relative pattern "${input}" needs a base URL`);
+        }
+        if (base !== undefined) {
+          init.baseURL = String(base);
+        }
+      } else if (typeof input === 'object' && input !== null) {
+        if (base !== undefined) {
+          throw new TypeError('A base URL can only be given with a string pattern');
+        }
+        init = input;
+      } else {
+        throw new TypeError('input must be a string or an object');
+      }
+
+      const processed = processInit(init, 'pattern', {});
+      for (const name of COMPONENTS) {
+        if (processed[name] === undefined) {
+          processed[name] = '*';
+        }
+      }
+      if (isSpecial(processed.protocol) && processed.port === SPECIAL_SCHEMES[processed.protocol]) {
+        processed.port = '';
+      }
+
+      const components = {};
+      components.protocol = compileComponent(
+        processed.protocol,
+        canonicalizeProtocol,
+        DEFAULT_OPTIONS,
+      );
+      components.username = compileComponent(
+        processed.username,
+        canonicalizeUsername,
+        DEFAULT_OPTIONS,
+      );
+      components.password = compileComponent(
+        processed.password,
+        canonicalizePassword,
+        DEFAULT_OPTIONS,
+      );
+      components.hostname = compileComponent(
+        processed.hostname,
+        isIPv6Pattern(processed.hostname) ? canonicalizeIPv6Hostname : canonicalizeHostname,
+        HOSTNAME_OPTIONS,
+      );
+      components.port = compileComponent(processed.port, canonicalizePort, DEFAULT_OPTIONS);
+      const compileOptions = { ...DEFAULT_OPTIONS, ignoreCase };
+      components.pathname = matchesSpecialScheme(components.protocol)
+        ? compileComponent(
+          processed.pathname,
+          canonicalizePathname,
+          { ...PATHNAME_OPTIONS, ignoreCase },
+        )
+        : compileComponent(processed.pathname, canonicalizeOpaquePathname, compileOptions);
+      components.search = compileComponent(processed.search, canonicalizeSearch, compileOptions);
+      components.hash = compileComponent(processed.hash, canonicalizeHash, compileOptions);
+      this.#components = components;
+    }
+
+    static {
+      for (const name of COMPONENTS) {
+        Object.defineProperty(URLPattern.prototype, name, {
+          get() {
+            return this.#components[name].pattern;
+          },
+          enumerable: true,
+          configurable: true,
+        });
+      }
+    }
+
+    get hasRegExpGroups() {
+      return COMPONENTS.some((name) => this.#components[name].hasRegExpGroups);
+    }
+
+    test(input = {}, baseURL = undefined) {
+      return this.exec(input, baseURL) !== null;
+    }
+
+    // The components of `input` with the groups each matched, or null if
+    // it doesn't match.
+    exec(input = {}, baseURL = undefined) {
+      const inputs = baseURL === undefined ? [input] : [input, baseURL];
+      let values;
+      if (typeof input === 'object' && input !== null) {
+        if (baseURL !== undefined) {
+          throw new TypeError('A base URL can only be given with a string input');
+        }
+        const empty = Object.fromEntries(COMPONENTS.map((name) => [name, '']));
+        try {
+          values = processInit(input, 'url', empty);
+        } catch {
+          return null;
+        }
+      } else {
+        const url = URL.parse(String(input), baseURL === undefined ? undefined : String(baseURL));
+        if (url === null) {
+          return null;
+        }
+        values = {
+          protocol: url.protocol.slice(0, -1),
+          username: url.username,
+          password: url.password,
+          hostname: url.hostname,
+          port: url.port,
+          pathname: url.pathname,
+          search: url.search.slice(1),
+          hash: url.hash.slice(1),
+        };
+      }
+
+      const result = { inputs };
+      for (const name of COMPONENTS) {
+        const component = this.#components[name];
+        const match = component.regexp.exec(values[name]);
+        if (match === null) {
+          return null;
+        }
+        const groups = {};
+        component.names.forEach((group, i) => {
+          groups[group] = match[i + 1];
+        });
+        result[name] = { input: values[name], groups };
+      }
+      return result;
+    }
+  }
+
+  Object.defineProperty(URLPattern.prototype, Symbol.toStringTag, {
+    value: 'URLPattern',
+    configurable: true,
+  });
+
+  globalThis.URLPattern = URLPattern;
+});
@@ -0,0 +1,634 @@
+// WASI
+// https://github.com/WebAssembly/WASI/blob/main/legacy/preview1/docs.md
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(({
+  fsOpen, fsClose, fsRead, fsWrite, fsSeek, fsStat, fsFstat, fsReadDir,
+  fsMkdir, fsRemove, fsRename, fsRealPath,
+}) => {
+  const ERRNO_SUCCESS = 0;
+  const ERRNO_2BIG = 1;
+  const ERRNO_ACCES = 2;
+  const ERRNO_BADF = 8;
+  const ERRNO_EXIST = 20;
+  const ERRNO_INVAL = 28;
+  const ERRNO_IO = 29;
+  const ERRNO_ISDIR = 31;
+  const ERRNO_NOENT = 44;
+  const ERRNO_NOSYS = 52;
+  const ERRNO_NOTDIR = 54;
+  const ERRNO_NOTEMPTY = 55;
+  const ERRNO_NOTSUP = 58;
+  const ERRNO_PIPE = 64;
+  const ERRNO_SPIPE = 70;
+  const ERRNO_NOTCAPABLE = 76;
+
+  const ERROR_CODES = {
+    E2BIG: ERRNO_2BIG,
+    EACCES: ERRNO_ACCES,
+    EEXIST: ERRNO_EXIST,
+    EINVAL: ERRNO_INVAL,
+    EISDIR: ERRNO_ISDIR,
+    ENOENT: ERRNO_NOENT,
+    ENOTDIR: ERRNO_NOTDIR,
+    ENOTEMPTY: ERRNO_NOTEMPTY,
+    ENOTSUP: ERRNO_NOTSUP,
+    EPIPE: ERRNO_PIPE,
+  };
+
+  const FILETYPE_CHARACTER_DEVICE = 2;
+  const FILETYPE_DIRECTORY = 3;
+  const FILETYPE_REGULAR_FILE = 4;
+  const FILETYPE_SYMBOLIC_LINK = 7;
+
+  const CLOCK_REALTIME = 0;
+  const CLOCK_MONOTONIC = 1;
+  const CLOCK_PROCESS_CPUTIME_ID = 2;
+  const CLOCK_THREAD_CPUTIME_ID = 3;
+
+  const FDFLAGS_APPEND = 1;
+  const LOOKUPFLAGS_SYMLINK_FOLLOW = 1;
+  const OFLAGS_CREAT = 1;
+  const OFLAGS_DIRECTORY = 2;
+  const OFLAGS_EXCL = 4;
+  const OFLAGS_TRUNC = 8;
+  const RIGHTS_FD_READ = 1n << 1n;
+  const RIGHTS_FD_WRITE = 1n << 6n;
+  const RIGHTS_ALL = (1n << 30n) - 1n;
+
+  const encoder = new TextEncoder();
+  const decoder = new TextDecoder();
+
+  class ExitStatus {
+    constructor(code) {
+      this.code = code;
+    }
+  }
+
+  function toErrno(e) {
+    if (e instanceof ExitStatus) {
+      throw e;
+    }
+    if (e && typeof e.code === 'string' && e.code in ERROR_CODES) {
+      return ERROR_CODES[e.code];
+    }
+    return ERRNO_IO;
+  }
+
+  function nanoseconds(ms) {
+    return ms == null ? 0n : BigInt(Math.round(ms * 1e6));
+  }
+
+  function filetype(stat) {
+    if (stat.isDirectory) return FILETYPE_DIRECTORY;
+    if (stat.isSymlink) return FILETYPE_SYMBOLIC_LINK;
+    if (stat.isFile) return FILETYPE_REGULAR_FILE;
+    return 0;
+  }
+
+  // Each syscall returns an errno. Exceptions thrown by the host bindings
+  // are translated, except for `proc_exit` which unwinds to `start()`.
+  function syscall(fn) {
+    return (...args) => {
+      try {
+        return fn(...args);
+      } catch (e) {
+        return toErrno(e);
+      }
+    };
+  }
+
+  // Preopened directories are real paths, so the only one with a trailing
+  // slash is `/`.
+  const joinPath = (root, segments) =>
+    segments.length === 0 ? root : `${root.replace(/\/$/, '')}/${segments.join('/')}`;
+  const parentPath = (path) => path.slice(0, path.lastIndexOf('/')) || '/';
+  const isWithin = (root, path) =>
+    path === root || path.startsWith(root.endsWith('/') ? root : `${root}/`);
+
+  class WASI {
+    #args;
+    #env;
+    #returnOnExit;
+    #fds = new Map();
+    #memory = null;
+    #started = false;
+
+    constructor(options = {}) {
+      if (options === null || typeof options !== 'object') {
+        throw new TypeError('options must be an object');
+      }
+      const { args = [], env = {}, preopens = {}, returnOnExit = true } = options;
+      if (!Array.isArray(args)) {
+        throw new TypeError('options.args must be an array');
+      }
+      this.#args = args.map(String);
+      this.#env = Object.entries(env).map(([k, v]) => `${k}=${v}`);
+      this.#returnOnExit = Boolean(returnOnExit);
+
+      this.#fds.set(0, { type: 'stdio', rid: 0 });
+      this.#fds.set(1, { type: 'stdio', rid: 1 });
+      this.#fds.set(2, { type: 'stdio', rid: 2 });
+      let fd = 3;
+      for (const [guestPath, hostPath] of Object.entries(preopens)) {
+        const root = fsRealPath(String(hostPath));
+        this.#fds.set(fd++, { type: 'dir', root, path: root, preopen: guestPath });
+      }
+
+      this.wasiImport = this.#createImports();
+    }
+
+    getImportObject() {
+      return { wasi_snapshot_preview1: this.wasiImport };
+    }
+
+    // Run a command module, returning its exit code.
+    start(instance) {
+      const { _start: entry } = this.#prepare(instance, '_start');
+      try {
+        entry();
+      } catch (e) {
+        if (e instanceof ExitStatus) {
+          if (this.#returnOnExit) {
+            return e.code;
+          }
+          throw new Error(`WASI exited with code ${e.code}`);
+        }
+        throw e;
+      }
+      return 0;
+    }
+
+    // Set up a reactor module.
+    initialize(instance) {
+      const { _initialize: init } = this.#prepare(instance, '_initialize', true);
+      if (init) {
+        init();
+      }
+    }
+
+    #prepare(instance, entry, optional = false) {
+      if (this.#started) {
+        throw new Error('WASI instance has already started');
+      }
+      if (!(instance instanceof WebAssembly.Instance)) {
+        throw new TypeError('instance must be a WebAssembly.Instance');
+      }
+      const { exports } = instance;
+      if (!(exports.memory instanceof WebAssembly.Memory)) {
+        throw new TypeError('instance must export a memory named "memory"');
+      }
+      if (!optional && typeof exports[entry] !== 'function') {
+        throw new TypeError(`instance must export a function named "${entry}"`);
+      }
+      this.#started = true;
+      this.#memory = exports.memory;
+      return exports;
+    }
+
+    #view() {
+      return new DataView(this.#memory.buffer);
+    }
+
+    #bytes(ptr, len) {
+      return new Uint8Array(this.#memory.buffer, ptr, len);
+    }
+
+    #string(ptr, len) {
+      return decoder.decode(this.#bytes(ptr, len));
+    }
+
+    #iovecs(iovs, iovsLen) {
+      const view = this.#view();
+      const buffers = [];
+      for (let i = 0; i < iovsLen; i++) {
+        const ptr = view.getUint32(iovs + i * 8, true);
+        const len = view.getUint32(iovs + i * 8 + 4, true);
+        buffers.push(this.#bytes(ptr, len));
+      }
+      return buffers;
+    }
+
+    // Resolve `path` relative to the directory `fd`, refusing to leave the
+    // preopened directory it came from. Unless `follow` is set, a symlink
+    // at `path` itself is left alone, and only its parent is checked.
+    #resolve(fd, pathPtr, pathLen, follow = true) {
+      const dir = this.#fds.get(fd);
+      if (dir === undefined) {
+        return [ERRNO_BADF];
+      }
+      if (dir.type !== 'dir') {
+        return [ERRNO_NOTDIR];
+      }
+      const path = this.#string(pathPtr, pathLen);
+      if (path.startsWith('/')) {
+        return [ERRNO_NOTCAPABLE];
+      }
+
+      const segments = dir.path.slice(dir.root.length).split('/').filter(Boolean);
+      for (const segment of path.split('/')) {
+        if (segment === '' || segment === '.') {
+          continue;
+        }
+        if (segment === '..') {
+          if (segments.length === 0) {
+            return [ERRNO_NOTCAPABLE];
+          }
+          segments.pop();
+        } else {
+          segments.push(segment);
+        }
+      }
+      const resolved = joinPath(dir.root, segments);
+
+      // Symlinks may still point outside of the preopen. For a path that
+      // doesn't exist yet, creating it would follow those in its nearest
+      // existing ancestor.
+      const checked = follow || segments.length === 0 ? resolved : parentPath(resolved);
+      const real = this.#realPath(checked, dir.root);
+      if (real === null || !isWithin(dir.root, real)) {
+        return [ERRNO_NOTCAPABLE];
+      }
+      return [ERRNO_SUCCESS, resolved, dir.root];
+    }
+
+    // The real path of `path`, or else of its nearest existing ancestor up
+    // to `root`, or null if there's none. Dangling symlinks, which would be
+    // followed when creating their target, have none.
+    #realPath(path, root) {
+      let existing = path;
+      for (;;) {
+        try {
+          return fsRealPath(existing);
+        } catch {
+          // Not a missing path, but a symlink to one.
+          try {
+            fsStat(existing, false);
+            return null;
+          } catch {
+            // Keep looking up.
+          }
+        }
+        if (existing === root || existing === '/') {
+          return null;
+        }
+        existing = parentPath(existing);
+      }
+    }
+
+    #writeFilestat(ptr, stat) {
+      const view = this.#view();
+      view.setBigUint64(ptr, BigInt(stat.dev || 0), true);
+      view.setBigUint64(ptr + 8, BigInt(stat.ino || 0), true);
+      view.setUint8(ptr + 16, filetype(stat));
+      view.setBigUint64(ptr + 24, BigInt(stat.nlink || 1), true);
+      view.setBigUint64(ptr + 32, BigInt(stat.size), true);
+      view.setBigUint64(ptr + 40, nanoseconds(stat.atime), true);
+      view.setBigUint64(ptr + 48, nanoseconds(stat.mtime), true);
+      view.setBigUint64(ptr + 56, nanoseconds(stat.mtime), true);
+    }
+
+    #writeStrings(strings, ptrs, buf) {
+      const view = this.#view();
+      for (const s of strings) {
+        const bytes = encoder.encode(`${s}\0`);
+        view.setUint32(ptrs, buf, true);
+        this.#bytes(buf, bytes.length).set(bytes);
+        ptrs += 4;
+        buf += bytes.length;
+      }
+      return ERRNO_SUCCESS;
+    }
+
+    #writeSizes(strings, countPtr, sizePtr) {
+      const view = this.#view();
+      const size = strings.reduce((n, s) => n + encoder.encode(s).length + 1, 0);
+      view.setUint32(countPtr, strings.length, true);
+      view.setUint32(sizePtr, size, true);
+      return ERRNO_SUCCESS;
+    }
+
+    #createImports() {
+      const nosys = () => ERRNO_NOSYS;
+      const imports = {
+        args_get: (argv, argvBuf) => this.#writeStrings(this.#args, argv, argvBuf),
+        args_sizes_get: (argc, argvBufSize) => this.#writeSizes(this.#args, argc, argvBufSize),
+        environ_get: (environ, environBuf) => this.#writeStrings(this.#env, environ, environBuf),
+        environ_sizes_get: (count, size) => this.#writeSizes(this.#env, count, size),
+
+        clock_res_get: (id, resolution) => {
+          if (id < CLOCK_REALTIME || id > CLOCK_THREAD_CPUTIME_ID) {
+            return ERRNO_INVAL;
+          }
+          this.#view().setBigUint64(resolution, id === CLOCK_REALTIME ? 1000000n : 1000n, true);
+          return ERRNO_SUCCESS;
+        },
+        clock_time_get: (id, _precision, time) => {
+          let ns;
+          if (id === CLOCK_REALTIME) {
+            ns = nanoseconds(Date.now());
+          } else if (id === CLOCK_MONOTONIC || id === CLOCK_PROCESS_CPUTIME_ID ||
+                     id === CLOCK_THREAD_CPUTIME_ID) {
+            ns = nanoseconds(performance.now());
+          } else {
+            return ERRNO_INVAL;
+          }
+          this.#view().setBigUint64(time, ns, true);
+          return ERRNO_SUCCESS;
+        },
+
+        random_get: (buf, bufLen) => {
+          crypto.getRandomValues(this.#bytes(buf, bufLen));
+          return ERRNO_SUCCESS;
+        },
+
+        proc_exit: (code) => {
+          throw new ExitStatus(code);
+        },
+        proc_raise: nosys,
+        sched_yield: () => ERRNO_SUCCESS,
+        poll_oneoff: nosys,
+
+        fd_advise: () => ERRNO_SUCCESS,
+        fd_allocate: nosys,
+        fd_close: (fd) => {
+          const entry = this.#fds.get(fd);
+          if (entry === undefined) {
+            return ERRNO_BADF;
+          }
+          if (entry.rid !== undefined && entry.type !== 'stdio') {
+            fsClose(entry.rid);
+          }
+          this.#fds.delete(fd);
+          return ERRNO_SUCCESS;
+        },
+        fd_datasync: () => ERRNO_SUCCESS,
+        fd_sync: () => ERRNO_SUCCESS,
+
+        fd_fdstat_get: (fd, ptr) => {
+          const entry = this.#fds.get(fd);
+          if (entry === undefined) {
+            return ERRNO_BADF;
+          }
+          const view = this.#view();
+          let type = FILETYPE_CHARACTER_DEVICE;
+          if (entry.type === 'dir') {
+            type = FILETYPE_DIRECTORY;
+          } else if (entry.type === 'file') {
+            type = FILETYPE_REGULAR_FILE;
+          }
+          view.setUint8(ptr, type);
+          view.setUint16(ptr + 2, entry.append ? FDFLAGS_APPEND : 0, true);
+          view.setBigUint64(ptr + 8, RIGHTS_ALL, true);
+          view.setBigUint64(ptr + 16, RIGHTS_ALL, true);
+          return ERRNO_SUCCESS;
+        },
+        fd_fdstat_set_flags: nosys,
+        fd_fdstat_set_rights: () => ERRNO_SUCCESS,
+
+        fd_filestat_get: (fd, ptr) => {
+          const entry = this.#fds.get(fd);
+          if (entry === undefined) {
+            return ERRNO_BADF;
+          }
+          if (entry.type === 'stdio') {
+            this.#writeFilestat(ptr, { size: 0 });
+            this.#view().setUint8(ptr + 16, FILETYPE_CHARACTER_DEVICE);
+          } else if (entry.type === 'dir') {
+            this.#writeFilestat(ptr, fsStat(entry.path));
+          } else {
+            this.#writeFilestat(ptr, fsFstat(entry.rid));
+          }
+          return ERRNO_SUCCESS;
+        },
+        fd_filestat_set_size: nosys,
+        fd_filestat_set_times: nosys,
+
+        fd_prestat_get: (fd, ptr) => {
+          const entry = this.#fds.get(fd);
+          if (entry === undefined || entry.preopen === undefined) {
+            return ERRNO_BADF;
+          }
+          const view = this.#view();
+          view.setUint8(ptr, 0);
+          view.setUint32(ptr + 4, encoder.encode(entry.preopen).length, true);
+          return ERRNO_SUCCESS;
+        },
+        fd_prestat_dir_name: (fd, pathPtr, pathLen) => {
+          const entry = this.#fds.get(fd);
+          if (entry === undefined || entry.preopen === undefined) {
+            return ERRNO_BADF;
+          }
+          const name = encoder.encode(entry.preopen);
+          this.#bytes(pathPtr, pathLen).set(name.subarray(0, pathLen));
+          return ERRNO_SUCCESS;
+        },
+
+        fd_pread: nosys,
+        fd_pwrite: nosys,
+        fd_read: (fd, iovs, iovsLen, nread) => {
+          const entry = this.#fds.get(fd);
+          if (entry === undefined || entry.type === 'dir') {
+            return ERRNO_BADF;
+          }
+          let total = 0;
+          for (const buffer of this.#iovecs(iovs, iovsLen)) {
+            const n = fsRead(entry.rid, buffer);
+            total += n;
+            if (n < buffer.length) {
+              break;
+            }
+          }
+          this.#view().setUint32(nread, total, true);
+          return ERRNO_SUCCESS;
+        },
+        fd_write: (fd, iovs, iovsLen, nwritten) => {
+          const entry = this.#fds.get(fd);
+          if (entry === undefined || entry.type === 'dir') {
+            return ERRNO_BADF;
+          }
+          let total = 0;
+          for (const buffer of this.#iovecs(iovs, iovsLen)) {
+            total += fsWrite(entry.rid, buffer);
+          }
+          this.#view().setUint32(nwritten, total, true);
+          return ERRNO_SUCCESS;
+        },
+
+        fd_readdir: (fd, buf, bufLen, cookie, bufUsed) => {
+          const entry = this.#fds.get(fd);
+          if (entry === undefined) {
+            return ERRNO_BADF;
+          }
+          if (entry.type !== 'dir') {
+            return ERRNO_NOTDIR;
+          }
+          const view = this.#view();
+          const entries = fsReadDir(entry.path);
+          let offset = 0;
+          for (let i = Number(cookie); i < entries.length; i++) {
+            const name = encoder.encode(entries[i].name);
+            const header = new DataView(new ArrayBuffer(24));
+            header.setBigUint64(0, BigInt(i + 1), true);
+            header.setBigUint64(8, 0n, true);
+            header.setUint32(16, name.length, true);
+            header.setUint8(20, filetype(entries[i]));
+
+            const dirent = new Uint8Array(24 + name.length);
+            dirent.set(new Uint8Array(header.buffer));
+            dirent.set(name, 24);
+            // A truncated entry tells the caller to retry with a bigger
+            // buffer.
+            const n = Math.min(dirent.length, bufLen - offset);
+            this.#bytes(buf + offset, n).set(dirent.subarray(0, n));
+            offset += n;
+            if (offset === bufLen) {
+              break;
+            }
+          }
+          view.setUint32(bufUsed, offset, true);
+          return ERRNO_SUCCESS;
+        },
+
+        fd_renumber: nosys,
+        fd_seek: (fd, offset, whence, newOffset) => {
+          const entry = this.#fds.get(fd);
+          if (entry === undefined) {
+            return ERRNO_BADF;
+          }
+          if (entry.type !== 'file') {
+            return ERRNO_SPIPE;
+          }
+          const position = fsSeek(entry.rid, Number(offset), whence);
+          this.#view().setBigUint64(newOffset, BigInt(position), true);
+          return ERRNO_SUCCESS;
+        },
+        fd_tell: (fd, offset) => {
+          const entry = this.#fds.get(fd);
+          if (entry === undefined) {
+            return ERRNO_BADF;
+          }
+          if (entry.type !== 'file') {
+            return ERRNO_SPIPE;
+          }
+          const position = fsSeek(entry.rid, 0, 1);
+          this.#view().setBigUint64(offset, BigInt(position), true);
+          return ERRNO_SUCCESS;
+        },
+
+        path_create_directory: (fd, pathPtr, pathLen) => {
+          const [errno, path] = this.#resolve(fd, pathPtr, pathLen);
+          if (errno !== ERRNO_SUCCESS) {
+            return errno;
+          }
+          fsMkdir(path, false);
+          return ERRNO_SUCCESS;
+        },
+        path_filestat_get: (fd, flags, pathPtr, pathLen, ptr) => {
+          const follow = (flags & LOOKUPFLAGS_SYMLINK_FOLLOW) !== 0;
+          const [errno, path] = this.#resolve(fd, pathPtr, pathLen, follow);
+          if (errno !== ERRNO_SUCCESS) {
+            return errno;
+          }
+          this.#writeFilestat(ptr, fsStat(path, follow));
+          return ERRNO_SUCCESS;
+        },
+        path_filestat_set_times: nosys,
+        path_link: nosys,
+        path_open: (dirfd, _dirflags, pathPtr, pathLen, oflags, rightsBase, _rightsInheriting,
+          fdflags, fdPtr) => {
+          const [errno, path, root] = this.#resolve(dirfd, pathPtr, pathLen);
+          if (errno !== ERRNO_SUCCESS) {
+            return errno;
+          }
+
+          let stat = null;
+          try {
+            stat = fsStat(path);
+          } catch (e) {
+            if ((oflags & OFLAGS_CREAT) === 0) {
+              throw e;
+            }
+          }
+
+          const fd = Math.max(...this.#fds.keys()) + 1;
+          if ((oflags & OFLAGS_DIRECTORY) !== 0 || (stat !== null && stat.isDirectory)) {
+            if (stat === null) {
+              return ERRNO_NOENT;
+            }
+            if (!stat.isDirectory) {
+              return ERRNO_NOTDIR;
+            }
+            this.#fds.set(fd, { type: 'dir', root, path });
+          } else {
+            const append = (fdflags & FDFLAGS_APPEND) !== 0;
+            const write = append || (BigInt(rightsBase) & RIGHTS_FD_WRITE) !== 0n ||
+              (oflags & (OFLAGS_CREAT | OFLAGS_TRUNC)) !== 0;
+            const read = (BigInt(rightsBase) & RIGHTS_FD_READ) !== 0n || !write;
+            const rid = fsOpen(path, {
+              read,
+              write: write && !append,
+              append,
+              truncate: (oflags & OFLAGS_TRUNC) !== 0,
+              create: (oflags & OFLAGS_CREAT) !== 0,
+              createNew: (oflags & OFLAGS_CREAT) !== 0 && (oflags & OFLAGS_EXCL) !== 0,
+            });
+            this.#fds.set(fd, { type: 'file', rid, path, append });
+          }
+          this.#view().setUint32(fdPtr, fd, true);
+          return ERRNO_SUCCESS;
+        },
+        path_readlink: nosys,
+        path_remove_directory: (fd, pathPtr, pathLen) => {
+          const [errno, path] = this.#resolve(fd, pathPtr, pathLen, false);
+          if (errno !== ERRNO_SUCCESS) {
+            return errno;
+          }
+          if (!fsStat(path, false).isDirectory) {
+            return ERRNO_NOTDIR;
+          }
+          fsRemove(path, false);
+          return ERRNO_SUCCESS;
+        },
+        path_rename: (oldFd, oldPtr, oldLen, newFd, newPtr, newLen) => {
+          const [oldErrno, oldPath] = this.#resolve(oldFd, oldPtr, oldLen, false);
+          if (oldErrno !== ERRNO_SUCCESS) {
+            return oldErrno;
+          }
+          const [newErrno, newPath] = this.#resolve(newFd, newPtr, newLen, false);
+          if (newErrno !== ERRNO_SUCCESS) {
+            return newErrno;
+          }
+          fsRename(oldPath, newPath);
+          return ERRNO_SUCCESS;
+        },
+        path_symlink: nosys,
+        path_unlink_file: (fd, pathPtr, pathLen) => {
+          const [errno, path] = this.#resolve(fd, pathPtr, pathLen, false);
+          if (errno !== ERRNO_SUCCESS) {
+            return errno;
+          }
+          if (fsStat(path, false).isDirectory) {
+            return ERRNO_ISDIR;
+          }
+          fsRemove(path, false);
+          return ERRNO_SUCCESS;
+        },
+
+        sock_accept: nosys,
+        sock_recv: nosys,
+        sock_send: nosys,
+        sock_shutdown: nosys,
+      };
+
+      for (const name of Object.keys(imports)) {
+        imports[name] = syscall(imports[name]);
+      }
+      return imports;
+    }
+  }
+
+  globalThis.WASI = WASI;
+});
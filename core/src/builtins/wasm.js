@@ -0,0 +1,34 @@
+// WebAssembly streaming compilation
+// https://webassembly.github.io/spec/web-api/#streaming-modules
+
+'use strict';
+
+// eslint-disable-next-line no-unused-expressions
+(() => {
+  async function responseBytes(source) {
+    const response = await source;
+    if (response === null || typeof response !== 'object' ||
+        typeof response.arrayBuffer !== 'function') {
+      throw new TypeError('WebAssembly: Argument 0 must be a Response or a Promise resolving to one');
+    }
+
+    const contentType = response.headers && response.headers.get('content-type');
+    const mimeType = contentType ? contentType.split(';')[0].trim().toLowerCase() : '';
+    if (mimeType !== 'application/wasm') {
+      throw new TypeError(`WebAssembly: Response has unsupported MIME type '${contentType}', expected 'application/wasm'`);
+    }
+    if (!response.ok) {
+      throw new TypeError(`WebAssembly: HTTP status code is not ok: ${response.status}`);
+    }
+
+    return response.arrayBuffer();
+  }
+
+  WebAssembly.compileStreaming = async (source) => {
+    return WebAssembly.compile(await responseBytes(source));
+  };
+
+  WebAssembly.instantiateStreaming = async (source, importObject) => {
+    return WebAssembly.instantiate(await responseBytes(source), importObject);
+  };
+});
@@ -0,0 +1,276 @@
+// Module graphs gathered into a single blob, so they can be run without
+// the files they were read from.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::path::{Component, Path, PathBuf};
+
+const MAGIC: &[u8] = b"jstime-bundle\0";
+// Bump whenever the layout written by `Bundle::to_bytes` changes.
+const VERSION: u32 = 2;
+
+/// Options for `JSTime::bundle`.
+#[derive(Default)]
+pub struct BundleOptions {
+    /// Include V8 code cache data for each JS module, to skip compiling
+    /// them from source when the bundle is run by the same version of
+    /// jstime. Defaults to `false`.
+    pub code_cache: bool,
+}
+
+/// The module graph of an entry module, as gathered by `JSTime::bundle`.
+/// Set `Options::bundle` to load modules from it instead of the file
+/// system.
+pub struct Bundle {
+    entry: String,
+    // Module sources, JSON and WebAssembly modules, and the source maps
+    // they reference, keyed by absolute path.
+    files: BTreeMap<String, Vec<u8>>,
+    code_cache: BTreeMap<String, Vec<u8>>,
+}
+
+impl Bundle {
+    /// Path of the entry module, to pass to `JSTime::import`.
+    pub fn entry(&self) -> &str {
+        &self.entry
+    }
+
+    pub(crate) fn read(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path).map(Vec::as_slice)
+    }
+
+    pub(crate) fn code_cache(&self, path: &str) -> Option<&[u8]> {
+        self.code_cache.get(path).map(Vec::as_slice)
+    }
+
+    /// Serialize the bundle, to be read back by `Bundle::from_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        write_bytes(&mut bytes, self.entry.as_bytes());
+        write_map(&mut bytes, &self.files);
+        write_map(&mut bytes, &self.code_cache);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Bundle, String> {
+        let mut reader = Reader(bytes);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("Not a jstime bundle".to_owned());
+        }
+        let version = u32::from_le_bytes(<[u8; 4]>::try_from(reader.take(4)?).unwrap());
+        if version != VERSION {
+            return Err(format!("Unsupported jstime bundle version {}", version));
+        }
+        let entry = reader.string()?;
+        let files = reader.map()?;
+        let code_cache = reader.map()?;
+        Ok(Bundle {
+            entry,
+            files,
+            code_cache,
+        })
+    }
+}
+
+fn write_map(out: &mut Vec<u8>, map: &BTreeMap<String, Vec<u8>>) {
+    out.extend_from_slice(&(map.len() as u64).to_le_bytes());
+    for (path, contents) in map {
+        write_bytes(out, path.as_bytes());
+        write_bytes(out, contents);
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < len {
+            return Err("Truncated jstime bundle".to_owned());
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(
+            <[u8; 8]>::try_from(self.take(8)?).unwrap(),
+        ))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = usize::try_from(self.u64()?).map_err(|e| e.to_string())?;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|e| e.to_string())
+    }
+
+    fn map(&mut self) -> Result<BTreeMap<String, Vec<u8>>, String> {
+        let mut map = BTreeMap::new();
+        for _ in 0..self.u64()? {
+            let path = self.string()?;
+            let contents = self.bytes()?.to_vec();
+            map.insert(path, contents);
+        }
+        Ok(map)
+    }
+}
+
+/// Resolve `requested` against `referrer` as the module loader does, but
+/// without touching the file system, so modules resolve the same way
+/// whether they're read from disk or from a bundle. Specifiers that don't
+/// resolve to a file are kept as they are, and aren't found.
+pub(crate) fn normalize_path(referrer: &str, requested: &str) -> String {
+    match crate::module::resolve_specifier(referrer, requested) {
+        Some(path) => normalize(&path),
+        None => requested.to_owned(),
+    }
+}
+
+/// Remove `.` and `..` components from `path`.
+pub(crate) fn normalize(path: &Path) -> String {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized.to_string_lossy().into_owned()
+}
+
+/// Read `filename` and every module it imports, directly or not, from disk.
+/// Specifiers are resolved relative to the importing module, as the loader
+/// resolves them. jstime has no import maps, so there are none to gather.
+pub(crate) fn gather(
+    scope: &mut v8::HandleScope,
+    filename: &str,
+    options: &BundleOptions,
+) -> Result<Bundle, String> {
+    let entry = std::env::current_dir()
+        .and_then(|cwd| cwd.join(filename).canonicalize())
+        .map_err(|e| format!("{}: {}", filename, e))?
+        .to_string_lossy()
+        .into_owned();
+
+    let mut files = BTreeMap::new();
+    let mut code_cache = BTreeMap::new();
+    let mut pending = vec![entry.clone()];
+    while let Some(path) = pending.pop() {
+        if files.contains_key(&path) {
+            continue;
+        }
+        let contents = std::fs::read(&path).map_err(|e| format!("{}: {}", path, e))?;
+        let specifiers = if path.ends_with(".wasm") {
+            wasm_imports(scope, &path, &contents)?
+        } else if path.ends_with(".json") {
+            Vec::new()
+        } else {
+            let source = String::from_utf8_lossy(&contents);
+            if let Some(map) = source_map_path(&path, &source) {
+                if let Ok(map_contents) = std::fs::read(&map) {
+                    files.insert(map, map_contents);
+                }
+            }
+            let (specifiers, data) = module_requests(scope, &path, &source, options)?;
+            if let Some(data) = data {
+                code_cache.insert(path.clone(), data);
+            }
+            specifiers
+        };
+        for specifier in specifiers {
+            if !specifier.starts_with("jstime:") {
+                pending.push(normalize_path(&path, &specifier));
+            }
+        }
+        files.insert(path, contents);
+    }
+    Ok(Bundle {
+        entry,
+        files,
+        code_cache,
+    })
+}
+
+/// The path of a source map file referenced by `source`, if any.
+fn source_map_path(path: &str, source: &str) -> Option<String> {
+    let reference = sourcemap::locate_sourcemap_reference_slice(source.as_bytes()).ok()??;
+    let url = reference.get_url();
+    if url.starts_with("data:") {
+        return None;
+    }
+    Some(normalize_path(path, url))
+}
+
+/// The specifiers imported by the module in `source`, and its code cache
+/// data if requested.
+fn module_requests(
+    scope: &mut v8::HandleScope,
+    path: &str,
+    source: &str,
+    options: &BundleOptions,
+) -> Result<(Vec<String>, Option<Vec<u8>>), String> {
+    let scope = &mut v8::TryCatch::new(scope);
+    let name = v8::String::new(scope, path).unwrap();
+    let origin = crate::js_loading::create_script_origin(scope, name, None, true);
+    let code = v8::String::new(scope, source).unwrap();
+    let source = v8::script_compiler::Source::new(code, Some(&origin));
+    let module = match v8::script_compiler::compile_module(scope, source) {
+        Some(module) => module,
+        None => {
+            let exception = scope.exception().unwrap();
+            return Err(format!(
+                "{}: {}",
+                path,
+                crate::error_to_string(scope, exception)
+            ));
+        }
+    };
+
+    let requests = module.get_module_requests();
+    let mut specifiers = Vec::new();
+    for i in 0..requests.length() {
+        let request = requests.get(scope, i).unwrap();
+        let request = v8::Local::<v8::ModuleRequest>::try_from(request).unwrap();
+        specifiers.push(request.get_specifier().to_rust_string_lossy(scope));
+    }
+    let data = if options.code_cache {
+        module
+            .get_unbound_module_script(scope)
+            .create_code_cache()
+            .map(|data| data.to_vec())
+    } else {
+        None
+    };
+    Ok((specifiers, data))
+}
+
+/// The specifiers a WebAssembly module imports from.
+fn wasm_imports(
+    scope: &mut v8::HandleScope,
+    path: &str,
+    contents: &[u8],
+) -> Result<Vec<String>, String> {
+    let scope = &mut v8::TryCatch::new(scope);
+    match crate::wasm::import_specifiers(scope, contents.to_vec()) {
+        Some(specifiers) => Ok(specifiers),
+        None => {
+            let error = match scope.exception() {
+                Some(exception) => crate::error_to_string(scope, exception),
+                None => "Invalid WebAssembly module".to_owned(),
+            };
+            Err(format!("{}: {}", path, error))
+        }
+    }
+}
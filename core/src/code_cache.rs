@@ -0,0 +1,81 @@
+// On-disk cache of V8 code cache data for compiled modules.
+
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+lazy_static! {
+    // V8 rejects code cache produced under different flags, so they are
+    // part of the cache key.
+    static ref V8_FLAGS: Mutex<String> = Mutex::new(String::new());
+}
+
+pub(crate) fn set_v8_flags(flags: &[String]) {
+    *V8_FLAGS.lock().unwrap() = flags.join(" ");
+}
+
+pub(crate) struct CodeCache {
+    dir: Option<PathBuf>,
+}
+
+impl CodeCache {
+    pub(crate) fn new(dir: Option<PathBuf>) -> Self {
+        Self { dir }
+    }
+
+    pub(crate) fn enabled(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    /// Cached data for `filepath`, if it was produced from the same source
+    /// by the same V8 version and flags.
+    pub(crate) fn get(&self, filepath: &str, source: &str) -> Option<Vec<u8>> {
+        let path = self.entry_path(filepath, source)?;
+        std::fs::read(path).ok()
+    }
+
+    /// Store `data` for `filepath`. Failures are ignored, the cache is only
+    /// an optimization.
+    pub(crate) fn set(&self, filepath: &str, source: &str, data: &[u8]) {
+        let path = match self.entry_path(filepath, source) {
+            Some(path) => path,
+            None => return,
+        };
+        if let Some(dir) = path.parent() {
+            if std::fs::create_dir_all(dir).is_err() {
+                return;
+            }
+        }
+        // Write to a temporary file first so a concurrent reader never
+        // sees a partially written entry.
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        if std::fs::write(&tmp, data).is_ok() && std::fs::rename(&tmp, &path).is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+    }
+
+    /// Remove the entry for `filepath`, e.g. after V8 rejected it.
+    pub(crate) fn remove(&self, filepath: &str, source: &str) {
+        if let Some(path) = self.entry_path(filepath, source) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    fn entry_path(&self, filepath: &str, source: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        let mut hasher = Sha256::new();
+        hasher.update(v8::V8::get_version());
+        hasher.update([0]);
+        hasher.update(V8_FLAGS.lock().unwrap().as_bytes());
+        hasher.update([0]);
+        hasher.update(filepath);
+        hasher.update([0]);
+        hasher.update(Sha256::digest(source));
+        let key: String = hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Some(dir.join(format!("{}.bin", key)))
+    }
+}
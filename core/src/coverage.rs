@@ -0,0 +1,352 @@
+//! Reports of the code coverage collected with `JSTime::start_coverage`.
+
+use crate::source_map::SourceMaps;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Line and function coverage per file, merged from any number of
+/// coverage dumps. Files with a source map are reported as their original
+/// sources.
+#[derive(Default)]
+pub struct Report {
+    files: BTreeMap<String, FileCoverage>,
+}
+
+#[derive(Default)]
+struct FileCoverage {
+    // Execution counts of lines with code on them, by 1-based line number.
+    lines: BTreeMap<u32, u64>,
+    // Call counts of functions, by 1-based line number and name.
+    functions: BTreeMap<(u32, String), u64>,
+}
+
+impl Report {
+    pub fn new() -> Report {
+        Report::default()
+    }
+
+    /// Merge every `.json` coverage dump in `dir`.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Report, String> {
+        let dir = dir.as_ref();
+        let mut paths = std::fs::read_dir(dir)
+            .map_err(|e| format!("{}: {}", dir.display(), e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension() == Some("json".as_ref()))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let mut report = Report::new();
+        for path in paths {
+            let coverage =
+                std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            report
+                .add(&coverage)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(report)
+    }
+
+    /// Merge a coverage dump as returned by `JSTime::stop_coverage`. The
+    /// covered files are read from disk to map offsets to lines.
+    pub fn add(&mut self, coverage: &str) -> Result<(), String> {
+        let coverage: serde_json::Value =
+            serde_json::from_str(coverage).map_err(|e| e.to_string())?;
+        let scripts = coverage["result"]
+            .as_array()
+            .ok_or("missing coverage result")?;
+
+        for script in scripts {
+            let (url, functions) = match (script["url"].as_str(), script["functions"].as_array()) {
+                (Some(url), Some(functions)) => (url, functions),
+                _ => return Err("malformed script coverage".to_owned()),
+            };
+            // Files that have since been removed can't be reported on.
+            let source = match std::fs::read_to_string(url) {
+                Ok(source) => source,
+                Err(_) => continue,
+            };
+            self.add_script(url, &source, functions);
+        }
+        Ok(())
+    }
+
+    fn add_script(&mut self, url: &str, source: &str, functions: &[serde_json::Value]) {
+        let mut source_maps = SourceMaps::new(true);
+        source_maps.register(url, source, |path| std::fs::read(path).ok());
+        let mapped = source_maps.contains(url);
+        let to_original = |line: u32, column: u32| {
+            if mapped {
+                source_maps.apply_to_line(url, line, column)
+            } else {
+                Some((url.to_owned(), line))
+            }
+        };
+
+        let script = Script::new(source, functions);
+
+        // A line is only as covered as its least covered code, also when
+        // several generated lines map to it.
+        let mut lines = BTreeMap::<(String, u32), u64>::new();
+        for (line, column, count) in script.positions() {
+            if let Some(key) = to_original(line, column) {
+                let min = lines.entry(key).or_insert(count);
+                *min = (*min).min(count);
+            }
+        }
+        for ((file, line), count) in lines {
+            let file = self.files.entry(file).or_default();
+            *file.lines.entry(line).or_insert(0) += count;
+        }
+
+        for (offset, name, count) in script.functions() {
+            let (line, column) = script.position_of(offset);
+            if let Some((file, line)) = to_original(line, column) {
+                let file = self.files.entry(file).or_default();
+                *file.functions.entry((line, name)).or_insert(0) += count;
+            }
+        }
+    }
+
+    /// The report in the LCOV tracefile format.
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        for (path, file) in &self.files {
+            let _ = writeln!(lcov, "TN:\nSF:{}", path);
+            for (line, name) in file.functions.keys() {
+                let _ = writeln!(lcov, "FN:{},{}", line, name);
+            }
+            for ((_, name), count) in &file.functions {
+                let _ = writeln!(lcov, "FNDA:{},{}", count, name);
+            }
+            let (hit, found) = file.function_totals();
+            let _ = writeln!(lcov, "FNF:{}\nFNH:{}", found, hit);
+            for (line, count) in &file.lines {
+                let _ = writeln!(lcov, "DA:{},{}", line, count);
+            }
+            let (hit, found) = file.line_totals();
+            let _ = writeln!(lcov, "LF:{}\nLH:{}\nend_of_record", found, hit);
+        }
+        lcov
+    }
+
+    /// A table of line and function coverage percentages per file, with
+    /// paths relative to the current directory where possible.
+    pub fn summary(&self) -> String {
+        let cwd = std::env::current_dir().ok();
+        let mut rows = Vec::new();
+        let (mut lines_hit, mut lines_found) = (0, 0);
+        let (mut functions_hit, mut functions_found) = (0, 0);
+        for (path, file) in &self.files {
+            let path = match &cwd {
+                Some(cwd) => Path::new(path)
+                    .strip_prefix(cwd)
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_else(|_| path.clone()),
+                None => path.clone(),
+            };
+            let (hit, found) = file.line_totals();
+            lines_hit += hit;
+            lines_found += found;
+            let lines = percentage(hit, found);
+            let (hit, found) = file.function_totals();
+            functions_hit += hit;
+            functions_found += found;
+            rows.push([path, lines, percentage(hit, found)]);
+        }
+        rows.push([
+            "All files".to_owned(),
+            percentage(lines_hit, lines_found),
+            percentage(functions_hit, functions_found),
+        ]);
+
+        let header = [
+            "File".to_owned(),
+            "Lines".to_owned(),
+            "Functions".to_owned(),
+        ];
+        let mut widths = [0; 3];
+        for row in std::iter::once(&header).chain(&rows) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let mut summary = String::new();
+        let total = rows.len() - 1;
+        for (i, row) in std::iter::once(&header).chain(&rows).enumerate() {
+            if i == 1 || i == total + 1 {
+                let rule = widths
+                    .iter()
+                    .map(|width| "-".repeat(*width))
+                    .collect::<Vec<_>>();
+                let _ = writeln!(summary, "{}", rule.join("-|-"));
+            }
+            let _ = writeln!(
+                summary,
+                "{:<w0$} | {:>w1$} | {:>w2$}",
+                row[0],
+                row[1],
+                row[2],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2]
+            );
+        }
+        summary
+    }
+}
+
+impl FileCoverage {
+    fn line_totals(&self) -> (usize, usize) {
+        let hit = self.lines.values().filter(|count| **count > 0).count();
+        (hit, self.lines.len())
+    }
+
+    fn function_totals(&self) -> (usize, usize) {
+        let hit = self.functions.values().filter(|count| **count > 0).count();
+        (hit, self.functions.len())
+    }
+}
+
+fn percentage(hit: usize, found: usize) -> String {
+    if found == 0 {
+        return "-".to_owned();
+    }
+    format!(
+        "{:.2}% ({}/{})",
+        hit as f64 * 100.0 / found as f64,
+        hit,
+        found
+    )
+}
+
+/// V8 block coverage of one script. Offsets are in UTF-16 code units.
+struct Script<'a> {
+    source: &'a str,
+    functions: &'a [serde_json::Value],
+    // The count of the innermost range around each code unit.
+    counts: Vec<u64>,
+    // Offsets at which each line starts.
+    line_starts: Vec<usize>,
+}
+
+impl<'a> Script<'a> {
+    fn new(source: &'a str, functions: &'a [serde_json::Value]) -> Self {
+        let len = source.encode_utf16().count();
+        let mut line_starts = vec![0];
+        let mut offset = 0;
+        for c in source.chars() {
+            offset += c.len_utf16();
+            if c == '\n' {
+                line_starts.push(offset);
+            }
+        }
+
+        // Ranges nest, so painting outer ones before inner ones leaves each
+        // offset with the count of its innermost range.
+        let mut ranges = functions
+            .iter()
+            .filter_map(|function| function["ranges"].as_array())
+            .flatten()
+            .filter_map(|range| {
+                let start = range["startOffset"].as_u64()? as usize;
+                let end = range["endOffset"].as_u64()? as usize;
+                Some((start.min(len), end.min(len), range["count"].as_u64()?))
+            })
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|(start, end, _)| (*start, std::cmp::Reverse(*end)));
+        let mut counts = vec![0; len];
+        for (start, end, count) in ranges {
+            for c in &mut counts[start..end] {
+                *c = count;
+            }
+        }
+
+        Script {
+            source,
+            functions,
+            counts,
+            line_starts,
+        }
+    }
+
+    /// The 1-based line, column and count of every character that isn't
+    /// whitespace or part of a comment starting a line, so that lines with
+    /// nothing but comments aren't counted.
+    fn positions(&self) -> Vec<(u32, u32, u64)> {
+        let chars = self.source.chars().collect::<Vec<_>>();
+        let mut positions = Vec::new();
+        let (mut line, mut column, mut offset) = (1, 1, 0);
+        // Only comments that start a line are recognized, as telling them
+        // apart from `//` and `/*` in strings and regexps takes a parser.
+        let (mut line_start, mut line_comment, mut block_comment) = (true, false, None);
+        for (i, &c) in chars.iter().enumerate() {
+            if c == '\n' {
+                line += 1;
+                column = 1;
+                line_start = true;
+                line_comment = false;
+            } else {
+                if line_start && !line_comment && block_comment.is_none() && c == '/' {
+                    match chars.get(i + 1) {
+                        Some('/') => line_comment = true,
+                        Some('*') => block_comment = Some(i),
+                        _ => {}
+                    }
+                }
+                if !c.is_whitespace() {
+                    line_start = false;
+                    if !line_comment && block_comment.is_none() {
+                        positions.push((line, column, self.counts[offset]));
+                    }
+                }
+                // The `*` that opened a block comment doesn't close it.
+                if let Some(start) = block_comment {
+                    if c == '/' && i >= start + 3 && chars[i - 1] == '*' {
+                        block_comment = None;
+                    }
+                }
+                column += c.len_utf16() as u32;
+            }
+            offset += c.len_utf16();
+        }
+        positions
+    }
+
+    /// The offset, name and call count of every function, except the
+    /// top-level script itself.
+    fn functions(&self) -> Vec<(usize, String, u64)> {
+        let mut functions = Vec::new();
+        for (i, function) in self.functions.iter().enumerate() {
+            let range = &function["ranges"][0];
+            let (offset, count) = match (range["startOffset"].as_u64(), range["count"].as_u64()) {
+                (Some(offset), Some(count)) => (offset as usize, count),
+                _ => continue,
+            };
+            let name = function["functionName"].as_str().unwrap_or("");
+            if i == 0 && name.is_empty() && offset == 0 {
+                continue;
+            }
+            let name = if name.is_empty() {
+                "(anonymous)".to_owned()
+            } else {
+                name.to_owned()
+            };
+            functions.push((offset, name, count));
+        }
+        functions
+    }
+
+    /// The 1-based line and column of `offset`.
+    fn position_of(&self, offset: usize) -> (u32, u32) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        (
+            line as u32 + 1,
+            (offset - self.line_starts[line]) as u32 + 1,
+        )
+    }
+}
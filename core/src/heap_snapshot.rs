@@ -0,0 +1,58 @@
+// Heap snapshots in the `.heapsnapshot` format loaded by Chrome DevTools.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub(crate) fn write(isolate: &mut v8::Isolate, path: &Path) -> io::Result<()> {
+    let mut file = io::BufWriter::new(std::fs::File::create(path)?);
+    let mut result = Ok(());
+    isolate.take_heap_snapshot(|chunk| match file.write_all(chunk) {
+        Ok(()) => true,
+        Err(e) => {
+            result = Err(e);
+            false
+        }
+    });
+    result?;
+    file.flush()
+}
+
+// Paths of requested heap snapshots, stored in an isolate slot.
+#[derive(Clone, Default)]
+pub(crate) struct Requests(Arc<Mutex<Vec<PathBuf>>>);
+
+/// Write the heap snapshots requested through `HeapSnapshotRequester`s.
+pub(crate) fn write_requested(isolate: &mut v8::Isolate) {
+    let paths = match isolate.get_slot::<Requests>() {
+        Some(requests) => std::mem::take(&mut *requests.0.lock().unwrap()),
+        None => return,
+    };
+    for path in paths {
+        if let Err(e) = write(isolate, &path) {
+            eprintln!("Failed to write heap snapshot {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Requests heap snapshots of a `JSTime` instance from any thread, e.g.
+/// from a signal handler.
+#[derive(Clone)]
+pub struct HeapSnapshotRequester {
+    pub(crate) isolate: v8::IsolateHandle,
+    pub(crate) requests: Requests,
+}
+
+impl HeapSnapshotRequester {
+    /// Write a heap snapshot to `path` as soon as possible: while JS is
+    /// running, or otherwise the next time the event loop comes around.
+    pub fn request(&self, path: PathBuf) {
+        self.requests.0.lock().unwrap().push(path);
+        self.isolate
+            .request_interrupt(interrupt, std::ptr::null_mut());
+    }
+}
+
+extern "C" fn interrupt(isolate: &mut v8::Isolate, _data: *mut std::ffi::c_void) {
+    write_requested(isolate);
+}
@@ -0,0 +1,337 @@
+// Chrome DevTools Protocol support through the V8 inspector.
+// https://v8.dev/docs/inspector
+
+mod server;
+
+use server::{Event, Server};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use v8::inspector::{
+    ChannelBase, ChannelImpl, StringBuffer, StringView, V8Inspector, V8InspectorClientBase,
+    V8InspectorClientImpl, V8InspectorSession,
+};
+
+const CONTEXT_GROUP_ID: i32 = 1;
+
+/// The V8 inspector of an isolate, with a server that DevTools clients
+/// connect to.
+///
+/// Messages from clients are only dispatched when the inspector is polled,
+/// or while execution is paused on a breakpoint.
+pub(crate) struct Inspector {
+    client: V8InspectorClientBase,
+    // Sessions must be dropped before the V8Inspector they belong to, and
+    // that before the server, so clients see their sessions close.
+    sessions: RefCell<HashMap<u32, Box<Session>>>,
+    v8_inspector: RefCell<Option<v8::UniqueRef<V8Inspector>>>,
+    events: Receiver<Event>,
+    _server: Option<Server>,
+    paused: Cell<bool>,
+    waiting_for_debugger: Cell<bool>,
+    // Sessions can't be dropped while a message is being dispatched to
+    // them, so disconnects during dispatch are handled afterwards.
+    dispatch_depth: Cell<u32>,
+    disconnected: RefCell<Vec<u32>>,
+    next_local_session_id: Cell<u32>,
+}
+
+impl Inspector {
+    /// Create an inspector for `context`, and if `addr` is given, start
+    /// listening for DevTools clients on it.
+    pub(crate) fn new(
+        scope: &mut v8::HandleScope,
+        context: v8::Local<v8::Context>,
+        addr: Option<SocketAddr>,
+    ) -> std::io::Result<Rc<Inspector>> {
+        let (sender, events) = mpsc::channel();
+        let server = match addr {
+            Some(addr) => {
+                let id = target_id();
+                let (server, addr) = Server::start(addr, id.clone(), sender)?;
+                eprintln!("Debugger listening on ws://{}/{}", addr, id);
+                eprintln!("Visit chrome://inspect to connect to the debugger.");
+                Some(server)
+            }
+            None => None,
+        };
+
+        let mut inspector = Rc::new(Inspector {
+            client: V8InspectorClientBase::new::<Inspector>(),
+            sessions: RefCell::new(HashMap::new()),
+            v8_inspector: RefCell::new(None),
+            events,
+            _server: server,
+            paused: Cell::new(false),
+            waiting_for_debugger: Cell::new(false),
+            dispatch_depth: Cell::new(0),
+            disconnected: RefCell::new(Vec::new()),
+            next_local_session_id: Cell::new(u32::MAX),
+        });
+
+        // V8 keeps a pointer to the client, which the Rc keeps in place.
+        let client = Rc::get_mut(&mut inspector).unwrap();
+        let mut v8_inspector = V8Inspector::create(scope, client);
+        let name = StringView::from(&b"jstime"[..]);
+        v8_inspector.context_created(context, CONTEXT_GROUP_ID, name);
+        *inspector.v8_inspector.borrow_mut() = Some(v8_inspector);
+
+        Ok(inspector)
+    }
+
+    /// Connect a session that lives on the isolate thread, for using
+    /// inspector domains such as `Profiler` from jstime itself.
+    pub(crate) fn local_session(self: &Rc<Self>) -> LocalSession {
+        // Server sessions count up from 1, local ones down from the top.
+        let id = self.next_local_session_id.get();
+        self.next_local_session_id.set(id - 1);
+        let (sender, receiver) = mpsc::channel();
+        self.connect(id, sender);
+        LocalSession {
+            inspector: self.clone(),
+            id,
+            receiver,
+            next_call_id: Cell::new(1),
+        }
+    }
+
+    /// Handle messages that arrived since the last poll.
+    pub(crate) fn poll(&self) {
+        while let Ok(event) = self.events.try_recv() {
+            self.handle(event);
+        }
+    }
+
+    /// Block until a client sends `Runtime.runIfWaitingForDebugger`, then
+    /// pause on the next statement.
+    pub(crate) fn wait_for_debugger(&self) {
+        self.waiting_for_debugger.set(true);
+        while self.waiting_for_debugger.get() {
+            match self.events.recv() {
+                Ok(event) => self.handle(event),
+                Err(_) => return,
+            }
+        }
+
+        for session in self.sessions.borrow_mut().values_mut() {
+            if let Some(v8_session) = session.v8_session.as_mut() {
+                let reason = StringView::from(&b"Break on start"[..]);
+                v8_session.schedule_pause_on_next_statement(reason, StringView::empty());
+            }
+        }
+    }
+
+    fn handle(&self, event: Event) {
+        match event {
+            Event::Connect(id, sender) => self.connect(id, sender),
+            Event::Message(id, message) => self.dispatch(id, &message),
+            Event::Disconnect(id) => self.disconnect(id),
+        }
+    }
+
+    fn disconnect(&self, id: u32) {
+        if self.dispatch_depth.get() == 0 {
+            self.sessions.borrow_mut().remove(&id);
+        } else {
+            self.disconnected.borrow_mut().push(id);
+        }
+    }
+
+    fn connect(&self, id: u32, sender: Sender<String>) {
+        let mut session = Box::new(Session {
+            channel: ChannelBase::new::<Session>(),
+            v8_session: None,
+            sender,
+        });
+        let v8_session = match self.v8_inspector.borrow_mut().as_mut() {
+            Some(v8_inspector) => {
+                v8_inspector.connect(CONTEXT_GROUP_ID, &mut *session, StringView::empty())
+            }
+            None => return,
+        };
+        session.v8_session = Some(v8_session);
+        self.sessions.borrow_mut().insert(id, session);
+    }
+
+    fn dispatch(&self, id: u32, message: &str) {
+        let session = match self.sessions.borrow_mut().get_mut(&id) {
+            Some(session) => &mut **session as *mut Session,
+            None => return,
+        };
+
+        // Dispatching can run JS that hits a breakpoint, which dispatches
+        // further messages from within `run_message_loop_on_pause`. The
+        // session stays alive until the outermost dispatch returns.
+        self.dispatch_depth.set(self.dispatch_depth.get() + 1);
+        if let Some(v8_session) = unsafe { (*session).v8_session.as_mut() } {
+            v8_session.dispatch_protocol_message(StringView::from(message.as_bytes()));
+        }
+        self.dispatch_depth.set(self.dispatch_depth.get() - 1);
+
+        if self.dispatch_depth.get() == 0 {
+            let disconnected = std::mem::take(&mut *self.disconnected.borrow_mut());
+            let mut sessions = self.sessions.borrow_mut();
+            for id in disconnected {
+                sessions.remove(&id);
+            }
+        }
+    }
+
+    fn has_sessions(&self) -> bool {
+        let disconnected = self.disconnected.borrow();
+        self.sessions
+            .borrow()
+            .keys()
+            .any(|id| !disconnected.contains(id))
+    }
+}
+
+impl V8InspectorClientImpl for Inspector {
+    fn base(&self) -> &V8InspectorClientBase {
+        &self.client
+    }
+
+    fn base_mut(&mut self) -> &mut V8InspectorClientBase {
+        &mut self.client
+    }
+
+    fn run_message_loop_on_pause(&mut self, _context_group_id: i32) {
+        self.paused.set(true);
+        while self.paused.get() {
+            match self.events.recv() {
+                Ok(event) => self.handle(event),
+                Err(_) => break,
+            }
+            // Nobody is left to resume execution.
+            if !self.has_sessions() {
+                break;
+            }
+        }
+        self.paused.set(false);
+    }
+
+    fn quit_message_loop_on_pause(&mut self) {
+        self.paused.set(false);
+    }
+
+    fn run_if_waiting_for_debugger(&mut self, _context_group_id: i32) {
+        self.waiting_for_debugger.set(false);
+    }
+}
+
+/// A connected DevTools client.
+struct Session {
+    channel: ChannelBase,
+    v8_session: Option<v8::UniqueRef<V8InspectorSession>>,
+    sender: Sender<String>,
+}
+
+impl Session {
+    fn send(&self, message: v8::UniquePtr<StringBuffer>) {
+        let message = message.unwrap().string().to_string();
+        // The client may be gone already, its disconnect is handled when
+        // the inspector is next polled.
+        let _ = self.sender.send(message);
+    }
+}
+
+impl ChannelImpl for Session {
+    fn base(&self) -> &ChannelBase {
+        &self.channel
+    }
+
+    fn base_mut(&mut self) -> &mut ChannelBase {
+        &mut self.channel
+    }
+
+    fn send_response(&mut self, _call_id: i32, message: v8::UniquePtr<StringBuffer>) {
+        self.send(message);
+    }
+
+    fn send_notification(&mut self, message: v8::UniquePtr<StringBuffer>) {
+        self.send(message);
+    }
+
+    fn flush_protocol_notifications(&mut self) {}
+}
+
+/// A session connected through `Inspector::local_session`.
+pub(crate) struct LocalSession {
+    inspector: Rc<Inspector>,
+    id: u32,
+    receiver: Receiver<String>,
+    next_call_id: Cell<u32>,
+}
+
+impl LocalSession {
+    /// Call an inspector protocol method, returning its result.
+    pub(crate) fn post(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let call_id = self.next_call_id.get();
+        self.next_call_id.set(call_id + 1);
+        let message = serde_json::json!({ "id": call_id, "method": method, "params": params });
+        self.inspector.dispatch(self.id, &message.to_string());
+
+        // Responses are sent while the message is dispatched, notifications
+        // sent along the way are of no interest.
+        while let Ok(message) = self.receiver.try_recv() {
+            let mut message: serde_json::Value = match serde_json::from_str(&message) {
+                Ok(message) => message,
+                Err(_) => continue,
+            };
+            if message["id"] != call_id {
+                continue;
+            }
+            return match message["error"]["message"].as_str() {
+                Some(error) => Err(format!("{}: {}", method, error)),
+                None => Ok(message["result"].take()),
+            };
+        }
+        Err(format!("{}: no response", method))
+    }
+}
+
+impl Drop for LocalSession {
+    fn drop(&mut self) {
+        self.inspector.disconnect(self.id);
+    }
+}
+
+/// The isolate's inspector, created without a server if there isn't one
+/// yet.
+pub(crate) fn get_or_create(scope: &mut v8::HandleScope) -> Rc<Inspector> {
+    let state = crate::IsolateState::get(scope);
+    let inspector = state.borrow().inspector.clone();
+    match inspector {
+        Some(inspector) => inspector,
+        None => {
+            let context = scope.get_current_context();
+            // Without a server there's nothing that can fail.
+            let inspector = Inspector::new(scope, context, None).unwrap();
+            state.borrow_mut().inspector = Some(inspector.clone());
+            inspector
+        }
+    }
+}
+
+/// A random UUID identifying the inspected target.
+fn target_id() -> String {
+    use rand::Rng;
+    let mut bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
@@ -0,0 +1,288 @@
+// HTTP and WebSocket endpoints for Chrome DevTools Protocol clients.
+// https://chromedevtools.github.io/devtools-protocol/#endpoints
+
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender, TryRecvError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tungstenite::{Message, WebSocket};
+
+// How long the server and connection threads block before checking
+// whether there's something else to do.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Sent from the server to the isolate thread.
+pub(crate) enum Event {
+    /// A client connected, messages for it go through the sender.
+    Connect(u32, Sender<String>),
+    Message(u32, String),
+    Disconnect(u32),
+}
+
+pub(crate) struct Server {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Listen on `addr`, reporting clients of the target `id` to `events`.
+    pub(crate) fn start(
+        addr: SocketAddr,
+        id: String,
+        events: Sender<Event>,
+    ) -> io::Result<(Server, SocketAddr)> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let stop = stop.clone();
+            thread::spawn(move || accept_loop(listener, addr, id, events, stop))
+        };
+
+        Ok((
+            Server {
+                stop,
+                thread: Some(thread),
+            },
+            addr,
+        ))
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn accept_loop(
+    listener: TcpListener,
+    addr: SocketAddr,
+    id: String,
+    events: Sender<Event>,
+    stop: Arc<AtomicBool>,
+) {
+    let mut connections = Vec::new();
+    let mut next_session_id = 0;
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                next_session_id += 1;
+                let session_id = next_session_id;
+                let id = id.clone();
+                let events = events.clone();
+                connections.push(thread::spawn(move || {
+                    let _ = handle_connection(stream, addr, &id, session_id, events);
+                }));
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(_) => break,
+        }
+    }
+    // Sessions are closed by the inspector before the server stops, which
+    // ends their threads.
+    drop(events);
+    for connection in connections {
+        let _ = connection.join();
+    }
+}
+
+struct Request {
+    path: String,
+    host: Option<String>,
+    upgrade: bool,
+    // Length of the request line and headers.
+    len: usize,
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    id: &str,
+    session_id: u32,
+    events: Sender<Event>,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    let request = match peek_request(&stream)? {
+        Some(request) => request,
+        None => return respond(&mut stream, "400 Bad Request", ""),
+    };
+    // Like Node, so pages can't reach the server through a domain that
+    // was rebound to its address.
+    if !request.host.as_deref().map_or(true, is_allowed_host) {
+        return respond(&mut stream, "400 Bad Request", "");
+    }
+
+    if request.upgrade {
+        if request.path.trim_start_matches('/') != id {
+            return respond(&mut stream, "404 Not Found", "");
+        }
+        // The handshake reads the request itself, which is why it was
+        // only peeked at.
+        if let Ok(ws) = tungstenite::accept(stream) {
+            run_session(ws, session_id, events);
+        }
+        return Ok(());
+    }
+
+    let mut head = vec![0; request.len];
+    stream.read_exact(&mut head)?;
+    let host = request.host.unwrap_or_else(|| addr.to_string());
+    match request.path.as_str() {
+        "/json" | "/json/list" => {
+            let targets = serde_json::json!([{
+                "description": "jstime instance",
+                "devtoolsFrontendUrl": format!(
+                    "devtools://devtools/bundled/js_app.html?experiments=true&v8only=true&ws={}/{}",
+                    host, id
+                ),
+                "faviconUrl": "https://nodejs.org/static/images/favicons/favicon.ico",
+                "id": id,
+                "title": format!("jstime[{}]", std::process::id()),
+                "type": "node",
+                "url": "",
+                "webSocketDebuggerUrl": format!("ws://{}/{}", host, id),
+            }]);
+            respond(&mut stream, "200 OK", &targets.to_string())
+        }
+        "/json/version" => {
+            let version = serde_json::json!({
+                "Browser": format!("jstime/{}", env!("CARGO_PKG_VERSION")),
+                "Protocol-Version": "1.3",
+                "V8-Version": v8::V8::get_version(),
+            });
+            respond(&mut stream, "200 OK", &version.to_string())
+        }
+        _ => respond(&mut stream, "404 Not Found", ""),
+    }
+}
+
+/// Parse the request line and headers without consuming them.
+fn peek_request(stream: &TcpStream) -> io::Result<Option<Request>> {
+    let mut buf = [0; 4096];
+    let len = loop {
+        let n = stream.peek(&mut buf)?;
+        if n == 0 {
+            return Ok(None);
+        }
+        if let Some(end) = buf[..n].windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if n == buf.len() {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..len]);
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or("").split(' ');
+    if request_line.next() != Some("GET") {
+        return Ok(None);
+    }
+    let path = match request_line.next() {
+        Some(path) => path.to_owned(),
+        None => return Ok(None),
+    };
+
+    let mut host = None;
+    let mut upgrade = false;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "host" => host = Some(value.to_owned()),
+                "upgrade" => upgrade = value.eq_ignore_ascii_case("websocket"),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(Some(Request {
+        path,
+        host,
+        upgrade,
+        len,
+    }))
+}
+
+/// Whether the value of a Host header is `localhost` or an IP address, with
+/// or without a port.
+fn is_allowed_host(host: &str) -> bool {
+    if let Some(rest) = host.strip_prefix('[') {
+        return match rest.split_once(']') {
+            Some((ip, port)) => {
+                ip.parse::<Ipv6Addr>().is_ok() && (port.is_empty() || port.starts_with(':'))
+            }
+            None => false,
+        };
+    }
+    let name = host.split_once(':').map_or(host, |(name, _)| name);
+    name.eq_ignore_ascii_case("localhost") || name.parse::<Ipv4Addr>().is_ok()
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json; charset=UTF-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// Forward messages between the client and the isolate until either side
+/// goes away.
+fn run_session(mut ws: WebSocket<TcpStream>, session_id: u32, events: Sender<Event>) {
+    let (sender, receiver) = mpsc::channel();
+    if events.send(Event::Connect(session_id, sender)).is_err() {
+        return;
+    }
+    if ws.get_ref().set_read_timeout(Some(POLL_INTERVAL)).is_err() {
+        let _ = events.send(Event::Disconnect(session_id));
+        return;
+    }
+
+    'session: loop {
+        loop {
+            match receiver.try_recv() {
+                Ok(message) => {
+                    if ws.write_message(Message::Text(message)).is_err() {
+                        break 'session;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // The inspector is shutting down.
+                    let _ = ws.close(None);
+                    let _ = ws.write_pending();
+                    return;
+                }
+            }
+        }
+
+        match ws.read_message() {
+            Ok(Message::Text(message)) => {
+                if events.send(Event::Message(session_id, message)).is_err() {
+                    return;
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+            }
+            Err(_) => break,
+        }
+    }
+    let _ = events.send(Event::Disconnect(session_id));
+}
@@ -1,16 +1,48 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 
 pub(crate) struct IsolateState {
     pub(crate) context: Option<v8::Global<v8::Context>>,
     pub(crate) module_map: crate::module::ModuleMap,
+    pub(crate) source_maps: crate::source_map::SourceMaps,
+    pub(crate) code_cache: crate::code_cache::CodeCache,
+    // Compiled `WebAssembly.Module`s awaiting evaluation, keyed by the
+    // module that instantiates them.
+    pub(crate) wasm_modules: HashMap<v8::Global<v8::Module>, v8::Global<v8::Object>>,
+    // Parsed JSON modules awaiting evaluation, keyed by their synthetic
+    // module.
+    pub(crate) json_modules: HashMap<v8::Global<v8::Module>, v8::Global<v8::Value>>,
+    pub(crate) inspector: Option<Rc<crate::inspector::Inspector>>,
+    // Modules are read from here instead of the file system when set.
+    pub(crate) bundle: Option<Rc<crate::Bundle>>,
+    pub(crate) permissions: crate::permissions::PermissionState,
+    // What jstime:process exposes as `args` and `exitCode`.
+    pub(crate) args: Vec<String>,
+    pub(crate) exit_code: i32,
+    // Set once the script called `exit`, which stops the event loop.
+    pub(crate) exiting: bool,
 }
 
 impl IsolateState {
-    pub(crate) fn new(context: v8::Global<v8::Context>) -> Rc<RefCell<IsolateState>> {
+    pub(crate) fn new(
+        context: v8::Global<v8::Context>,
+        options: &crate::Options,
+        bundle: Option<crate::Bundle>,
+    ) -> Rc<RefCell<IsolateState>> {
         Rc::new(RefCell::new(IsolateState {
             context: Some(context),
             module_map: crate::module::ModuleMap::new(),
+            source_maps: crate::source_map::SourceMaps::new(options.source_maps),
+            code_cache: crate::code_cache::CodeCache::new(options.code_cache.clone()),
+            wasm_modules: HashMap::new(),
+            json_modules: HashMap::new(),
+            inspector: None,
+            bundle: bundle.map(Rc::new),
+            permissions: crate::permissions::PermissionState::new(options.permissions.clone()),
+            args: options.args.clone(),
+            exit_code: 0,
+            exiting: false,
         }))
     }
 
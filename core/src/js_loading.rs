@@ -1,8 +1,29 @@
 // Common code used by both `module.rs` and `script.rs`
 
+/// Record any source map referenced by `source` so stack traces can be
+/// remapped, returning its URL for the script origin.
+pub(crate) fn register_source_map(
+    scope: &mut v8::Isolate,
+    filepath: &str,
+    source: &str,
+) -> Option<String> {
+    let state = crate::IsolateState::get(scope);
+    let mut state = state.borrow_mut();
+    let bundle = state.bundle.clone();
+    state
+        .source_maps
+        .register(filepath, source, |path| match &bundle {
+            Some(bundle) => bundle
+                .read(&crate::bundle::normalize(path))
+                .map(<[u8]>::to_vec),
+            None => std::fs::read(path).ok(),
+        })
+}
+
 pub(crate) fn create_script_origin<'s>(
     scope: &mut v8::HandleScope<'s, ()>,
     filepath: v8::Local<'s, v8::String>,
+    source_map_url: Option<&str>,
     is_module: bool,
 ) -> v8::ScriptOrigin<'s> {
     let resource_name = filepath.into();
@@ -10,7 +31,10 @@ pub(crate) fn create_script_origin<'s>(
     let resource_column_offset = 0;
     let resource_is_shared_cross_origin = false;
     let script_id = 0;
-    let source_map_url = v8::String::new(scope, "soure_map_url").unwrap().into();
+    let source_map_url = match source_map_url {
+        Some(url) => v8::String::new(scope, url).unwrap().into(),
+        None => v8::undefined(scope).into(),
+    };
     let resource_is_opaque = true;
     let is_wasm = false;
 
@@ -1,20 +1,38 @@
 #[macro_use]
 extern crate lazy_static;
+use std::convert::TryFrom;
 use std::thread;
 use v8::Handle;
+mod bench_runner;
 mod builtins;
+mod bundle;
+mod code_cache;
+pub mod coverage;
+mod heap_snapshot;
+mod inspector;
 mod isolate_state;
 mod js_loading;
 mod module;
+mod permissions;
 mod script;
+mod source_map;
+mod test_runner;
+mod wasm;
 
+pub use bench_runner::{BenchOptions, BenchResults};
+pub use bundle::{Bundle, BundleOptions};
+pub use heap_snapshot::HeapSnapshotRequester;
 pub(crate) use isolate_state::IsolateState;
+pub use permissions::{Permission, Permissions};
+pub use test_runner::{TestOptions, TestReporter};
 
 pub fn init(v8_flags: Option<Vec<String>>) {
     if let Some(mut v8_flags) = v8_flags {
         v8_flags.push("jstime".to_owned());
         v8_flags.rotate_right(1);
 
+        code_cache::set_v8_flags(&v8_flags);
+
         v8::V8::set_flags_from_command_line(v8_flags);
     }
 
@@ -24,12 +42,44 @@ pub fn init(v8_flags: Option<Vec<String>>) {
 }
 
 /// Options for `JSTime::new`.
-#[derive(Default)]
 pub struct Options {
     pub snapshot: Option<&'static [u8]>,
+    /// Remap stack traces through `//# sourceMappingURL` source maps.
+    /// Defaults to `true`.
+    pub source_maps: bool,
+    /// Directory to store V8 code cache data for compiled modules in.
+    /// Modules are always compiled from source when `None`.
+    pub code_cache: Option<std::path::PathBuf>,
+    /// Listen for Chrome DevTools Protocol clients on this address.
+    pub inspect: Option<std::net::SocketAddr>,
+    /// Wait for a DevTools client to attach, and pause before the first
+    /// statement of the first imported module. Only used with `inspect`.
+    pub inspect_brk: bool,
+    /// Load modules from this bundle, rather than from the file system.
+    pub bundle: Option<Bundle>,
+    /// What scripts may access. Defaults to `Permissions::allow_all()`.
+    pub permissions: Permissions,
+    /// Arguments for the script, exposed as `args` by `jstime:process`.
+    pub args: Vec<String>,
     taking_snapshot: bool,
 }
 
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            snapshot: None,
+            source_maps: true,
+            code_cache: None,
+            inspect: None,
+            inspect_brk: false,
+            bundle: None,
+            permissions: Permissions::allow_all(),
+            args: Vec::new(),
+            taking_snapshot: false,
+        }
+    }
+}
+
 impl Options {
     pub fn new(snapshot: Option<&'static [u8]>) -> Options {
         Options {
@@ -44,6 +94,9 @@ impl Options {
 pub struct JSTime {
     isolate: Option<v8::OwnedIsolate>,
     taking_snapshot: bool,
+    inspect_brk: bool,
+    cpu_profile: Option<inspector::LocalSession>,
+    coverage: Option<inspector::LocalSession>,
     // pending_promises: Vec<v8::Global<v8::Promise>>,
 }
 
@@ -95,14 +148,16 @@ impl JSTime {
         }
     }
 
-    fn create(options: Options, mut isolate: v8::OwnedIsolate) -> JSTime {
+    fn create(mut options: Options, mut isolate: v8::OwnedIsolate) -> JSTime {
         let global_context = {
             let scope = &mut v8::HandleScope::new(&mut isolate);
             let context = v8::Context::new(scope);
             v8::Global::new(scope, context)
         };
 
-        isolate.set_slot(IsolateState::new(global_context));
+        let bundle = options.bundle.take();
+        isolate.set_slot(IsolateState::new(global_context, &options, bundle));
+        isolate.set_host_initialize_import_meta_object_callback(module::import_meta_callback);
 
         {
             let context = IsolateState::get(&mut isolate).borrow().context();
@@ -113,11 +168,24 @@ impl JSTime {
                 builtins::Builtins::create(scope);
             }
             builtins::Builtins::init(scope);
+
+            if let (Some(addr), false) = (options.inspect, options.taking_snapshot) {
+                let context = scope.get_current_context();
+                match inspector::Inspector::new(scope, context, Some(addr)) {
+                    Ok(inspector) => {
+                        IsolateState::get(scope).borrow_mut().inspector = Some(inspector)
+                    }
+                    Err(e) => eprintln!("Starting inspector on {} failed: {}", addr, e),
+                }
+            }
         }
 
         JSTime {
             isolate: Some(isolate),
             taking_snapshot: options.taking_snapshot,
+            inspect_brk: options.inspect_brk,
+            cpu_profile: None,
+            coverage: None,
         }
     }
 
@@ -136,18 +204,15 @@ impl JSTime {
 
     /// Import a module by filename.
     pub fn import(&mut self, filename: &str) -> Result<(), String> {
-        let scope = &mut self.handle_scope();
-        let loader = module::Loader::new();
-
-        let mut cwd = std::env::current_dir().unwrap();
-        cwd.push("jstime");
-        let cwd = cwd.into_os_string().into_string().unwrap();
-        let res = match loader.import(scope, &cwd, filename) {
-            Ok(res) => res,
-            Err(e) => return Err(e.to_string(scope).unwrap().to_rust_string_lossy(scope)),
+        let scope = &mut self.wait_for_debugger();
+        let res = match import_module(scope, filename)? {
+            Some(res) => res,
+            None => return Ok(()),
         };
-
-        while builtins::tick(scope) {}
+        builtins::events::load(scope, res);
+        if run_module(scope, res)? {
+            unload(scope);
+        }
         // let resolver_global = scope
         //     .remove_slot::<v8::Global<v8::PromiseResolver>>()
         //     .unwrap();
@@ -162,6 +227,180 @@ impl JSTime {
         Ok(())
     }
 
+    /// A scope to run the main module in, once a debugger attached when
+    /// `inspect_brk` was set.
+    fn wait_for_debugger(&mut self) -> v8::HandleScope {
+        let inspect_brk = std::mem::take(&mut self.inspect_brk);
+        let mut scope = self.handle_scope();
+        if inspect_brk {
+            if let Some(inspector) = inspector(&mut scope) {
+                inspector.wait_for_debugger();
+            }
+        }
+        scope
+    }
+
+    /// Gather the module graph of `filename`, to be run later through
+    /// `Options::bundle` without the files it was read from.
+    pub fn bundle(&mut self, filename: &str, options: &BundleOptions) -> Result<Bundle, String> {
+        let scope = &mut self.handle_scope();
+        bundle::gather(scope, filename, options)
+    }
+
+    /// Import each of `files` and run the tests they register through the
+    /// `jstime:test` module, printing a report to stdout. Files that fail to
+    /// import are reported as failing tests. `load` is dispatched once all
+    /// of them were imported, and `unload` once the tests ran. Returns
+    /// whether all tests passed.
+    pub fn run_tests(&mut self, files: &[String], options: &TestOptions) -> Result<bool, String> {
+        let scope = &mut self.wait_for_debugger();
+        import_for_runner(scope, "test", files)?;
+        let promise = test_runner::run(scope, options)?;
+        run_event_loop(scope);
+        unload(scope);
+        Ok(settled(scope, promise)?.is_true())
+    }
+
+    /// Import each of `files` and run the benchmarks they register through
+    /// the `jstime:bench` module, printing a report to stdout. Files that
+    /// fail to import are reported as failing benchmarks. `load` and
+    /// `unload` are dispatched like for `run_tests`.
+    pub fn run_benchmarks(
+        &mut self,
+        files: &[String],
+        options: &BenchOptions,
+    ) -> Result<BenchResults, String> {
+        let scope = &mut self.wait_for_debugger();
+        import_for_runner(scope, "bench", files)?;
+        let promise = bench_runner::run(scope, options)?;
+        run_event_loop(scope);
+        unload(scope);
+        let results = settled(scope, promise)?;
+        Ok(bench_runner::results(scope, results))
+    }
+
+    /// Run a WebAssembly command module built for WASI, returning its exit
+    /// code. `args` are passed to the module as-is, each directory in
+    /// `preopens` is made available to it under the same path.
+    pub fn run_wasi(
+        &mut self,
+        filename: &str,
+        args: &[String],
+        preopens: &[String],
+    ) -> Result<i32, String> {
+        let bytes = std::fs::read(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        let scope = &mut self.handle_scope();
+        wasm::run_wasi(scope, bytes, args, preopens).map_err(|e| error_to_string(scope, e))
+    }
+
+    /// Start sampling the JS call stack every `interval`, until
+    /// `stop_cpu_profiling` is called.
+    pub fn start_cpu_profiling(&mut self, interval: std::time::Duration) -> Result<(), String> {
+        if self.cpu_profile.is_some() {
+            return Err("CPU profiling is already started".to_owned());
+        }
+        let session = {
+            let scope = &mut self.handle_scope();
+            inspector::get_or_create(scope).local_session()
+        };
+        let interval = serde_json::json!({ "interval": interval.as_micros() as u64 });
+        session.post("Profiler.enable", serde_json::json!({}))?;
+        session.post("Profiler.setSamplingInterval", interval)?;
+        session.post("Profiler.start", serde_json::json!({}))?;
+        self.cpu_profile = Some(session);
+        Ok(())
+    }
+
+    /// Stop CPU profiling, returning the profile in the JSON format of
+    /// `.cpuprofile` files loaded by Chrome DevTools.
+    pub fn stop_cpu_profiling(&mut self) -> Result<String, String> {
+        let session = match self.cpu_profile.take() {
+            Some(session) => session,
+            None => return Err("CPU profiling is not started".to_owned()),
+        };
+        let _scope = &mut self.handle_scope();
+        let mut result = session.post("Profiler.stop", serde_json::json!({}))?;
+        Ok(result["profile"].take().to_string())
+    }
+
+    /// Start collecting precise code coverage, with call counts. Only code
+    /// compiled from then on gets block level coverage, so start before
+    /// importing modules.
+    pub fn start_coverage(&mut self) -> Result<(), String> {
+        if self.coverage.is_some() {
+            return Err("Coverage is already started".to_owned());
+        }
+        let session = {
+            let scope = &mut self.handle_scope();
+            inspector::get_or_create(scope).local_session()
+        };
+        let params = serde_json::json!({ "callCount": true, "detailed": true });
+        session.post("Profiler.enable", serde_json::json!({}))?;
+        session.post("Profiler.startPreciseCoverage", params)?;
+        self.coverage = Some(session);
+        Ok(())
+    }
+
+    /// Stop collecting coverage, returning the coverage of modules loaded by
+    /// `import` as JSON, in the format of the inspector's
+    /// `Profiler.takePreciseCoverage` result. See `coverage::Report`.
+    pub fn stop_coverage(&mut self) -> Result<String, String> {
+        let session = match self.coverage.take() {
+            Some(session) => session,
+            None => return Err("Coverage is not started".to_owned()),
+        };
+        let scope = &mut self.handle_scope();
+        let mut coverage = session.post("Profiler.takePreciseCoverage", serde_json::json!({}))?;
+
+        // Leave out builtins and scripts that weren't loaded as modules.
+        let state = IsolateState::get(scope);
+        let state = state.borrow();
+        let scripts = match coverage["result"].as_array_mut() {
+            Some(scripts) => std::mem::take(scripts),
+            None => Vec::new(),
+        };
+        let scripts = scripts
+            .into_iter()
+            .filter(|script| match script["url"].as_str() {
+                Some(url) => state.module_map.contains(url),
+                None => false,
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::json!({ "result": scripts }).to_string())
+    }
+
+    /// Write a snapshot of the JS heap to `path`, in the JSON format of
+    /// `.heapsnapshot` files loaded by Chrome DevTools.
+    pub fn write_heap_snapshot<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<(), String> {
+        let path = path.as_ref();
+        heap_snapshot::write(self.isolate(), path).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Get a handle for requesting heap snapshots from other threads.
+    pub fn heap_snapshot_requester(&mut self) -> HeapSnapshotRequester {
+        let isolate = self.isolate();
+        if isolate.get_slot::<heap_snapshot::Requests>().is_none() {
+            isolate.set_slot(heap_snapshot::Requests::default());
+        }
+        HeapSnapshotRequester {
+            isolate: isolate.thread_safe_handle(),
+            requests: isolate
+                .get_slot::<heap_snapshot::Requests>()
+                .unwrap()
+                .clone(),
+        }
+    }
+
+    /// The code the script asked to exit with, through `exit` or `exitCode`
+    /// of `jstime:process`. Otherwise 1 if a promise rejection went
+    /// unhandled, and 0 if not.
+    pub fn exit_code(&mut self) -> i32 {
+        IsolateState::get(self.isolate()).borrow().exit_code
+    }
+
     /// Run a script and get a string representation of the result.
     pub fn run_script(&mut self, source: &str, filename: &str) -> Result<String, String> {
         let context = IsolateState::get(self.isolate()).borrow().context();
@@ -173,14 +412,7 @@ impl JSTime {
     }
     fn pump_v8_message_loop(&mut self) {
         let scope = &mut self.handle_scope();
-        while v8::Platform::pump_message_loop(
-            &v8::V8::get_current_platform(),
-            scope,
-            false, // don't block if there are no tasks
-        ) {
-            // do nothing
-        }
-        scope.perform_microtask_checkpoint();
+        pump_message_loop(scope);
     }
 
     pub fn poll_event_loop(&mut self) -> Result<(), String> {
@@ -195,12 +427,188 @@ impl JSTime {
     }
 }
 
+/// Import the module at `filename`, relative to the current directory.
+/// Returns the result of evaluating it, or `None` if the script called
+/// `exit` or a listener for `error` handled the failure to import it.
+fn import_module<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    filename: &str,
+) -> Result<Option<v8::Local<'a, v8::Value>>, String> {
+    let loader = module::Loader::new();
+
+    // The file name is a path rather than a URL, so it's resolved
+    // before the loader resolves specifiers as URLs.
+    let mut cwd = std::env::current_dir().unwrap();
+    let path = cwd.join(filename).to_string_lossy().into_owned();
+    cwd.push("jstime");
+    let cwd = cwd.into_os_string().into_string().unwrap();
+    match loader.import(scope, &cwd, &path) {
+        Ok(res) => Ok(Some(res)),
+        Err(_) if exiting(scope) => Ok(None),
+        // A listener for `error` can handle it instead.
+        Err(e) if !builtins::events::dispatch_error(scope, e) => Ok(None),
+        Err(e) => Err(error_to_string(scope, e)),
+    }
+}
+
+/// Run the event loop for a module `import_module` evaluated to `res`.
+/// Returns whether there's JS left to run, as the script didn't call
+/// `exit`.
+fn run_module(scope: &mut v8::HandleScope, res: v8::Local<v8::Value>) -> Result<bool, String> {
+    run_event_loop(scope);
+    // There's no more JS to run once the script called `exit`.
+    if IsolateState::get(scope).borrow().exiting {
+        return Ok(false);
+    }
+    // With top-level await, evaluating the module settles `res` rather
+    // than returning its result, which it should have by now.
+    if let Ok(evaluation) = v8::Local::<v8::Promise>::try_from(res) {
+        match evaluation.state() {
+            v8::PromiseState::Rejected => {
+                let error = evaluation.result(scope);
+                if builtins::events::dispatch_error(scope, error) {
+                    return Err(error_to_string(scope, error));
+                }
+            }
+            _ => {
+                settled(scope, res)?;
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Import `files` one by one, telling the runner registered as
+/// `internals.<runner>` which file registers what, and which files failed
+/// to import. `load` is dispatched once all of them were imported.
+fn import_for_runner(
+    scope: &mut v8::HandleScope,
+    runner: &str,
+    files: &[String],
+) -> Result<(), String> {
+    for file in files {
+        let name = v8::String::new(scope, file).unwrap();
+        builtins::call_internal(scope, runner, "setFile", &[name.into()])?;
+        let imported = match import_module(scope, file) {
+            Ok(Some(res)) => run_module(scope, res).map(|_| ()),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = imported {
+            let error = v8::String::new(scope, &e).unwrap();
+            builtins::call_internal(scope, runner, "loadError", &[name.into(), error.into()])?;
+        }
+    }
+    let undefined = v8::undefined(scope);
+    builtins::events::load(scope, undefined.into());
+    Ok(())
+}
+
+/// Dispatch `unload` once there's nothing left to do, and report the
+/// rejections its listeners left unhandled.
+fn unload(scope: &mut v8::HandleScope) {
+    builtins::events::unload(scope);
+    pump_message_loop(scope);
+    builtins::events::report_rejections(scope);
+}
+
+/// Run timers, fetches and platform tasks until there are none left, or
+/// the script calls `exit`.
+fn run_event_loop(scope: &mut v8::HandleScope) {
+    // Rejections left unhandled by what ran so far are reported before the
+    // first timers run.
+    builtins::events::report_rejections(scope);
+    loop {
+        let pending = builtins::tick(scope);
+        // Async WebAssembly compilation completes through platform tasks.
+        pump_message_loop(scope);
+        if exiting(scope) {
+            break;
+        }
+        builtins::events::report_rejections(scope);
+        if let Some(inspector) = inspector(scope) {
+            inspector.poll();
+        }
+        heap_snapshot::write_requested(scope);
+        if !pending && !scope.has_pending_background_tasks() {
+            break;
+        }
+    }
+}
+
+/// Whether the script called `exit`. JS stops running once it has, but the
+/// isolate can still be used afterwards, e.g. to inspect what the script did.
+fn exiting(scope: &mut v8::HandleScope) -> bool {
+    if !IsolateState::get(scope).borrow().exiting {
+        return false;
+    }
+    scope.cancel_terminate_execution();
+    true
+}
+
+/// The value of a promise that should have settled by the time the event
+/// loop ran out of work.
+fn settled<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    promise: v8::Local<'a, v8::Value>,
+) -> Result<v8::Local<'a, v8::Value>, String> {
+    let promise = v8::Local::<v8::Promise>::try_from(promise).unwrap();
+    match promise.state() {
+        v8::PromiseState::Fulfilled => Ok(promise.result(scope)),
+        v8::PromiseState::Rejected => {
+            let error = promise.result(scope);
+            Err(error_to_string(scope, error))
+        }
+        v8::PromiseState::Pending => {
+            Err("Still running when there was nothing left to wait for".to_owned())
+        }
+    }
+}
+
+fn pump_message_loop(scope: &mut v8::HandleScope) {
+    while v8::Platform::pump_message_loop(
+        &v8::V8::get_current_platform(),
+        scope,
+        false, // don't block if there are no tasks
+    ) {
+        // do nothing
+    }
+    scope.perform_microtask_checkpoint();
+}
+
+fn inspector(scope: &mut v8::Isolate) -> Option<std::rc::Rc<inspector::Inspector>> {
+    IsolateState::get(scope).borrow().inspector.clone()
+}
+
+/// Prefer an error's (source mapped) `stack` over its plain string form.
+fn error_to_string(scope: &mut v8::HandleScope, error: v8::Local<v8::Value>) -> String {
+    if error.is_native_error() {
+        let error = v8::Local::<v8::Object>::try_from(error).unwrap();
+        let key = v8::String::new(scope, "stack").unwrap();
+        if let Some(stack) = error.get(scope, key.into()) {
+            if stack.is_string() {
+                return stack.to_rust_string_lossy(scope);
+            }
+        }
+    }
+    error.to_string(scope).unwrap().to_rust_string_lossy(scope)
+}
+
 impl Drop for JSTime {
     fn drop(&mut self) {
         if self.taking_snapshot {
             // The isolate is not actually owned by JSTime if we're
             // snapshotting, it's owned by the SnapshotCreator.
             std::mem::forget(self.isolate.take().unwrap())
+        } else {
+            // Close inspector sessions while the isolate is still around.
+            self.cpu_profile.take();
+            self.coverage.take();
+            let inspector = IsolateState::get(self.isolate())
+                .borrow_mut()
+                .inspector
+                .take();
+            drop(inspector);
         }
     }
 }
@@ -1,16 +1,17 @@
 use crate::IsolateState;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use url::Url;
 
 pub(crate) struct ModuleMap {
-    hash_to_absolute_path: HashMap<i32, String>,
+    module_to_absolute_path: HashMap<v8::Global<v8::Module>, String>,
     absolute_path_to_module: HashMap<String, v8::Global<v8::Module>>,
 }
 
 impl ModuleMap {
     pub(crate) fn new() -> Self {
         Self {
-            hash_to_absolute_path: HashMap::new(),
+            module_to_absolute_path: HashMap::new(),
             absolute_path_to_module: HashMap::new(),
         }
     }
@@ -21,12 +22,25 @@ impl ModuleMap {
         filepath: &str,
         module: v8::Local<v8::Module>,
     ) {
-        self.hash_to_absolute_path
-            .insert(module.get_identity_hash(), filepath.to_owned());
         let module = v8::Global::new(scope, module);
+        self.module_to_absolute_path
+            .insert(module.clone(), filepath.to_owned());
         self.absolute_path_to_module
             .insert(filepath.to_owned(), module);
     }
+
+    pub(crate) fn path_for(
+        &self,
+        scope: &mut v8::HandleScope,
+        module: v8::Local<v8::Module>,
+    ) -> Option<String> {
+        let module = v8::Global::new(scope, module);
+        self.module_to_absolute_path.get(&module).cloned()
+    }
+
+    pub(crate) fn contains(&self, filepath: &str) -> bool {
+        self.absolute_path_to_module.contains_key(filepath)
+    }
 }
 
 pub(crate) struct Loader {}
@@ -45,14 +59,28 @@ impl Loader {
         let scope = &mut v8::TryCatch::new(scope);
         match resolve(scope, referrer, specifier) {
             Some(m) => {
-                m.instantiate_module(scope, module_resolve_callback)
-                    .unwrap();
-                let res = m.evaluate(scope).unwrap();
+                // Fails when a dependency can't be resolved, e.g. an
+                // unknown builtin module.
+                if m.instantiate_module(scope, module_resolve_callback)
+                    .is_none()
+                {
+                    return Err(scope.exception().unwrap());
+                }
+                // Evaluation only fails when the script calls `exit`.
+                let res = match m.evaluate(scope) {
+                    Some(res) => res,
+                    None => return Err(v8::undefined(scope).into()),
+                };
                 let promise = unsafe { v8::Local::<v8::Promise>::cast(res) };
                 match promise.state() {
                     v8::PromiseState::Pending => Ok(res),
                     v8::PromiseState::Fulfilled => Ok(promise.result(scope)),
-                    v8::PromiseState::Rejected => Err(promise.result(scope)),
+                    // Reported as the result of the import, rather than as
+                    // an unhandled rejection.
+                    v8::PromiseState::Rejected => {
+                        crate::builtins::events::handled(scope, promise);
+                        Err(promise.result(scope))
+                    }
                 }
             }
             None => Err(scope.stack_trace().unwrap()),
@@ -60,14 +88,25 @@ impl Loader {
     }
 }
 
-fn resolve<'a>(
+pub(crate) fn resolve<'a>(
     scope: &mut v8::HandleScope<'a>,
     referrer: &str,
     specifier: &str,
 ) -> Option<v8::Local<'a, v8::Module>> {
     let state = IsolateState::get(scope);
+    let bundle = state.borrow().bundle.clone();
 
-    let requested_abs_path = normalize_path(referrer, specifier);
+    // Builtin modules are keyed by their specifier.
+    let requested_abs_path = if specifier.starts_with("jstime:") {
+        specifier.to_owned()
+    } else if bundle.is_some() {
+        crate::bundle::normalize_path(referrer, specifier)
+    } else {
+        match normalize_path(referrer, specifier) {
+            Some(path) => path,
+            None => return throw_not_found(scope, specifier),
+        }
+    };
     if let Some(module) = state
         .borrow()
         .module_map
@@ -77,14 +116,48 @@ fn resolve<'a>(
         return Some(v8::Local::new(scope, module));
     }
 
-    let requested_string = v8::String::new(scope, &requested_abs_path).unwrap();
-    let origin = crate::js_loading::create_script_origin(scope, requested_string, true);
-    let js_src = std::fs::read_to_string(&requested_abs_path)
-        .expect("Something went wrong reading the file");
-    let code = v8::String::new(scope, &js_src).unwrap();
-    let source = v8::script_compiler::Source::new(code, Some(&origin));
-
-    let module = v8::script_compiler::compile_module(scope, source);
+    let module = if requested_abs_path.starts_with("jstime:") {
+        builtin(scope, &requested_abs_path)
+    } else {
+        let bytes = match &bundle {
+            Some(bundle) => match bundle.read(&requested_abs_path) {
+                Some(bytes) => bytes.to_vec(),
+                None => return throw_not_found(scope, &requested_abs_path),
+            },
+            None => match std::fs::read(&requested_abs_path) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    let message = format!("Cannot read module '{}': {}", requested_abs_path, e);
+                    return throw_error(scope, &message);
+                }
+            },
+        };
+        if requested_abs_path.ends_with(".wasm") {
+            crate::wasm::compile(scope, &requested_abs_path, bytes)
+        } else {
+            let src = match String::from_utf8(bytes) {
+                Ok(src) => src,
+                Err(_) => {
+                    let message = format!("Module '{}' is not valid UTF-8", requested_abs_path);
+                    return throw_error(scope, &message);
+                }
+            };
+            if requested_abs_path.ends_with(".json") {
+                json(scope, &requested_abs_path, &src)
+            } else {
+                let source_map_url =
+                    crate::js_loading::register_source_map(scope, &requested_abs_path, &src);
+                let requested_string = v8::String::new(scope, &requested_abs_path).unwrap();
+                let origin = crate::js_loading::create_script_origin(
+                    scope,
+                    requested_string,
+                    source_map_url.as_deref(),
+                    true,
+                );
+                compile(scope, &requested_abs_path, &src, &origin)
+            }
+        }
+    };
     if let Some(module) = module {
         let state = IsolateState::get(scope);
         state
@@ -95,17 +168,244 @@ fn resolve<'a>(
     module
 }
 
-fn normalize_path(referrer_path: &str, requested: &str) -> String {
-    let req_path = Path::new(requested);
-    if req_path.is_absolute() {
-        return requested.to_string();
+/// A synthetic module exporting the properties of a builtin module's exports
+/// object, which builtins register in `Builtins::create`.
+fn builtin<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    specifier: &str,
+) -> Option<v8::Local<'a, v8::Module>> {
+    let exports = match crate::builtins::module_exports(scope, specifier) {
+        Some(exports) => exports,
+        None => return throw_not_found(scope, specifier),
+    };
+    let export_names = export_names(scope, exports)?;
+    let module_name = v8::String::new(scope, specifier).unwrap();
+    Some(v8::Module::create_synthetic_module(
+        scope,
+        module_name,
+        &export_names,
+        evaluate_builtin,
+    ))
+}
+
+fn throw_not_found<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    specifier: &str,
+) -> Option<v8::Local<'a, v8::Module>> {
+    let message = format!("Cannot find module '{}'", specifier);
+    throw_error(scope, &message)
+}
+
+fn throw_error<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    message: &str,
+) -> Option<v8::Local<'a, v8::Module>> {
+    let message = v8::String::new(scope, message).unwrap();
+    let exception = v8::Exception::type_error(scope, message);
+    scope.throw_exception(exception);
+    None
+}
+
+fn evaluate_builtin<'a>(
+    context: v8::Local<'a, v8::Context>,
+    module: v8::Local<v8::Module>,
+) -> Option<v8::Local<'a, v8::Value>> {
+    let scope = &mut unsafe { v8::CallbackScope::new(context) };
+
+    let state = IsolateState::get(scope);
+    let specifier = state.borrow().module_map.path_for(scope, module)?;
+    let exports = crate::builtins::module_exports(scope, &specifier)?;
+    for name in export_names(scope, exports)? {
+        let value = exports.get(scope, name.into())?;
+        module.set_synthetic_module_export(scope, name, value)?;
     }
-    let ref_dir = Path::new(referrer_path).parent().unwrap();
-    let normalized = ref_dir.join(req_path).canonicalize();
-    normalized.unwrap().to_string_lossy().into()
+
+    let resolver = v8::PromiseResolver::new(scope)?;
+    let undefined = v8::undefined(scope);
+    resolver.resolve(scope, undefined.into());
+    Some(resolver.get_promise(scope).into())
 }
 
-fn module_resolve_callback<'a>(
+/// A synthetic module whose default export is the parsed contents of a JSON
+/// file.
+fn json<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    filepath: &str,
+    json_src: &str,
+) -> Option<v8::Local<'a, v8::Module>> {
+    let json_src = v8::String::new(scope, json_src).unwrap();
+    let value = v8::json::parse(scope, json_src)?;
+    let module_name = v8::String::new(scope, filepath).unwrap();
+    let export_names = [v8::String::new(scope, "default").unwrap()];
+    let module =
+        v8::Module::create_synthetic_module(scope, module_name, &export_names, evaluate_json);
+
+    let state = IsolateState::get(scope);
+    let value = v8::Global::new(scope, value);
+    state
+        .borrow_mut()
+        .json_modules
+        .insert(v8::Global::new(scope, module), value);
+    Some(module)
+}
+
+fn evaluate_json<'a>(
+    context: v8::Local<'a, v8::Context>,
+    module: v8::Local<v8::Module>,
+) -> Option<v8::Local<'a, v8::Value>> {
+    let scope = &mut unsafe { v8::CallbackScope::new(context) };
+
+    let state = IsolateState::get(scope);
+    let key = v8::Global::new(scope, module);
+    let value = state.borrow_mut().json_modules.remove(&key)?;
+    let value = v8::Local::new(scope, value);
+    let name = v8::String::new(scope, "default").unwrap();
+    module.set_synthetic_module_export(scope, name, value)?;
+
+    let resolver = v8::PromiseResolver::new(scope)?;
+    let undefined = v8::undefined(scope);
+    resolver.resolve(scope, undefined.into());
+    Some(resolver.get_promise(scope).into())
+}
+
+fn export_names<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    exports: v8::Local<v8::Object>,
+) -> Option<Vec<v8::Local<'a, v8::String>>> {
+    let names = exports.get_own_property_names(scope)?;
+    (0..names.length())
+        .map(|i| names.get_index(scope, i)?.to_string(scope))
+        .collect()
+}
+
+/// Compile a module, consuming code cache data from the bundle, and
+/// consuming and producing V8 code cache data when a cache directory is
+/// configured.
+fn compile<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    filepath: &str,
+    js_src: &str,
+    origin: &v8::ScriptOrigin,
+) -> Option<v8::Local<'a, v8::Module>> {
+    use v8::script_compiler::Source;
+
+    let state = IsolateState::get(scope);
+    let bundled = state
+        .borrow()
+        .bundle
+        .as_ref()
+        .and_then(|bundle| bundle.code_cache(filepath).map(<[u8]>::to_vec));
+    // The bundle can't be updated, so data it has that V8 rejects is only
+    // skipped.
+    if let Some(data) = bundled {
+        if let Some(module) = compile_with_cache(scope, js_src, origin, &data) {
+            return Some(module);
+        }
+    }
+
+    if !state.borrow().code_cache.enabled() {
+        let code = v8::String::new(scope, js_src).unwrap();
+        let source = Source::new(code, Some(origin));
+        return v8::script_compiler::compile_module(scope, source);
+    }
+
+    let cached = state.borrow().code_cache.get(filepath, js_src);
+    if let Some(data) = cached {
+        let module = compile_with_cache(scope, js_src, origin, &data);
+        if module.is_some() {
+            return module;
+        }
+        // V8 couldn't use the cached data, drop it and compile from source,
+        // which replaces it below.
+        state.borrow().code_cache.remove(filepath, js_src);
+    }
+
+    let code = v8::String::new(scope, js_src).unwrap();
+    let source = Source::new(code, Some(origin));
+    let module = v8::script_compiler::compile_module(scope, source)?;
+    if let Some(data) = module.get_unbound_module_script(scope).create_code_cache() {
+        state.borrow().code_cache.set(filepath, js_src, &data);
+    }
+    Some(module)
+}
+
+/// Compile a module consuming code cache `data`, or return `None` if V8
+/// rejected it, e.g. as it was corrupted or produced with other flags.
+fn compile_with_cache<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    js_src: &str,
+    origin: &v8::ScriptOrigin,
+    data: &[u8],
+) -> Option<v8::Local<'a, v8::Module>> {
+    use v8::script_compiler::{CachedData, CompileOptions, NoCacheReason, Source};
+
+    let code = v8::String::new(scope, js_src).unwrap();
+    let mut source = Source::new_with_cached_data(code, Some(origin), CachedData::new(data));
+    let module = v8::script_compiler::compile_module2(
+        scope,
+        &mut source,
+        CompileOptions::ConsumeCodeCache,
+        NoCacheReason::NoReason,
+    )?;
+    // V8 compiles from source rather than failing when it can't use the
+    // data.
+    let rejected = source
+        .get_cached_data()
+        .map_or(true, |cached| cached.rejected());
+    if rejected {
+        return None;
+    }
+    Some(module)
+}
+
+/// Resolve `specifier` like a URL relative to the `file:` URL of the module
+/// at `referrer`, returning the path it refers to. Absolute paths are taken
+/// as they are, and specifiers that resolve to URLs other than `file:` ones
+/// return `None`.
+pub(crate) fn resolve_specifier(referrer: &str, specifier: &str) -> Option<PathBuf> {
+    let url = if Path::new(specifier).is_absolute() {
+        Url::from_file_path(specifier).ok()?
+    } else {
+        file_url(referrer)?.join(specifier).ok()?
+    };
+    if url.scheme() != "file" {
+        return None;
+    }
+    url.to_file_path().ok()
+}
+
+/// The `file:` URL of the module at `path`, which is its `import.meta.url`.
+pub(crate) fn file_url(path: &str) -> Option<Url> {
+    Url::from_file_path(path).ok()
+}
+
+fn normalize_path(referrer_path: &str, requested: &str) -> Option<String> {
+    let path = resolve_specifier(referrer_path, requested)?;
+    let normalized = path.canonicalize().ok()?;
+    Some(normalized.to_string_lossy().into())
+}
+
+/// Set `import.meta.url` on the `meta` object of a module loaded from a
+/// file, or from a bundle, and `import.meta.wasmModule` on that of a
+/// WebAssembly module.
+pub(crate) extern "C" fn import_meta_callback(
+    context: v8::Local<v8::Context>,
+    module: v8::Local<v8::Module>,
+    meta: v8::Local<v8::Object>,
+) {
+    let scope = &mut unsafe { v8::CallbackScope::new(context) };
+
+    let state = IsolateState::get(scope);
+    let path = state.borrow().module_map.path_for(scope, module);
+    if let Some(url) = path.as_deref().and_then(file_url) {
+        let key = v8::String::new(scope, "url").unwrap();
+        let value = v8::String::new(scope, url.as_str()).unwrap();
+        meta.create_data_property(scope, key.into(), value.into());
+    }
+    crate::wasm::initialize_import_meta(scope, module, meta);
+}
+
+pub(crate) fn module_resolve_callback<'a>(
     context: v8::Local<'a, v8::Context>,
     specifier: v8::Local<'a, v8::String>,
     _import_assertions: v8::Local<'a, v8::FixedArray>,
@@ -113,17 +413,13 @@ fn module_resolve_callback<'a>(
 ) -> Option<v8::Local<'a, v8::Module>> {
     let scope = unsafe { &mut v8::CallbackScope::new(context) };
 
-    let hash = referrer.get_identity_hash();
-
+    let requested_rel_path = specifier.to_rust_string_lossy(scope);
     let state = IsolateState::get(scope);
-    let referrer_path = state
-        .borrow()
-        .module_map
-        .hash_to_absolute_path
-        .get(&hash)
-        .unwrap()
-        .to_owned();
+    let referrer_path = state.borrow().module_map.path_for(scope, referrer);
+    let referrer_path = match referrer_path {
+        Some(path) => path,
+        None => return throw_not_found(scope, &requested_rel_path),
+    };
 
-    let requested_rel_path = specifier.to_rust_string_lossy(scope);
     resolve(scope, &referrer_path, &requested_rel_path)
 }
@@ -0,0 +1,313 @@
+// What scripts may access through builtins, checked by every binding that
+// reaches outside of the isolate.
+
+use std::convert::TryFrom;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+/// Access granted to one kind of resource, see `Permissions`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Permission {
+    Denied,
+    Granted,
+    /// Granted only to these hosts, paths, variable names or programs.
+    GrantedFor(Vec<String>),
+}
+
+/// What scripts are allowed to access. Access that isn't granted throws a
+/// `PermissionDenied` error.
+#[derive(Clone, Debug)]
+pub struct Permissions {
+    /// Network access, e.g. by `fetch`. Entries are hosts, optionally with
+    /// a port, like `example.com` or `127.0.0.1:8080`.
+    pub net: Permission,
+    /// Reading files. Entries are files or directories, relative ones to
+    /// the working directory at startup. A directory grants access to
+    /// everything in it. Symlinks are resolved before paths are checked,
+    /// so one in an allowed directory only grants access if what it points
+    /// to is allowed too.
+    pub read: Permission,
+    /// Writing files, with entries like `read`.
+    pub write: Permission,
+    /// Reading and changing environment variables. Entries are variable
+    /// names.
+    pub env: Permission,
+    /// Running programs. Entries are program names or paths, as they're
+    /// passed to the builtin.
+    pub run: Permission,
+    /// Ask on the terminal whether to grant access that wasn't, rather than
+    /// denying it right away.
+    pub prompt: bool,
+}
+
+impl Permissions {
+    pub fn allow_all() -> Permissions {
+        Permissions {
+            net: Permission::Granted,
+            read: Permission::Granted,
+            write: Permission::Granted,
+            env: Permission::Granted,
+            run: Permission::Granted,
+            prompt: false,
+        }
+    }
+
+    pub fn deny_all() -> Permissions {
+        Permissions {
+            net: Permission::Denied,
+            read: Permission::Denied,
+            write: Permission::Denied,
+            env: Permission::Denied,
+            run: Permission::Denied,
+            prompt: false,
+        }
+    }
+
+    fn get_mut(&mut self, kind: Kind) -> &mut Permission {
+        match kind {
+            Kind::Net => &mut self.net,
+            Kind::Read => &mut self.read,
+            Kind::Write => &mut self.write,
+            Kind::Env => &mut self.env,
+            Kind::Run => &mut self.run,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Kind {
+    Net,
+    Read,
+    Write,
+    Env,
+    Run,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Net => "net",
+            Kind::Read => "read",
+            Kind::Write => "write",
+            Kind::Env => "env",
+            Kind::Run => "run",
+        }
+    }
+}
+
+/// `Permissions` as they stand while a script runs, including what was
+/// denied at a prompt so it isn't asked again.
+pub(crate) struct PermissionState {
+    permissions: Permissions,
+    denied: Vec<(Kind, String)>,
+}
+
+impl PermissionState {
+    pub(crate) fn new(mut permissions: Permissions) -> Self {
+        // Relative paths are relative to the working directory at startup,
+        // even if the script changes it later.
+        make_absolute(&mut permissions.read);
+        make_absolute(&mut permissions.write);
+        Self {
+            permissions,
+            denied: Vec::new(),
+        }
+    }
+
+    /// Check access of `kind` to `resource`, which is a path for reads and
+    /// writes, `host:port` for the network, and a name otherwise.
+    fn check(&mut self, kind: Kind, resource: &str) -> Result<(), String> {
+        let (resource, target) = match kind {
+            Kind::Read | Kind::Write => {
+                let path = absolute(resource);
+                let real = real_path(&path);
+                (path, real)
+            }
+            _ => (resource.to_owned(), resource.to_owned()),
+        };
+        let granted = match self.permissions.get_mut(kind) {
+            Permission::Denied => false,
+            Permission::Granted => true,
+            Permission::GrantedFor(entries) => {
+                entries.iter().any(|entry| matches(kind, entry, &target))
+            }
+        };
+        if granted {
+            return Ok(());
+        }
+
+        let message = format!("Requires {} access to \"{}\"", kind.name(), resource);
+        if !self.permissions.prompt || self.denied.contains(&(kind, resource.clone())) {
+            return Err(message);
+        }
+        match prompt(kind, &resource) {
+            Answer::Yes => {
+                let permission = self.permissions.get_mut(kind);
+                match permission {
+                    Permission::GrantedFor(entries) => entries.push(target),
+                    _ => *permission = Permission::GrantedFor(vec![target]),
+                }
+                Ok(())
+            }
+            Answer::All => {
+                *self.permissions.get_mut(kind) = Permission::Granted;
+                Ok(())
+            }
+            Answer::No => {
+                self.denied.push((kind, resource));
+                Err(message)
+            }
+        }
+    }
+
+    /// Check that access of `kind` is granted to everything. This isn't
+    /// asked for at the prompt.
+    fn check_all(&mut self, kind: Kind) -> Result<(), String> {
+        match self.permissions.get_mut(kind) {
+            Permission::Granted => Ok(()),
+            _ => Err(format!("Requires full {} access", kind.name())),
+        }
+    }
+}
+
+fn make_absolute(permission: &mut Permission) {
+    if let Permission::GrantedFor(paths) = permission {
+        for path in paths.iter_mut() {
+            *path = real_path(&absolute(path));
+        }
+    }
+}
+
+/// Where the absolute `path` really is once symlinks are resolved. For a
+/// path that doesn't exist yet, that's where its nearest existing ancestor
+/// is, with the rest of it appended, as creating it would follow those.
+fn real_path(path: &str) -> String {
+    // Allow for as many symlinks as the OS does, but no loops.
+    const MAX_LINKS: usize = 40;
+
+    let mut path = Path::new(path).to_path_buf();
+    let mut links = 0;
+    'resolve: loop {
+        let mut existing = path.as_path();
+        let mut rest = Vec::new();
+        loop {
+            if let Ok(mut real) = existing.canonicalize() {
+                real.extend(rest.iter().rev());
+                return real.to_string_lossy().into_owned();
+            }
+            // A dangling symlink, which creating the path would follow.
+            if let Ok(target) = std::fs::read_link(existing) {
+                if links < MAX_LINKS {
+                    links += 1;
+                    let mut resolved = match existing.parent() {
+                        Some(parent) => parent.join(target),
+                        None => target,
+                    };
+                    resolved.extend(rest.iter().rev());
+                    path = Path::new(&crate::bundle::normalize(&resolved)).to_path_buf();
+                    continue 'resolve;
+                }
+            }
+            match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    rest.push(name.to_owned());
+                    existing = parent;
+                }
+                _ => return path.to_string_lossy().into_owned(),
+            }
+        }
+    }
+}
+
+fn matches(kind: Kind, entry: &str, resource: &str) -> bool {
+    match kind {
+        Kind::Read | Kind::Write => Path::new(resource).starts_with(entry),
+        // A host without a port allows all of its ports.
+        Kind::Net => {
+            entry == resource
+                || resource
+                    .rsplit_once(':')
+                    .map_or(false, |(host, _)| entry == host)
+        }
+        Kind::Env | Kind::Run => entry == resource,
+    }
+}
+
+fn absolute(path: &str) -> String {
+    match std::env::current_dir() {
+        Ok(cwd) => crate::bundle::normalize(&cwd.join(path)),
+        Err(_) => crate::bundle::normalize(Path::new(path)),
+    }
+}
+
+enum Answer {
+    Yes,
+    No,
+    All,
+}
+
+fn prompt(kind: Kind, resource: &str) -> Answer {
+    let mut stderr = std::io::stderr();
+    let _ = write!(
+        stderr,
+        "jstime requests {} access to \"{}\". Allow? [y/n/A (all {} access)] ",
+        kind.name(),
+        resource,
+        kind.name()
+    );
+    let _ = stderr.flush();
+
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line).is_err() {
+        return Answer::No;
+    }
+    match line.trim() {
+        "y" | "Y" | "yes" => Answer::Yes,
+        "A" => Answer::All,
+        _ => Answer::No,
+    }
+}
+
+/// Check access of `kind` to `resource`, throwing a `PermissionDenied`
+/// error and returning `false` if it isn't granted.
+pub(crate) fn check(scope: &mut v8::HandleScope, kind: Kind, resource: &str) -> bool {
+    let state = crate::IsolateState::get(scope);
+    let result = state.borrow_mut().permissions.check(kind, resource);
+    allowed(scope, result)
+}
+
+/// Check that access of `kind` is granted to everything, for operations
+/// whose effects can't be limited to the resources they're given, like
+/// `check`.
+pub(crate) fn check_all(scope: &mut v8::HandleScope, kind: Kind) -> bool {
+    let state = crate::IsolateState::get(scope);
+    let result = state.borrow_mut().permissions.check_all(kind);
+    allowed(scope, result)
+}
+
+fn allowed(scope: &mut v8::HandleScope, result: Result<(), String>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(message) => {
+            let message = v8::String::new(scope, &message).unwrap();
+            let exception = v8::Exception::error(scope, message);
+            let error = v8::Local::<v8::Object>::try_from(exception).unwrap();
+            let key = v8::String::new(scope, "name").unwrap();
+            let name = v8::String::new(scope, "PermissionDenied").unwrap();
+            error.set(scope, key.into(), name.into());
+            scope.throw_exception(exception);
+            false
+        }
+    }
+}
+
+/// The `host:port` a URL connects to, for checking net access. This uses
+/// the same parser as the HTTP client, so that the host checked is the one
+/// it connects to.
+pub(crate) fn net_resource(url: &url::Url) -> Option<String> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    match url.port_or_known_default() {
+        Some(port) => Some(format!("{}:{}", host, port)),
+        None => Some(host),
+    }
+}
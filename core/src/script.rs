@@ -7,8 +7,10 @@ pub(crate) fn run<'s>(
 ) -> Result<v8::Local<'s, v8::Value>, v8::Local<'s, v8::Value>> {
     let scope = &mut v8::TryCatch::new(scope);
 
+    let source_map_url = js_loading::register_source_map(scope, filepath, js);
     let filepath = v8::String::new(scope, filepath).unwrap();
-    let origin = js_loading::create_script_origin(scope, filepath, false);
+    let origin =
+        js_loading::create_script_origin(scope, filepath, source_map_url.as_deref(), false);
 
     let code = v8::String::new(scope, js).unwrap();
 
@@ -0,0 +1,130 @@
+// Source map discovery and stack frame remapping.
+// https://sourcemaps.info/spec.html
+
+use std::collections::HashMap;
+use std::path::Path;
+
+pub(crate) struct SourceMaps {
+    enabled: bool,
+    maps: HashMap<String, Entry>,
+}
+
+struct Entry {
+    map: sourcemap::DecodedMap,
+    // Directory that relative `sources` entries are resolved against.
+    base: Option<String>,
+}
+
+impl SourceMaps {
+    pub(crate) fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            maps: HashMap::new(),
+        }
+    }
+
+    /// Look for a `//# sourceMappingURL=` comment in `source` and load the
+    /// map it points to, either inline as a data URL or as a file relative
+    /// to `filepath`, read through `read`. Returns the URL so it can be
+    /// attached to the script origin.
+    pub(crate) fn register<F>(&mut self, filepath: &str, source: &str, read: F) -> Option<String>
+    where
+        F: Fn(&Path) -> Option<Vec<u8>>,
+    {
+        let reference = sourcemap::locate_sourcemap_reference_slice(source.as_bytes()).ok()??;
+        let url = reference.get_url().to_owned();
+        if !self.enabled {
+            return Some(url);
+        }
+
+        let entry = if url.starts_with("data:") {
+            reference
+                .get_embedded_sourcemap()
+                .ok()
+                .flatten()
+                .map(|map| Entry {
+                    map,
+                    base: parent_dir(filepath),
+                })
+        } else {
+            let map_path = match parent_dir(filepath) {
+                Some(dir) => Path::new(&dir).join(&url),
+                None => Path::new(&url).to_path_buf(),
+            };
+            read(&map_path)
+                .and_then(|bytes| sourcemap::decode_slice(&bytes).ok())
+                .map(|map| Entry {
+                    map,
+                    base: map_path.parent().map(|p| p.to_string_lossy().into_owned()),
+                })
+        };
+
+        if let Some(entry) = entry {
+            self.maps.insert(filepath.to_owned(), entry);
+        }
+        Some(url)
+    }
+
+    /// Map a 1-based line and column in `filepath` back to the original
+    /// source, if a source map was registered for it.
+    pub(crate) fn apply(
+        &self,
+        filepath: &str,
+        line: u32,
+        column: u32,
+    ) -> Option<(String, u32, u32)> {
+        if !self.enabled || line == 0 || column == 0 {
+            return None;
+        }
+        let entry = self.maps.get(filepath)?;
+        let token = entry.map.lookup_token(line - 1, column - 1)?;
+        let source = entry.source(&token)?;
+        Some((source, token.get_src_line() + 1, token.get_src_col() + 1))
+    }
+
+    /// Like `apply`, but only for mappings that start on the same line, and
+    /// returning just the original source and line.
+    pub(crate) fn apply_to_line(
+        &self,
+        filepath: &str,
+        line: u32,
+        column: u32,
+    ) -> Option<(String, u32)> {
+        if !self.enabled || line == 0 || column == 0 {
+            return None;
+        }
+        let entry = self.maps.get(filepath)?;
+        let token = entry.map.lookup_token(line - 1, column - 1)?;
+        if token.get_dst_line() != line - 1 {
+            return None;
+        }
+        Some((entry.source(&token)?, token.get_src_line() + 1))
+    }
+
+    pub(crate) fn contains(&self, filepath: &str) -> bool {
+        self.enabled && self.maps.contains_key(filepath)
+    }
+}
+
+impl Entry {
+    fn source(&self, token: &sourcemap::Token) -> Option<String> {
+        let source = token.get_source()?;
+        Some(match &self.base {
+            Some(base) if is_relative(source) => {
+                Path::new(base).join(source).to_string_lossy().into_owned()
+            }
+            _ => source.to_owned(),
+        })
+    }
+}
+
+fn parent_dir(filepath: &str) -> Option<String> {
+    Path::new(filepath)
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .map(|p| p.to_string_lossy().into_owned())
+}
+
+fn is_relative(source: &str) -> bool {
+    !source.contains("://") && Path::new(source).is_relative()
+}
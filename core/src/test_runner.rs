@@ -0,0 +1,70 @@
+// Glue between `JSTime::run_tests` and the runner in builtins/test.js.
+
+use std::time::Duration;
+
+/// How `JSTime::run_tests` reports results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestReporter {
+    /// Human readable, grouped by file and `describe` block.
+    Spec,
+    /// Test Anything Protocol, version 13.
+    Tap,
+    /// JUnit XML, as read by most CI systems.
+    Junit,
+}
+
+impl TestReporter {
+    fn name(self) -> &'static str {
+        match self {
+            TestReporter::Spec => "spec",
+            TestReporter::Tap => "tap",
+            TestReporter::Junit => "junit",
+        }
+    }
+}
+
+impl std::str::FromStr for TestReporter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spec" => Ok(TestReporter::Spec),
+            "tap" => Ok(TestReporter::Tap),
+            "junit" => Ok(TestReporter::Junit),
+            _ => Err(format!("unknown reporter: {}", s)),
+        }
+    }
+}
+
+/// Options for `JSTime::run_tests`.
+pub struct TestOptions {
+    pub reporter: TestReporter,
+    /// Timeout of tests and hooks that don't set their own. Zero disables
+    /// it. Defaults to 5 seconds.
+    pub timeout: Duration,
+}
+
+impl Default for TestOptions {
+    fn default() -> TestOptions {
+        TestOptions {
+            reporter: TestReporter::Spec,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Start running the tests registered through `jstime:test`, returning a
+/// promise of whether all of them passed.
+pub(crate) fn run<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    options: &TestOptions,
+) -> Result<v8::Local<'a, v8::Value>, String> {
+    let object = v8::Object::new(scope);
+    let key = v8::String::new(scope, "reporter").unwrap();
+    let value = v8::String::new(scope, options.reporter.name()).unwrap();
+    object.set(scope, key.into(), value.into());
+    let key = v8::String::new(scope, "timeout").unwrap();
+    let value = v8::Number::new(scope, options.timeout.as_millis() as f64);
+    object.set(scope, key.into(), value.into());
+    crate::builtins::call_internal(scope, "test", "run", &[object.into()])
+}
@@ -0,0 +1,208 @@
+// WebAssembly modules in the ES module graph.
+// https://github.com/WebAssembly/esm-integration/tree/main/proposals/esm-integration
+
+use crate::IsolateState;
+use std::convert::TryFrom;
+
+/// Compile `bytes` into a module that imports the modules the WebAssembly
+/// module imports from, instantiates it with their namespaces, and re-exports
+/// its exports. Its imports are then linked and evaluated like those of any
+/// other module, so cycles and top-level await in them are handled by V8.
+pub(crate) fn compile<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    filepath: &str,
+    bytes: Vec<u8>,
+) -> Option<v8::Local<'a, v8::Module>> {
+    let module_constructor = web_assembly_constructor(scope, "Module")?;
+    let store = v8::ArrayBuffer::new_backing_store_from_boxed_slice(bytes.into_boxed_slice());
+    let buffer = v8::ArrayBuffer::with_backing_store(scope, &store.make_shared());
+    let wasm_module = module_constructor.new_instance(scope, &[buffer.into()])?;
+
+    let mut specifiers = Vec::<String>::new();
+    for (specifier, _) in descriptors(scope, module_constructor, wasm_module, "imports")? {
+        let specifier = specifier.to_rust_string_lossy(scope);
+        if !specifiers.contains(&specifier) {
+            specifiers.push(specifier);
+        }
+    }
+    let export_names = descriptors(scope, module_constructor, wasm_module, "exports")?
+        .into_iter()
+        .map(|(_, name)| name.to_rust_string_lossy(scope))
+        .collect::<Vec<_>>();
+
+    let src = wrapper_source(&specifiers, &export_names);
+    let module_name = v8::String::new(scope, filepath).unwrap();
+    let origin = crate::js_loading::create_script_origin(scope, module_name, None, true);
+    let code = v8::String::new(scope, &src).unwrap();
+    let source = v8::script_compiler::Source::new(code, Some(&origin));
+    let module = v8::script_compiler::compile_module(scope, source)?;
+
+    let state = IsolateState::get(scope);
+    let wasm_module = v8::Global::new(scope, wasm_module);
+    state
+        .borrow_mut()
+        .wasm_modules
+        .insert(v8::Global::new(scope, module), wasm_module);
+    Some(module)
+}
+
+/// The source of the module `compile` returns. The `WebAssembly.Module` is
+/// passed to it as `import.meta.wasmModule`.
+fn wrapper_source(specifiers: &[String], export_names: &[String]) -> String {
+    let quote = |s: &str| serde_json::to_string(s).unwrap();
+    let mut src = String::new();
+    for (i, specifier) in specifiers.iter().enumerate() {
+        src += &format!("import * as import{} from {};\n", i, quote(specifier));
+    }
+    src += "const { exports } = new WebAssembly.Instance(import.meta.wasmModule, {\n";
+    for (i, specifier) in specifiers.iter().enumerate() {
+        src += &format!("  {}: import{},\n", quote(specifier), i);
+    }
+    src += "});\n";
+    for (i, name) in export_names.iter().enumerate() {
+        src += &format!("const export{} = exports[{}];\n", i, quote(name));
+        src += &format!("export {{ export{} as {} }};\n", i, quote(name));
+    }
+    src
+}
+
+/// Set `import.meta.wasmModule` for a module returned by `compile`.
+pub(crate) fn initialize_import_meta(
+    scope: &mut v8::HandleScope,
+    module: v8::Local<v8::Module>,
+    meta: v8::Local<v8::Object>,
+) {
+    let state = IsolateState::get(scope);
+    let key = v8::Global::new(scope, module);
+    let wasm_module = state.borrow_mut().wasm_modules.remove(&key);
+    if let Some(wasm_module) = wasm_module {
+        let key = v8::String::new(scope, "wasmModule").unwrap();
+        let value = v8::Local::new(scope, wasm_module);
+        meta.create_data_property(scope, key.into(), value.into());
+    }
+}
+
+/// The specifiers of the modules a WebAssembly module imports from.
+pub(crate) fn import_specifiers(
+    scope: &mut v8::HandleScope,
+    bytes: Vec<u8>,
+) -> Option<Vec<String>> {
+    let module_constructor = web_assembly_constructor(scope, "Module")?;
+    let store = v8::ArrayBuffer::new_backing_store_from_boxed_slice(bytes.into_boxed_slice());
+    let buffer = v8::ArrayBuffer::with_backing_store(scope, &store.make_shared());
+    let wasm_module = module_constructor.new_instance(scope, &[buffer.into()])?;
+    let specifiers = descriptors(scope, module_constructor, wasm_module, "imports")?
+        .into_iter()
+        .map(|(specifier, _)| specifier.to_rust_string_lossy(scope))
+        .collect();
+    Some(specifiers)
+}
+
+/// Look up a constructor such as `WebAssembly.Module` on the global object.
+fn web_assembly_constructor<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    name: &str,
+) -> Option<v8::Local<'a, v8::Function>> {
+    let global = scope.get_current_context().global(scope);
+    let key = v8::String::new(scope, "WebAssembly").unwrap();
+    let web_assembly = global.get(scope, key.into())?;
+    let web_assembly = v8::Local::<v8::Object>::try_from(web_assembly).ok()?;
+    let key = v8::String::new(scope, name).unwrap();
+    let constructor = web_assembly.get(scope, key.into())?;
+    v8::Local::<v8::Function>::try_from(constructor).ok()
+}
+
+/// Call `WebAssembly.Module.imports` or `WebAssembly.Module.exports` and
+/// collect the `module` and `name` of each descriptor.
+fn descriptors<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    module_constructor: v8::Local<v8::Function>,
+    wasm_module: v8::Local<v8::Object>,
+    kind: &str,
+) -> Option<Vec<(v8::Local<'a, v8::String>, v8::Local<'a, v8::String>)>> {
+    let key = v8::String::new(scope, kind).unwrap();
+    let func = module_constructor.get(scope, key.into())?;
+    let func = v8::Local::<v8::Function>::try_from(func).ok()?;
+    let list = func.call(scope, module_constructor.into(), &[wasm_module.into()])?;
+    let list = v8::Local::<v8::Array>::try_from(list).ok()?;
+
+    let module_key = v8::String::new(scope, "module").unwrap();
+    let name_key = v8::String::new(scope, "name").unwrap();
+    let mut result = Vec::new();
+    for i in 0..list.length() {
+        let descriptor = list.get_index(scope, i)?;
+        let descriptor = v8::Local::<v8::Object>::try_from(descriptor).ok()?;
+        let module = descriptor
+            .get(scope, module_key.into())
+            .and_then(|v| v.to_string(scope))
+            .unwrap_or_else(|| v8::String::empty(scope));
+        let name = descriptor.get(scope, name_key.into())?.to_string(scope)?;
+        result.push((module, name));
+    }
+    Some(result)
+}
+
+/// Run a WASI command module through the `WASI` builtin, returning its exit
+/// code.
+pub(crate) fn run_wasi<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    bytes: Vec<u8>,
+    args: &[String],
+    preopens: &[String],
+) -> Result<i32, v8::Local<'a, v8::Value>> {
+    let scope = &mut v8::TryCatch::new(scope);
+    match start_wasi(scope, bytes, args, preopens) {
+        Some(code) => Ok(code),
+        None => Err(scope
+            .exception()
+            .unwrap_or_else(|| v8::undefined(scope).into())),
+    }
+}
+
+fn start_wasi(
+    scope: &mut v8::HandleScope,
+    bytes: Vec<u8>,
+    args: &[String],
+    preopens: &[String],
+) -> Option<i32> {
+    let options = v8::Object::new(scope);
+    let args = args
+        .iter()
+        .map(|arg| v8::String::new(scope, arg).unwrap().into())
+        .collect::<Vec<v8::Local<v8::Value>>>();
+    let args = v8::Array::new_with_elements(scope, &args);
+    let key = v8::String::new(scope, "args").unwrap();
+    options.set(scope, key.into(), args.into());
+    let dirs = v8::Object::new(scope);
+    for dir in preopens {
+        let dir = v8::String::new(scope, dir).unwrap();
+        dirs.set(scope, dir.into(), dir.into());
+    }
+    let key = v8::String::new(scope, "preopens").unwrap();
+    options.set(scope, key.into(), dirs.into());
+
+    let global = scope.get_current_context().global(scope);
+    let key = v8::String::new(scope, "WASI").unwrap();
+    let wasi_constructor = global.get(scope, key.into())?;
+    let wasi_constructor = v8::Local::<v8::Function>::try_from(wasi_constructor).ok()?;
+    let wasi = wasi_constructor.new_instance(scope, &[options.into()])?;
+
+    let key = v8::String::new(scope, "getImportObject").unwrap();
+    let get_import_object = wasi.get(scope, key.into())?;
+    let get_import_object = v8::Local::<v8::Function>::try_from(get_import_object).ok()?;
+    let import_object = get_import_object.call(scope, wasi.into(), &[])?;
+
+    let store = v8::ArrayBuffer::new_backing_store_from_boxed_slice(bytes.into_boxed_slice());
+    let buffer = v8::ArrayBuffer::with_backing_store(scope, &store.make_shared());
+    let module =
+        web_assembly_constructor(scope, "Module")?.new_instance(scope, &[buffer.into()])?;
+    let instance = web_assembly_constructor(scope, "Instance")?
+        .new_instance(scope, &[module.into(), import_object])?;
+
+    let key = v8::String::new(scope, "start").unwrap();
+    let start = wasi.get(scope, key.into())?;
+    let start = v8::Local::<v8::Function>::try_from(start).ok()?;
+    start
+        .call(scope, wasi.into(), &[instance.into()])?
+        .int32_value(scope)
+}
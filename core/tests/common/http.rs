@@ -0,0 +1,106 @@
+// A minimal HTTP server for tests of `fetch`, which answers each connection
+// with a canned response.
+
+// Not every test binary that includes `common` serves HTTP.
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Sender};
+use std::thread::JoinHandle;
+
+/// A request as the server received it.
+pub struct Request {
+    /// The request line and headers, lowercased, each followed by `\n`.
+    pub head: String,
+    /// The body, sent with a Content-Length or chunked.
+    pub body: String,
+}
+
+/// Read a request from `stream`.
+pub fn read_request(stream: &TcpStream) -> Request {
+    let mut reader = BufReader::new(stream);
+    let mut head = String::new();
+    let mut length = 0;
+    let mut chunked = false;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line == "\r\n" {
+            break;
+        }
+        let line = line.trim_end().to_ascii_lowercase();
+        if let Some(value) = line.strip_prefix("content-length: ") {
+            length = value.parse().unwrap();
+        }
+        if line == "transfer-encoding: chunked" {
+            chunked = true;
+        }
+        head.push_str(&line);
+        head.push('\n');
+    }
+
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            let mut size = String::new();
+            reader.read_line(&mut size).unwrap();
+            let size = usize::from_str_radix(size.trim_end(), 16).unwrap();
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk).unwrap();
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+    } else {
+        body.resize(length, 0);
+        reader.read_exact(&mut body).unwrap();
+    }
+    Request {
+        head,
+        body: String::from_utf8(body).unwrap(),
+    }
+}
+
+/// A server on a free port of 127.0.0.1 that accepts a connection for each
+/// response it was started with, in turn.
+pub struct Server {
+    /// The URL of the server, without a trailing `/`.
+    pub base: String,
+    done: Sender<()>,
+    thread: JoinHandle<Vec<Request>>,
+}
+
+impl Server {
+    /// Start serving `responses`. Each connection gets the next one once
+    /// its request was read, or no response if it's `None`. Connections
+    /// stay open until `finish`, so a response can stop partway.
+    pub fn start(responses: Vec<Option<&'static str>>) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let (done, wait) = channel();
+        let thread = std::thread::spawn(move || {
+            let mut streams = Vec::new();
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                requests.push(read_request(&stream));
+                if let Some(response) = response {
+                    stream.write_all(response.as_bytes()).unwrap();
+                }
+                streams.push(stream);
+            }
+            let _ = wait.recv();
+            requests
+        });
+        Server { base, done, thread }
+    }
+
+    /// Close the connections once every response was served, returning the
+    /// requests the server received.
+    pub fn finish(self) -> Vec<Request> {
+        let _ = self.done.send(());
+        self.thread.join().unwrap()
+    }
+}
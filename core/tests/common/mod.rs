@@ -14,6 +14,8 @@ use std::sync::Mutex;
 
 use jstime_core as jstime;
 
+pub mod http;
+
 lazy_static! {
     static ref INIT_LOCK: Mutex<u32> = Mutex::new(0);
 }
@@ -0,0 +1,2 @@
+import data from '../greeting.json';
+export const greet = (name) => `${data.greeting}, ${name}`;
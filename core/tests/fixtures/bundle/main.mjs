@@ -0,0 +1,2 @@
+import { greet } from './lib/greet.mjs';
+globalThis.result = greet('bundle');
@@ -0,0 +1,7 @@
+// Comments aren't code, so they aren't reported as lines.
+import { add } from './math.js';
+
+/*
+ * Read by the test.
+ */
+globalThis.sum = add(1, 2);
@@ -0,0 +1,20 @@
+const controller = new AbortController();
+setTimeout(() => controller.abort(), 50);
+const error = await fetch(globalThis.base, { signal: controller.signal }).catch((e) => e);
+const before = await fetch(globalThis.base, { signal: AbortSignal.abort() }).catch((e) => e);
+// Aborting while the body is read cancels the read.
+const streaming = new AbortController();
+const response = await fetch(`${globalThis.base}/body`, { signal: streaming.signal });
+const reader = response.body.getReader();
+const { value } = await reader.read();
+setTimeout(() => streaming.abort(), 50);
+const body = await reader.read().catch((e) => e);
+// A pending timeout doesn't keep jstime running.
+AbortSignal.timeout(60000);
+globalThis.result = [
+  error.name,
+  error === controller.signal.reason,
+  before.name,
+  new TextDecoder().decode(value),
+  body.name,
+].join(',');
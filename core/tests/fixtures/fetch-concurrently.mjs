@@ -0,0 +1,5 @@
+const texts = await Promise.all([1, 2, 3, 4].map(async (n) => {
+  const response = await fetch(`${globalThis.base}/${n}`);
+  return response.text();
+}));
+globalThis.result = texts.join();
@@ -0,0 +1,21 @@
+const encoder = new TextEncoder();
+const body = new ReadableStream({
+  start(controller) {
+    controller.enqueue(encoder.encode('ab'));
+    controller.enqueue(encoder.encode('cd'));
+    controller.close();
+  },
+});
+const response = await fetch(`${globalThis.base}/upload`, {
+  method: 'PUT',
+  body,
+  duplex: 'half',
+});
+const reader = response.body.getReader({ mode: 'byob' });
+const { value } = await reader.read(new Uint8Array(4));
+reader.releaseLock();
+let rest = '';
+for await (const chunk of response.body) {
+  rest += new TextDecoder().decode(chunk);
+}
+globalThis.result = [new TextDecoder().decode(value), rest, body.locked].join(',');
@@ -0,0 +1,15 @@
+const response = await fetch(`${globalThis.base}/redirect`, {
+  method: 'POST',
+  headers: { 'X-Test': 'yes' },
+  body: 'ping',
+});
+const error = await fetch('http://127.0.0.1:1/').catch((e) => e.name);
+globalThis.result = [
+  response.status,
+  response.redirected,
+  response.url === `${globalThis.base}/final`,
+  response.headers.get('Content-Type'),
+  (await response.json()).ok,
+  response.bodyUsed,
+  error,
+].join(',');
@@ -0,0 +1,21 @@
+import * as fs from 'jstime:fs';
+
+const { dir } = globalThis;
+fs.writeTextFileSync(`${dir}a.txt`, 'hello');
+await fs.writeFile(`${dir}a.txt`, ' world', { append: true });
+await fs.copyFile(`${dir}a.txt`, `${dir}b.txt`);
+await fs.mkdir(`${dir}sub/deep`, { recursive: true });
+await fs.rename(`${dir}b.txt`, `${dir}sub/c.txt`);
+const file = await fs.open(`${dir}sub/c.txt`);
+const buffer = new Uint8Array(5);
+await file.read(buffer);
+file.close();
+const names = (await fs.readDir(`${dir}sub`)).map((e) => e.name).sort();
+const missing = await fs.stat(`${dir}missing`).catch((e) => e.code);
+globalThis.result = [
+  await fs.readTextFile(`${dir}sub/c.txt`),
+  new TextDecoder().decode(buffer),
+  fs.statSync(`${dir}a.txt`).size,
+  names.join(' '),
+  missing,
+].join(',');
@@ -0,0 +1,9 @@
+import dep from './my%20dir/dep.mjs';
+
+const sibling = new URL('./my%20dir/dep.mjs', import.meta.url);
+globalThis.result = [
+  import.meta.url.startsWith('file:///'),
+  import.meta.url.endsWith('/main.mjs'),
+  dep === sibling.href,
+  dep.endsWith('/my%20dir/dep.mjs'),
+].join(',');
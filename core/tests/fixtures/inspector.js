@@ -0,0 +1,2 @@
+console.log('hello inspector');
+globalThis.inspected = true;
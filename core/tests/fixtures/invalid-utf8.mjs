@@ -0,0 +1 @@
+import data from './invalid-utf8.json';
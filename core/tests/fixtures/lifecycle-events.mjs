@@ -0,0 +1,21 @@
+const events = [];
+addEventListener('load', () => events.push('load'));
+addEventListener('error', (event) => {
+  events.push(`error ${event.error.message}`);
+  event.preventDefault();
+});
+addEventListener('unhandledrejection', (event) => {
+  events.push(`unhandledrejection ${event.reason}`);
+  event.preventDefault();
+});
+addEventListener('unload', () => {
+  events.push('unload');
+  globalThis.result = events.join();
+});
+setTimeout(() => {
+  throw new Error('timer');
+});
+Promise.reject('nobody');
+Promise.reject('handled').catch(() => {});
+await null;
+events.push('evaluated');
@@ -0,0 +1,14 @@
+const attempt = (url, init) => fetch(url, init).then(() => 'ok', (e) => `${e.name}: ${e.message}`);
+
+globalThis.result = [
+  await attempt('https://example.org/'),
+  await attempt('http://user@EXAMPLE.org:8080/'),
+  // The host checked is the one the request would go to.
+  await attempt('http://evil.com\\@example.com/'),
+  // Denied before a streamed body is taken.
+  await attempt('http://example.net/', {
+    method: 'POST',
+    body: new ReadableStream({ pull: (c) => c.close() }),
+    duplex: 'half',
+  }),
+].join('\n');
@@ -0,0 +1,17 @@
+import * as fs from 'jstime:fs';
+
+const { dir } = globalThis;
+const attempt = (f) => {
+  try {
+    f();
+    return 'ok';
+  } catch (e) {
+    return e.name;
+  }
+};
+globalThis.result = [
+  attempt(() => fs.readTextFileSync(`${dir}inside.txt`)),
+  attempt(() => fs.readTextFileSync(`${dir}link/secret.txt`)),
+  attempt(() => fs.writeTextFileSync(`${dir}link/new.txt`, 'new')),
+  attempt(() => fs.symlinkSync('/', `${dir}root`)),
+].join(',');
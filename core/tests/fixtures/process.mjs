@@ -0,0 +1,18 @@
+import process, { args, env, versions } from 'jstime:process';
+
+env.set('JSTIME_TEST_VAR', 'value');
+const value = env.get('JSTIME_TEST_VAR');
+env.delete('JSTIME_TEST_VAR');
+globalThis.result = [
+  args.join(' '),
+  value,
+  env.get('JSTIME_TEST_VAR'),
+  typeof versions.v8,
+  process.pid > 0,
+].join(',');
+
+process.exitCode = 1;
+setTimeout(() => process.exit(3), 0);
+setTimeout(() => {
+  globalThis.result = 'still running';
+}, 10);
@@ -0,0 +1,6 @@
+"use strict";
+function fail() {
+  throw new Error("oh no, mapped");
+}
+fail();
+//# sourceMappingURL=data:application/json;base64,eyJ2ZXJzaW9uIjogMywgImZpbGUiOiAiaW5saW5lLmpzIiwgInNvdXJjZXMiOiBbIm9yaWdpbmFsLnRzIl0sICJuYW1lcyI6IFtdLCAibWFwcGluZ3MiOiAiO0FBQ0EsU0FBUztFQUVQLE1BQU07QUFDUjtBQUVBIn0=
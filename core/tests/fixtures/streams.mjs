@@ -0,0 +1,47 @@
+const source = new ReadableStream({
+  start(controller) {
+    controller.enqueue('a');
+    controller.enqueue('b');
+    controller.close();
+  },
+});
+const [left, right] = source.tee();
+const iterated = [];
+for await (const chunk of left) {
+  iterated.push(chunk);
+}
+const { value: first } = await right.getReader().read();
+
+const written = [];
+await ReadableStream.from(['x', 'y'])
+  .pipeThrough(new TransformStream({
+    transform(chunk, controller) {
+      controller.enqueue(chunk.toUpperCase());
+    },
+  }))
+  .pipeTo(new WritableStream({
+    write(chunk) {
+      written.push(chunk);
+    },
+  }));
+
+const bytes = new ReadableStream({
+  type: 'bytes',
+  start(controller) {
+    controller.enqueue(new Uint8Array([1, 2, 3]));
+    controller.close();
+  },
+});
+const reader = bytes.getReader({ mode: 'byob' });
+const { value: view } = await reader.read(new Uint8Array(2));
+
+const writer = new WritableStream({}, new CountQueuingStrategy({ highWaterMark: 2 })).getWriter();
+const desiredSize = writer.desiredSize;
+
+globalThis.result = [
+  iterated.join(''),
+  first,
+  written.join(''),
+  view.join(' '),
+  desiredSize,
+].join(',');
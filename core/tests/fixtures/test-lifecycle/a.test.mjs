@@ -0,0 +1,8 @@
+import { test } from 'jstime:test';
+
+globalThis.events ??= [];
+addEventListener('load', () => events.push('load a'));
+addEventListener('unload', () => events.push('unload a'));
+test('a', () => {
+  events.push('test a');
+});
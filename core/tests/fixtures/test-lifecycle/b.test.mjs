@@ -0,0 +1,8 @@
+import { test } from 'jstime:test';
+
+globalThis.events ??= [];
+addEventListener('load', () => events.push('load b'));
+addEventListener('unload', () => events.push('unload b'));
+test('b', () => {
+  events.push('test b');
+});
@@ -0,0 +1,7 @@
+import test from 'jstime:test';
+
+test('fails', () => {
+  throw new Error('expected failure');
+});
+
+test('times out', { timeout: 10 }, () => new Promise(() => {}));
@@ -0,0 +1,20 @@
+import { test, describe, beforeEach } from 'jstime:test';
+
+let count = 0;
+beforeEach(() => {
+  count += 1;
+});
+
+test('runs beforeEach hooks', () => {
+  if (count !== 1) throw new Error(`count is ${count}`);
+});
+
+describe('async', () => {
+  test('waits for timers', async () => {
+    await new Promise((resolve) => setTimeout(resolve, 10));
+  });
+
+  test.skip('is skipped', () => {
+    throw new Error('skipped tests should not run');
+  });
+});
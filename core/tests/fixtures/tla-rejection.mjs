@@ -0,0 +1,3 @@
+await new Promise((resolve, reject) => {
+  setTimeout(() => reject(new Error('late')));
+});
@@ -0,0 +1 @@
+Promise.reject(new Error('nobody'));
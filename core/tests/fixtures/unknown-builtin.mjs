@@ -0,0 +1,3 @@
+import { nope } from 'jstime:nope';
+
+nope();
@@ -0,0 +1,38 @@
+import * as fs from 'jstime:fs';
+
+const { dir } = globalThis;
+fs.mkdirSync(`${dir}sandbox`);
+fs.writeTextFileSync(`${dir}inside.txt`, 'hi');
+fs.symlinkSync(dir, `${dir}sandbox/escape`);
+
+// A module that only exports its memory, to call the imports against.
+const bytes = new Uint8Array([
+  0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00,
+  0x05, 0x03, 0x01, 0x00, 0x01,
+  0x07, 0x0a, 0x01, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00,
+]);
+const instance = new WebAssembly.Instance(new WebAssembly.Module(bytes));
+const wasi = new WASI({ preopens: { '/': '/', '/sandbox': `${dir}sandbox` } });
+wasi.initialize(instance);
+
+const { memory } = instance.exports;
+const path = (value) => {
+  const encoded = new TextEncoder().encode(value);
+  new Uint8Array(memory.buffer).set(encoded, 0);
+  return [0, encoded.length];
+};
+const { path_filestat_get: stat, path_unlink_file: unlink } = wasi.wasiImport;
+
+const results = [
+  stat(3, 1, ...path(`${dir.slice(1)}inside.txt`), 64),
+  new DataView(memory.buffer).getBigUint64(64 + 32, true),
+  stat(4, 1, ...path('escape'), 64),
+  unlink(4, ...path('escape')),
+];
+try {
+  fs.lstatSync(`${dir}sandbox/escape`);
+  results.push('still there');
+} catch (e) {
+  results.push(e.code);
+}
+globalThis.result = results.join();
@@ -47,4 +47,708 @@ mod api {
         let result = jstime.run_script("globalThis.hello", "jstime");
         assert_eq!(result.unwrap(), "hello world");
     }
+    #[test]
+    fn import_with_code_cache() {
+        let _setup_guard = common::setup();
+        let cache_dir =
+            std::env::temp_dir().join(format!("jstime-code-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&cache_dir);
+        let hello_path = "./tests/fixtures/hello-world.js";
+
+        // The first run produces the cache, the second consumes it.
+        for _ in 0..2 {
+            let mut options = jstime::Options::default();
+            options.code_cache = Some(cache_dir.clone());
+            let mut jstime = jstime::JSTime::new(options);
+            jstime.import(hello_path).unwrap();
+            let result = jstime.run_script("globalThis.hello", "jstime");
+            assert_eq!(result.unwrap(), "hello world");
+            assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 1);
+        }
+
+        // A corrupted entry that V8 rejects is replaced.
+        let entry = std::fs::read_dir(&cache_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        std::fs::write(&entry, b"corrupted").unwrap();
+        let mut options = jstime::Options::default();
+        options.code_cache = Some(cache_dir.clone());
+        let mut jstime = jstime::JSTime::new(options);
+        jstime.import(hello_path).unwrap();
+        let result = jstime.run_script("globalThis.hello", "jstime");
+        assert_eq!(result.unwrap(), "hello world");
+        let data = std::fs::read(&entry).unwrap();
+        assert!(!data.is_empty() && data != b"corrupted");
+
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+    #[test]
+    fn source_map_inline() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let source = std::fs::read_to_string("./tests/fixtures/source-map-inline.js").unwrap();
+        let err = match jstime.run_script(&source, "inline.js") {
+            Ok(_result) => panic!(),
+            Err(e) => e,
+        };
+        assert_eq!(
+            err,
+            "Error: oh no, mapped\n    at fail (original.ts:4:9)\n    at original.ts:7:1"
+        );
+    }
+    #[test]
+    fn source_map_disabled() {
+        let _setup_guard = common::setup();
+        let mut options = jstime::Options::default();
+        options.source_maps = false;
+        let mut jstime = jstime::JSTime::new(options);
+        let source = std::fs::read_to_string("./tests/fixtures/source-map-inline.js").unwrap();
+        let err = match jstime.run_script(&source, "inline.js") {
+            Ok(_result) => panic!(),
+            Err(e) => e,
+        };
+        assert_eq!(
+            err,
+            "Error: oh no, mapped\n    at fail (inline.js:3:9)\n    at inline.js:5:1"
+        );
+    }
+    #[test]
+    fn inspect_brk() {
+        use std::io::{Read, Write};
+        use tungstenite::Message;
+
+        fn send(ws: &mut tungstenite::WebSocket<impl Read + Write>, id: u32, method: &str) {
+            let message = serde_json::json!({ "id": id, "method": method });
+            ws.write_message(Message::Text(message.to_string()))
+                .unwrap();
+        }
+
+        let _setup_guard = common::setup();
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut options = jstime::Options::default();
+        options.inspect = Some(addr);
+        options.inspect_brk = true;
+        let mut jstime = jstime::JSTime::new(options);
+
+        let client = std::thread::spawn(move || {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            write!(stream, "GET /json/list HTTP/1.1\r\nHost: {}\r\n\r\n", addr).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            let body = response.split("\r\n\r\n").nth(1).unwrap();
+            let targets: serde_json::Value = serde_json::from_str(body).unwrap();
+            let url = targets[0]["webSocketDebuggerUrl"].as_str().unwrap();
+
+            let (mut ws, _) = tungstenite::connect(url).unwrap();
+            send(&mut ws, 1, "Runtime.enable");
+            send(&mut ws, 2, "Debugger.enable");
+            send(&mut ws, 3, "Runtime.runIfWaitingForDebugger");
+
+            let mut paused = false;
+            let mut logged = Vec::new();
+            // Read until the session is closed by dropping the runtime.
+            while let Ok(message) = ws.read_message() {
+                let message: serde_json::Value = match message {
+                    Message::Text(text) => serde_json::from_str(&text).unwrap(),
+                    _ => continue,
+                };
+                match message["method"].as_str() {
+                    Some("Debugger.paused") => {
+                        paused = true;
+                        send(&mut ws, 4, "Debugger.resume");
+                    }
+                    Some("Runtime.consoleAPICalled") => {
+                        logged.push(message["params"]["args"][0]["value"].clone())
+                    }
+                    _ => {}
+                }
+            }
+            (paused, logged)
+        });
+
+        jstime.import("./tests/fixtures/inspector.js").unwrap();
+        let result = jstime.run_script("globalThis.inspected", "jstime");
+        assert_eq!(result.unwrap(), "true");
+        drop(jstime);
+
+        let (paused, logged) = client.join().unwrap();
+        assert!(paused);
+        assert_eq!(logged, vec![serde_json::json!("hello inspector")]);
+    }
+    #[test]
+    fn inspect_host_check() {
+        use std::io::{Read, Write};
+
+        fn get(addr: std::net::SocketAddr, path: &str, headers: &str) -> String {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\n{}\r\n", path, headers).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        }
+
+        let _setup_guard = common::setup();
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut options = jstime::Options::default();
+        options.inspect = Some(addr);
+        let _jstime = jstime::JSTime::new(options);
+
+        let port = addr.port();
+        for host in [
+            format!("localhost:{}", port),
+            format!("127.0.0.1:{}", port),
+            format!("[::1]:{}", port),
+        ] {
+            let response = get(addr, "/json/version", &format!("Host: {}\r\n", host));
+            assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", host);
+        }
+
+        let response = get(addr, "/json/list", "Host: evil.example:80\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+        let response = get(addr, "/json/list", "Host: localhost\r\n");
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let targets: serde_json::Value = serde_json::from_str(body).unwrap();
+        let path = format!("/{}", targets[0]["id"].as_str().unwrap());
+        let upgrade = "Host: evil.example\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                       Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n";
+        let response = get(addr, &path, upgrade);
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+    #[test]
+    fn cpu_profiling() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let interval = std::time::Duration::from_micros(100);
+        jstime.start_cpu_profiling(interval).unwrap();
+        assert!(jstime.start_cpu_profiling(interval).is_err());
+        let source = "function spin() { let n = 0; for (let i = 0; i < 1e7; i++) n += i; return n; } spin();";
+        jstime.run_script(source, "spin.js").unwrap();
+        let profile = jstime.stop_cpu_profiling().unwrap();
+        assert!(jstime.stop_cpu_profiling().is_err());
+
+        let profile: serde_json::Value = serde_json::from_str(&profile).unwrap();
+        assert!(profile["startTime"].is_number());
+        assert!(profile["endTime"].is_number());
+        assert!(!profile["samples"].as_array().unwrap().is_empty());
+        let functions = profile["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|node| node["callFrame"]["functionName"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert!(functions.contains(&"spin"));
+    }
+    #[test]
+    fn write_heap_snapshot() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        jstime
+            .run_script("globalThis.leak = new Array(100).fill('leak');", "jstime")
+            .unwrap();
+        let path = std::env::temp_dir().join(format!("jstime-{}.heapsnapshot", std::process::id()));
+        jstime.write_heap_snapshot(&path).unwrap();
+
+        let snapshot: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(snapshot["snapshot"]["meta"]["node_fields"].is_array());
+        assert!(!snapshot["nodes"].as_array().unwrap().is_empty());
+        assert!(snapshot["strings"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("leak")));
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn heap_snapshot_requester() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let path = std::env::temp_dir().join(format!(
+            "jstime-requested-{}.heapsnapshot",
+            std::process::id()
+        ));
+        let requester = jstime.heap_snapshot_requester();
+        let requested = path.clone();
+        std::thread::spawn(move || requester.request(requested))
+            .join()
+            .unwrap();
+        assert!(!path.exists());
+
+        // The request is handled once JS runs.
+        jstime
+            .run_script("for (let i = 0; i < 1e6; i++);", "jstime")
+            .unwrap();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn coverage() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        jstime.start_coverage().unwrap();
+        jstime.import("./tests/fixtures/coverage/main.js").unwrap();
+        jstime.run_script("globalThis.sum", "jstime").unwrap();
+        let coverage = jstime.stop_coverage().unwrap();
+
+        let mut report = jstime::coverage::Report::new();
+        report.add(&coverage).unwrap();
+        let lcov = report.to_lcov();
+        // Only imported modules are reported, math.js through its source map.
+        let files = lcov
+            .lines()
+            .filter_map(|line| line.strip_prefix("SF:"))
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].ends_with("coverage/main.js"));
+        assert!(files[1].ends_with("coverage/src/math.ts"));
+        assert!(lcov.contains("FNF:0\nFNH:0\nDA:2,1\nDA:7,1\nLF:2\nLH:2\n"));
+        assert!(lcov.contains(
+            "FN:1,add\nFN:5,unused\nFNDA:1,add\nFNDA:0,unused\nFNF:2\nFNH:1\n\
+             DA:1,1\nDA:2,1\nDA:3,1\nDA:5,0\nDA:6,0\nDA:7,0\nLF:6\nLH:3\n"
+        ));
+    }
+    #[test]
+    fn run_tests() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let files = vec!["./tests/fixtures/test/pass.test.mjs".to_owned()];
+        let result = jstime.run_tests(&files, &jstime::TestOptions::default());
+        assert_eq!(result, Ok(true));
+    }
+    #[test]
+    fn run_tests_failing() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let files = vec![
+            "./tests/fixtures/test/pass.test.mjs".to_owned(),
+            "./tests/fixtures/test/fail.test.js".to_owned(),
+        ];
+        let result = jstime.run_tests(&files, &jstime::TestOptions::default());
+        assert_eq!(result, Ok(false));
+    }
+    #[test]
+    fn run_tests_lifecycle_events() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let files = vec![
+            "./tests/fixtures/test-lifecycle/a.test.mjs".to_owned(),
+            "./tests/fixtures/test-lifecycle/b.test.mjs".to_owned(),
+        ];
+        let result = jstime.run_tests(&files, &jstime::TestOptions::default());
+        assert_eq!(result, Ok(true));
+        let result = jstime.run_script("globalThis.events.join()", "jstime");
+        assert_eq!(
+            result.unwrap(),
+            "load a,load b,test a,test b,unload a,unload b"
+        );
+    }
+    #[test]
+    fn import_unknown_builtin() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let err = jstime
+            .import("./tests/fixtures/unknown-builtin.mjs")
+            .unwrap_err();
+        assert!(err.contains("Cannot find module 'jstime:nope'"));
+    }
+    #[test]
+    fn import_invalid_utf8() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let err = jstime
+            .import("./tests/fixtures/invalid-utf8.mjs")
+            .unwrap_err();
+        assert!(err.contains("invalid-utf8.json' is not valid UTF-8"));
+    }
+    #[test]
+    fn run_benchmarks() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let files = vec!["./tests/fixtures/bench/math.bench.mjs".to_owned()];
+        let options = jstime::BenchOptions {
+            warmup: std::time::Duration::from_millis(1),
+            time: std::time::Duration::from_millis(10),
+            baseline: Some(
+                std::fs::read_to_string("./tests/fixtures/bench/baseline.json").unwrap(),
+            ),
+            ..jstime::BenchOptions::default()
+        };
+        let results = jstime.run_benchmarks(&files, &options).unwrap();
+        let key = "./tests/fixtures/bench/math.bench.mjs > sum";
+        assert_eq!(results.regressions, vec![key.to_owned()]);
+        assert!(results.failures.is_empty());
+
+        let json: serde_json::Value = serde_json::from_str(&results.json).unwrap();
+        let stats = &json["benchmarks"][key];
+        for stat in &["mean", "median", "p99", "opsPerSec"] {
+            assert!(stats[stat].as_f64().unwrap() > 0.0);
+        }
+        assert!(stats["samples"].as_u64().unwrap() >= 10);
+        // Skipped benchmarks have no results.
+        assert_eq!(json["benchmarks"].as_object().unwrap().len(), 1);
+    }
+    #[test]
+    fn bundle() {
+        let _setup_guard = common::setup();
+        // The bundle has to run without the files it was read from, so
+        // they're copied somewhere to remove them from.
+        let dir = std::env::temp_dir().join(format!("jstime-bundle-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        for file in &["main.mjs", "lib/greet.mjs", "greeting.json"] {
+            std::fs::copy(format!("./tests/fixtures/bundle/{}", file), dir.join(file)).unwrap();
+        }
+
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let options = jstime::BundleOptions { code_cache: true };
+        let bundle = jstime
+            .bundle(dir.join("main.mjs").to_str().unwrap(), &options)
+            .unwrap();
+        let bytes = bundle.to_bytes();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let bundle = jstime::Bundle::from_bytes(&bytes).unwrap();
+        let entry = bundle.entry().to_owned();
+        let mut options = jstime::Options::default();
+        options.bundle = Some(bundle);
+        let mut jstime = jstime::JSTime::new(options);
+        jstime.import(&entry).unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        assert_eq!(result.unwrap(), "hello, bundle");
+
+        assert!(jstime::Bundle::from_bytes(b"not a bundle").is_err());
+    }
+    #[test]
+    fn permissions() {
+        let _setup_guard = common::setup();
+        let mut options = jstime::Options::default();
+        options.permissions = jstime::Permissions::deny_all();
+        options.permissions.net = jstime::Permission::GrantedFor(vec!["example.com".to_owned()]);
+        let mut jstime = jstime::JSTime::new(options);
+        jstime
+            .import("./tests/fixtures/permissions-net.mjs")
+            .unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        assert_eq!(
+            result.unwrap(),
+            [
+                "PermissionDenied: Requires net access to \"example.org:443\"",
+                "PermissionDenied: Requires net access to \"example.org:8080\"",
+                "PermissionDenied: Requires net access to \"evil.com:80\"",
+                "PermissionDenied: Requires net access to \"example.net:80\"",
+            ]
+            .join("\n")
+        );
+    }
+    #[cfg(unix)]
+    #[test]
+    fn permissions_symlinks() {
+        let _setup_guard = common::setup();
+        let dir = std::env::temp_dir().join(format!("jstime-symlinks-{}", std::process::id()));
+        let allowed = dir.join("allowed");
+        std::fs::create_dir_all(&allowed).unwrap();
+        std::fs::create_dir_all(dir.join("outside")).unwrap();
+        std::fs::write(allowed.join("inside.txt"), "inside").unwrap();
+        std::fs::write(dir.join("outside").join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink("../outside", allowed.join("link")).unwrap();
+
+        let allowed_path = allowed.to_str().unwrap().to_owned();
+        let mut options = jstime::Options::default();
+        options.permissions = jstime::Permissions::deny_all();
+        options.permissions.read = jstime::Permission::GrantedFor(vec![allowed_path.clone()]);
+        options.permissions.write = jstime::Permission::GrantedFor(vec![allowed_path]);
+        let mut jstime = jstime::JSTime::new(options);
+        let set_dir = format!("globalThis.dir = {:?}", format!("{}/", allowed.display()));
+        jstime.run_script(&set_dir, "jstime").unwrap();
+        jstime
+            .import("./tests/fixtures/permissions-symlink.mjs")
+            .unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        let created = dir.join("outside").join("new.txt").exists();
+        let linked = allowed.join("root").symlink_metadata().is_ok();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            result.unwrap(),
+            "ok,PermissionDenied,PermissionDenied,PermissionDenied"
+        );
+        assert!(!created);
+        assert!(!linked);
+    }
+    #[test]
+    fn fs() {
+        let _setup_guard = common::setup();
+        let dir = std::env::temp_dir().join(format!("jstime-fs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let set_dir = format!("globalThis.dir = {:?}", format!("{}/", dir.display()));
+        jstime.run_script(&set_dir, "jstime").unwrap();
+        jstime.import("./tests/fixtures/fs.mjs").unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(result.unwrap(), "hello world,hello,11,c.txt deep,ENOENT");
+    }
+    #[test]
+    fn wasi_preopen() {
+        let _setup_guard = common::setup();
+        let dir = std::env::temp_dir().join(format!("jstime-wasi-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = dir.canonicalize().unwrap();
+
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let set_dir = format!("globalThis.dir = {:?}", format!("{}/", dir.display()));
+        jstime.run_script(&set_dir, "jstime").unwrap();
+        jstime.import("./tests/fixtures/wasi-preopen.mjs").unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(result.unwrap(), "0,2,76,0,ENOENT");
+    }
+    #[test]
+    fn process() {
+        let _setup_guard = common::setup();
+        let mut options = jstime::Options::default();
+        options.args = vec!["a".to_owned(), "--b".to_owned()];
+        let mut jstime = jstime::JSTime::new(options);
+        jstime.import("./tests/fixtures/process.mjs").unwrap();
+        assert_eq!(jstime.exit_code(), 3);
+        let result = jstime.run_script("globalThis.result", "jstime");
+        assert_eq!(result.unwrap(), "a --b,value,,string,true");
+    }
+    #[test]
+    fn streams() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        jstime.import("./tests/fixtures/streams.mjs").unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        assert_eq!(result.unwrap(), "ab,a,XY,1 2,2");
+    }
+    #[test]
+    fn lifecycle_events() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        jstime
+            .import("./tests/fixtures/lifecycle-events.mjs")
+            .unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        assert_eq!(
+            result.unwrap(),
+            "evaluated,load,unhandledrejection nobody,error timer,unload"
+        );
+    }
+    #[test]
+    fn top_level_await() {
+        let _setup_guard = common::setup();
+        let mut jstime = jstime::JSTime::new(jstime::Options::default());
+        let rejected = jstime.import("./tests/fixtures/tla-rejection.mjs");
+        assert!(rejected.unwrap_err().contains("late"));
+
+        let mut jstime = jstime::JSTime::new(jstime::Options::default());
+        jstime
+            .run_script(
+                "addEventListener('error', (event) => event.preventDefault())",
+                "jstime",
+            )
+            .unwrap();
+        jstime.import("./tests/fixtures/tla-rejection.mjs").unwrap();
+
+        let mut jstime = jstime::JSTime::new(jstime::Options::default());
+        let pending = jstime.import("./tests/fixtures/tla-pending.mjs");
+        assert_eq!(
+            pending.unwrap_err(),
+            "Still running when there was nothing left to wait for"
+        );
+    }
+    #[test]
+    fn unhandled_rejection_exit_code() {
+        let _setup_guard = common::setup();
+        let mut jstime = jstime::JSTime::new(jstime::Options::default());
+        jstime
+            .import("./tests/fixtures/unhandled-rejection.mjs")
+            .unwrap();
+        assert_eq!(jstime.exit_code(), 1);
+
+        let mut jstime = jstime::JSTime::new(jstime::Options::default());
+        jstime
+            .run_script(
+                "addEventListener('unhandledrejection', (event) => event.preventDefault())",
+                "jstime",
+            )
+            .unwrap();
+        jstime
+            .import("./tests/fixtures/unhandled-rejection.mjs")
+            .unwrap();
+        assert_eq!(jstime.exit_code(), 0);
+    }
+
+    #[test]
+    fn fetch() {
+        let _setup_guard = common::setup();
+        let server = common::http::Server::start(vec![
+            Some("HTTP/1.1 303 See Other\r\nLocation: /final\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+            Some("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 11\r\nConnection: close\r\n\r\n{\"ok\":true}"),
+        ]);
+
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let set_base = format!("globalThis.base = '{}'", server.base);
+        jstime.run_script(&set_base, "jstime").unwrap();
+        jstime.import("./tests/fixtures/fetch.mjs").unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        assert_eq!(
+            result.unwrap(),
+            "200,true,true,application/json,true,true,TypeError"
+        );
+
+        let requests = server.finish();
+        assert!(requests[0].head.starts_with("post /redirect http/1.1\n"));
+        assert!(requests[0].head.contains("x-test: yes\n"));
+        assert_eq!(requests[0].body, "ping");
+        assert!(requests[1].head.starts_with("get /final http/1.1\n"));
+        assert!(!requests[1].head.contains("content-type"));
+    }
+
+    #[test]
+    fn fetch_streams() {
+        let _setup_guard = common::setup();
+        let server = common::http::Server::start(vec![Some(
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\n0123456789",
+        )]);
+
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let set_base = format!("globalThis.base = '{}'", server.base);
+        jstime.run_script(&set_base, "jstime").unwrap();
+        jstime.import("./tests/fixtures/fetch-streams.mjs").unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        assert_eq!(result.unwrap(), "0123,456789,true");
+
+        let requests = server.finish();
+        assert!(requests[0].head.starts_with("put /upload http/1.1\n"));
+        // The body is chunked, as its length isn't known up front.
+        assert!(requests[0].head.contains("transfer-encoding: chunked\n"));
+        assert_eq!(requests[0].body, "abcd");
+    }
+
+    #[test]
+    fn fetch_concurrently() {
+        use std::io::Write;
+        use std::time::{Duration, Instant};
+
+        let _setup_guard = common::setup();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // Responds only once every request arrived, or gives up waiting so
+        // that the test fails rather than hangs if they're sent one by one.
+        let server = std::thread::spawn(move || {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            let mut streams = Vec::new();
+            while streams.len() < 4 && Instant::now() < deadline {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        stream.set_nonblocking(false).unwrap();
+                        common::http::read_request(&stream);
+                        streams.push(stream);
+                    }
+                    Err(_) => std::thread::sleep(Duration::from_millis(10)),
+                }
+            }
+            let arrived = streams.len();
+            for mut stream in streams {
+                stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    )
+                    .unwrap();
+            }
+            arrived
+        });
+
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let set_base = format!("globalThis.base = 'http://127.0.0.1:{}'", port);
+        jstime.run_script(&set_base, "jstime").unwrap();
+        jstime
+            .import("./tests/fixtures/fetch-concurrently.mjs")
+            .unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        assert_eq!(server.join().unwrap(), 4);
+        assert_eq!(result.unwrap(), "ok,ok,ok,ok");
+    }
+
+    #[test]
+    fn fetch_abort() {
+        use std::io::{Read, Write};
+        use std::time::Duration;
+
+        let _setup_guard = common::setup();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // Never responds to the first request, and only sends part of the
+        // body for the second. Aborting either closes its connection.
+        let server = std::thread::spawn(move || {
+            let mut closed = Vec::new();
+            for response in [
+                None,
+                Some("HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc"),
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                common::http::read_request(&stream);
+                if let Some(response) = response {
+                    stream.write_all(response.as_bytes()).unwrap();
+                }
+                stream
+                    .set_read_timeout(Some(Duration::from_secs(10)))
+                    .unwrap();
+                closed.push(matches!(stream.read(&mut [0; 1]), Ok(0)));
+            }
+            closed
+        });
+
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let set_base = format!("globalThis.base = 'http://127.0.0.1:{}'", port);
+        jstime.run_script(&set_base, "jstime").unwrap();
+        let start = std::time::Instant::now();
+        jstime.import("./tests/fixtures/fetch-abort.mjs").unwrap();
+        let elapsed = start.elapsed();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        assert_eq!(result.unwrap(), "AbortError,true,AbortError,abc,AbortError");
+        assert!(elapsed < Duration::from_secs(30));
+        assert_eq!(server.join().unwrap(), vec![true, true]);
+    }
+    #[test]
+    fn import_meta_url() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        jstime
+            .import("./tests/fixtures/import-meta/main.mjs")
+            .unwrap();
+        let result = jstime.run_script("globalThis.result", "jstime");
+        assert_eq!(result.unwrap(), "true,true,true,true");
+    }
 }
@@ -14,6 +14,28 @@ mod tests {
         assert_eq!(result.unwrap(), "function");
     }
     #[test]
+    fn web_assembly_streaming() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let result = jstime.run_script(
+            "[typeof WebAssembly.compileStreaming, typeof WebAssembly.instantiateStreaming]",
+            "jstime",
+        );
+        assert_eq!(result.unwrap(), "function,function");
+    }
+    #[test]
+    fn wasi() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let result = jstime.run_script(
+            "typeof new WASI({ args: ['a'] }).getImportObject().wasi_snapshot_preview1.fd_write",
+            "jstime",
+        );
+        assert_eq!(result.unwrap(), "function");
+    }
+    #[test]
     fn console() {
         let _setup_guard = common::setup();
         let options = jstime::Options::default();
@@ -21,4 +43,168 @@ mod tests {
         let result = jstime.run_script("Object.keys(console);", "jstime");
         assert_eq!(result.unwrap(), "debug,error,info,log,warn,dir,dirxml,table,trace,group,groupCollapsed,groupEnd,clear,count,countReset,assert,profile,profileEnd,time,timeLog,timeEnd,timeStamp,context");
     }
+    #[test]
+    fn timers() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let result = jstime.run_script(
+            "const id = setTimeout(() => {}, 10); clearTimeout(id); typeof id",
+            "jstime",
+        );
+        assert_eq!(result.unwrap(), "number");
+    }
+    #[test]
+    fn streams() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let result = jstime.run_script(
+            "[typeof ReadableStream, typeof WritableStream, typeof TransformStream]",
+            "jstime",
+        );
+        assert_eq!(result.unwrap(), "function,function,function");
+    }
+    #[test]
+    fn streams_byob_detach() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        // The view passed to read(view) is detached, and the stream fills
+        // a copy of its buffer.
+        let result = jstime.run_script(
+            "const stream = new ReadableStream({ type: 'bytes' });
+            const view = new Uint8Array(4);
+            stream.getReader({ mode: 'byob' }).read(view);
+            [view.byteLength, view.buffer.byteLength].join(',')",
+            "jstime",
+        );
+        assert_eq!(result.unwrap(), "0,0");
+    }
+    #[test]
+    fn fetch() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let result = jstime.run_script(
+            "const headers = new Headers([['B', '1'], ['a', ' 2 ']]);
+            headers.append('b', '3');
+            const request = new Request('http://example.com/', { method: 'post', body: 'x' });
+            const response = new Response(null, { status: 404 });
+            [
+              [...headers.keys()].join(' '),
+              headers.get('B'),
+              request.method,
+              request.headers.get('content-type'),
+              response.ok,
+              response.body,
+            ].join(',')",
+            "jstime",
+        );
+        assert_eq!(
+            result.unwrap(),
+            "a b,1, 3,POST,text/plain;charset=UTF-8,false,"
+        );
+    }
+    #[test]
+    fn events() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let result = jstime.run_script(
+            "const target = new EventTarget();
+            const seen = [];
+            target.addEventListener('ping', (event) => {
+              seen.push(event.detail);
+              event.preventDefault();
+            });
+            target.addEventListener('ping', () => seen.push('once'), { once: true });
+            const event = new CustomEvent('ping', { detail: 'a', cancelable: true });
+            const notCanceled = target.dispatchEvent(event);
+            target.dispatchEvent(new CustomEvent('ping', { detail: 'b' }));
+            [
+              seen.join(' '),
+              notCanceled,
+              event.defaultPrevented,
+              event.isTrusted,
+              globalThis instanceof EventTarget,
+            ].join(',')",
+            "jstime",
+        );
+        assert_eq!(result.unwrap(), "a once b,false,true,false,true");
+    }
+    #[test]
+    fn abort() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let result = jstime.run_script(
+            "const controller = new AbortController();
+            const events = [];
+            controller.signal.addEventListener('abort', (event) => events.push(event.type));
+            const any = AbortSignal.any([controller.signal]);
+            controller.abort();
+            controller.abort();
+            [
+              events.join(' '),
+              controller.signal.reason instanceof DOMException,
+              controller.signal.reason.name,
+              controller.signal.reason.code,
+              any.aborted,
+              AbortSignal.abort('why').reason,
+              new DOMException('message', 'TimeoutError').code,
+            ].join(',')",
+            "jstime",
+        );
+        assert_eq!(result.unwrap(), "abort,true,AbortError,20,true,why,23");
+    }
+    #[test]
+    fn url() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let result = jstime.run_script(
+            "const url = new URL('../b?x=1#h', 'HTTPS://Example.com:443/a/c');
+            url.searchParams.append('y', 'a b');
+            const params = new URLSearchParams({ b: '2', a: '1' });
+            params.sort();
+            [
+              url.href,
+              url.origin,
+              url.searchParams.get('x'),
+              URL.canParse('nope'),
+              params.toString(),
+            ].join(',')",
+            "jstime",
+        );
+        assert_eq!(
+            result.unwrap(),
+            "https://example.com/b?x=1&y=a+b#h,https://example.com,1,false,a=1&b=2"
+        );
+    }
+    #[test]
+    fn url_pattern() {
+        let _setup_guard = common::setup();
+        let options = jstime::Options::default();
+        let mut jstime = jstime::JSTime::new(options);
+        let result = jstime.run_script(
+            "const books = new URLPattern('https://example.com/books/:id(\\\\d+)');
+            const files = new URLPattern({ pathname: '/files/:path+' });
+            const hosts = new URLPattern({ hostname: '{*.}?example.com' });
+            [
+              books.pathname,
+              books.test('https://example.com/books/12'),
+              books.test('https://example.com/books/ab'),
+              books.exec('https://example.com/books/12').pathname.groups.id,
+              files.exec({ pathname: '/files/a/b' }).pathname.groups.path,
+              hosts.test({ hostname: 'www.example.com' }),
+              hosts.test({ hostname: 'example.org' }),
+            ].join(',')",
+            "jstime",
+        );
+        assert_eq!(
+            result.unwrap(),
+            "/books/:id(\\d+),true,false,12,a/b,true,false"
+        );
+    }
 }